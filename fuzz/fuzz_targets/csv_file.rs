@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tabelle_core::csv::CsvFile;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    // Parsing may fail, but it must never panic.
+    if let Ok(csv) = text.parse::<CsvFile>() {
+        assert_eq!(csv.cells.len(), csv.width * csv.height);
+        // Cells only contain characters of the input without escaping.
+        let cell_bytes: usize = csv.cells.iter().map(String::len).sum();
+        assert!(cell_bytes <= text.len());
+    }
+});
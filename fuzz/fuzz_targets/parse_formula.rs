@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+/// The size of the spreadsheet the formula is parsed for.
+const SIZE: (usize, usize) = (10, 10);
+/// Ranges are expanded to python code, which lists every column they span.
+/// A range has at least three characters, so its code is bounded by this
+/// factor for a spreadsheet of `SIZE`.
+const MAX_EXPANSION: usize = 40;
+
+fuzz_target!(|raw: &str| {
+    let (parsed, reference_count) = tabelle_core::parse_formula(raw, SIZE);
+    assert!(reference_count <= raw.chars().count());
+    assert!(parsed.len() <= raw.len() * MAX_EXPANSION);
+});
@@ -0,0 +1,121 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::{CellContent, ColumnType, Error, Spreadsheet};
+
+/// Collects rows of values and turns them into a [`Spreadsheet`], checking
+/// that every row has one value per column and that the values fit the
+/// column types.
+#[derive(Debug, Default)]
+pub struct SpreadsheetBuilder {
+    headers: Vec<String>,
+    column_types: Vec<ColumnType>,
+    default_width: Option<usize>,
+    rows: Vec<Vec<CellContent>>,
+}
+
+impl SpreadsheetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The names of the columns, which become the first row. The row stays
+    /// visible while scrolling.
+    pub fn headers(mut self, headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.headers = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn column_types(mut self, column_types: impl IntoIterator<Item = ColumnType>) -> Self {
+        self.column_types = column_types.into_iter().collect();
+        self
+    }
+
+    /// The width of every column in characters. Without it the columns are
+    /// as wide as their widest value.
+    pub fn default_width(mut self, width: usize) -> Self {
+        self.default_width = Some(width);
+        self
+    }
+
+    pub fn push_row<C: Into<CellContent>>(mut self, row: impl IntoIterator<Item = C>) -> Self {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Appends every row of `rows` like [`SpreadsheetBuilder::push_row`].
+    pub fn rows<C: Into<CellContent>>(
+        mut self,
+        rows: impl IntoIterator<Item = impl IntoIterator<Item = C>>,
+    ) -> Self {
+        self.rows.extend(
+            rows.into_iter()
+                .map(|row| row.into_iter().map(Into::into).collect()),
+        );
+        self
+    }
+
+    /// The number of columns. It is given by the headers, the column types
+    /// or the first row, in that order.
+    fn columns(&self) -> usize {
+        if !self.headers.is_empty() {
+            self.headers.len()
+        } else if !self.column_types.is_empty() {
+            self.column_types.len()
+        } else {
+            self.rows.first().map_or(0, Vec::len)
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let columns = self.columns();
+        if !self.column_types.is_empty() && self.column_types.len() != columns {
+            return Err(Error::Builder(format!(
+                "{} column types for {columns} columns",
+                self.column_types.len()
+            )));
+        }
+        let header_offset = usize::from(!self.headers.is_empty());
+        for (index, row) in self.rows.iter().enumerate() {
+            let y = index + header_offset;
+            if row.len() != columns {
+                return Err(Error::Builder(format!(
+                    "Row {y} has {} values, but there are {columns} columns",
+                    row.len()
+                )));
+            }
+            for (x, (content, column_type)) in row.iter().zip(&self.column_types).enumerate() {
+                if !column_type.accepts(content) {
+                    return Err(Error::Builder(format!(
+                        "Cell ({x}, {y}) does not contain a value of type {column_type:?}"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the spreadsheet and evaluates its formulas. Fails, if the
+    /// rows do not match the columns.
+    pub fn build(self) -> Result<Spreadsheet, Error> {
+        self.validate()?;
+        let columns = self.columns();
+        let has_headers = !self.headers.is_empty();
+        let headers: Option<Vec<_>> =
+            has_headers.then(|| self.headers.into_iter().map(CellContent::Text).collect());
+        let mut result = Spreadsheet::from_rows(headers.into_iter().chain(self.rows));
+        result.fixed_rows = usize::from(has_headers);
+        result.column_types = self.column_types;
+        result.evaluate();
+        for column in 0..columns {
+            let width = self.default_width.unwrap_or_else(|| {
+                (0..result.height)
+                    .map(|row| result.cell_at((column, row)).display_content().width())
+                    .max()
+                    .unwrap_or_default()
+                    .max(3)
+            });
+            result.set_column_width(column, width);
+        }
+        Ok(result)
+    }
+}
@@ -39,11 +39,7 @@ impl CellPosition {
 
 impl PartialOrd for CellPosition {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match self.1.partial_cmp(&other.1) {
-            Some(core::cmp::Ordering::Equal) => {}
-            ord => return ord,
-        }
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
@@ -63,11 +59,101 @@ impl ops::Sub for CellPosition {
     }
 }
 
+/// Overrides the alignment of a cell, which is otherwise decided by its
+/// content.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum CellAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// How a cell is displayed independent of its content and unit.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct CellStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub foreground: Option<(u8, u8, u8)>,
+    pub background: Option<(u8, u8, u8)>,
+    pub alignment: Option<CellAlignment>,
+}
+
+impl From<&umya_spreadsheet::Style> for CellStyle {
+    fn from(value: &umya_spreadsheet::Style) -> Self {
+        let font = value.get_font().as_ref();
+        let background = value
+            .get_fill()
+            .as_ref()
+            .and_then(|fill| fill.get_pattern_fill().as_ref())
+            .filter(|fill| fill.get_pattern_type() == &umya_spreadsheet::PatternValues::Solid)
+            .and_then(|fill| fill.get_foreground_color().as_ref())
+            .and_then(|color| parse_argb(color.get_argb()));
+        let alignment =
+            value
+                .get_alignment()
+                .as_ref()
+                .and_then(|alignment| match alignment.get_horizontal() {
+                    umya_spreadsheet::HorizontalAlignmentValues::Left => Some(CellAlignment::Left),
+                    umya_spreadsheet::HorizontalAlignmentValues::Center => {
+                        Some(CellAlignment::Center)
+                    }
+                    umya_spreadsheet::HorizontalAlignmentValues::Right => {
+                        Some(CellAlignment::Right)
+                    }
+                    _ => None,
+                });
+        Self {
+            bold: font.is_some_and(|font| *font.get_bold()),
+            italic: font.is_some_and(|font| *font.get_italic()),
+            foreground: font.and_then(|font| parse_argb(font.get_color().get_argb())),
+            background,
+            alignment,
+        }
+    }
+}
+
+impl CellStyle {
+    /// Writes the style into the style of a xlsx cell.
+    pub(crate) fn apply_to(&self, style: &mut umya_spreadsheet::Style) {
+        if *self == CellStyle::default() {
+            return;
+        }
+        let font = style.get_font_mut();
+        font.set_bold(self.bold);
+        font.set_italic(self.italic);
+        if let Some((r, g, b)) = self.foreground {
+            font.get_color_mut()
+                .set_argb(format!("FF{r:02X}{g:02X}{b:02X}"));
+        }
+        if let Some((r, g, b)) = self.background {
+            style.set_background_color(format!("FF{r:02X}{g:02X}{b:02X}"));
+        }
+        if let Some(alignment) = self.alignment {
+            style.get_alignment_mut().set_horizontal(match alignment {
+                CellAlignment::Left => umya_spreadsheet::HorizontalAlignmentValues::Left,
+                CellAlignment::Center => umya_spreadsheet::HorizontalAlignmentValues::Center,
+                CellAlignment::Right => umya_spreadsheet::HorizontalAlignmentValues::Right,
+            });
+        }
+    }
+}
+
+/// Parses colors like `FFFF8000`. Theme colors have no argb value.
+fn parse_argb(argb: &str) -> Option<(u8, u8, u8)> {
+    if argb.len() != 8 || !argb.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&argb[i..i + 2], 16).ok();
+    Some((channel(2)?, channel(4)?, channel(6)?))
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Cell {
     pub(crate) content: CellContent,
     pub(crate) position: CellPosition,
     pub(crate) unit: UnitKind,
+    #[serde(default)]
+    pub(crate) style: CellStyle,
 }
 
 impl Cell {
@@ -83,15 +169,15 @@ impl Cell {
         (self.position.0, self.position.1)
     }
 
-    pub fn serialize_display_content(&self) -> Cow<str> {
+    pub fn serialize_display_content(&self) -> Cow<'_, str> {
         self.content.serialize_display()
     }
 
-    pub fn long_display_content(&self) -> Cow<str> {
+    pub fn long_display_content(&self) -> Cow<'_, str> {
         self.content.long_display()
     }
 
-    pub fn display_content(&self) -> Cow<str> {
+    pub fn display_content(&self) -> Cow<'_, str> {
         self.unit.display(&self.content)
     }
 
@@ -115,9 +201,21 @@ impl Cell {
         self.content.is_empty()
     }
 
+    pub fn unit(&self) -> UnitKind {
+        self.unit.clone()
+    }
+
     pub fn set_unit(&mut self, unit: UnitKind) {
         self.unit = unit;
     }
+
+    pub fn style(&self) -> CellStyle {
+        self.style
+    }
+
+    pub fn set_style(&mut self, style: CellStyle) {
+        self.style = style;
+    }
 }
 
 impl std::fmt::Debug for Cell {
@@ -138,7 +236,7 @@ impl PartialEq for Cell {
 
 impl PartialOrd for Cell {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.position.partial_cmp(&other.position)
+        Some(self.cmp(other))
     }
 }
 
@@ -1,11 +1,16 @@
 use std::{borrow::Cow, cmp};
 
-use pyo3::{Py, PyAny, Python, ToPyObject};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use pyo3::{
+    types::{PyDate, PyDateTime},
+    Py, PyAny, Python, ToPyObject,
+};
 use serde::{Deserialize, Serialize};
 
-use crate::Spreadsheet;
+use crate::{search::Pattern, units, ColumnType, Spreadsheet};
 
-pub(crate) use self::formula::{Formula, Value};
+pub(crate) use self::formula::{run_script, CellReference, CompiledCode, Expression, Value};
+pub use self::formula::{Formula, FormulaReference, FormulaTokenKind};
 
 use super::CellPosition;
 
@@ -19,15 +24,34 @@ pub enum CellContent {
     Number(i64),
     FloatNumber(f64, i32),
     Formula(Formula),
+    /// A link, which shows `display` instead of the url.
+    Hyperlink {
+        display: String,
+        url: String,
+    },
+    /// A date like `2023-01-05`.
+    Date(NaiveDate),
+    /// A date with a time like `2023-01-05 13:30:00`.
+    DateTime(NaiveDateTime),
 }
 
+/// The formats of dates, which are recognized when parsing a cell.
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
 impl CellContent {
     pub(super) fn is_right_aligned(&self) -> bool {
         match self {
             CellContent::Empty => false,
             CellContent::Text(_) => false,
+            CellContent::Hyperlink { .. } => false,
             CellContent::Number(_) => true,
             CellContent::FloatNumber(..) => true,
+            CellContent::Date(_) | CellContent::DateTime(_) => true,
             CellContent::Formula(f) => f.is_right_aligned(),
         }
     }
@@ -38,7 +62,7 @@ impl CellContent {
         }
     }
 
-    pub fn serialize_display(&self) -> Cow<str> {
+    pub fn serialize_display(&self) -> Cow<'_, str> {
         if let CellContent::Formula(it) = self {
             it.long_display()
         } else {
@@ -46,20 +70,24 @@ impl CellContent {
         }
     }
 
-    pub fn long_display(&self) -> Cow<str> {
+    pub fn long_display(&self) -> Cow<'_, str> {
         match self {
             CellContent::Empty => "Press ENTER to edit".into(),
             CellContent::Formula(it) => it.long_display(),
+            CellContent::Hyperlink { display, url } => format!("{display} ({url})").into(),
             _ => self.display(),
         }
     }
 
-    pub(super) fn display(&self) -> Cow<str> {
+    pub(crate) fn display(&self) -> Cow<'_, str> {
         match self {
             CellContent::Empty => "".into(),
             CellContent::Text(it) => it.into(),
+            CellContent::Hyperlink { display, .. } => display.into(),
             CellContent::Number(it) => it.to_string().into(),
-            CellContent::FloatNumber(it, _) => it.to_string().into(),
+            CellContent::FloatNumber(it, _) => units::display_float(*it).into(),
+            CellContent::Date(it) => it.format("%Y-%m-%d").to_string().into(),
+            CellContent::DateTime(it) => it.format("%Y-%m-%d %H:%M:%S").to_string().into(),
             CellContent::Formula(it) => it.display(),
         }
     }
@@ -76,14 +104,30 @@ impl CellContent {
                     },
                 };
             }
-            CellContent::Text(it) => it.push(ch),
+            CellContent::Text(it) => {
+                it.push(ch);
+                if let Some(number) = Self::parse_negative_number(it) {
+                    *self = number;
+                }
+            }
             CellContent::Formula(f) => {
                 f.push_char(ch);
             }
+            cell @ CellContent::Hyperlink { .. } => {
+                *cell = CellContent::Text(format!("{}{ch}", cell.as_str().unwrap()));
+            }
+            cell @ (CellContent::Date(_) | CellContent::DateTime(_)) => {
+                *cell = CellContent::Text(format!("{}{ch}", cell.display()));
+            }
             CellContent::Number(it) if ch.is_ascii_digit() => {
-                let digit = ch.to_digit(10).unwrap();
-                *it *= 10;
-                *it += digit as i64;
+                let digit = ch.to_digit(10).unwrap() as i64;
+                let digit = if *it < 0 { -digit } else { digit };
+                match it.checked_mul(10).and_then(|it| it.checked_add(digit)) {
+                    Some(number) => *it = number,
+                    // Numbers too large for an i64 are kept as they were
+                    // typed.
+                    None => *self = CellContent::Text(format!("{it}{ch}")),
+                }
             }
             cell @ CellContent::Number(_) if ch == '.' => {
                 *cell = CellContent::FloatNumber(cell.as_number().unwrap() as f64, 1);
@@ -95,7 +139,11 @@ impl CellContent {
                 let digit = ch.to_digit(10).unwrap() as f64;
                 let digit = digit / 10.0f64.powi(*digit_count);
                 *digit_count += 1;
-                *it += digit;
+                if it.is_sign_negative() {
+                    *it -= digit;
+                } else {
+                    *it += digit;
+                }
             }
             cell @ CellContent::FloatNumber(..) => {
                 *cell = CellContent::Text(format!("{}{ch}", cell.as_float_number().unwrap()));
@@ -103,6 +151,24 @@ impl CellContent {
         }
     }
 
+    /// Parses text, which is typed in character by character, into a
+    /// negative number. `-` and `-0` stay text, since their sign would get
+    /// lost otherwise.
+    fn parse_negative_number(text: &str) -> Option<CellContent> {
+        if !text.starts_with('-') {
+            return None;
+        }
+        if let Some(integer) = text.strip_suffix('.') {
+            let integer: i64 = integer.parse().ok()?;
+            Some(CellContent::FloatNumber(-(integer.abs() as f64), 1))
+        } else {
+            match text.parse() {
+                Ok(0) | Err(_) => None,
+                Ok(it) => Some(CellContent::Number(it)),
+            }
+        }
+    }
+
     pub fn as_number(&self) -> Option<i64> {
         if let Self::Number(v) = self {
             Some(*v)
@@ -119,14 +185,130 @@ impl CellContent {
         }
     }
 
+    /// Returns the numeric value of the cell content. For formulas this is
+    /// the last evaluated value.
+    pub fn numeric_value(&self) -> Option<f64> {
+        match self {
+            CellContent::Number(it) => Some(*it as f64),
+            CellContent::FloatNumber(it, _) => Some(*it),
+            CellContent::Formula(it) => match it.value {
+                Value::Number(it) => Some(it as f64),
+                Value::FloatNumber(it) => Some(it),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the date as the number of days since 1899-12-30, which is how
+    /// xlsx files store dates.
+    pub(crate) fn date_serial(&self) -> Option<f64> {
+        match self {
+            CellContent::Date(it) => Some(units::serial_from_date_time(it.and_hms_opt(0, 0, 0)?)),
+            CellContent::DateTime(it) => Some(units::serial_from_date_time(*it)),
+            _ => None,
+        }
+    }
+
+    fn parse_date(cell: &str) -> Option<CellContent> {
+        if let Ok(it) = NaiveDate::parse_from_str(cell, "%Y-%m-%d") {
+            return Some(CellContent::Date(it));
+        }
+        DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(cell, format).ok())
+            .map(CellContent::DateTime)
+    }
+
+    /// Returns the type of the value or `None` for empty cells, errors and
+    /// formulas, which were not evaluated yet.
+    pub(crate) fn value_type(&self) -> Option<ColumnType> {
+        match self {
+            CellContent::Empty => None,
+            CellContent::Text(_) | CellContent::Hyperlink { .. } => Some(ColumnType::Text),
+            CellContent::Number(_) => Some(ColumnType::Integer),
+            CellContent::FloatNumber(..) => Some(ColumnType::Number),
+            CellContent::Date(_) => Some(ColumnType::Date),
+            CellContent::DateTime(_) => Some(ColumnType::DateTime),
+            CellContent::Formula(it) => match it.value {
+                Value::String(_) => Some(ColumnType::Text),
+                Value::Number(_) => Some(ColumnType::Integer),
+                Value::FloatNumber(_) => Some(ColumnType::Number),
+                Value::Empty | Value::Error | Value::Cycle => None,
+            },
+        }
+    }
+
+    /// Returns `true` if the cell content is a [`Self::Formula`].
+    #[must_use]
+    pub fn is_formula(&self) -> bool {
+        matches!(self, Self::Formula(_))
+    }
+
+    /// Returns the text of the cell. For hyperlinks this is the displayed
+    /// text.
     pub fn as_str(&self) -> Option<&str> {
-        if let Self::Text(v) = self {
-            Some(v)
+        match self {
+            Self::Text(v) | Self::Hyperlink { display: v, .. } => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the text of the cell. For formulas this is the last evaluated
+    /// value, if it is a text.
+    pub(crate) fn text_value(&self) -> Option<&str> {
+        match self {
+            Self::Formula(f) => match &f.value {
+                Value::String(it) => Some(it),
+                _ => None,
+            },
+            _ => self.as_str(),
+        }
+    }
+
+    /// Returns the url, if the cell contains a [`Self::Hyperlink`].
+    pub fn url(&self) -> Option<&str> {
+        if let Self::Hyperlink { url, .. } = self {
+            Some(url)
         } else {
             None
         }
     }
 
+    /// Returns the text, which find and replace changes: the text of text
+    /// cells, the shown text of hyperlinks and the source of formulas
+    /// including the leading `=`. Numbers and dates are never changed.
+    pub(crate) fn replaceable_text(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Self::Text(it) | Self::Hyperlink { display: it, .. } => Some(it.into()),
+            Self::Formula(it) => Some(it.long_display()),
+            _ => None,
+        }
+    }
+
+    /// Replaces `pattern` in the [`Self::replaceable_text`] and returns the
+    /// new content with the number of replacements. Hyperlinks keep their url
+    /// and formulas are parsed again.
+    pub(crate) fn replaced(
+        &self,
+        pattern: &Pattern,
+        replacement: &str,
+        position: (usize, usize),
+        size: (usize, usize),
+    ) -> Option<(CellContent, usize)> {
+        let (text, count) = pattern.replace(&self.replaceable_text()?, replacement)?;
+        let content = match self {
+            Self::Hyperlink { url, .. } => Self::Hyperlink {
+                display: text,
+                url: url.clone(),
+            },
+            Self::Formula(_) => Self::parse(&text, position, size),
+            _ if text.is_empty() => Self::Empty,
+            _ => Self::Text(text),
+        };
+        Some((content, count))
+    }
+
     /// Returns `true` if the cell content is a formula with a syntax or
     /// semantic error.
     #[must_use]
@@ -144,12 +326,16 @@ impl CellContent {
         if cell.is_empty() {
             CellContent::Empty
         } else {
+            // Integers too large for an i64 and numbers like `1e9` become
+            // floating point numbers. Text like `inf` or `NaN` stays text.
             match cell.parse::<i64>() {
                 Ok(it) => CellContent::Number(it),
-                Err(_) => match cell.parse::<f64>() {
-                    Ok(it) => CellContent::FloatNumber(it, 0),
-                    Err(_) => {
-                        if let Some(raw_formula) = cell.strip_prefix('=') {
+                Err(_) => match cell.parse::<f64>().ok().filter(|it| it.is_finite()) {
+                    Some(it) => CellContent::FloatNumber(it, 0),
+                    None => {
+                        if let Some(date) = Self::parse_date(cell) {
+                            date
+                        } else if let Some(raw_formula) = cell.strip_prefix('=') {
                             let (parsed, references) = Formula::parse_raw(raw_formula, size);
                             CellContent::Formula(Formula {
                                 position: CellPosition(cell_position.0, cell_position.1),
@@ -157,6 +343,7 @@ impl CellContent {
                                 raw: raw_formula.to_owned(),
                                 parsed,
                                 value: Value::Empty,
+                                compiled_code: CompiledCode::default(),
                             })
                         } else {
                             CellContent::Text(cell.into())
@@ -170,9 +357,29 @@ impl CellContent {
     pub(crate) fn try_to_object(&self, py: Python) -> Option<Py<PyAny>> {
         match &self {
             CellContent::Empty => None,
-            CellContent::Text(it) => Some(it.to_object(py)),
+            CellContent::Text(it) | CellContent::Hyperlink { display: it, .. } => {
+                Some(it.to_object(py))
+            }
             CellContent::Number(it) => Some(it.to_object(py)),
             CellContent::FloatNumber(it, _) => Some(it.to_object(py)),
+            CellContent::Date(it) => {
+                let date = PyDate::new(py, it.year(), it.month() as u8, it.day() as u8);
+                date.ok().map(|it| it.to_object(py))
+            }
+            CellContent::DateTime(it) => {
+                let date_time = PyDateTime::new(
+                    py,
+                    it.year(),
+                    it.month() as u8,
+                    it.day() as u8,
+                    it.hour() as u8,
+                    it.minute() as u8,
+                    it.second() as u8,
+                    it.nanosecond() / 1000,
+                    None,
+                );
+                date_time.ok().map(|it| it.to_object(py))
+            }
             CellContent::Formula(it) => match &it.value {
                 Value::String(it) => Some(it.to_object(py)),
                 Value::Number(it) => Some(it.to_object(py)),
@@ -181,10 +388,28 @@ impl CellContent {
             },
         }
     }
+
+    pub(crate) fn to_json_value(&self) -> serde_json::Value {
+        match &self {
+            CellContent::Empty => serde_json::Value::Null,
+            CellContent::Text(it) | CellContent::Hyperlink { display: it, .. } => {
+                it.as_str().into()
+            }
+            CellContent::Number(it) => (*it).into(),
+            CellContent::FloatNumber(it, _) => (*it).into(),
+            CellContent::Date(_) | CellContent::DateTime(_) => self.display().into(),
+            CellContent::Formula(it) => match &it.value {
+                Value::String(it) => it.as_str().into(),
+                Value::Number(it) => (*it).into(),
+                Value::FloatNumber(it) => (*it).into(),
+                Value::Empty | Value::Error | Value::Cycle => serde_json::Value::Null,
+            },
+        }
+    }
 }
 
-impl cmp::PartialOrd for CellContent {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+impl CellContent {
+    fn compare(&self, other: &Self) -> Option<cmp::Ordering> {
         Some(match self {
             CellContent::Empty => {
                 if other.is_empty() {
@@ -193,7 +418,7 @@ impl cmp::PartialOrd for CellContent {
                     cmp::Ordering::Less
                 }
             }
-            CellContent::Text(text) => {
+            CellContent::Text(text) | CellContent::Hyperlink { display: text, .. } => {
                 if let Some(other) = other.as_str() {
                     text.as_str().partial_cmp(other)?.reverse()
                 } else {
@@ -202,30 +427,41 @@ impl cmp::PartialOrd for CellContent {
             }
             &CellContent::Number(value) => match other {
                 CellContent::Empty => cmp::Ordering::Greater,
-                CellContent::Text(_) => cmp::Ordering::Less,
+                CellContent::Text(_) | CellContent::Hyperlink { .. } => cmp::Ordering::Less,
                 CellContent::Number(other) => value.partial_cmp(other)?,
                 CellContent::FloatNumber(other, _) => (value as f64).partial_cmp(other)?,
+                other @ (CellContent::Date(_) | CellContent::DateTime(_)) => {
+                    (value as f64).partial_cmp(&other.date_serial()?)?
+                }
                 CellContent::Formula(other) => match &other.value {
                     Value::String(_) => cmp::Ordering::Less,
                     Value::Number(other) => value.partial_cmp(other)?,
                     Value::FloatNumber(other) => (value as f64).partial_cmp(other)?,
                     Value::Empty => cmp::Ordering::Greater,
-                    Value::Error => cmp::Ordering::Greater,
+                    Value::Error | Value::Cycle => cmp::Ordering::Greater,
                 },
             },
             CellContent::FloatNumber(value, _) => match other {
                 CellContent::Empty => cmp::Ordering::Greater,
-                CellContent::Text(_) => cmp::Ordering::Less,
+                CellContent::Text(_) | CellContent::Hyperlink { .. } => cmp::Ordering::Less,
                 CellContent::Number(other) => value.partial_cmp(&(*other as f64))?,
                 CellContent::FloatNumber(other, _) => value.partial_cmp(other)?,
+                other @ (CellContent::Date(_) | CellContent::DateTime(_)) => {
+                    value.partial_cmp(&other.date_serial()?)?
+                }
                 CellContent::Formula(other) => match &other.value {
                     Value::String(_) => cmp::Ordering::Less,
                     Value::Number(other) => value.partial_cmp(&(*other as f64))?,
                     Value::FloatNumber(other) => value.partial_cmp(other)?,
                     Value::Empty => cmp::Ordering::Greater,
-                    Value::Error => cmp::Ordering::Greater,
+                    Value::Error | Value::Cycle => cmp::Ordering::Greater,
                 },
             },
+            // Dates are sorted like the numbers they are stored as in xlsx
+            // files.
+            CellContent::Date(_) | CellContent::DateTime(_) => {
+                return CellContent::FloatNumber(self.date_serial()?, 0).compare(other);
+            }
             CellContent::Formula(f) => match &f.value {
                 Value::String(text) => {
                     if let Some(other) = other.as_str() {
@@ -236,41 +472,91 @@ impl cmp::PartialOrd for CellContent {
                 }
                 &Value::Number(value) => match other {
                     CellContent::Empty => cmp::Ordering::Greater,
-                    CellContent::Text(_) => cmp::Ordering::Less,
+                    CellContent::Text(_) | CellContent::Hyperlink { .. } => cmp::Ordering::Less,
                     CellContent::Number(other) => value.partial_cmp(other)?,
                     CellContent::FloatNumber(other, _) => (value as f64).partial_cmp(other)?,
+                    other @ (CellContent::Date(_) | CellContent::DateTime(_)) => {
+                        (value as f64).partial_cmp(&other.date_serial()?)?
+                    }
                     CellContent::Formula(other) => match &other.value {
                         Value::String(_) => cmp::Ordering::Less,
                         Value::Number(other) => value.partial_cmp(other)?,
                         Value::FloatNumber(other) => (value as f64).partial_cmp(other)?,
                         Value::Empty => cmp::Ordering::Greater,
-                        Value::Error => cmp::Ordering::Greater,
+                        Value::Error | Value::Cycle => cmp::Ordering::Greater,
                     },
                 },
                 Value::FloatNumber(value) => match other {
                     CellContent::Empty => cmp::Ordering::Greater,
-                    CellContent::Text(_) => cmp::Ordering::Less,
+                    CellContent::Text(_) | CellContent::Hyperlink { .. } => cmp::Ordering::Less,
                     CellContent::Number(other) => value.partial_cmp(&(*other as f64))?,
                     CellContent::FloatNumber(other, _) => value.partial_cmp(other)?,
+                    other @ (CellContent::Date(_) | CellContent::DateTime(_)) => {
+                        value.partial_cmp(&other.date_serial()?)?
+                    }
                     CellContent::Formula(other) => match &other.value {
                         Value::String(_) => cmp::Ordering::Less,
                         Value::Number(other) => value.partial_cmp(&(*other as f64))?,
                         Value::FloatNumber(other) => value.partial_cmp(other)?,
                         Value::Empty => cmp::Ordering::Greater,
-                        Value::Error => cmp::Ordering::Greater,
+                        Value::Error | Value::Cycle => cmp::Ordering::Greater,
                     },
                 },
                 Value::Empty => cmp::Ordering::Less,
-                Value::Error => cmp::Ordering::Less,
+                Value::Error | Value::Cycle => cmp::Ordering::Less,
             },
         })
     }
 }
 
+impl cmp::PartialOrd for CellContent {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl cmp::Eq for CellContent {}
 
 impl cmp::Ord for CellContent {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.partial_cmp(other).unwrap_or(cmp::Ordering::Equal)
+        self.compare(other).unwrap_or(cmp::Ordering::Equal)
+    }
+}
+
+impl From<i64> for CellContent {
+    fn from(value: i64) -> Self {
+        CellContent::Number(value)
+    }
+}
+
+impl From<f64> for CellContent {
+    fn from(value: f64) -> Self {
+        CellContent::FloatNumber(value, 0)
+    }
+}
+
+/// Text is never parsed, so `"=1+2"` stays text instead of becoming a
+/// formula.
+impl From<String> for CellContent {
+    fn from(value: String) -> Self {
+        CellContent::Text(value)
+    }
+}
+
+impl From<&str> for CellContent {
+    fn from(value: &str) -> Self {
+        CellContent::Text(value.to_owned())
+    }
+}
+
+impl From<NaiveDate> for CellContent {
+    fn from(value: NaiveDate) -> Self {
+        CellContent::Date(value)
+    }
+}
+
+impl From<NaiveDateTime> for CellContent {
+    fn from(value: NaiveDateTime) -> Self {
+        CellContent::DateTime(value)
     }
 }
@@ -1,15 +1,48 @@
 use std::{
     borrow::Cow,
+    collections::HashSet,
     fmt::{Display, Write},
+    ops::{Range, RangeInclusive},
 };
 
 use pyo3::{
-    types::{PyDict, PyFloat, PyList, PyLong, PyString},
-    PyAny,
+    once_cell::GILOnceCell,
+    types::{PyDate, PyDict, PyFloat, PyList, PyLong, PyModule, PyString},
+    Py, PyAny, PyObject,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{cells::CellPosition, to_column_name, Spreadsheet};
+use crate::{
+    cells::{cell_content::CellContent, CellPosition},
+    to_column_name, units, Error, Spreadsheet,
+};
+
+const SEPERATORS: &str = " ()*-+/,.;[]%!";
+
+/// The kind of a token of a raw formula, see [`Formula::tokenize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormulaTokenKind {
+    Reference,
+    Operator,
+    Other,
+}
+
+/// A reference in the text of a formula and the cells it covers, see
+/// [`Formula::references`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormulaReference {
+    /// Where the reference is in the raw formula (without the leading `=`),
+    /// like the whole `A0:B3` of a range.
+    pub text_range: Range<usize>,
+    pub columns: RangeInclusive<usize>,
+    pub rows: RangeInclusive<usize>,
+}
+
+impl FormulaReference {
+    pub fn contains(&self, (column, row): (usize, usize)) -> bool {
+        self.columns.contains(&column) && self.rows.contains(&row)
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Formula {
@@ -21,15 +54,89 @@ pub struct Formula {
     /// occurence
     pub(super) references: Vec<CellReference>,
     pub(super) value: Value,
+    #[serde(skip)]
+    pub(super) compiled_code: CompiledCode,
+}
+
+/// The python code object compiled from [`Formula::parsed`]. It is reused
+/// between evaluations and has to be reset, whenever the parsed code changes.
+/// It is ignored when comparing formulas.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompiledCode(Option<PyObject>);
+
+impl PartialEq for CompiledCode {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub(crate) enum CellReference {
     Cell(CellPosition),
+    /// A cell with a `$` in front of its column, its row or both, like
+    /// `$A$0`. These parts stay the same, when the formula is copied.
+    Absolute(CellPosition, Anchor),
     Row(usize),
     Column(usize),
 }
 
+/// Which parts of an absolute cell reference are fixed.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct Anchor {
+    pub(crate) column: bool,
+    pub(crate) row: bool,
+}
+
+/// Parses a cell name like `B3`, where the column and the row can be made
+/// absolute with a `$` in front of them, like `$B$3`.
+fn parse_cell_name(name: &str) -> Option<((usize, usize), Anchor)> {
+    let (column_anchor, rest) = match name.strip_prefix('$') {
+        Some(rest) => (true, rest),
+        None => (false, name),
+    };
+    let (column, row) = rest.split_at(rest.find(|c: char| !c.is_ascii_alphabetic())?);
+    let (row_anchor, row) = match row.strip_prefix('$') {
+        Some(row) => (true, row),
+        None => (false, row),
+    };
+    let position = crate::cell_name_to_position(&format!("{column}{row}")).ok()?;
+    Some((
+        position,
+        Anchor {
+            column: column_anchor,
+            row: row_anchor,
+        },
+    ))
+}
+
+impl CellReference {
+    fn cell((x, y): (usize, usize), anchor: Anchor) -> Self {
+        if anchor == Anchor::default() {
+            CellReference::Cell(CellPosition(x, y))
+        } else {
+            CellReference::Absolute(CellPosition(x, y), anchor)
+        }
+    }
+
+    /// Returns the name of the reference, as it appears in
+    /// [`Formula::raw`].
+    fn name(&self) -> String {
+        let dollar = |absolute| if absolute { "$" } else { "" };
+        match self {
+            CellReference::Cell(c) => c.name(),
+            CellReference::Absolute(c, anchor) => format!(
+                "{}{}{}{}",
+                dollar(anchor.column),
+                to_column_name(c.0),
+                dollar(anchor.row),
+                c.1
+            ),
+            CellReference::Row(r) => r.to_string(),
+            CellReference::Column(c) => to_column_name(*c),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Default, Clone, Serialize, Deserialize)]
 pub(crate) enum Value {
     String(String),
@@ -38,6 +145,8 @@ pub(crate) enum Value {
     #[default]
     Empty,
     Error,
+    /// The formula is part of a cycle of references or depends on one.
+    Cycle,
 }
 
 impl Display for Value {
@@ -45,9 +154,10 @@ impl Display for Value {
         match self {
             Value::String(it) => write!(f, "{it}"),
             Value::Number(it) => write!(f, "{it}"),
-            Value::FloatNumber(it) => write!(f, "{it}"),
+            Value::FloatNumber(it) => write!(f, "{}", units::display_float(*it)),
             Value::Empty => write!(f, ""),
             Value::Error => write!(f, "#error"),
+            Value::Cycle => write!(f, "#cycle"),
         }
     }
 }
@@ -57,12 +167,18 @@ impl From<&PyAny> for Value {
         match it.downcast::<PyFloat>() {
             Ok(it) => Value::FloatNumber(it.value()),
             Err(_) => match it.downcast::<PyLong>() {
+                // Integers too large for an i64 lose some precision.
                 Ok(it) => match it.extract::<i64>() {
                     Ok(it) => Value::Number(it),
-                    Err(_) => Value::Error,
+                    Err(_) => it.extract().map_or(Value::Error, Value::FloatNumber),
                 },
                 Err(_) => match it.downcast::<PyString>() {
                     Ok(it) => Value::String(it.to_string()),
+                    // Dates are shown in the same format as date cells.
+                    Err(_) if it.downcast::<PyDate>().is_ok() => it
+                        .call_method1("isoformat", (" ",))
+                        .or_else(|_| it.call_method0("isoformat"))
+                        .map_or(Value::Error, |it| Value::String(it.to_string())),
                     Err(_) => Value::Error,
                 },
             },
@@ -70,18 +186,247 @@ impl From<&PyAny> for Value {
     }
 }
 
+/// Functions for ranges like `avg(A0:B3)`, which python does not have
+/// built in. Like in other spreadsheet applications they take any number of
+/// ranges and cells and ignore everything, which is not a number.
+const RANGE_FUNCTIONS: &str = r#"
+def _numbers(values):
+    for value in values:
+        if isinstance(value, (list, tuple)):
+            yield from _numbers(value)
+        elif isinstance(value, (int, float)) and not isinstance(value, bool):
+            yield value
+
+def avg(*values):
+    numbers = list(_numbers(values))
+    return sum(numbers) / len(numbers)
+
+def count(*values):
+    return len(list(_numbers(values)))
+"#;
+
+static RANGE_MODULE: GILOnceCell<Option<Py<PyModule>>> = GILOnceCell::new();
+
+/// Creates the globals every python expression has access to.
+fn default_globals(py: pyo3::Python<'_>) -> &PyDict {
+    use pyo3::ToPyObject;
+    let globals = PyDict::new(py);
+    let modules = ["random", "math"];
+    for module in modules {
+        if let Ok(py_module) = py.import(module) {
+            let _ = globals.set_item(module.to_object(py), py_module);
+        }
+    }
+    let range_module = RANGE_MODULE.get_or_init(py, || {
+        PyModule::from_code(py, RANGE_FUNCTIONS, "range_functions.py", "range_functions")
+            .ok()
+            .map(Into::into)
+    });
+    if let Some(range_module) = range_module {
+        for name in ["avg", "count"] {
+            if let Ok(function) = range_module.as_ref(py).getattr(name) {
+                let _ = globals.set_item(name, function);
+            }
+        }
+    }
+    globals
+}
+
+/// The `sheet` passed to scripts. Written cells are collected in `changes`
+/// and copied into the spreadsheet once the script finished. Cells are
+/// named like in formulas, e.g. `sheet["A0"]`.
+const SCRIPT_MODULE: &str = r#"
+import contextlib
+import io
+import re
+
+class Sheet:
+    def __init__(self, cells):
+        self._cells = cells
+        self.changes = {}
+
+    @property
+    def columns(self):
+        return max([len(row) for row in self._cells] + [column + 1 for column, _ in self.changes])
+
+    @property
+    def rows(self):
+        return max([len(self._cells)] + [row + 1 for _, row in self.changes])
+
+    def get(self, column, row):
+        if (column, row) in self.changes:
+            return self.changes[(column, row)]
+        if 0 <= row < len(self._cells) and 0 <= column < len(self._cells[row]):
+            return self._cells[row][column]
+        return None
+
+    def set(self, column, row, value):
+        if column < 0 or row < 0:
+            raise IndexError(f"cell ({column}, {row}) is outside of the sheet")
+        self.changes[(column, row)] = value
+
+    def __getitem__(self, cell):
+        return self.get(*_position(cell))
+
+    def __setitem__(self, cell, value):
+        self.set(*_position(cell), value)
+
+    def __iter__(self):
+        for row in range(self.rows):
+            yield [self.get(column, row) for column in range(self.columns)]
+
+def _position(cell):
+    if isinstance(cell, tuple):
+        return cell
+    match = re.fullmatch(r"([A-Za-z]+)([0-9]+)", cell)
+    if match is None:
+        raise KeyError(cell)
+    column = 0
+    for letter in match[1].upper():
+        column = column * 26 + ord(letter) - ord("A")
+    return column, int(match[2])
+
+def run(code, globals):
+    output = io.StringIO()
+    with contextlib.redirect_stdout(output):
+        exec(compile(code, "<script>", "exec"), globals)
+    return output.getvalue()
+"#;
+
+/// What a script printed and the cells it wrote, see [`run_script`].
+pub(crate) struct ScriptResult {
+    pub(crate) output: String,
+    pub(crate) changes: Vec<((usize, usize), Value)>,
+}
+
+/// Runs the python `code` with the spreadsheet bound to `sheet`. The
+/// spreadsheet is not changed, the written cells are returned instead.
+pub(crate) fn run_script(spreadsheet: &Spreadsheet, code: &str) -> Result<ScriptResult, Error> {
+    pyo3::prepare_freethreaded_python();
+    pyo3::Python::with_gil(|py| {
+        let to_error = |err: pyo3::PyErr| Error::Script(err.to_string());
+        let module =
+            PyModule::from_code(py, SCRIPT_MODULE, "sheet.py", "sheet").map_err(to_error)?;
+        let cells = PyList::empty(py);
+        for row in spreadsheet.as_rows() {
+            let values = PyList::empty(py);
+            for cell in row {
+                let value = cell.content.try_to_object(py).unwrap_or_else(|| py.None());
+                values.append(value).map_err(to_error)?;
+            }
+            cells.append(values).map_err(to_error)?;
+        }
+        let sheet = module
+            .getattr("Sheet")
+            .and_then(|sheet| sheet.call1((cells,)))
+            .map_err(to_error)?;
+        let globals = default_globals(py);
+        globals.set_item("sheet", sheet).map_err(to_error)?;
+        let output = module
+            .getattr("run")
+            .and_then(|run| run.call1((code, globals)))
+            .and_then(|output| output.extract())
+            .map_err(to_error)?;
+        let changes: &PyDict = sheet
+            .getattr("changes")
+            .and_then(|changes| Ok(changes.downcast()?))
+            .map_err(to_error)?;
+        let mut result = Vec::new();
+        for (position, value) in changes {
+            let position: (usize, usize) = position.extract().map_err(to_error)?;
+            let converted = if value.is_none() {
+                Value::Empty
+            } else {
+                Value::from(value)
+            };
+            if matches!(converted, Value::Error | Value::Cycle) {
+                return Err(Error::Script(format!(
+                    "Unsupported value {value} of type {} for cell {}",
+                    value.get_type(),
+                    crate::cell_position_to_name(position)
+                )));
+            }
+            result.push((position, converted));
+        }
+        Ok(ScriptResult {
+            output,
+            changes: result,
+        })
+    })
+}
+
+/// A python expression, which is evaluated with the value of a single cell
+/// bound to `x`.
+pub(crate) struct Expression {
+    code: PyObject,
+}
+
+impl Expression {
+    pub(crate) fn compile(expression: &str) -> Result<Self, Error> {
+        pyo3::prepare_freethreaded_python();
+        pyo3::Python::with_gil(|py| {
+            let code = py
+                .import("builtins")
+                .and_then(|b| b.getattr("compile"))
+                .and_then(|compile| compile.call1((expression, "<expression>", "eval")))
+                .map_err(|err| Error::Formula(err.to_string()))?;
+            Ok(Self { code: code.into() })
+        })
+    }
+
+    pub(crate) fn evaluate(&self, x: &CellContent) -> Result<CellContent, Error> {
+        pyo3::Python::with_gil(|py| {
+            let globals = default_globals(py);
+            if let Some(x) = x.try_to_object(py) {
+                let _ = globals.set_item("x", x);
+            }
+            let result = py
+                .import("builtins")
+                .and_then(|b| b.getattr("eval"))
+                .and_then(|eval| eval.call1((self.code.clone_ref(py), globals)))
+                .map_err(|err| Error::Formula(err.to_string()))?;
+            if result.is_none() {
+                return Ok(CellContent::Empty);
+            }
+            match Value::from(result) {
+                Value::String(it) => Ok(CellContent::Text(it)),
+                Value::Number(it) => Ok(CellContent::Number(it)),
+                Value::FloatNumber(it) => Ok(CellContent::FloatNumber(it, 0)),
+                Value::Empty => Ok(CellContent::Empty),
+                Value::Error | Value::Cycle => Err(Error::Formula(format!(
+                    "Unsupported result {result} of type {}",
+                    result.get_type()
+                ))),
+            }
+        })
+    }
+}
+
 impl Formula {
     pub(crate) fn value(&self) -> &Value {
         &self.value
     }
 
+    pub(crate) fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub(crate) fn set_position(&mut self, position: CellPosition) {
+        self.position = position;
+    }
+
     pub(super) fn push_char(&mut self, ch: char) {
         self.raw.push(ch);
+        self.compiled_code = CompiledCode::default();
         todo!("Update referenced. Honestly, this code path should probably not be used at all..");
     }
 
     pub(super) fn is_error(&self) -> bool {
-        self.value == Value::Error
+        matches!(self.value, Value::Error | Value::Cycle)
+    }
+
+    pub(crate) fn mark_as_cycle(&mut self) {
+        self.value = Value::Cycle;
     }
 
     pub(super) fn evaluate(&mut self, spreadsheet: &Spreadsheet) {
@@ -91,12 +436,7 @@ impl Formula {
             self.value = if self.parsed.is_empty() {
                 Value::Empty
             } else {
-                let globals = PyDict::new(py);
-                let modules = ["random", "math"];
-                for module in modules {
-                    let py_module = py.import(module).unwrap();
-                    globals.set_item(module.to_object(py), py_module).unwrap();
-                }
+                let globals = default_globals(py);
                 for cell in &spreadsheet.cells {
                     if cell.position == self.position {
                         continue;
@@ -126,7 +466,18 @@ impl Formula {
                         let _ = globals.set_item(name, list);
                     }
                 }
-                match py.eval(&self.parsed, Some(globals), None) {
+                let value = py.import("builtins").and_then(|builtins| {
+                    let code = match &self.compiled_code.0 {
+                        Some(code) => code.clone_ref(py),
+                        None => builtins
+                            .getattr("compile")?
+                            .call1((&self.parsed, "<formula>", "eval"))?
+                            .into(),
+                    };
+                    self.compiled_code = CompiledCode(Some(code.clone_ref(py)));
+                    builtins.getattr("eval")?.call1((code, globals))
+                });
+                match value {
                     Ok(it) => it.into(),
                     Err(_) => Value::Error,
                 }
@@ -134,18 +485,18 @@ impl Formula {
         })
     }
 
-    pub(super) fn long_display(&self) -> Cow<str> {
+    pub(super) fn long_display(&self) -> Cow<'_, str> {
         format!("={}", self.raw).into()
     }
 
-    pub(super) fn display(&self) -> Cow<str> {
+    pub(super) fn display(&self) -> Cow<'_, str> {
         self.value.to_string().into()
     }
 
     pub(crate) fn is_right_aligned(&self) -> bool {
         matches!(
             self.value,
-            Value::Number(_) | Value::FloatNumber(_) | Value::Error
+            Value::Number(_) | Value::FloatNumber(_) | Value::Error | Value::Cycle
         )
     }
 
@@ -156,174 +507,339 @@ impl Formula {
             parsed: String::new(),
             references: Vec::new(),
             value: Value::Empty,
+            compiled_code: CompiledCode::default(),
         }
     }
 
     pub(crate) fn moved_to(&self, position: CellPosition, size: (usize, usize)) -> Formula {
         let (x_offset, y_offset) = position - self.position;
-        let mut references = self.references.clone();
+        let shift = |value: &mut usize, offset: isize| *value = (*value as isize + offset) as usize;
+        let mut result = Formula {
+            position,
+            value: Value::Empty,
+            ..self.clone()
+        };
+        result.map_references(size, |r| match r {
+            CellReference::Cell(c) => {
+                shift(&mut c.0, x_offset);
+                shift(&mut c.1, y_offset);
+            }
+            CellReference::Absolute(c, anchor) => {
+                if !anchor.column {
+                    shift(&mut c.0, x_offset);
+                }
+                if !anchor.row {
+                    shift(&mut c.1, y_offset);
+                }
+            }
+            CellReference::Row(r) => shift(r, y_offset),
+            CellReference::Column(c) => shift(c, x_offset),
+        });
+        result
+    }
+
+    /// Changes every reference of this formula with `map` and rewrites
+    /// [`Formula::raw`] accordingly. Only the text of changed references is
+    /// replaced, so everything else keeps its spelling.
+    pub(crate) fn map_references(
+        &mut self,
+        size: (usize, usize),
+        map: impl Fn(&mut CellReference),
+    ) {
+        let parser = RawParser::parse(&self.raw, size);
         let mut raw = self.raw.clone();
+        // Replacing from the back keeps the earlier spans valid.
+        for (mut reference, span) in parser.references.into_iter().zip(parser.spans).rev() {
+            let old = reference.clone();
+            map(&mut reference);
+            if reference != old {
+                raw.replace_range(span, &reference.name());
+            }
+        }
+        let (parsed, references) = Self::parse_raw(&raw, size);
+        self.raw = raw;
+        self.parsed = parsed;
+        self.references = references;
+        self.compiled_code = CompiledCode::default();
+    }
 
-        let mut cursor = 0;
-        for r in references.iter_mut() {
-            let (old, new) = match r {
-                CellReference::Cell(c) => {
-                    let old = c.name();
-                    c.0 = (c.0 as isize + x_offset) as usize;
-                    c.1 = (c.1 as isize + y_offset) as usize;
-                    let replace_with = c.name();
-                    (old, replace_with)
-                }
-                CellReference::Row(r) => {
-                    let old = r.to_string();
-                    *r = (*r as isize + y_offset) as usize;
-                    let replace_with = r.to_string();
-                    (old, replace_with)
+    /// Splits a raw formula into tokens the same way [`Formula::parse_raw`]
+    /// does, so that references can be highlighted while typing.
+    pub(crate) fn tokenize(raw: &str, size: (usize, usize)) -> Vec<(FormulaTokenKind, &str)> {
+        // Around a colon a plain number is part of a row range. Words after
+        // a dot are attributes like the `e` in `math.e`.
+        let word_kind = |word: &str, next_to_colon: bool| {
+            let start = word.as_ptr() as usize - raw.as_ptr() as usize;
+            if raw[..start].ends_with('.') {
+                FormulaTokenKind::Other
+            } else if parse_cell_name(word).is_some()
+                || crate::column_name_to_index(word).is_ok_and(|c| c < size.0)
+                || (next_to_colon && word.parse::<usize>().is_ok())
+            {
+                FormulaTokenKind::Reference
+            } else {
+                FormulaTokenKind::Other
+            }
+        };
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        let mut after_colon = false;
+        for (index, ch) in raw.char_indices() {
+            if ch != ':' && !SEPERATORS.contains(ch) {
+                continue;
+            }
+            let word = &raw[start..index];
+            if !word.is_empty() {
+                tokens.push((word_kind(word, after_colon || ch == ':'), word));
+            }
+            let kind = if ch.is_whitespace() {
+                FormulaTokenKind::Other
+            } else {
+                FormulaTokenKind::Operator
+            };
+            tokens.push((kind, &raw[index..index + ch.len_utf8()]));
+            after_colon = ch == ':';
+            start = index + ch.len_utf8();
+        }
+        if start < raw.len() {
+            tokens.push((word_kind(&raw[start..], after_colon), &raw[start..]));
+        }
+        tokens
+    }
+
+    /// Returns the positions of all cells the formula reads. Ranges and whole
+    /// columns are expanded to the cells they cover.
+    pub(crate) fn referenced_positions(&self, size: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        let mut seen = HashSet::new();
+        for reference in Self::find_references(&self.raw, size) {
+            for row in reference.rows {
+                for column in reference.columns.clone() {
+                    if seen.insert((column, row)) {
+                        result.push((column, row));
+                    }
                 }
-                CellReference::Column(c) => {
-                    let old = crate::to_column_name(*c);
-                    *c = (*c as isize + x_offset) as usize;
-                    let replace_with = crate::to_column_name(*c);
-                    (old, replace_with)
+            }
+        }
+        result
+    }
+
+    /// Returns the references of the formula in the order they appear in
+    /// its text. The areas are clamped to the spreadsheet of size `size`.
+    pub fn references(&self, size: (usize, usize)) -> Vec<FormulaReference> {
+        Self::find_references(&self.raw, size)
+    }
+
+    pub(crate) fn find_references(raw: &str, size: (usize, usize)) -> Vec<FormulaReference> {
+        let tokens: Vec<_> = Self::tokenize(raw, size)
+            .into_iter()
+            .filter(|(_, token)| !token.trim().is_empty())
+            .collect();
+        // The tokens are slices of `raw`.
+        let offset = |token: &str| token.as_ptr() as usize - raw.as_ptr() as usize;
+        let area = |first: &str, last: Option<&str>| {
+            let first_cell = parse_cell_name(first).map(|(position, _)| position);
+            let last_cell = last.and_then(parse_cell_name).map(|(position, _)| position);
+            let last_row = last.and_then(|l| l.parse::<usize>().ok());
+            match (first_cell, last_cell, last_row) {
+                (Some(first), Some(last), _) => Some((first.0..=last.0, first.1..=last.1)),
+                (Some(first), None, Some(row)) => Some((first.0..=first.0, first.1..=row)),
+                (Some(cell), None, None) => Some((cell.0..=cell.0, cell.1..=cell.1)),
+                (None, _, Some(row)) => first
+                    .parse::<usize>()
+                    .ok()
+                    .map(|first| (0..=size.0.saturating_sub(1), first..=row)),
+                (None, _, None) => crate::column_name_to_index(first)
+                    .ok()
+                    .map(|column| (column..=column, 0..=size.1.saturating_sub(1))),
+            }
+        };
+        let mut result = Vec::new();
+        let mut index = 0;
+        while index < tokens.len() {
+            let (kind, token) = tokens[index];
+            index += 1;
+            if kind != FormulaTokenKind::Reference {
+                continue;
+            }
+            let last = match tokens.get(index..index + 2) {
+                Some([(_, ":"), (FormulaTokenKind::Reference, last)]) => {
+                    index += 2;
+                    Some(*last)
                 }
+                _ => None,
             };
-            cursor += raw[cursor..].find(&old).unwrap();
-            raw.replace_range(cursor..cursor + old.len(), &new);
-        }
-        let (parsed, parsed_referenced) = Self::parse_raw(&raw, size);
-        assert_eq!(
-            references, parsed_referenced,
-            "raw = '{raw}', parsed = '{parsed}', self.raw = {}, self.parsed = {}",
-            self.raw, self.parsed
-        );
-
-        Formula {
-            position,
-            parsed,
-            raw,
-            references,
-            value: Value::Empty,
+            let Some((columns, rows)) = area(token, last) else {
+                continue;
+            };
+            let columns = *columns.start()..=(*columns.end()).min(size.0.saturating_sub(1));
+            let rows = *rows.start()..=(*rows.end()).min(size.1.saturating_sub(1));
+            if columns.is_empty() || rows.is_empty() {
+                continue;
+            }
+            let end = last.unwrap_or(token);
+            result.push(FormulaReference {
+                text_range: offset(token)..offset(end) + end.len(),
+                columns,
+                rows,
+            });
         }
+        result
     }
 
     pub(crate) fn parse_raw(raw: &str, size: (usize, usize)) -> (String, Vec<CellReference>) {
-        const SEPERATORS: &str = " ()*-+/,.;[]%!";
-        let mut variable_buffer = String::with_capacity(raw.len());
-        let mut parsed = String::with_capacity(raw.len());
-        let mut references = Vec::new();
-        let mut last_position = None;
+        let parser = RawParser::parse(raw, size);
+        (parser.parsed, parser.references)
+    }
+}
+
+/// The start of a range before its colon, like `A0:` or `1:`.
+enum RangeStart {
+    Cell((usize, usize), Anchor),
+    Row(usize),
+}
 
-        for ch in raw.chars() {
+/// Translates a raw formula into python code, see [`Formula::parse_raw`].
+struct RawParser {
+    size: (usize, usize),
+    parsed: String,
+    references: Vec<CellReference>,
+    /// Where each of the references is in the raw formula.
+    spans: Vec<Range<usize>>,
+    range_start: Option<(RangeStart, Range<usize>)>,
+}
+
+impl RawParser {
+    fn parse(raw: &str, size: (usize, usize)) -> Self {
+        let mut parser = Self {
+            size,
+            parsed: String::with_capacity(raw.len()),
+            references: Vec::new(),
+            spans: Vec::new(),
+            range_start: None,
+        };
+        let mut start = 0;
+        for (index, ch) in raw.char_indices() {
             if ch == ':' {
-                last_position = crate::cell_name_to_position(&variable_buffer).ok();
-                variable_buffer.clear();
+                parser.colon(&raw[start..index], start..index);
+                start = index + 1;
             } else if SEPERATORS.contains(ch) {
-                if let Some(last_position) = last_position.take() {
-                    references.push(CellReference::Cell(CellPosition(
-                        last_position.0,
-                        last_position.1,
-                    )));
-                    if let Ok(new_position) = crate::cell_name_to_position(&variable_buffer) {
-                        references.push(CellReference::Cell(CellPosition(
-                            new_position.0,
-                            new_position.1,
-                        )));
-                        let mut python_code = String::new();
-                        for x in last_position.0..=new_position.0 {
-                            if !python_code.is_empty() {
-                                python_code.push_str(" + ");
-                            }
-                            write!(
-                                python_code,
-                                "{}[{}:{}]",
-                                crate::to_column_name(x),
-                                last_position.1,
-                                new_position.1 + 1,
-                            )
-                            .unwrap();
-                        }
-                        parsed.push_str(&python_code);
-                    } else if let Ok(row) = variable_buffer.parse::<usize>() {
-                        references.push(CellReference::Row(row));
-                        let python_code = format!(
-                            "{}[{}:{}]",
-                            crate::to_column_name(last_position.0),
-                            last_position.1,
-                            row + 1,
-                        );
-                        parsed.push_str(&python_code);
-                    } else {
-                        dbg!(ch, last_position, parsed, variable_buffer);
-                        todo!("This should probaly not happen. This would mean you had a valid first cell, but after the column your cell becomes invalid...");
-                    }
-                } else {
-                    if let Ok(cell) = crate::cell_name_to_position(&variable_buffer) {
-                        references.push(CellReference::Cell(CellPosition(cell.0, cell.1)));
-                    } else if let Ok(column) = crate::column_name_to_index(&variable_buffer) {
-                        if column < size.0 {
-                            references.push(CellReference::Column(column));
-                        }
-                    }
-                    parsed.push_str(&variable_buffer);
+                parser.word(&raw[start..index], start..index);
+                if !parser.parsed.is_empty() || !ch.is_whitespace() {
+                    parser.parsed.push(ch);
                 }
-                variable_buffer.clear();
-                if !parsed.is_empty() || !ch.is_whitespace() {
-                    parsed.push(ch);
-                }
-            } else {
-                variable_buffer.push(ch);
+                start = index + ch.len_utf8();
             }
         }
-        if let Some(last_position) = last_position.take() {
-            references.push(CellReference::Cell(CellPosition(
-                last_position.0,
-                last_position.1,
-            )));
-            if let Ok(new_position) = crate::cell_name_to_position(&variable_buffer) {
-                references.push(CellReference::Cell(CellPosition(
-                    new_position.0,
-                    new_position.1,
-                )));
-                let mut python_code = String::new();
-                for x in last_position.0..=new_position.0 {
-                    if !python_code.is_empty() {
-                        python_code.push_str(" + ");
+        parser.word(&raw[start..], start..raw.len());
+        parser
+    }
+
+    fn push_reference(&mut self, reference: CellReference, span: Range<usize>) {
+        self.references.push(reference);
+        self.spans.push(span);
+    }
+
+    /// Remembers the cell or row before a colon as the start of a range.
+    fn colon(&mut self, word: &str, span: Range<usize>) {
+        self.range_start = match parse_cell_name(word) {
+            Some((position, anchor)) => Some((RangeStart::Cell(position, anchor), span)),
+            None => word.parse().ok().map(|row| (RangeStart::Row(row), span)),
+        };
+    }
+
+    /// Translates the word before a separator, which ends a range, if one
+    /// was started.
+    fn word(&mut self, word: &str, span: Range<usize>) {
+        match self.range_start.take() {
+            Some((RangeStart::Row(first_row), first_span)) => {
+                self.push_row_range(first_row, first_span, word, span)
+            }
+            Some((RangeStart::Cell(first, first_anchor), first_span)) => {
+                self.push_reference(CellReference::cell(first, first_anchor), first_span);
+                // Ranges into columns, which do not exist, are left to python
+                // as errors instead of listing every column up to them.
+                if let Some((last, last_anchor)) =
+                    parse_cell_name(word).filter(|(position, _)| position.0 < self.size.0)
+                {
+                    self.push_reference(CellReference::cell(last, last_anchor), span);
+                    let mut python_code = String::new();
+                    for x in first.0..=last.0 {
+                        if !python_code.is_empty() {
+                            python_code.push_str(" + ");
+                        }
+                        write!(
+                            python_code,
+                            "{}[{}:{}]",
+                            crate::to_column_name(x),
+                            first.1,
+                            last.1 + 1,
+                        )
+                        .unwrap();
                     }
+                    self.parsed.push_str(&python_code);
+                } else if let Ok(row) = word.parse::<usize>() {
+                    self.push_reference(CellReference::Row(row), span);
                     write!(
-                        python_code,
+                        self.parsed,
                         "{}[{}:{}]",
-                        crate::to_column_name(x),
-                        last_position.1,
-                        new_position.1 + 1,
+                        crate::to_column_name(first.0),
+                        first.1,
+                        row + 1,
+                    )
+                    .unwrap();
+                } else {
+                    // Not a valid range, python will report the syntax error.
+                    write!(
+                        self.parsed,
+                        "{}:{word}",
+                        crate::cell_position_to_name(first)
                     )
                     .unwrap();
                 }
-                parsed.push_str(&python_code);
-            } else if let Ok(row) = variable_buffer.parse::<usize>() {
-                references.push(CellReference::Row(row));
-                let python_code = format!(
-                    "{}[{}:{}]",
-                    crate::to_column_name(last_position.0),
-                    last_position.1,
-                    row + 1,
-                );
-                parsed.push_str(&python_code);
-            } else {
-                dbg!(last_position, parsed, variable_buffer);
-                todo!("This should probaly not happen. This would mean you had a valid first cell, but after the column your cell becomes invalid...");
             }
-        } else {
-            if let Ok(cell) = crate::cell_name_to_position(&variable_buffer) {
-                references.push(CellReference::Cell(CellPosition(cell.0, cell.1)));
-            } else if let Ok(column) = crate::column_name_to_index(&variable_buffer) {
-                if column < size.0 {
-                    references.push(CellReference::Column(column));
+            // Attributes like the `e` in `math.e` are no references.
+            None if self.parsed.ends_with('.') => self.parsed.push_str(word),
+            None => {
+                if let Some((cell, anchor)) = parse_cell_name(word) {
+                    self.push_reference(CellReference::cell(cell, anchor), span);
+                    // Python only knows the name without the `$`.
+                    self.parsed.push_str(&crate::cell_position_to_name(cell));
+                } else {
+                    if let Ok(column) = crate::column_name_to_index(word) {
+                        if column < self.size.0 {
+                            self.push_reference(CellReference::Column(column), span);
+                        }
+                    }
+                    self.parsed.push_str(word);
                 }
             }
-            parsed.push_str(&variable_buffer);
         }
-        variable_buffer.clear();
+    }
 
-        (parsed, references)
+    /// Translates the row range `first_row:last_row` into a python list of
+    /// the cells of all columns in these rows.
+    fn push_row_range(
+        &mut self,
+        first_row: usize,
+        first_span: Range<usize>,
+        last_row: &str,
+        last_span: Range<usize>,
+    ) {
+        match last_row.parse::<usize>() {
+            Ok(last_row) => {
+                self.push_reference(CellReference::Row(first_row), first_span);
+                self.push_reference(CellReference::Row(last_row), last_span);
+                let columns: Vec<_> = (0..self.size.0).map(crate::to_column_name).collect();
+                write!(
+                    self.parsed,
+                    "[cell for col in [{}] for cell in col[{first_row}:{}]]",
+                    columns.join(", "),
+                    last_row + 1,
+                )
+                .unwrap();
+            }
+            Err(_) => write!(self.parsed, "{first_row}:{last_row}").unwrap(),
+        }
     }
 }
@@ -0,0 +1,97 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CellAlignment, CellContent};
+
+/// The kind of values a column contains, see
+/// [`crate::Spreadsheet::column_type`]. Empty cells and formulas, which were
+/// not evaluated yet, fit every type.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnType {
+    /// The column has no type and every value fits it.
+    #[default]
+    Any,
+    Text,
+    Integer,
+    /// Integers and floating point numbers.
+    Number,
+    Date,
+    DateTime,
+}
+
+impl Display for ColumnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ColumnType::Any => "any",
+                ColumnType::Text => "text",
+                ColumnType::Integer => "integer",
+                ColumnType::Number => "number",
+                ColumnType::Date => "date",
+                ColumnType::DateTime => "datetime",
+            }
+        )
+    }
+}
+
+impl ColumnType {
+    /// Returns `true` if `content` is a value of this type. For formulas
+    /// their last evaluated value is checked.
+    pub fn accepts(self, content: &CellContent) -> bool {
+        match content.value_type() {
+            None => true,
+            Some(value_type) => {
+                self == ColumnType::Any
+                    || self == value_type
+                    || (self == ColumnType::Number && value_type == ColumnType::Integer)
+            }
+        }
+    }
+
+    /// Numbers and dates are aligned right and text is aligned left. Columns
+    /// without a type leave the alignment to the cells.
+    pub(crate) fn alignment(self) -> Option<CellAlignment> {
+        match self {
+            ColumnType::Any => None,
+            ColumnType::Text => Some(CellAlignment::Left),
+            ColumnType::Integer | ColumnType::Number | ColumnType::Date | ColumnType::DateTime => {
+                Some(CellAlignment::Right)
+            }
+        }
+    }
+
+    /// Returns the type most of the values have. Integers and floating point
+    /// numbers are counted together, so a column of both is a
+    /// [`ColumnType::Number`] column. Text wins ties.
+    pub(crate) fn infer<'a>(contents: impl IntoIterator<Item = &'a CellContent>) -> Self {
+        let (mut integers, mut floats, mut dates, mut date_times, mut texts) = (0, 0, 0, 0, 0);
+        for content in contents {
+            match content.value_type() {
+                Some(ColumnType::Integer) => integers += 1,
+                Some(ColumnType::Number) => floats += 1,
+                Some(ColumnType::Date) => dates += 1,
+                Some(ColumnType::DateTime) => date_times += 1,
+                Some(ColumnType::Text) => texts += 1,
+                Some(ColumnType::Any) | None => {}
+            }
+        }
+        let number = if floats == 0 {
+            ColumnType::Integer
+        } else {
+            ColumnType::Number
+        };
+        [
+            (number, integers + floats),
+            (ColumnType::Date, dates),
+            (ColumnType::DateTime, date_times),
+            (ColumnType::Text, texts),
+        ]
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .max_by_key(|&(_, count)| count)
+        .map_or(ColumnType::Any, |(column_type, _)| column_type)
+    }
+}
@@ -1,4 +1,6 @@
-use std::{cmp::Ordering, str::FromStr};
+use std::{borrow::Cow, cmp::Ordering, io::BufRead, str::FromStr};
+
+use crate::Error;
 
 #[derive(Debug, Clone)]
 pub struct CsvFile {
@@ -11,8 +13,91 @@ pub struct CsvFile {
     pub seperator: char,
 }
 
+impl CsvFile {
+    pub fn rows(&self) -> impl Iterator<Item = &[String]> {
+        self.cells.chunks(self.width)
+    }
+
+    /// Parses `s` with the given seperator instead of detecting it.
+    pub fn parse_with_seperator(s: &str, seperator: char) -> Result<Self, CsvParseError> {
+        let (width, height) = parse_size_of_csv(s, seperator)?;
+        parse_csv(s, seperator, width, height)
+    }
+}
+
 const KNOWN_SEPERATORS: &str = ",;\t";
 
+/// Escapes a cell as described in RFC 4180. Cells containing the seperator,
+/// quotes or line breaks are wrapped in quotes and quotes are doubled.
+pub fn escape(cell: &str, seperator: char) -> Cow<'_, str> {
+    if cell.contains([seperator, '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\"")).into()
+    } else {
+        cell.into()
+    }
+}
+
+/// When cells are wrapped in quotes while writing csv.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuotePolicy {
+    /// Only cells containing the separator, quotes or line breaks.
+    #[default]
+    Necessary,
+    Always,
+    /// Cells are written as they are, even if the csv can not be read again.
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Controls how a spreadsheet is written as csv, see
+/// [`crate::Spreadsheet::serialize_csv_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvWriteOptions {
+    pub separator: char,
+    pub quote: QuotePolicy,
+    pub line_ending: LineEnding,
+    /// Writes every column of the spreadsheet instead of stopping at the
+    /// last column with content.
+    pub trailing_empty_columns: bool,
+}
+
+impl Default for CsvWriteOptions {
+    fn default() -> Self {
+        Self {
+            separator: ',',
+            quote: QuotePolicy::default(),
+            line_ending: LineEnding::default(),
+            trailing_empty_columns: false,
+        }
+    }
+}
+
+impl CsvWriteOptions {
+    /// Escapes `cell` according to the quote policy.
+    pub fn escape<'a>(&self, cell: &'a str) -> Cow<'a, str> {
+        match self.quote {
+            QuotePolicy::Necessary => escape(cell, self.separator),
+            QuotePolicy::Always => format!("\"{}\"", cell.replace('"', "\"\"")).into(),
+            QuotePolicy::Never => cell.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum CsvParseError {
     NoSuccessfullParse(Box<CsvParseError>),
@@ -25,29 +110,108 @@ impl FromStr for CsvFile {
     type Err = CsvParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut size: Vec<Result<(char, (usize, usize)), _>> = KNOWN_SEPERATORS
-            .chars()
-            .map(|sep| parse_size_of_csv(s, sep).map(|s| (sep, s)))
-            .collect();
-        size.sort_unstable_by(|a, b| match (a, b) {
-            (Ok(a), Ok(b)) => b.1.cmp(&a.1),
-            (Ok(_), Err(_)) => Ordering::Less,
-            (Err(_), Ok(_)) => Ordering::Greater,
-            (Err(_), Err(_)) => Ordering::Equal,
-        });
-        let ((width, height), seperator) = if let Ok((seperator, size)) = size[0] {
-            (size, seperator)
-        } else {
-            // We know that the first one is an error, since otherwise we would
-            // have an valid index found.
-            return Err(CsvParseError::NoSuccessfullParse(Box::new(
-                size[0].as_ref().unwrap_err().clone(),
-            )));
-        };
+        let (seperator, (width, height)) = detect_seperator(s)?;
         parse_csv(s, seperator, width, height)
     }
 }
 
+/// Finds the known seperator, which splits `s` into the most columns.
+/// Returns it with the size of the csv.
+fn detect_seperator(s: &str) -> Result<(char, (usize, usize)), CsvParseError> {
+    let mut size: Vec<Result<(char, (usize, usize)), _>> = KNOWN_SEPERATORS
+        .chars()
+        .map(|sep| parse_size_of_csv(s, sep).map(|s| (sep, s)))
+        .collect();
+    size.sort_unstable_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => b.1.cmp(&a.1),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => Ordering::Equal,
+    });
+    match &size[0] {
+        Ok(it) => Ok(*it),
+        // We know that the first one is an error, since otherwise we would
+        // have an valid index found.
+        Err(err) => Err(CsvParseError::NoSuccessfullParse(Box::new(err.clone()))),
+    }
+}
+
+/// Reads the rows of a csv one after another, so the whole file never has to
+/// be in memory. Like [`CsvFile`], empty lines are skipped.
+pub struct CsvRowReader<R> {
+    reader: R,
+    seperator: char,
+    /// The lines of the current row, which can be more than one, if a quoted
+    /// cell contains line breaks.
+    record: String,
+    /// The number of bytes read from `reader`.
+    position: u64,
+}
+
+impl<R: BufRead> CsvRowReader<R> {
+    pub fn new(reader: R, seperator: char) -> Self {
+        Self {
+            reader,
+            seperator,
+            record: String::new(),
+            position: 0,
+        }
+    }
+
+    /// Detects the seperator from the first lines of `reader`, which are not
+    /// read again. Falls back to a comma, if none of the known seperators
+    /// works.
+    pub fn detect_seperator(reader: &mut R) -> Result<char, Error> {
+        let mut sample = String::new();
+        for _ in 0..100 {
+            if reader.read_line(&mut sample)? == 0 {
+                break;
+            }
+        }
+        Ok(detect_seperator(&sample).map_or(',', |(seperator, _)| seperator))
+    }
+
+    pub fn seperator(&self) -> char {
+        self.seperator
+    }
+
+    /// The number of bytes read so far. Between rows, this is the offset of
+    /// the next row from where the reader started.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<R: BufRead> Iterator for CsvRowReader<R> {
+    type Item = Result<Vec<String>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let read = match self.reader.read_line(&mut self.record) {
+                Ok(read) => read,
+                Err(err) => return Some(Err(err.into())),
+            };
+            self.position += read as u64;
+            match parse_rows(&self.record, self.seperator) {
+                // The line break is part of a quoted cell.
+                Err(CsvParseError::UnfinishedEscaping) if read != 0 => continue,
+                Err(err) => {
+                    self.record.clear();
+                    return Some(Err(err.into()));
+                }
+                Ok(rows) => {
+                    self.record.clear();
+                    if let Some(row) = rows.into_iter().next() {
+                        return Some(Ok(row));
+                    } else if read == 0 {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum CsvParseState {
     NewCell,
@@ -56,55 +220,77 @@ enum CsvParseState {
     InCellEndEscape,
 }
 
-fn parse_size_of_csv(s: &str, sep: char) -> Result<(usize, usize), CsvParseError> {
-    let mut width = 0;
-    let mut height = 0;
-
+/// Splits the csv into rows of cells. Line breaks inside of quoted cells are
+/// part of the cell and empty lines are skipped.
+fn parse_rows(s: &str, seperator: char) -> Result<Vec<Vec<String>>, CsvParseError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut current_cell = String::new();
+    let mut line_is_empty = true;
     let mut state = CsvParseState::NewCell;
-
-    for line in s.lines() {
-        let mut current_width = !line.is_empty() as _;
-        for ch in line.chars() {
-            match ch {
-                '"' if state != CsvParseState::InCell => {
-                    // This handles starting and ending quotes and also double
-                    // quotes in the middle of the escaped text. Normal quotes
-                    // in a cell are not handled here. If a escaped text is not
-                    // ended at the end this functions returns a
-                    // CsvParseError::InvalidEscaping in the next iteration
-                    // of this loop.
-                    state = match state {
-                        CsvParseState::InCellEndEscape | CsvParseState::NewCell => {
-                            CsvParseState::InCellEscaped
-                        }
-                        CsvParseState::InCell => {
-                            unreachable!("The if guard should make this impossible!")
-                        }
-                        CsvParseState::InCellEscaped => CsvParseState::InCellEndEscape,
-                    };
-                }
-                seperator if seperator == sep && state != CsvParseState::InCellEscaped => {
-                    current_width += 1;
-                    state = CsvParseState::NewCell;
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\r' if state != CsvParseState::InCellEscaped && chars.peek() == Some(&'\n') => {
+                continue;
+            }
+            '\n' if state != CsvParseState::InCellEscaped => {
+                if !line_is_empty {
+                    row.push(std::mem::take(&mut current_cell));
+                    rows.push(std::mem::take(&mut row));
                 }
-                _ => match state {
+                line_is_empty = true;
+                state = CsvParseState::NewCell;
+                continue;
+            }
+            '"' if state != CsvParseState::InCell => {
+                // This handles starting and ending quotes and also double
+                // quotes in the middle of the escaped text. Normal quotes
+                // in a cell are not handled here. If a escaped text is not
+                // ended at the end this functions returns a
+                // CsvParseError::InvalidEscaping in the next iteration
+                // of this loop.
+                state = match state {
+                    CsvParseState::InCellEndEscape => {
+                        current_cell.push('"');
+                        CsvParseState::InCellEscaped
+                    }
+                    CsvParseState::NewCell => CsvParseState::InCellEscaped,
+                    CsvParseState::InCell => {
+                        unreachable!("The if guard should make this impossible!")
+                    }
+                    CsvParseState::InCellEscaped => CsvParseState::InCellEndEscape,
+                };
+            }
+            sep if seperator == sep && state != CsvParseState::InCellEscaped => {
+                row.push(std::mem::take(&mut current_cell));
+                state = CsvParseState::NewCell;
+            }
+            default => {
+                current_cell.push(default);
+                match state {
                     CsvParseState::NewCell => state = CsvParseState::InCell,
                     CsvParseState::InCell | CsvParseState::InCellEscaped => {}
                     CsvParseState::InCellEndEscape => return Err(CsvParseError::InvalidEscaping),
-                },
-            }
-            match state {
-                CsvParseState::NewCell => {}
-                CsvParseState::InCell => {}
-                CsvParseState::InCellEndEscape => {}
-                CsvParseState::InCellEscaped => return Err(CsvParseError::UnfinishedEscaping),
+                }
             }
         }
-        if width < current_width {
-            width = current_width;
-        }
-        height += (current_width > 0) as usize;
+        line_is_empty = false;
     }
+    if state == CsvParseState::InCellEscaped {
+        return Err(CsvParseError::UnfinishedEscaping);
+    }
+    if !line_is_empty {
+        row.push(current_cell);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_size_of_csv(s: &str, sep: char) -> Result<(usize, usize), CsvParseError> {
+    let rows = parse_rows(s, sep)?;
+    let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let height = rows.len();
     if width == 0 || height == 0 {
         Err(CsvParseError::NoCellsFound(width, height))
     } else {
@@ -112,6 +298,8 @@ fn parse_size_of_csv(s: &str, sep: char) -> Result<(usize, usize), CsvParseError
     }
 }
 
+/// Parses the csv into `width * height` cells. Rows with less than `width`
+/// cells are filled up with empty cells.
 fn parse_csv(
     s: &str,
     seperator: char,
@@ -119,65 +307,18 @@ fn parse_csv(
     height: usize,
 ) -> Result<CsvFile, CsvParseError> {
     let mut cells = Vec::with_capacity(width * height);
-    let capacity = s.len() / (width * height);
-    let mut current_cell = String::with_capacity(capacity);
-    let mut state = CsvParseState::NewCell;
-
-    for line in s.lines() {
-        let cell_count = cells.len();
-        for ch in line.chars() {
-            match ch {
-                '"' if state != CsvParseState::InCell => {
-                    // This handles starting and ending quotes and also double
-                    // quotes in the middle of the escaped text. Normal quotes
-                    // in a cell are not handled here. If a escaped text is not
-                    // ended at the end this functions returns a
-                    // CsvParseError::InvalidEscaping in the next iteration
-                    // of this loop.
-                    state = match state {
-                        CsvParseState::InCellEndEscape => {
-                            current_cell.push('"');
-                            CsvParseState::InCellEscaped
-                        }
-                        CsvParseState::NewCell => CsvParseState::InCellEscaped,
-                        CsvParseState::InCell => {
-                            unreachable!("The if guard should make this impossible!")
-                        }
-                        CsvParseState::InCellEscaped => CsvParseState::InCellEndEscape,
-                    };
-                }
-                sep if seperator == sep && state != CsvParseState::InCellEscaped => {
-                    cells.push(current_cell);
-                    current_cell = String::with_capacity(capacity);
-                    state = CsvParseState::NewCell;
-                }
-                default => {
-                    current_cell.push(default);
-                    match state {
-                        CsvParseState::NewCell => state = CsvParseState::InCell,
-                        CsvParseState::InCell | CsvParseState::InCellEscaped => {}
-                        CsvParseState::InCellEndEscape => {
-                            return Err(CsvParseError::InvalidEscaping)
-                        }
-                    }
-                }
-            }
-        }
-        if cells.len() == cell_count && current_cell.is_empty() {
-            continue;
-        }
-        cells.push(current_cell);
-        current_cell = String::with_capacity(capacity);
-        while cells.len() < cell_count + width {
-            cells.push(String::new());
-        }
+    let rows = parse_rows(s, seperator)?;
+    let height = rows.len();
+    let width = rows
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or_default()
+        .max(width);
+    for mut row in rows {
+        row.resize(width, String::new());
+        cells.append(&mut row);
     }
-
-    assert_eq!(
-        cells.len(),
-        width * height,
-        "cells = {cells:#?}, width = {width}, height = {height}"
-    );
     Ok(CsvFile {
         cells,
         width,
@@ -370,4 +511,32 @@ Support excel datatype to support formulas,,,,,";
         assert_eq!(csv.height, 8);
         assert_eq!(csv.seperator, ',');
     }
+
+    #[test]
+    pub fn quoted_cells_and_ragged_rows() {
+        let csv: CsvFile =
+            "name,comment\r\n\"Doe, Jane\",\"said \"\"hi\"\"\nand left\"\n\nsingle\n1,2,3"
+                .parse()
+                .unwrap();
+        assert_eq!(csv.seperator, ',');
+        assert_eq!((csv.width, csv.height), (3, 4));
+        assert_eq!(
+            csv.cells,
+            [
+                "name",
+                "comment",
+                "",
+                "Doe, Jane",
+                "said \"hi\"\nand left",
+                "",
+                "single",
+                "",
+                "",
+                "1",
+                "2",
+                "3",
+            ]
+        );
+        assert!("\"unfinished,cell".parse::<CsvFile>().is_err());
+    }
 }
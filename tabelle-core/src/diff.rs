@@ -0,0 +1,46 @@
+use crate::Spreadsheet;
+
+/// A cell, which differs between two spreadsheets, see
+/// [`crate::Spreadsheet::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellDiff {
+    pub position: (usize, usize),
+    pub kind: CellDiffKind,
+}
+
+/// How a cell changed. The contents are serialized like in a csv file, so
+/// formulas are compared by their text and not by their value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellDiffKind {
+    /// The cell was empty or outside of the old spreadsheet.
+    Added(String),
+    /// The cell is empty or outside of the new spreadsheet.
+    Removed(String),
+    Changed {
+        old: String,
+        new: String,
+    },
+}
+
+pub(crate) fn diff(old: &Spreadsheet, new: &Spreadsheet) -> Vec<CellDiff> {
+    let content = |spreadsheet: &Spreadsheet, (column, row): (usize, usize)| {
+        spreadsheet
+            .row_at(row)
+            .and_then(|cells| cells.get(column))
+            .filter(|cell| !cell.is_empty())
+            .map(|cell| cell.serialize_display_content().into_owned())
+    };
+    let width = old.columns().max(new.columns());
+    let height = old.rows().max(new.rows());
+    let mut result = Vec::new();
+    for position in (0..height).flat_map(|row| (0..width).map(move |column| (column, row))) {
+        let kind = match (content(old, position), content(new, position)) {
+            (None, Some(new)) => CellDiffKind::Added(new),
+            (Some(old), None) => CellDiffKind::Removed(old),
+            (Some(old), Some(new)) if old != new => CellDiffKind::Changed { old, new },
+            _ => continue,
+        };
+        result.push(CellDiff { position, kind });
+    }
+    result
+}
@@ -0,0 +1,67 @@
+use std::fmt::Display;
+
+use crate::csv::CsvParseError;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Xlsx(String),
+    Ods(String),
+    Parquet(String),
+    Csv(CsvParseError),
+    Markdown(String),
+    Json(String),
+    Formula(String),
+    Script(String),
+    Builder(String),
+    Regex(String),
+    ReadOnly,
+    OutOfBounds {
+        col: usize,
+        row: usize,
+        width: usize,
+        height: usize,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Xlsx(err) => write!(f, "Invalid xlsx file: {err}"),
+            Error::Ods(err) => write!(f, "Invalid ods file: {err}"),
+            Error::Parquet(err) => write!(f, "Invalid parquet file: {err}"),
+            Error::Csv(err) => write!(f, "Invalid csv file: {err:?}"),
+            Error::Markdown(err) => write!(f, "Invalid markdown table: {err}"),
+            Error::Json(err) => write!(f, "Invalid json file: {err}"),
+            Error::Formula(err) => write!(f, "Invalid formula: {err}"),
+            Error::Script(err) => write!(f, "Error in script: {err}"),
+            Error::Builder(err) => write!(f, "Invalid spreadsheet: {err}"),
+            Error::Regex(err) => write!(f, "Invalid regex: {err}"),
+            Error::ReadOnly => write!(f, "The spreadsheet is read-only"),
+            Error::OutOfBounds {
+                col,
+                row,
+                width,
+                height,
+            } => write!(
+                f,
+                "Cell ({col}, {row}) is outside of the spreadsheet with size ({width}, {height})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<CsvParseError> for Error {
+    fn from(value: CsvParseError) -> Self {
+        Self::Csv(value)
+    }
+}
@@ -0,0 +1,90 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{sort::natural_cmp, Cell, CellContent};
+
+/// Keeps the rows, whose cell in `column` matches, see
+/// [`crate::Spreadsheet::apply_filter`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Filter {
+    pub column: usize,
+    pub operator: FilterOperator,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Contains,
+}
+
+impl FilterOperator {
+    pub fn parse(text: &str) -> Option<Self> {
+        Some(match text {
+            "=" | "==" => Self::Equal,
+            "!=" | "<>" => Self::NotEqual,
+            "<" => Self::Less,
+            "<=" => Self::LessOrEqual,
+            ">" => Self::Greater,
+            ">=" => Self::GreaterOrEqual,
+            "contains" => Self::Contains,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+            Self::Contains => "contains",
+        }
+    }
+
+    fn accepts(&self, ordering: Ordering) -> bool {
+        match self {
+            Self::Equal => ordering.is_eq(),
+            Self::NotEqual => ordering.is_ne(),
+            Self::Less => ordering.is_lt(),
+            Self::LessOrEqual => ordering.is_le(),
+            Self::Greater => ordering.is_gt(),
+            Self::GreaterOrEqual => ordering.is_ge(),
+            Self::Contains => unreachable!("contains does not compare"),
+        }
+    }
+}
+
+impl Filter {
+    /// Numbers and dates are compared by their value, everything else by the
+    /// displayed text ignoring the case. If the value of the filter is a
+    /// number, only cells with numbers can be less or greater than it.
+    pub fn matches(&self, cell: &Cell) -> bool {
+        let text = cell.display_content();
+        if self.operator == FilterOperator::Contains {
+            return text.to_lowercase().contains(&self.value.to_lowercase());
+        }
+        let expected = CellContent::parse(&self.value, (0, 0), (1, 1));
+        let expected = expected.numeric_value().or_else(|| expected.date_serial());
+        let actual = cell
+            .content
+            .numeric_value()
+            .or_else(|| cell.content.date_serial());
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => self.operator.accepts(actual.total_cmp(&expected)),
+            (Some(_), None) if !matches!(self.operator, FilterOperator::NotEqual) => false,
+            _ => self.operator.accepts(natural_cmp(
+                &text.to_lowercase(),
+                &self.value.to_lowercase(),
+            )),
+        }
+    }
+}
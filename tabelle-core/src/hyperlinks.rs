@@ -0,0 +1,85 @@
+use std::{collections::HashMap, fs::File, io::Read, path::Path};
+
+use quick_xml::{events::Event, Reader};
+use zip::ZipArchive;
+
+/// Reads the targets of the external hyperlinks of the first worksheet of an
+/// xlsx file by their cell name (e.g. `B1`). umya-spreadsheet only keeps
+/// hyperlinks to locations inside the workbook and drops the urls of all
+/// others while reading. Files, which can not be read, have no hyperlinks.
+pub(crate) fn read_external_hyperlinks(path: &Path) -> HashMap<String, String> {
+    try_read_external_hyperlinks(path).unwrap_or_default()
+}
+
+fn try_read_external_hyperlinks(path: &Path) -> Option<HashMap<String, String>> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    let workbook = read_file(&mut archive, "xl/workbook.xml")?;
+    let sheet_id = elements(&workbook, "sheet")?.first()?.get("r:id")?.clone();
+    let workbook_relationships = read_file(&mut archive, "xl/_rels/workbook.xml.rels")?;
+    let workbook_relationships = relationships(&workbook_relationships)?;
+    let target = workbook_relationships.get(&sheet_id)?;
+    let sheet_path = match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_owned(),
+        None => format!("xl/{target}"),
+    };
+    let (directory, file) = sheet_path.rsplit_once('/')?;
+    let sheet_relationships = read_file(&mut archive, &format!("{directory}/_rels/{file}.rels"))?;
+    let sheet_relationships = relationships(&sheet_relationships)?;
+    let sheet = read_file(&mut archive, &sheet_path)?;
+    Some(
+        elements(&sheet, "hyperlink")?
+            .into_iter()
+            .filter_map(|mut link| {
+                let url = sheet_relationships.get(link.get("r:id")?)?;
+                Some((link.remove("ref")?, url.clone()))
+            })
+            .collect(),
+    )
+}
+
+fn read_file(archive: &mut ZipArchive<File>, name: &str) -> Option<String> {
+    let mut result = String::new();
+    archive
+        .by_name(name)
+        .ok()?
+        .read_to_string(&mut result)
+        .ok()?;
+    Some(result)
+}
+
+/// Maps the ids of the relationships in a `.rels` file to their targets.
+fn relationships(xml: &str) -> Option<HashMap<String, String>> {
+    Some(
+        elements(xml, "Relationship")?
+            .into_iter()
+            .filter_map(|mut element| Some((element.remove("Id")?, element.remove("Target")?)))
+            .collect(),
+    )
+}
+
+/// Returns the unescaped attributes of all elements with the name `tag` or
+/// `None`, if `xml` is invalid.
+fn elements(xml: &str, tag: &str) -> Option<Vec<HashMap<String, String>>> {
+    let mut reader = Reader::from_str(xml);
+    let mut result = Vec::new();
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.name().as_ref() == tag.as_bytes() =>
+            {
+                let attributes = element
+                    .attributes()
+                    .map(|attribute| {
+                        let attribute = attribute.ok()?;
+                        let name = std::str::from_utf8(attribute.key.as_ref()).ok()?;
+                        let value = attribute.unescape_value().ok()?;
+                        Some((name.to_owned(), value.into_owned()))
+                    })
+                    .collect::<Option<_>>()?;
+                result.push(attributes);
+            }
+            Event::Eof => return Some(result),
+            _ => {}
+        }
+    }
+}
@@ -1,21 +1,167 @@
-use cells::{Cell, CellPosition};
+use cells::{
+    cell_content::{run_script, CellReference, Expression, Value},
+    CellPosition,
+};
+use search::Pattern;
 use serde::{Deserialize, Serialize};
 use std::{
-    fmt::Write,
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Write},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
 };
 use unicode_width::UnicodeWidthStr;
 use units::UnitKind;
+mod builder;
 mod cells;
-mod csv;
+mod column_type;
+pub mod csv;
+mod diff;
+mod error;
+mod filter;
+mod hyperlinks;
+mod markdown;
+mod ods;
+#[cfg(feature = "parquet")]
+mod parquet_file;
+mod search;
+mod series;
+mod sort;
+mod stream;
+mod svg;
 pub mod units;
-pub use cells::cell_content::CellContent;
+pub use builder::SpreadsheetBuilder;
+pub use cells::{
+    cell_content::{CellContent, Formula, FormulaReference, FormulaTokenKind},
+    Cell, CellAlignment, CellStyle,
+};
+pub use column_type::ColumnType;
+pub use diff::{CellDiff, CellDiffKind};
+pub use error::Error;
+pub use filter::{Filter, FilterOperator};
+#[cfg(feature = "parquet")]
+pub use parquet_file::ParquetFile;
+pub use sort::SortKey;
+pub use stream::{CsvStream, LazyRows};
 
 pub fn dump(path: &str) {
     _ = dbg!(umya_spreadsheet::reader::xlsx::read(path));
     panic!("damn");
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SheetStatistics {
+    pub total_cells: usize,
+    pub filled_cells: usize,
+    pub formula_cells: usize,
+    pub error_cells: usize,
+    pub numeric_sum: f64,
+    pub numeric_max: f64,
+    pub numeric_min: f64,
+}
+
+/// An overview over the cells of a column, see [`Spreadsheet::column_stats`].
+/// The numeric fields only consider cells with numbers and are `None` if
+/// there are none.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ColumnStats {
+    /// The number of cells, which are not empty.
+    pub count: usize,
+    pub sum: f64,
+    pub mean: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The number of different displayed values.
+    pub distinct: usize,
+}
+
+/// The sum, average and count of a range of cells, see
+/// [`Spreadsheet::aggregate_range`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Aggregates {
+    /// The sum of the cells with numbers.
+    pub sum: f64,
+    /// The average of the cells with numbers or `None` if there are none.
+    pub average: Option<f64>,
+    /// The number of cells, which are not empty.
+    pub count: usize,
+    pub numeric_count: usize,
+}
+
+/// How far loading a file got, see
+/// [`Spreadsheet::load_xlsx_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub loaded_rows: usize,
+    /// The number of rows of the file, which is 0 while the file is read.
+    pub total_rows: usize,
+}
+
+impl Progress {
+    /// The loaded rows in percent, if the number of rows is known.
+    pub fn percent(&self) -> Option<usize> {
+        (self.total_rows > 0).then(|| self.loaded_rows * 100 / self.total_rows)
+    }
+}
+
+/// Colors whole rows depending on the numeric value in one column. Rows with
+/// a value above the threshold get `above_color` as background, rows with a
+/// value below it get `below_color`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FormatRule {
+    pub column: usize,
+    pub threshold: f64,
+    pub above_color: Option<(u8, u8, u8)>,
+    pub below_color: Option<(u8, u8, u8)>,
+}
+
+impl FormatRule {
+    /// Returns the background color for a row with `value` in the column of
+    /// the rule.
+    pub fn color_for(&self, value: f64) -> Option<(u8, u8, u8)> {
+        if value > self.threshold {
+            self.above_color
+        } else if value < self.threshold {
+            self.below_color
+        } else {
+            None
+        }
+    }
+}
+
+/// A rectangle of cells, which is shown as one cell. Only the top left cell
+/// keeps its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergedCells {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+impl MergedCells {
+    pub fn contains(&self, (column, row): (usize, usize)) -> bool {
+        (self.from.0..=self.to.0).contains(&column) && (self.from.1..=self.to.1).contains(&row)
+    }
+
+    fn overlaps(&self, other: &MergedCells) -> bool {
+        self.from.0 <= other.to.0
+            && other.from.0 <= self.to.0
+            && self.from.1 <= other.to.1
+            && other.from.1 <= self.to.1
+    }
+}
+
+/// How [`Spreadsheet::find_with`] searches the cells.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FindOptions {
+    /// Ignores the case of letters.
+    pub case_insensitive: bool,
+    /// Searches towards the first cell instead of the last one.
+    pub backwards: bool,
+    /// The needle is a regex.
+    pub regex: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Spreadsheet {
     current_cell: CellPosition,
@@ -23,9 +169,118 @@ pub struct Spreadsheet {
     height: usize,
     cells: Vec<Cell>,
     column_widths: Vec<usize>,
-    used_cells: CellPosition,
+    #[serde(default)]
+    column_headers: Vec<Option<String>>,
     fixed_rows: usize,
+    #[serde(default)]
+    fixed_columns: usize,
+    #[serde(default)]
+    format_rules: Vec<FormatRule>,
     path: Option<PathBuf>,
+    #[serde(skip)]
+    readonly: bool,
+    /// Rows hidden by a filter. Rows past the end of this are visible.
+    #[serde(default)]
+    hidden_rows: Vec<bool>,
+    /// The number of lines of each row. Rows past the end of this have a
+    /// height of 1.
+    #[serde(default)]
+    row_heights: Vec<usize>,
+    #[serde(default)]
+    merged_cells: Vec<MergedCells>,
+    /// The types of the columns. Columns past the end of this have no type.
+    #[serde(default)]
+    column_types: Vec<ColumnType>,
+    #[serde(skip)]
+    undo_stack: Vec<Snapshot>,
+    #[serde(skip)]
+    redo_stack: Vec<Snapshot>,
+    /// The separator used when saving as csv. Loading a csv file sets it to
+    /// the separator of the file.
+    #[serde(default = "default_csv_separator")]
+    csv_separator: char,
+    /// Set if there are changes since the last save.
+    #[serde(default)]
+    dirty: bool,
+    /// The row of the file shown as the first row, if a [`LazyRows`] dropped
+    /// the rows before it to save memory.
+    #[serde(default)]
+    first_row: usize,
+    /// The formulas depending on each cell, which are kept by
+    /// [`Spreadsheet::evaluate_dirty`] until a formula changes.
+    #[serde(skip)]
+    dependency_graph: Option<DependencyGraph>,
+}
+
+/// The formulas reading each cell, indexed like [`Spreadsheet::cells`].
+#[derive(Debug, Clone)]
+struct DependencyGraph {
+    size: (usize, usize),
+    /// The index and text of every formula the graph was built from.
+    formulas: Vec<(usize, String)>,
+    dependents: HashMap<usize, Vec<usize>>,
+}
+
+impl DependencyGraph {
+    fn new(spreadsheet: &Spreadsheet) -> Self {
+        let size = (spreadsheet.width, spreadsheet.height);
+        let mut formulas = Vec::new();
+        let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (index, formula) in spreadsheet.formulas() {
+            for position in formula.referenced_positions(size) {
+                dependents
+                    .entry(spreadsheet.index(position))
+                    .or_default()
+                    .push(index);
+            }
+            formulas.push((index, formula.raw().to_owned()));
+        }
+        Self {
+            size,
+            formulas,
+            dependents,
+        }
+    }
+
+    /// Returns `true` if no formula changed since the graph was built.
+    fn is_up_to_date(&self, spreadsheet: &Spreadsheet) -> bool {
+        self.size == (spreadsheet.width, spreadsheet.height)
+            && spreadsheet
+                .formulas()
+                .map(|(index, formula)| (index, formula.raw()))
+                .eq(self
+                    .formulas
+                    .iter()
+                    .map(|(index, raw)| (*index, raw.as_str())))
+    }
+}
+
+const CSV_SEPARATOR: char = ',';
+
+fn default_csv_separator() -> char {
+    CSV_SEPARATOR
+}
+
+/// The number of changes, which can be undone.
+const MAX_UNDO_STEPS: usize = 100;
+
+/// The contents and layout of a spreadsheet, as restored by
+/// [`Spreadsheet::undo`] and [`Spreadsheet::redo`].
+#[derive(Debug, Clone)]
+struct Snapshot {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    column_widths: Vec<usize>,
+    column_headers: Vec<Option<String>>,
+    fixed_rows: usize,
+    fixed_columns: usize,
+    format_rules: Vec<FormatRule>,
+    hidden_rows: Vec<bool>,
+    row_heights: Vec<usize>,
+    merged_cells: Vec<MergedCells>,
+    column_types: Vec<ColumnType>,
+    csv_separator: char,
 }
 
 impl Spreadsheet {
@@ -37,23 +292,37 @@ impl Spreadsheet {
                     content: CellContent::default(),
                     position: CellPosition(x, y),
                     unit: UnitKind::None,
+                    style: CellStyle::default(),
                 });
             }
         }
-        let column_widths = std::iter::repeat(10).take(width).collect();
+        let column_widths = std::iter::repeat_n(10, width).collect();
         Self {
             current_cell: CellPosition(0, 0),
             width,
             height,
             cells,
-            used_cells: CellPosition(0, 0),
             column_widths,
+            column_headers: vec![None; width],
             fixed_rows: 0,
+            fixed_columns: 0,
+            format_rules: Vec::new(),
             path: None,
+            readonly: false,
+            hidden_rows: Vec::new(),
+            row_heights: Vec::new(),
+            merged_cells: Vec::new(),
+            column_types: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            csv_separator: CSV_SEPARATOR,
+            dirty: false,
+            first_row: 0,
+            dependency_graph: None,
         }
     }
 
-    pub fn load_csv(csv: &str) -> Result<Self, csv::CsvParseError> {
+    pub fn load_csv(csv: &str) -> Result<Self, Error> {
         let csv: csv::CsvFile = csv.parse()?;
         let cells = csv
             .cells
@@ -66,29 +335,109 @@ impl Spreadsheet {
                     content: CellContent::parse(&s, (x, y), (csv.width, csv.height)),
                     position: CellPosition(x, y),
                     unit: UnitKind::None,
+                    style: CellStyle::default(),
                 }
             })
             .collect();
-        let column_widths = std::iter::repeat(10).take(csv.width).collect();
+        let column_widths = std::iter::repeat_n(10, csv.width).collect();
         Ok(Self {
             current_cell: CellPosition(0, 0),
             width: csv.width,
             height: csv.height,
             cells,
-            used_cells: CellPosition(0, 0),
             column_widths,
+            column_headers: vec![None; csv.width],
             fixed_rows: 0,
+            fixed_columns: 0,
+            format_rules: Vec::new(),
             path: None,
+            readonly: false,
+            hidden_rows: Vec::new(),
+            row_heights: Vec::new(),
+            merged_cells: Vec::new(),
+            column_types: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            csv_separator: csv.seperator,
+            dirty: false,
+            first_row: 0,
+            dependency_graph: None,
         })
     }
 
-    pub fn load_xlsx(path: impl AsRef<Path>) -> Self {
+    /// Builds a spreadsheet from rows of values without parsing them. The
+    /// spreadsheet is as wide as the longest row, shorter rows are filled
+    /// with empty cells. Formulas are not evaluated.
+    pub fn from_rows<R, C>(rows: impl IntoIterator<Item = R>) -> Self
+    where
+        R: IntoIterator<Item = C>,
+        C: Into<CellContent>,
+    {
+        let rows: Vec<Vec<CellContent>> = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(Into::into).collect())
+            .collect();
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+        let mut result = Self::new(width.max(1), rows.len().max(1));
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, content) in row.into_iter().enumerate() {
+                result.cell_at_mut((x, y)).content = content;
+            }
+        }
+        result
+    }
+
+    pub fn load_xlsx(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::load_xlsx_with_progress(path, |_| {})
+    }
+
+    /// Like [`Spreadsheet::load_xlsx`], but calls `progress` before the file
+    /// is read and after each row is loaded.
+    pub fn load_xlsx_with_progress(
+        path: impl AsRef<Path>,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<Self, Error> {
         let path = path.as_ref();
-        let spreadsheet = umya_spreadsheet::reader::xlsx::read(path).unwrap();
-        let worksheet = spreadsheet.get_sheet(&0).unwrap();
+        progress(Progress {
+            loaded_rows: 0,
+            total_rows: 0,
+        });
+        let spreadsheet = umya_spreadsheet::reader::xlsx::read(path)
+            .map_err(|err| Error::Xlsx(err.to_string()))?;
+        let worksheet = spreadsheet
+            .get_sheet(&0)
+            .map_err(|err| Error::Xlsx(err.into()))?;
         let (width, height) = worksheet.get_highest_column_and_row();
-        let (width, height) = (width as usize, height as usize);
-        let current_cell = CellPosition::parse(worksheet.get_active_cell()).unwrap();
+        // An empty worksheet still needs at least one cell to put the cursor
+        // on.
+        let (width, height) = ((width as usize).max(1), (height as usize).max(1));
+        let current_cell = CellPosition::parse(worksheet.get_active_cell())
+            .map_err(|cell| Error::Xlsx(format!("Invalid active cell {cell}")))?;
+        let current_cell = CellPosition(
+            current_cell.0.min(width - 1),
+            current_cell.1.min(height - 1),
+        );
+        // Rows and columns frozen in Excel are the fixed rows and columns of
+        // tabelle.
+        let (fixed_rows, fixed_columns) = worksheet
+            .get_sheets_views()
+            .get_sheet_view_list()
+            .first()
+            .and_then(|view| view.get_pane().as_ref())
+            .filter(|pane| {
+                matches!(
+                    pane.get_state(),
+                    umya_spreadsheet::PaneStateValues::Frozen
+                        | umya_spreadsheet::PaneStateValues::FrozenSplit
+                )
+            })
+            .map_or((0, 0), |pane| {
+                (
+                    *pane.get_vertical_split() as usize,
+                    *pane.get_horizontal_split() as usize,
+                )
+            });
+        let hyperlinks = hyperlinks::read_external_hyperlinks(path);
         let mut cells = Vec::with_capacity(width * height);
         let mut column_widths = vec![10; width];
         let mut needs_evaluation = false;
@@ -100,51 +449,228 @@ impl Spreadsheet {
                     .get_column_dimension_by_number(&col)
                     .map(|c| (*c.get_width()) as usize)
                     .unwrap_or(10);
-                let unit = worksheet
-                    .get_style((&col, &row))
+                let style = worksheet.get_style((&col, &row));
+                let mut unit = style
                     .get_numbering_format()
                     .as_ref()
-                    .and_then(|n| UnitKind::try_from(n).ok())
+                    .map(UnitKind::from)
                     .unwrap_or_default();
+                let style = CellStyle::from(style);
                 let content = if let Some(cell) = worksheet.get_cell((&col, &row)) {
                     if cell.is_formula() || cell.get_value().starts_with('=') {
                         needs_evaluation = true;
                     }
-                    CellContent::parse(&cell.get_value(), (x, y), (width, height))
+                    let url = cell
+                        .get_hyperlink()
+                        .as_ref()
+                        .filter(|link| !link.get_location())
+                        .and_then(|_| hyperlinks.get(&format!("{}{row}", to_column_name(x))));
+                    match url {
+                        Some(url) => CellContent::Hyperlink {
+                            display: cell.get_value().into_owned(),
+                            url: url.clone(),
+                        },
+                        None => CellContent::parse(&cell.get_value(), (x, y), (width, height)),
+                    }
                 } else {
                     CellContent::Empty
                 };
+                // xlsx files store dates as numbers with a date format.
+                let serial = content
+                    .as_float_number()
+                    .or(content.as_number().map(|it| it as f64));
+                let content = match serial.and_then(|it| unit.content_from_serial(it)) {
+                    Some(date) => {
+                        unit = UnitKind::None;
+                        date
+                    }
+                    None => content,
+                };
                 cells.push(Cell {
                     content,
                     position: CellPosition(x, y),
                     unit,
+                    style,
                 })
             }
+            progress(Progress {
+                loaded_rows: y + 1,
+                total_rows: height,
+            });
         }
         assert_eq!(cells.len(), width * height);
+        let merged_cells = worksheet
+            .get_merge_cells()
+            .iter()
+            .filter_map(|range| {
+                let range = range.get_range().replace('$', "");
+                let (from, to) = range.split_once(':')?;
+                let from = CellPosition::parse(from).ok()?;
+                let to = CellPosition::parse(to).ok()?;
+                // Rows start at 1 in xlsx files.
+                let merged = MergedCells {
+                    from: (from.0, from.1.checked_sub(1)?),
+                    to: (to.0.min(width - 1), to.1.checked_sub(1)?.min(height - 1)),
+                };
+                // Merges, which start outside of the cells or shrink to a
+                // single cell, are dropped.
+                (merged.from.0 <= merged.to.0
+                    && merged.from.1 <= merged.to.1
+                    && merged.from != merged.to)
+                    .then_some(merged)
+            })
+            .collect();
         let mut result = Self {
             current_cell,
             width,
             height,
             cells,
-            used_cells: CellPosition(width, height),
             column_widths,
-            fixed_rows: 0,
+            column_headers: vec![None; width],
+            fixed_rows,
+            fixed_columns,
+            format_rules: Vec::new(),
             path: Some(path.into()),
+            readonly: false,
+            hidden_rows: Vec::new(),
+            row_heights: Vec::new(),
+            merged_cells,
+            column_types: Vec::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            csv_separator: CSV_SEPARATOR,
+            dirty: false,
+            first_row: 0,
+            dependency_graph: None,
         };
-        // This is very brute forcey. Could be fixed probably.
         if needs_evaluation {
-            for _ in 0..width * height {
-                result.evaluate()
+            result.evaluate();
+        }
+        Ok(result)
+    }
+
+    /// Loads the first table of an OpenDocument spreadsheet, as saved by
+    /// LibreOffice.
+    pub fn load_ods(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let cells = ods::read(path)?;
+        let width = cells
+            .iter()
+            .map(|cell| cell.position.0 + 1)
+            .max()
+            .unwrap_or(1);
+        let height = cells
+            .iter()
+            .map(|cell| cell.position.1 + 1)
+            .max()
+            .unwrap_or(1);
+        let mut result = Self::new(width, height);
+        let mut needs_evaluation = false;
+        for cell in cells {
+            let content = match cell.url {
+                Some(url) => CellContent::Hyperlink {
+                    display: cell.text,
+                    url,
+                },
+                None if cell.is_text => CellContent::Text(cell.text),
+                None => CellContent::parse(&cell.text, cell.position, (width, height)),
+            };
+            needs_evaluation |= content.is_formula();
+            let index = result.index(cell.position);
+            result.cells[index].content = content;
+        }
+        result.path = Some(path.into());
+        if needs_evaluation {
+            result.evaluate();
+        }
+        Ok(result)
+    }
+
+    /// Loads a whole parquet file below a fixed header row with the column
+    /// names. Use [`ParquetFile`] to load only some of the rows.
+    #[cfg(feature = "parquet")]
+    pub fn load_parquet(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = ParquetFile::open(path)?;
+        file.load(0..file.rows())
+    }
+
+    /// Loads the first GitHub flavored markdown table of `markdown`. The
+    /// header becomes a fixed row and the alignment of each column is applied
+    /// to all of its cells.
+    pub fn load_markdown(markdown: &str) -> Result<Self, Error> {
+        let table = markdown::parse(markdown)?;
+        let (width, height) = (table.alignments.len(), table.rows.len());
+        let mut result = Self::new(width, height);
+        for (y, row) in table.rows.iter().enumerate() {
+            for (x, text) in row.iter().enumerate() {
+                let index = result.index((x, y));
+                let cell = &mut result.cells[index];
+                cell.content = CellContent::parse(text, (x, y), (width, height));
+                cell.style.alignment = table.alignments[x];
             }
         }
-        result
+        result.fixed_rows = 1;
+        Ok(result)
+    }
+
+    /// Loads a json array of objects. The keys of the objects become the
+    /// header in the first row, which is fixed, in the order they first
+    /// appear. Missing keys are left empty, strings stay text (even if they
+    /// look like a number or formula) and nested arrays or objects are shown
+    /// as json.
+    pub fn load_json(json: &str) -> Result<Self, Error> {
+        let json: serde_json::Value =
+            serde_json::from_str(json).map_err(|err| Error::Json(err.to_string()))?;
+        let objects = json
+            .as_array()
+            .and_then(|values| {
+                values
+                    .iter()
+                    .map(|v| v.as_object())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| Error::Json("expected an array of objects".into()))?;
+        let mut keys: Vec<&String> = Vec::new();
+        let mut known_keys = HashSet::new();
+        for key in objects.iter().flat_map(|object| object.keys()) {
+            if known_keys.insert(key) {
+                keys.push(key);
+            }
+        }
+        let (width, height) = (keys.len().max(1), objects.len() + 1);
+        let mut result = Self::new(width, height);
+        for (x, key) in keys.iter().enumerate() {
+            result.cells[x].content = CellContent::Text((*key).clone());
+        }
+        for (y, object) in objects.iter().enumerate() {
+            let y = y + 1;
+            for (x, key) in keys.iter().enumerate() {
+                let content = match object.get(*key) {
+                    None | Some(serde_json::Value::Null) => CellContent::Empty,
+                    Some(serde_json::Value::String(text)) if text.is_empty() => CellContent::Empty,
+                    // Formulas are not evaluated, since they could come from
+                    // anywhere.
+                    Some(serde_json::Value::String(text)) => CellContent::Text(text.clone()),
+                    Some(value) => CellContent::parse(&value.to_string(), (x, y), (width, height)),
+                };
+                let index = result.index((x, y));
+                result.cells[index].content = content;
+            }
+        }
+        result.fixed_rows = 1;
+        Ok(result)
     }
 
     pub fn columns(&self) -> usize {
         self.width
     }
 
+    /// The row of the file shown as the first row. It is only greater than 0
+    /// for large files, which are not loaded completely.
+    pub fn first_row(&self) -> usize {
+        self.first_row
+    }
+
     pub fn rows(&self) -> usize {
         self.height
     }
@@ -157,11 +683,171 @@ impl Spreadsheet {
         self.column_widths[column] = width;
     }
 
+    /// The number of lines the cells of `row` are shown with.
+    pub fn row_height(&self, row: usize) -> usize {
+        self.row_heights.get(row).copied().unwrap_or(1)
+    }
+
+    /// Sets the number of lines of `row`, which is at least 1.
+    pub fn set_row_height(&mut self, row: usize, height: usize) {
+        if row >= self.row_heights.len() {
+            self.row_heights.resize(row + 1, 1);
+        }
+        self.row_heights[row] = height.max(1);
+        self.trim_row_heights();
+    }
+
+    /// Removes the trailing rows with a height of 1, since that is the
+    /// default.
+    fn trim_row_heights(&mut self) {
+        while self.row_heights.last() == Some(&1) {
+            self.row_heights.pop();
+        }
+    }
+
+    /// The rows before the returned row may have a height other than 1, all
+    /// rows after it have a height of 1.
+    pub fn rows_with_custom_height(&self) -> usize {
+        self.row_heights.len()
+    }
+
+    /// The type of the values in `column`, see
+    /// [`Spreadsheet::infer_column_types`].
+    pub fn column_type(&self, column: usize) -> ColumnType {
+        self.column_types.get(column).copied().unwrap_or_default()
+    }
+
+    pub fn set_column_type(&mut self, column: usize, column_type: ColumnType) {
+        if column >= self.column_types.len() {
+            self.column_types.resize(column + 1, ColumnType::Any);
+        }
+        self.column_types[column] = column_type;
+        self.trim_column_types();
+    }
+
+    /// Gives every column the type most of its values below the fixed rows
+    /// have.
+    pub fn infer_column_types(&mut self) {
+        let fixed_rows = self.fixed_rows.min(self.height);
+        self.column_types = (0..self.width)
+            .map(|column| {
+                ColumnType::infer(
+                    (fixed_rows..self.height).map(|row| &self.cell_at((column, row)).content),
+                )
+            })
+            .collect();
+        self.trim_column_types();
+    }
+
+    /// Removes the types of all columns.
+    pub fn clear_column_types(&mut self) {
+        self.column_types.clear();
+    }
+
+    /// Removes the trailing columns without a type, since that is the
+    /// default.
+    fn trim_column_types(&mut self) {
+        while self.column_types.last() == Some(&ColumnType::Any) {
+            self.column_types.pop();
+        }
+    }
+
+    /// Returns `true` if the cell at `position` has a value, which does not
+    /// fit the type of its column. Cells in fixed rows always fit, since
+    /// they usually contain the names of the columns.
+    pub fn is_type_mismatch(&self, position: (usize, usize)) -> bool {
+        position.1 >= self.fixed_rows
+            && !self
+                .column_type(position.0)
+                .accepts(&self.cell_at(position).content)
+    }
+
+    /// The alignment of the cell at `position`. Without an alignment in its
+    /// style, the cell is aligned by the type of its column, so values not
+    /// fitting the type line up with the others. In columns without a type
+    /// numbers and dates are aligned right.
+    pub fn cell_alignment(&self, position: (usize, usize)) -> CellAlignment {
+        let cell = self.cell_at(position);
+        let column_type = (position.1 >= self.fixed_rows)
+            .then(|| self.column_type(position.0).alignment())
+            .flatten();
+        match cell.style().alignment.or(column_type) {
+            Some(alignment) => alignment,
+            None if cell.is_right_aligned() => CellAlignment::Right,
+            None => CellAlignment::Left,
+        }
+    }
+
+    /// Returns the top left and bottom right position of the smallest
+    /// rectangle, which contains all non empty cells. It is computed from
+    /// the cells, so it is right after every change, no matter if the cells
+    /// were typed, pasted, filled or cleared. Spreadsheets without any
+    /// content return the first cell for both.
+    pub fn used_range(&self) -> ((usize, usize), (usize, usize)) {
+        let mut result: Option<((usize, usize), (usize, usize))> = None;
+        for cell in self.cells.iter().filter(|c| !c.is_empty()) {
+            let (x, y) = cell.position();
+            result = Some(match result {
+                Some(((min_x, min_y), (max_x, max_y))) => {
+                    ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+                }
+                None => ((x, y), (x, y)),
+            });
+        }
+        result.unwrap_or_default()
+    }
+
+    /// Returns the custom name of the column or the default name (like `A`)
+    /// if it has none.
+    pub fn column_header(&self, column: usize) -> Cow<'_, str> {
+        match self.column_headers.get(column) {
+            Some(Some(header)) => header.into(),
+            _ => to_column_name(column).into(),
+        }
+    }
+
+    pub fn set_column_header(&mut self, column: usize, name: String) {
+        if self.column_headers.len() < self.width {
+            self.column_headers.resize(self.width, None);
+        }
+        self.column_headers[column] = Some(name);
+    }
+
+    /// Gives `to_column` the width, the header, the type and the units of
+    /// `from_column`. There are no units per column, so the unit of every
+    /// cell is copied to the cell in the same row.
+    pub fn copy_column_format(&mut self, from_column: usize, to_column: usize) {
+        self.column_widths[to_column] = self.column_widths[from_column];
+        if let Some(Some(header)) = self.column_headers.get(from_column) {
+            self.set_column_header(to_column, header.clone());
+        }
+        self.set_column_type(to_column, self.column_type(from_column));
+        for row in 0..self.height {
+            let from = self.cell_at((from_column, row));
+            let (unit, style) = (from.unit(), from.style());
+            let to = self.cell_at_mut((to_column, row));
+            to.set_unit(unit);
+            to.set_style(style);
+        }
+    }
+
     pub fn path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
 
+    /// Remembers the file, which was loaded from text like a csv file.
+    pub fn set_path(&mut self, path: impl Into<PathBuf>) {
+        self.path = Some(path.into());
+    }
+
+    /// Changes the number of columns and rows, keeping at least one of each.
+    /// Shrinking removes the cells outside of the new size and moves the
+    /// cursor into the remaining cells.
     pub fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width < self.width || height < self.height {
+            self.truncate(width.min(self.width), height.min(self.height));
+        }
         let additional = width * height - self.cells.len();
         self.column_widths.reserve(width - self.column_widths.len());
         self.cells.reserve(additional);
@@ -171,9 +857,11 @@ impl Spreadsheet {
                     content: CellContent::Empty,
                     position: CellPosition(x, y),
                     unit: UnitKind::None,
+                    style: CellStyle::default(),
                 });
             }
         }
+        self.column_headers.resize(width, None);
         for x in self.width..width {
             self.column_widths.push(10);
             for y in 0..self.height {
@@ -181,6 +869,7 @@ impl Spreadsheet {
                     content: CellContent::Empty,
                     position: CellPosition(x, y),
                     unit: UnitKind::None,
+                    style: CellStyle::default(),
                 });
             }
         }
@@ -189,284 +878,3841 @@ impl Spreadsheet {
         self.height = height;
     }
 
-    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
-        let index = self.index(self.current_cell());
-        let mut cells = self.cells[index..]
-            .iter()
-            .skip(1)
-            .chain(self.cells[..index].iter());
-        cells.find_map(|c| match &c.content {
-            CellContent::Empty => None,
-            CellContent::Text(it) => {
-                if it.contains(text) {
-                    Some(c.position())
-                } else {
-                    None
-                }
-            }
-            CellContent::Number(_) => None,
-            CellContent::FloatNumber(_, _) => None,
-            CellContent::Formula(_) => None,
-        })
-    }
-
-    pub fn set_cursor(&mut self, cell_position: (usize, usize)) {
-        self.current_cell = CellPosition(cell_position.0, cell_position.1);
-    }
-
-    pub fn move_cursor(&mut self, x: isize, y: isize) -> bool {
-        let mut result = true;
-        let x = self.current_cell.0 as isize + x;
-        let y = self.current_cell.1 as isize + y;
-        let x = if x < 0 {
-            result = false;
-            0
-        } else if x as usize >= self.width {
-            result = false;
-            self.width - 1
-        } else {
-            x as usize
-        };
-        let y = if y < 0 {
-            result = false;
-            0
-        } else if y as usize >= self.height {
-            result = false;
-            self.height - 1
-        } else {
-            y as usize
-        };
-        self.current_cell = CellPosition(x, y);
-        result
+    /// Removes the empty rows and columns after the last used cell, so they
+    /// are not saved. Merged cells and cells referenced by formulas count as
+    /// used. Returns the number of removed columns and rows.
+    pub fn shrink_to_used(&mut self) -> (usize, usize) {
+        let size = (self.width, self.height);
+        let (_, mut last) = self.used_range();
+        let merged = self.merged_cells.iter().map(|merged| merged.to);
+        let referenced = self.cells.iter().flat_map(|cell| match &cell.content {
+            CellContent::Formula(f) => f.referenced_positions(size),
+            _ => Vec::new(),
+        });
+        for (column, row) in merged.chain(referenced) {
+            last = (last.0.max(column), last.1.max(row));
+        }
+        let (width, height) = ((last.0 + 1).min(self.width), (last.1 + 1).min(self.height));
+        self.truncate(width, height);
+        (size.0 - width, size.1 - height)
     }
 
-    pub fn input_char(&mut self, ch: char) {
-        self.used_cells = CellPosition(
-            self.current_cell.0.max(self.used_cells.0),
-            self.current_cell.1.max(self.used_cells.1),
+    /// Removes the columns from `width` on and the rows from `height` on.
+    /// The cursor and everything else referring to removed cells is moved
+    /// inside the remaining cells or removed.
+    fn truncate(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.clamp(1, self.width), height.clamp(1, self.height));
+        self.cells
+            .retain(|cell| cell.column() < width && cell.row() < height);
+        self.column_widths.truncate(width);
+        self.column_headers.truncate(width);
+        self.column_types.truncate(width);
+        self.hidden_rows.truncate(height);
+        self.row_heights.truncate(height);
+        self.format_rules.retain(|rule| rule.column < width);
+        self.merged_cells.retain_mut(|merged| {
+            merged.to = (merged.to.0.min(width - 1), merged.to.1.min(height - 1));
+            merged.from.0 < width && merged.from.1 < height && merged.from != merged.to
+        });
+        self.fixed_rows = self.fixed_rows.min(height);
+        self.fixed_columns = self.fixed_columns.min(width);
+        self.current_cell = CellPosition(
+            self.current_cell.0.min(width - 1),
+            self.current_cell.1.min(height - 1),
         );
-        let index = self.index(self.current_cell());
-        self.cells[index].content.input_char(ch, self.current_cell);
-    }
-
-    pub fn clear_current_cell(&mut self) {
-        let index = self.index(self.current_cell());
-        self.cells[index].content = CellContent::Empty;
+        self.width = width;
+        self.height = height;
     }
 
-    pub fn current_cell(&self) -> (usize, usize) {
-        (self.current_cell.0, self.current_cell.1)
+    /// Appends the rows of a csv below the last row. The spreadsheet gets
+    /// wider, if a row has more cells than it has columns.
+    pub(crate) fn append_csv_rows(&mut self, rows: Vec<Vec<String>>) {
+        if rows.is_empty() {
+            return;
+        }
+        let offset = self.height;
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+        self.resize(self.width.max(width), offset + rows.len());
+        let size = (self.width, self.height);
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, text) in row.into_iter().enumerate() {
+                let position = (x, offset + y);
+                let index = self.index(position);
+                self.cells[index].content = CellContent::parse(&text, position, size);
+            }
+        }
     }
 
-    pub fn cell_at(&self, cell_position: (usize, usize)) -> &Cell {
-        let index = self.index(cell_position);
-        &self.cells[index]
+    /// Inserts the rows of a csv before the first row, which were dropped by
+    /// [`Spreadsheet::drop_first_rows`] before.
+    pub(crate) fn prepend_csv_rows(&mut self, rows: Vec<Vec<String>>) {
+        if rows.is_empty() {
+            return;
+        }
+        let count = rows.len();
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+        self.resize(self.width.max(width), self.height);
+        let (width, size) = (self.width, (self.width, self.height + count));
+        let cells = rows
+            .into_iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                let mut row = row.into_iter();
+                (0..width).map(move |x| Cell {
+                    content: row.next().map_or(CellContent::Empty, |text| {
+                        CellContent::parse(&text, (x, y), size)
+                    }),
+                    position: CellPosition(x, y),
+                    unit: UnitKind::None,
+                    style: CellStyle::default(),
+                })
+            })
+            .collect::<Vec<_>>();
+        self.cells.splice(0..0, cells);
+        if !self.hidden_rows.is_empty() {
+            self.hidden_rows
+                .splice(0..0, std::iter::repeat_n(false, count));
+        }
+        if !self.row_heights.is_empty() {
+            self.row_heights.splice(0..0, std::iter::repeat_n(1, count));
+        }
+        for merged in &mut self.merged_cells {
+            merged.from.1 += count;
+            merged.to.1 += count;
+        }
+        self.current_cell.1 += count;
+        self.height += count;
+        self.first_row -= count;
+        self.update_positions();
     }
 
-    pub fn cell_at_mut(&mut self, cell_position: (usize, usize)) -> &mut Cell {
-        let index = self.index(cell_position);
-        &mut self.cells[index]
+    /// Removes the first `count` rows to save memory, while keeping at least
+    /// one row. Unlike deleting rows, the rows after them keep their row
+    /// numbers, because [`Spreadsheet::first_row`] grows instead.
+    pub(crate) fn drop_first_rows(&mut self, count: usize) {
+        let count = count.min(self.height - 1);
+        self.cells.drain(..count * self.width);
+        self.hidden_rows.drain(..count.min(self.hidden_rows.len()));
+        self.row_heights.drain(..count.min(self.row_heights.len()));
+        self.merged_cells.retain(|merged| merged.from.1 >= count);
+        for merged in &mut self.merged_cells {
+            merged.from.1 -= count;
+            merged.to.1 -= count;
+        }
+        self.current_cell.1 = self.current_cell.1.saturating_sub(count);
+        self.height -= count;
+        self.first_row += count;
+        self.update_positions();
     }
 
-    fn index(&self, cell_position: (usize, usize)) -> usize {
-        cell_position.1 * self.width + cell_position.0
+    /// Inserts `count` empty rows before the row `before`. References of
+    /// formulas are updated to still point to the same cells.
+    pub fn insert_rows_before(&mut self, before: usize, count: usize) {
+        let index = before * self.width;
+        let rows = std::iter::repeat_with(|| Cell {
+            content: CellContent::Empty,
+            position: CellPosition(0, 0),
+            unit: UnitKind::None,
+            style: CellStyle::default(),
+        })
+        .take(count * self.width);
+        self.cells.splice(index..index, rows);
+        if before < self.hidden_rows.len() {
+            self.hidden_rows
+                .splice(before..before, std::iter::repeat_n(false, count));
+        }
+        if before < self.row_heights.len() {
+            self.row_heights
+                .splice(before..before, std::iter::repeat_n(1, count));
+        }
+        self.height += count;
+        // Rows inserted into merged cells make them taller.
+        for merged in &mut self.merged_cells {
+            if merged.from.1 >= before {
+                merged.from.1 += count;
+            }
+            if merged.to.1 >= before {
+                merged.to.1 += count;
+            }
+        }
+        self.update_positions();
+        self.map_formula_references(|r| match r {
+            CellReference::Cell(c) | CellReference::Absolute(c, _) if c.1 >= before => c.1 += count,
+            CellReference::Row(r) if *r >= before => *r += count,
+            _ => {}
+        });
     }
 
-    pub fn evaluate(&mut self) {
-        let mut cells = self.cells.clone();
-        for cell in &mut cells {
-            cell.evaluate(self)
+    /// Inserts `count` empty columns before the column `before`. References
+    /// of formulas are updated to still point to the same cells.
+    pub fn insert_columns_before(&mut self, before: usize, count: usize) {
+        let width = self.width + count;
+        let mut cells = Vec::with_capacity(width * self.height);
+        let mut old_cells = self.cells.drain(..);
+        for _ in 0..self.height {
+            cells.extend(old_cells.by_ref().take(before));
+            cells.extend(
+                std::iter::repeat_with(|| Cell {
+                    content: CellContent::Empty,
+                    position: CellPosition(0, 0),
+                    unit: UnitKind::None,
+                    style: CellStyle::default(),
+                })
+                .take(count),
+            );
+            cells.extend(old_cells.by_ref().take(self.width - before));
         }
+        drop(old_cells);
         self.cells = cells;
-    }
-
-    pub fn serialize_as_csv(&self) -> String {
-        let mut result = String::new();
-        for cell in self {
-            if cell.column() > self.used_cells.0 || cell.row() > self.used_cells.1 {
-                continue;
+        self.width = width;
+        self.column_widths
+            .splice(before..before, std::iter::repeat_n(10, count));
+        if before <= self.column_headers.len() {
+            self.column_headers
+                .splice(before..before, std::iter::repeat_n(None, count));
+        }
+        if before < self.column_types.len() {
+            self.column_types
+                .splice(before..before, std::iter::repeat_n(ColumnType::Any, count));
+        }
+        for merged in &mut self.merged_cells {
+            if merged.from.0 >= before {
+                merged.from.0 += count;
             }
-            if cell.column() == 0 && cell.row() != 0 {
-                result.push('\n');
+            if merged.to.0 >= before {
+                merged.to.0 += count;
             }
-            write!(result, "{},", cell.serialize_display_content()).unwrap();
         }
-        result
+        self.update_positions();
+        self.map_formula_references(|r| match r {
+            CellReference::Cell(c) | CellReference::Absolute(c, _) if c.0 >= before => c.0 += count,
+            CellReference::Column(c) if *c >= before => *c += count,
+            _ => {}
+        });
     }
 
-    pub fn save_as_xlsx(&self, path: impl AsRef<Path>) {
-        let path = path.as_ref();
-        let mut spreadsheet = umya_spreadsheet::new_file();
-        let worksheet = spreadsheet.get_sheet_mut(&0).unwrap();
-        worksheet.set_name("Sheet!").set_active_cell(&format!(
-            "{}{}",
-            to_column_name(self.current_cell.0),
-            self.current_cell.1 + 1
-        ));
-        for column in 0..self.columns() {
-            // TODO: Find out if column is zero based or one based, because we
-            // use it differently here then down below..
-            worksheet
-                .get_column_dimension_by_number_mut(&(column as u32))
-                .set_width(self.column_width(column) as f64);
-            for row in 0..self.rows() {
-                worksheet
-                    .get_cell_mut((&(column as u32 + 1), &(row as u32 + 1)))
-                    .set_value(self.cell_at((column, row)).content.serialize_display());
-                worksheet
-                    .get_style_mut((&(column as u32 + 1), &(row as u32 + 1)))
-                    .set_numbering_format(self.cell_at((column, row)).unit.into());
+    /// Appends all rows of `other` below the last row. If the column counts
+    /// differ, the narrower spreadsheet is padded with empty cells. References
+    /// of the appended formulas are moved along with their rows.
+    pub fn merge_from(&mut self, other: &Spreadsheet) {
+        let offset = self.height;
+        let width = self.width.max(other.width);
+        self.resize(width, self.height);
+        for row in other.as_rows() {
+            for x in 0..width {
+                self.cells.push(row.get(x).cloned().unwrap_or(Cell {
+                    content: CellContent::Empty,
+                    position: CellPosition(0, 0),
+                    unit: UnitKind::None,
+                    style: CellStyle::default(),
+                }));
             }
         }
-        umya_spreadsheet::writer::xlsx::write(&spreadsheet, path).unwrap();
-    }
-
-    pub fn recommended_cell_content(&self, position: (usize, usize)) -> CellContent {
-        let from_cell = self.cell_at(position);
-        let x_diff = self.current_cell().0 as isize - position.0 as isize;
-        let y_diff = self.current_cell().1 as isize - position.1 as isize;
-        match &from_cell.content {
-            CellContent::Empty => CellContent::Empty,
-            CellContent::Text(it) => CellContent::Text(it.clone()),
-            CellContent::Number(it) => CellContent::Number(*it + x_diff as i64 + y_diff as i64),
-            CellContent::FloatNumber(it, d) => CellContent::FloatNumber(*it, *d),
-            CellContent::Formula(f) => {
-                CellContent::Formula(f.moved_to(self.current_cell, (self.width, self.height)))
+        self.height += other.height;
+        for (width, other) in self.column_widths.iter_mut().zip(&other.column_widths) {
+            *width = (*width).max(*other);
+        }
+        self.update_positions();
+        let size = (self.width, self.height);
+        for cell in &mut self.cells[offset * width..] {
+            if let CellContent::Formula(f) = &mut cell.content {
+                f.map_references(size, |r| match r {
+                    CellReference::Cell(CellPosition(_, r))
+                    | CellReference::Absolute(CellPosition(_, r), _)
+                    | CellReference::Row(r) => *r += offset,
+                    CellReference::Column(_) => {}
+                });
             }
         }
     }
 
-    pub fn update_cell_at(&mut self, cell_position: (usize, usize), cell_content: CellContent) {
-        let index = self.index(cell_position);
-        self.cells[index].content = cell_content;
+    /// Inserts a copy of the row `row` below it. Formulas in the copy keep
+    /// referencing the same cells as the original formulas.
+    pub fn duplicate_row(&mut self, row: usize) {
+        self.insert_rows_before(row + 1, 1);
+        let start = row * self.width;
+        let (original, copy) = self.cells[start..].split_at_mut(self.width);
+        copy[..self.width].clone_from_slice(original);
+        self.set_row_height(row + 1, self.row_height(row));
+        self.update_positions();
     }
 
-    pub fn as_rows(&self) -> SpreadsheetRowIter {
-        SpreadsheetRowIter {
-            spreadsheet: self,
-            index: 0,
+    /// Inserts a copy of the column `column` right of it. Formulas in the
+    /// copy keep referencing the same cells as the original formulas.
+    pub fn duplicate_column(&mut self, column: usize) {
+        self.insert_columns_before(column + 1, 1);
+        for row in 0..self.height {
+            let index = row * self.width + column;
+            self.cells[index + 1] = self.cells[index].clone();
         }
+        self.column_widths[column + 1] = self.column_widths[column];
+        self.update_positions();
     }
 
-    pub fn sort_column(&mut self, column: usize) {
-        let rows: Vec<_> = self.as_rows().skip(self.fixed_rows).collect();
-        let mut rows = rows.clone();
-        rows.sort_by_cached_key(|r| &r[column].content);
-        rows.reverse();
-        self.cells = self
-            .as_rows()
-            .take(self.fixed_rows)
-            .chain(rows.into_iter())
-            .flatten()
-            .cloned()
+    /// Moves the column `from` to the index `to`, the columns in between are
+    /// shifted by one. References of formulas are updated to still point to
+    /// the same cells.
+    pub fn move_column(&mut self, from: usize, to: usize) {
+        if from == to {
+            return;
+        }
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for row in self.as_rows() {
+            let mut row = row.to_vec();
+            let cell = row.remove(from);
+            row.insert(to, cell);
+            cells.extend(row);
+        }
+        self.cells = cells;
+        let width = self.column_widths.remove(from);
+        self.column_widths.insert(to, width);
+        if self.column_headers.len() == self.width {
+            let header = self.column_headers.remove(from);
+            self.column_headers.insert(to, header);
+        }
+        if from.max(to) >= self.column_types.len() {
+            self.column_types.resize(from.max(to) + 1, ColumnType::Any);
+        }
+        let column_type = self.column_types.remove(from);
+        self.column_types.insert(to, column_type);
+        self.trim_column_types();
+        self.remap_merged_cells(|c| moved_index(c, from, to), |r| r);
+        self.update_positions();
+        self.map_formula_references(|r| match r {
+            CellReference::Cell(CellPosition(c, _))
+            | CellReference::Absolute(CellPosition(c, _), _)
+            | CellReference::Column(c) => *c = moved_index(*c, from, to),
+            CellReference::Row(_) => {}
+        });
+    }
+
+    /// Moves the row `from` to the index `to`, the rows in between are
+    /// shifted by one. References of formulas are updated to still point to
+    /// the same cells.
+    pub fn move_row(&mut self, from: usize, to: usize) {
+        if from == to {
+            return;
+        }
+        let row: Vec<_> = self
+            .cells
+            .drain(from * self.width..(from + 1) * self.width)
             .collect();
+        let index = to * self.width;
+        self.cells.splice(index..index, row);
+        if from.max(to) < self.hidden_rows.len() {
+            let hidden = self.hidden_rows.remove(from);
+            self.hidden_rows.insert(to, hidden);
+        }
+        if from < self.row_heights.len() || to < self.row_heights.len() {
+            self.row_heights
+                .resize(self.row_heights.len().max(from.max(to) + 1), 1);
+            let height = self.row_heights.remove(from);
+            self.row_heights.insert(to, height);
+            self.trim_row_heights();
+        }
+        self.remap_merged_cells(|c| c, |r| moved_index(r, from, to));
+        self.update_positions();
+        self.map_formula_references(|r| match r {
+            CellReference::Cell(CellPosition(_, r))
+            | CellReference::Absolute(CellPosition(_, r), _)
+            | CellReference::Row(r) => *r = moved_index(*r, from, to),
+            CellReference::Column(_) => {}
+        });
+    }
+
+    /// Moves the merged cells to the columns and rows returned by `column`
+    /// and `row`. Merged cells, whose columns or rows are not next to each
+    /// other anymore, are unmerged.
+    fn remap_merged_cells(
+        &mut self,
+        column: impl Fn(usize) -> usize,
+        row: impl Fn(usize) -> usize,
+    ) {
+        self.merged_cells.retain_mut(|merged| {
+            let columns = remapped_range(merged.from.0..=merged.to.0, &column);
+            let rows = remapped_range(merged.from.1..=merged.to.1, &row);
+            let (Some(columns), Some(rows)) = (columns, rows) else {
+                return false;
+            };
+            merged.from = (*columns.start(), *rows.start());
+            merged.to = (*columns.end(), *rows.end());
+            true
+        });
+    }
+
+    /// Sets the position of every cell to match its index.
+    fn update_positions(&mut self) {
         for (index, cell) in self.cells.iter_mut().enumerate() {
             cell.position = CellPosition::from_index(index, self.width);
+            if let CellContent::Formula(f) = &mut cell.content {
+                f.set_position(cell.position);
+            }
         }
     }
 
-    pub fn fit_column_width(&mut self, column: usize) {
-        let width = self
-            .as_rows()
-            .map(|r| r[column].display_content().as_ref().width())
-            .fold(0, |a, w| a.max(w));
-        self.set_column_width(column, width + 1);
+    fn map_formula_references(&mut self, map: impl Fn(&mut CellReference)) {
+        let size = (self.width, self.height);
+        for cell in &mut self.cells {
+            if let CellContent::Formula(f) = &mut cell.content {
+                f.map_references(size, &map);
+            }
+        }
     }
 
-    pub fn fix_rows(&mut self, fixed_rows: usize) {
-        self.fixed_rows = fixed_rows;
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        self.find_with(text, FindOptions::default())
     }
-}
 
-impl<'a> IntoIterator for &'a Spreadsheet {
-    type Item = &'a Cell;
+    /// Finds the next cell after the current one, which contains `needle`.
+    /// Numbers are searched as they are written and formulas by their text
+    /// including the leading `=`. The search wraps around at the end of the
+    /// spreadsheet, so the current cell is checked last.
+    pub fn find_with(&self, needle: &str, options: FindOptions) -> Option<(usize, usize)> {
+        let pattern = if options.regex {
+            Pattern::regex(needle, options.case_insensitive).ok()?
+        } else if options.case_insensitive {
+            Pattern::Text(needle.to_lowercase())
+        } else {
+            Pattern::Text(needle.into())
+        };
+        let lowercase = options.case_insensitive && !options.regex;
+        let index = self.index(self.current_cell());
+        let cells: Box<dyn Iterator<Item = &Cell>> = if options.backwards {
+            Box::new(self.cells_from(index).rev())
+        } else {
+            Box::new(self.cells_from(index + 1))
+        };
+        cells
+            .filter(|cell| !cell.is_empty())
+            .find(|cell| {
+                let text = cell.serialize_display_content();
+                if lowercase {
+                    pattern.is_match(&text.to_lowercase())
+                } else {
+                    pattern.is_match(&text)
+                }
+            })
+            .map(Cell::position)
+    }
 
-    type IntoIter = std::slice::Iter<'a, Cell>;
+    /// Returns the positions of all cells matching `regex` row by row. Like
+    /// [`Spreadsheet::find_with`] formulas are matched by their text.
+    pub fn find_regex(
+        &self,
+        regex: &str,
+        case_insensitive: bool,
+    ) -> Result<impl Iterator<Item = (usize, usize)> + '_, Error> {
+        let pattern = Pattern::regex(regex, case_insensitive)?;
+        Ok(self
+            .cells
+            .iter()
+            .filter(|cell| !cell.is_empty())
+            .filter(move |cell| pattern.is_match(&cell.serialize_display_content()))
+            .map(Cell::position))
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.cells.iter()
+    /// Returns all cells starting at `index` and continuing with the first
+    /// cell after the last one.
+    fn cells_from(&self, index: usize) -> impl DoubleEndedIterator<Item = &Cell> {
+        let index = index.min(self.cells.len());
+        self.cells[index..].iter().chain(self.cells[..index].iter())
     }
-}
 
-pub struct SpreadsheetRowIter<'a> {
-    spreadsheet: &'a Spreadsheet,
-    index: usize,
-}
+    pub fn sheet_statistics(&self) -> SheetStatistics {
+        let mut result = SheetStatistics {
+            total_cells: self.cells.len(),
+            ..Default::default()
+        };
+        let mut has_numbers = false;
+        for cell in &self.cells {
+            if cell.is_empty() {
+                continue;
+            }
+            result.filled_cells += 1;
+            if cell.content.is_formula() {
+                result.formula_cells += 1;
+            }
+            if cell.is_error() {
+                result.error_cells += 1;
+            }
+            if let Some(value) = cell.content.numeric_value() {
+                result.numeric_sum += value;
+                if has_numbers {
+                    result.numeric_max = result.numeric_max.max(value);
+                    result.numeric_min = result.numeric_min.min(value);
+                } else {
+                    result.numeric_max = value;
+                    result.numeric_min = value;
+                    has_numbers = true;
+                }
+            }
+        }
+        result
+    }
 
-impl<'a> Iterator for SpreadsheetRowIter<'a> {
-    type Item = &'a [Cell];
+    /// Compares every cell with the cell at the same position in `other`,
+    /// which is the newer version of this spreadsheet. Cells outside of the
+    /// smaller spreadsheet count as empty. The differences are ordered by
+    /// row and then by column.
+    pub fn diff(&self, other: &Spreadsheet) -> Vec<CellDiff> {
+        diff::diff(self, other)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.spreadsheet.height {
-            None
+    /// Computes an overview over the cells of `column`. The fixed rows are
+    /// skipped, since they usually contain the header, and so are rows
+    /// hidden by a filter.
+    pub fn column_stats(&self, column: usize) -> ColumnStats {
+        let mut result = ColumnStats::default();
+        let mut numbers = 0;
+        let mut values = HashSet::new();
+        let cells = self
+            .visible_rows()
+            .skip_while(|&row| row < self.fixed_rows)
+            .map(|row| self.cell_at((column, row)))
+            .filter(|cell| !cell.is_empty());
+        for cell in cells {
+            result.count += 1;
+            values.insert(cell.display_content());
+            if let Some(value) = cell.content.numeric_value() {
+                numbers += 1;
+                result.sum += value;
+                result.min = Some(result.min.map_or(value, |min| min.min(value)));
+                result.max = Some(result.max.map_or(value, |max| max.max(value)));
+            }
+        }
+        result.distinct = values.len();
+        result.mean = (numbers > 0).then(|| result.sum / numbers as f64);
+        result
+    }
+
+    pub fn set_cursor(&mut self, cell_position: (usize, usize)) {
+        self.current_cell = CellPosition(cell_position.0, cell_position.1);
+    }
+
+    pub fn move_cursor(&mut self, x: isize, y: isize) -> bool {
+        let mut result = true;
+        let x = self.current_cell.0 as isize + x;
+        // Rows hidden by a filter are skipped.
+        let y = self.display_row(self.current_cell.1) as isize + y;
+        let x = if x < 0 {
+            result = false;
+            0
+        } else if x as usize >= self.width {
+            result = false;
+            self.width - 1
+        } else {
+            x as usize
+        };
+        let y = if y < 0 {
+            result = false;
+            0
+        } else if y as usize >= self.visible_row_count() {
+            result = false;
+            self.visible_row_count() - 1
         } else {
-            let start = self.index * self.spreadsheet.width;
-            self.index += 1;
-            Some(&self.spreadsheet.cells[start..][..self.spreadsheet.width])
+            y as usize
+        };
+        self.current_cell = CellPosition(x, self.row_at_display(y));
+        result
+    }
+
+    pub fn input_char(&mut self, ch: char) {
+        if self.readonly {
+            return;
         }
+        self.dirty = true;
+        let index = self.index(self.current_cell());
+        self.cells[index].content.input_char(ch, self.current_cell);
     }
-}
 
-pub fn to_column_name(mut index: usize) -> String {
-    let mut result = String::new();
-    let letters = [
-        'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R',
-        'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
-    ];
-    while index >= 26 {
-        result.insert(0, letters[index % 26]);
-        index /= 26;
+    pub fn clear_current_cell(&mut self) {
+        if self.readonly {
+            return;
+        }
+        self.dirty = true;
+        let index = self.index(self.current_cell());
+        self.cells[index].content = CellContent::Empty;
     }
-    result.insert(0, letters[index]);
-    result
-}
 
-pub fn column_name_to_index(column: &str) -> Result<usize, &str> {
-    let mut result = 0;
-    if column.is_empty() {
-        return Err(column);
+    pub fn current_cell(&self) -> (usize, usize) {
+        (self.current_cell.0, self.current_cell.1)
     }
-    for ch in column.chars() {
-        if ch.is_ascii_alphabetic() {
-            let ch = ch.to_ascii_uppercase();
-            let digit = (ch as u8) - b'A';
-            result = result * 26 + digit as usize;
-        } else {
-            return Err(column);
+
+    /// Returns the cell closest to `position`, which is inside the
+    /// spreadsheet. Positions kept outside of it, like the start of a
+    /// selection, have to be clamped after the spreadsheet shrinks.
+    pub fn clamp_position(&self, (column, row): (usize, usize)) -> (usize, usize) {
+        (column.min(self.width - 1), row.min(self.height - 1))
+    }
+
+    pub fn cell_at(&self, cell_position: (usize, usize)) -> &Cell {
+        let index = self.index(cell_position);
+        &self.cells[index]
+    }
+
+    /// Returns the formula of the cell without the leading `=`, if the cell
+    /// contains a formula.
+    pub fn get_formula(&self, position: (usize, usize)) -> Option<&str> {
+        match &self.cell_at(position).content {
+            CellContent::Formula(f) => Some(f.raw()),
+            _ => None,
         }
     }
-    Ok(result)
-}
 
-pub fn cell_name_to_position(cell: &str) -> Result<(usize, usize), &str> {
-    let mut x = 0;
-    let mut y = 0;
-    let mut is_at_column = true;
-    let mut has_column = false;
-    for (i, ch) in cell.char_indices() {
-        if is_at_column && ch.is_ascii_alphabetic() && ch.is_ascii_uppercase() {
-            has_column = true;
-            let ch = ch.to_ascii_uppercase();
-            let digit = (ch as u8) - b'A';
-            x = x * 26 + digit as usize;
-        } else if is_at_column {
-            if !has_column {
-                return Err(cell);
+    /// Returns the positions of all cells the formula at `position` depends
+    /// on. Cells without a formula have no dependencies.
+    pub fn formula_dependencies(&self, position: (usize, usize)) -> Vec<(usize, usize)> {
+        match &self.cell_at(position).content {
+            CellContent::Formula(f) => f.referenced_positions((self.width, self.height)),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn cell_at_mut(&mut self, cell_position: (usize, usize)) -> &mut Cell {
+        let index = self.index(cell_position);
+        &mut self.cells[index]
+    }
+
+    fn index(&self, cell_position: (usize, usize)) -> usize {
+        cell_position.1 * self.width + cell_position.0
+    }
+
+    /// Evaluates all formulas after the formulas they depend on. Formulas,
+    /// which are part of a cycle or depend on one, get the value `#cycle`.
+    pub fn evaluate(&mut self) {
+        let formulas = self.formula_indices();
+        self.evaluate_formulas(&formulas);
+    }
+
+    /// Like [`Spreadsheet::evaluate`], but the formulas, which do not depend
+    /// on each other, are evaluated at the same time as blocking tasks of
+    /// tokio, which are collected with `join_all`.
+    ///
+    /// Formulas are evaluated by python and each task has to hold the python
+    /// GIL while it runs, so the tasks only run in parallel while python
+    /// releases the GIL, e.g. in functions of python modules waiting for
+    /// I/O. This keeps an async program responsive, but it does not make
+    /// plain calculations any faster. Needs the `async` feature.
+    #[cfg(feature = "async")]
+    pub async fn evaluate_async(&mut self) {
+        let formulas = self.formula_indices();
+        let (mut missing_dependencies, dependents) = self.formula_dependents(&formulas);
+        let mut ready: Vec<usize> = formulas
+            .iter()
+            .copied()
+            .filter(|&index| missing_dependencies[index] == 0)
+            .collect();
+        let mut evaluated = vec![false; self.cells.len()];
+        while !ready.is_empty() {
+            // The ready formulas only depend on formulas evaluated before, so
+            // they all can be evaluated with the same copy.
+            let spreadsheet = std::sync::Arc::new(self.clone_without_history());
+            let tasks = ready.iter().map(|&index| {
+                let spreadsheet = std::sync::Arc::clone(&spreadsheet);
+                tokio::task::spawn_blocking(move || {
+                    let mut cell = spreadsheet.cells[index].clone();
+                    cell.evaluate(&spreadsheet);
+                    cell
+                })
+            });
+            let cells = futures::future::join_all(tasks).await;
+            let mut next = Vec::new();
+            for (index, cell) in ready.into_iter().zip(cells) {
+                self.cells[index] = match cell {
+                    Ok(cell) => cell,
+                    Err(err) => std::panic::resume_unwind(err.into_panic()),
+                };
+                evaluated[index] = true;
+                for &dependent in dependents.get(&index).into_iter().flatten() {
+                    missing_dependencies[dependent] -= 1;
+                    if missing_dependencies[dependent] == 0 {
+                        next.push(dependent);
+                    }
+                }
             }
-            is_at_column = false;
-            y = cell[i..].parse().map_err(|_| &cell[i..])?;
-            break;
+            ready = next;
+        }
+        self.mark_cycles(&formulas, &evaluated);
+    }
+
+    /// Clones the spreadsheet without its undo and redo history, which is
+    /// not needed to evaluate formulas.
+    #[cfg(feature = "async")]
+    fn clone_without_history(&mut self) -> Spreadsheet {
+        let undo_stack = std::mem::take(&mut self.undo_stack);
+        let redo_stack = std::mem::take(&mut self.redo_stack);
+        let result = self.clone();
+        self.undo_stack = undo_stack;
+        self.redo_stack = redo_stack;
+        result
+    }
+
+    /// Returns the formulas with the indices of their cells.
+    fn formulas(&self) -> impl Iterator<Item = (usize, &Formula)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cell)| match &cell.content {
+                CellContent::Formula(formula) => Some((index, formula)),
+                _ => None,
+            })
+    }
+
+    /// Returns the indices of all cells containing a formula.
+    fn formula_indices(&self) -> Vec<usize> {
+        (0..self.cells.len())
+            .filter(|&index| self.cells[index].content.is_formula())
+            .collect()
+    }
+
+    /// Evaluates only the formulas, which depend on the cell at `changed`
+    /// (directly or through other formulas), and the cell itself. All other
+    /// formulas have to be up to date already, so this is meant to be called
+    /// after every single edit instead of [`Spreadsheet::evaluate`].
+    pub fn evaluate_dirty(&mut self, changed: (usize, usize)) {
+        if changed.0 >= self.width || changed.1 >= self.height {
+            return;
+        }
+        let graph = match self.dependency_graph.take() {
+            Some(graph) if graph.is_up_to_date(self) => graph,
+            _ => DependencyGraph::new(self),
+        };
+        let mut affected = vec![false; self.cells.len()];
+        let mut pending = vec![self.index(changed)];
+        while let Some(index) = pending.pop() {
+            if !std::mem::replace(&mut affected[index], true) {
+                pending.extend(graph.dependents.get(&index).into_iter().flatten());
+            }
+        }
+        self.dependency_graph = Some(graph);
+        let formulas: Vec<usize> = (0..self.cells.len())
+            .filter(|&index| affected[index] && self.cells[index].content.is_formula())
+            .collect();
+        self.evaluate_formulas(&formulas);
+    }
+
+    /// Evaluates the formulas at the indices `formulas` after the ones they
+    /// depend on. Formulas not in `formulas` are not evaluated again.
+    fn evaluate_formulas(&mut self, formulas: &[usize]) {
+        let (mut missing_dependencies, dependents) = self.formula_dependents(formulas);
+        let mut ready: VecDeque<usize> = formulas
+            .iter()
+            .copied()
+            .filter(|&index| missing_dependencies[index] == 0)
+            .collect();
+        let mut evaluated = vec![false; self.cells.len()];
+        while let Some(index) = ready.pop_front() {
+            let mut cell = self.cells[index].clone();
+            cell.evaluate(self);
+            self.cells[index] = cell;
+            evaluated[index] = true;
+            for &dependent in dependents.get(&index).into_iter().flatten() {
+                missing_dependencies[dependent] -= 1;
+                if missing_dependencies[dependent] == 0 {
+                    ready.push_back(dependent);
+                }
+            }
+        }
+        self.mark_cycles(formulas, &evaluated);
+    }
+
+    /// Returns how many of the other formulas in `formulas` each cell depends
+    /// on and the formulas depending on each cell.
+    fn formula_dependents(&self, formulas: &[usize]) -> (Vec<usize>, HashMap<usize, Vec<usize>>) {
+        let size = (self.width, self.height);
+        let mut is_pending = vec![false; self.cells.len()];
+        for &index in formulas {
+            is_pending[index] = true;
+        }
+        let mut missing_dependencies = vec![0; self.cells.len()];
+        let mut dependents: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &index in formulas {
+            let CellContent::Formula(formula) = &self.cells[index].content else {
+                continue;
+            };
+            // A formula never sees its own cell, so it does not depend on it.
+            for dependency in formula
+                .referenced_positions(size)
+                .into_iter()
+                .map(|position| self.index(position))
+                .filter(|&dependency| dependency != index)
+                .filter(|&dependency| is_pending[dependency])
+            {
+                missing_dependencies[index] += 1;
+                dependents.entry(dependency).or_default().push(index);
+            }
+        }
+        (missing_dependencies, dependents)
+    }
+
+    /// Marks the formulas in `formulas`, which were not evaluated, as part
+    /// of a cycle.
+    fn mark_cycles(&mut self, formulas: &[usize], evaluated: &[bool]) {
+        for &index in formulas {
+            if let (false, CellContent::Formula(formula)) =
+                (evaluated[index], &mut self.cells[index].content)
+            {
+                formula.mark_as_cycle();
+            }
+        }
+    }
+
+    /// Serializes the spreadsheet as csv with the separator it was loaded
+    /// with, see [`Spreadsheet::serialize_csv_with`].
+    pub fn serialize_as_csv(&self) -> String {
+        self.serialize_csv_with(&csv::CsvWriteOptions {
+            separator: self.csv_separator,
+            ..Default::default()
+        })
+    }
+
+    /// Serializes the spreadsheet as csv. Rows after the last row with
+    /// content are left out.
+    pub fn serialize_csv_with(&self, options: &csv::CsvWriteOptions) -> String {
+        let (_, last) = self.used_range();
+        let columns = if options.trailing_empty_columns {
+            self.width
         } else {
-            return Err(&cell[i..]);
+            last.0 + 1
+        };
+        let mut result = String::new();
+        // Rows hidden by a filter are left out as well.
+        let rows = self
+            .as_rows()
+            .take(last.1 + 1)
+            .enumerate()
+            .filter(|(row, _)| !self.is_row_hidden(*row));
+        for (y, (_, row)) in rows.enumerate() {
+            if y != 0 {
+                result.push_str(options.line_ending.as_str());
+            }
+            for (x, cell) in row.iter().take(columns).enumerate() {
+                if x != 0 {
+                    result.push(options.separator);
+                }
+                result.push_str(&options.escape(&cell.serialize_display_content()));
+            }
         }
+        result
     }
-    if cell.is_empty() || is_at_column {
-        Err(cell)
-    } else {
-        Ok((x, y))
+
+    /// Serializes the cells inside the rectangle between `from` and `to`
+    /// (both inclusive) as csv.
+    pub fn cell_range_as_csv(&self, from: (usize, usize), to: (usize, usize)) -> String {
+        self.cell_range_with_separator(from, to, ',')
     }
-}
 
-pub fn cell_position_to_name((x, y): (usize, usize)) -> String {
-    CellPosition(x, y).name()
+    /// Serializes the cells inside the rectangle between `from` and `to`
+    /// (both inclusive) with tabs between the cells, like other spreadsheet
+    /// applications put them into the clipboard.
+    pub fn cell_range_as_tsv(&self, from: (usize, usize), to: (usize, usize)) -> String {
+        self.cell_range_with_separator(from, to, '\t')
+    }
+
+    fn cell_range_with_separator(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+        separator: char,
+    ) -> String {
+        let mut result = String::new();
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            for x in from.0.min(to.0)..=from.0.max(to.0) {
+                if x != from.0.min(to.0) {
+                    result.push(separator);
+                }
+                let cell = self.cell_at((x, y));
+                result.push_str(&csv::escape(&cell.serialize_display_content(), separator));
+            }
+        }
+        result
+    }
+
+    /// Serializes the spreadsheet as a json array of objects, which can be
+    /// read by pandas with `orient='records'`. If there are fixed rows, the
+    /// first row is used for the keys, otherwise they are named `col_0`,
+    /// `col_1` and so on.
+    pub fn serialize_as_dataframe_json(&self) -> String {
+        let keys = self.json_keys(self.width, self.fixed_rows >= 1);
+        self.json_records(&keys, self.as_rows().skip(self.fixed_rows))
+    }
+
+    /// Serializes the rows below the first row as a json array of objects,
+    /// which are keyed by the first row. Empty keys are named `col_0`,
+    /// `col_1` and so on. Like csv, only the used range is written and rows
+    /// hidden by a filter are left out.
+    pub fn serialize_as_json(&self) -> String {
+        let (_, last) = self.used_range();
+        let keys = self.json_keys(last.0 + 1, true);
+        let rows = self
+            .visible_rows()
+            .filter(|&row| row > 0 && row <= last.1)
+            .map(|row| &self.row_at(row).unwrap()[..keys.len()]);
+        self.json_records(&keys, rows)
+    }
+
+    /// The keys of the first `columns` columns, which are the contents of
+    /// the first row if `from_first_row` is set. Keys, which were used by a
+    /// column before, get a suffix like `name_2`, so no column overwrites
+    /// another one.
+    fn json_keys(&self, columns: usize, from_first_row: bool) -> Vec<String> {
+        let mut used = HashSet::new();
+        (0..columns)
+            .map(|column| {
+                let header = if from_first_row {
+                    self.cell_at((column, 0)).display_content()
+                } else {
+                    "".into()
+                };
+                let header = if header.is_empty() {
+                    format!("col_{column}")
+                } else {
+                    header.into_owned()
+                };
+                let mut key = header.clone();
+                for suffix in 2.. {
+                    if used.insert(key.clone()) {
+                        break;
+                    }
+                    key = format!("{header}_{suffix}");
+                }
+                key
+            })
+            .collect()
+    }
+
+    fn json_records<'a>(&self, keys: &[String], rows: impl Iterator<Item = &'a [Cell]>) -> String {
+        let records: Vec<serde_json::Value> = rows
+            .map(|row| {
+                keys.iter()
+                    .cloned()
+                    .zip(row.iter().map(|cell| cell.content.to_json_value()))
+                    .collect::<serde_json::Map<_, _>>()
+                    .into()
+            })
+            .collect();
+        serde_json::to_string(&records).expect("Failed to convert to json?")
+    }
+
+    /// Serializes the spreadsheet as a html `<table>`. The fixed rows become
+    /// the `<thead>`, and the background colors of the format rules are
+    /// written as inline css.
+    pub fn serialize_as_html(&self) -> String {
+        // There can be more fixed rows than rows, `<thead>` still has to be
+        // closed after the last row.
+        let fixed_rows = self.fixed_rows.min(self.height);
+        let mut result = String::from("<table>\n");
+        for (row_index, row) in self.as_rows().enumerate() {
+            let is_header = row_index < fixed_rows;
+            if row_index == 0 && is_header {
+                result.push_str("<thead>\n");
+            } else if row_index == fixed_rows {
+                result.push_str("<tbody>\n");
+            }
+            match self.row_background(row_index) {
+                Some((r, g, b)) => writeln!(
+                    result,
+                    "<tr style=\"background-color:#{r:02x}{g:02x}{b:02x}\">"
+                )
+                .unwrap(),
+                None => result.push_str("<tr>\n"),
+            }
+            let tag = if is_header { "th" } else { "td" };
+            for cell in row {
+                let attributes = if cell.content.is_error() {
+                    " class=\"error\""
+                } else if cell.content.numeric_value().is_some() {
+                    " class=\"number\" style=\"text-align:right\""
+                } else {
+                    ""
+                };
+                writeln!(
+                    result,
+                    "<{tag}{attributes}>{}</{tag}>",
+                    escape_html(&cell.display_content())
+                )
+                .unwrap();
+            }
+            result.push_str("</tr>\n");
+            if row_index + 1 == fixed_rows {
+                result.push_str("</thead>\n");
+            }
+        }
+        if fixed_rows < self.height {
+            result.push_str("</tbody>\n");
+        }
+        result.push_str("</table>\n");
+        result
+    }
+
+    /// Serializes the spreadsheet as a GitHub flavored markdown table. The
+    /// first fixed row is used as the header, otherwise the names of the
+    /// columns are. Each column is aligned like its first cell with content
+    /// below the header. Rows hidden by a filter are left out.
+    pub fn serialize_as_markdown(&self) -> String {
+        let (_, last) = self.used_range();
+        let columns = last.0 + 1;
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let body = if self.fixed_rows == 0 {
+            rows.push(
+                (0..columns)
+                    .map(|column| self.column_header(column).into_owned())
+                    .collect(),
+            );
+            0
+        } else {
+            1
+        };
+        rows.extend(
+            self.visible_rows()
+                .take_while(|&row| row <= last.1)
+                .map(|row| {
+                    (0..columns)
+                        .map(|column| self.cell_at((column, row)).display_content().into_owned())
+                        .collect()
+                }),
+        );
+        let alignments = (0..columns)
+            .map(|column| {
+                let cell = (body..=last.1)
+                    .map(|row| self.cell_at((column, row)))
+                    .find(|cell| !cell.is_empty())?;
+                cell.style()
+                    .alignment
+                    .or_else(|| cell.is_right_aligned().then_some(CellAlignment::Right))
+            })
+            .collect();
+        markdown::write(&markdown::MarkdownTable { rows, alignments })
+    }
+
+    /// Draws the cells between `from` and `to` as a svg image with their
+    /// borders and styles, like the terminal shows them.
+    pub fn serialize_range_as_svg(&self, from: (usize, usize), to: (usize, usize)) -> String {
+        svg::write(self, from, to)
+    }
+
+    pub fn save_range_as_svg(
+        &self,
+        path: impl AsRef<Path>,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_range_as_svg(from, to))?;
+        Ok(())
+    }
+
+    pub fn save_as_csv(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_as_csv())?;
+        Ok(())
+    }
+
+    /// Saves the spreadsheet as csv like [`Spreadsheet::serialize_csv_with`].
+    pub fn save_csv_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: &csv::CsvWriteOptions,
+    ) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_csv_with(options))?;
+        Ok(())
+    }
+
+    /// Saves the cells between `from` and `to` (both inclusive) as csv with
+    /// `separator`.
+    pub fn save_range_as_csv(
+        &self,
+        path: impl AsRef<Path>,
+        from: (usize, usize),
+        to: (usize, usize),
+        separator: char,
+    ) -> Result<(), Error> {
+        let csv = self.cell_range_with_separator(from, to, separator);
+        std::fs::write(path, csv)?;
+        Ok(())
+    }
+
+    /// Returns `true` if the spreadsheet changed since it was loaded or
+    /// [`Spreadsheet::mark_saved`] was called.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+
+    pub fn csv_separator(&self) -> char {
+        self.csv_separator
+    }
+
+    pub fn set_csv_separator(&mut self, separator: char) {
+        self.csv_separator = separator;
+    }
+
+    pub fn save_as_xlsx(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut spreadsheet = umya_spreadsheet::new_file();
+        let worksheet = spreadsheet
+            .get_sheet_mut(&0)
+            .map_err(|err| Error::Xlsx(err.into()))?;
+        worksheet.set_name("Sheet!").set_active_cell(format!(
+            "{}{}",
+            to_column_name(self.current_cell.0),
+            self.current_cell.1 + 1
+        ));
+        if self.fixed_rows > 0 || self.fixed_columns > 0 {
+            // The split of the pane is at (xSplit, ySplit), umya calls them
+            // the horizontal and the vertical split.
+            let mut pane = umya_spreadsheet::Pane::default();
+            if self.fixed_rows > 0 {
+                pane.set_vertical_split(self.fixed_rows as f64);
+            }
+            if self.fixed_columns > 0 {
+                pane.set_horizontal_split(self.fixed_columns as f64);
+            }
+            let active_pane = match (self.fixed_rows, self.fixed_columns) {
+                (_, 0) => umya_spreadsheet::PaneValues::BottomLeft,
+                (0, _) => umya_spreadsheet::PaneValues::TopRight,
+                _ => umya_spreadsheet::PaneValues::BottomRight,
+            };
+            pane.set_state(umya_spreadsheet::PaneStateValues::Frozen)
+                .set_active_pane(active_pane)
+                .get_top_left_cell_mut()
+                .set_col_num(self.fixed_columns as u32 + 1)
+                .set_row_num(self.fixed_rows as u32 + 1);
+            let sheet_views = worksheet.get_sheet_views_mut().get_sheet_view_list_mut();
+            if sheet_views.is_empty() {
+                sheet_views.push(umya_spreadsheet::SheetView::default());
+            }
+            sheet_views[0].set_pane(pane);
+        }
+        for column in 0..self.columns() {
+            // TODO: Find out if column is zero based or one based, because we
+            // use it differently here then down below..
+            worksheet
+                .get_column_dimension_by_number_mut(&(column as u32))
+                .set_width(self.column_width(column) as f64);
+            for row in 0..self.rows() {
+                let content = &self.cell_at((column, row)).content;
+                let cell = worksheet.get_cell_mut((&(column as u32 + 1), &(row as u32 + 1)));
+                match content.date_serial() {
+                    Some(serial) => cell.set_value_number(serial),
+                    None => cell.set_value(content.serialize_display()),
+                };
+                if let Some(url) = content.url() {
+                    let mut link = umya_spreadsheet::Hyperlink::default();
+                    link.set_url(url);
+                    cell.set_hyperlink(link);
+                }
+                let style = worksheet.get_style_mut((&(column as u32 + 1), &(row as u32 + 1)));
+                let unit = match content {
+                    CellContent::Date(_) => UnitKind::Date,
+                    CellContent::DateTime(_) => UnitKind::Custom("yyyy-mm-dd h:mm:ss".into()),
+                    _ => self.cell_at((column, row)).unit.clone(),
+                };
+                style.set_numbering_format(unit.into());
+                self.cell_at((column, row)).style.apply_to(style);
+            }
+        }
+        for merged in &self.merged_cells {
+            worksheet.add_merge_cells(format!(
+                "{}{}:{}{}",
+                to_column_name(merged.from.0),
+                merged.from.1 + 1,
+                to_column_name(merged.to.0),
+                merged.to.1 + 1
+            ));
+        }
+        umya_spreadsheet::writer::xlsx::write(&spreadsheet, path)
+            .map_err(|err| Error::Xlsx(err.to_string()))
+    }
+
+    /// Saves the spreadsheet as an OpenDocument spreadsheet. Formulas are
+    /// saved as text, since other applications can not evaluate them.
+    pub fn save_as_ods(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        ods::write(path.as_ref(), self.as_rows(), self.width)
+    }
+
+    pub fn save_as_json(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_as_json())?;
+        Ok(())
+    }
+
+    pub fn save_as_markdown(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, self.serialize_as_markdown())?;
+        Ok(())
+    }
+
+    pub fn recommended_cell_content(&self, position: (usize, usize)) -> CellContent {
+        self.continued_cell_content(position, self.current_cell())
+    }
+
+    /// Returns the content of the cell at `from` as it would continue at
+    /// `to`. Formulas reference the cells relative to `to`. Numbers, dates,
+    /// names like `Mon` and text ending with a number continue the series
+    /// of the cell before `from` (above it or, when `to` is in the same row,
+    /// left of it) and `from`, see [`series::continue_series`].
+    fn continued_cell_content(&self, from: (usize, usize), to: (usize, usize)) -> CellContent {
+        let content = &self.cell_at(from).content;
+        if let CellContent::Formula(f) = content {
+            return CellContent::Formula(
+                f.moved_to(CellPosition(to.0, to.1), (self.width, self.height)),
+            );
+        }
+        let distance = (to.0 as i64 - from.0 as i64) + (to.1 as i64 - from.1 as i64);
+        let previous = if to.1 == from.1 {
+            from.0.checked_sub(1).map(|x| (x, from.1))
+        } else {
+            from.1.checked_sub(1).map(|y| (from.0, y))
+        };
+        let previous = previous.map(|position| &self.cell_at(position).content);
+        series::continue_series(previous, content, distance).unwrap_or_else(|| content.clone())
+    }
+
+    /// Returns the cells inside the rectangle between `from` and `to` (both
+    /// inclusive) row by row, in the format expected by
+    /// [`Spreadsheet::paste_range`].
+    pub fn copy_range(&self, from: (usize, usize), to: (usize, usize)) -> Vec<Vec<String>> {
+        (from.1.min(to.1)..=from.1.max(to.1))
+            .map(|y| {
+                (from.0.min(to.0)..=from.0.max(to.0))
+                    .map(|x| {
+                        self.cell_at((x, y))
+                            .serialize_display_content()
+                            .into_owned()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Aggregates the cells inside the rectangle between `from` and `to`
+    /// (both inclusive). Rows hidden by a filter are skipped.
+    pub fn aggregate_range(&self, from: (usize, usize), to: (usize, usize)) -> Aggregates {
+        let mut result = Aggregates::default();
+        let cells = (from.1.min(to.1)..=from.1.max(to.1))
+            .filter(|&y| !self.is_row_hidden(y))
+            .flat_map(|y| (from.0.min(to.0)..=from.0.max(to.0)).map(move |x| (x, y)))
+            .map(|position| self.cell_at(position))
+            .filter(|cell| !cell.is_empty());
+        for cell in cells {
+            result.count += 1;
+            if let Some(value) = cell.content.numeric_value() {
+                result.numeric_count += 1;
+                result.sum += value;
+            }
+        }
+        result.average =
+            (result.numeric_count > 0).then(|| result.sum / result.numeric_count as f64);
+        result
+    }
+
+    /// Empties the cells inside the rectangle between `from` and `to` (both
+    /// inclusive).
+    pub fn clear_range(&mut self, from: (usize, usize), to: (usize, usize)) {
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            for x in from.0.min(to.0)..=from.0.max(to.0) {
+                self.update_cell_at((x, y), CellContent::Empty);
+            }
+        }
+    }
+
+    /// Fills the rectangle between `from` and `to` (both inclusive) with the
+    /// content of the cell at `from`, like [`Spreadsheet::recommended_cell_content`].
+    pub fn fill_range(&mut self, from: (usize, usize), to: (usize, usize)) {
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            for x in from.0.min(to.0)..=from.0.max(to.0) {
+                if (x, y) != from {
+                    let content = self.continued_cell_content(from, (x, y));
+                    self.update_cell_at((x, y), content);
+                }
+            }
+        }
+    }
+
+    /// Pastes text from the clipboard starting at `at`. Text with tabs is read
+    /// as cells written by [`Spreadsheet::cell_range_as_tsv`] and other text
+    /// with line breaks as csv, whose separator is detected like in
+    /// [`Spreadsheet::load_csv`]. Any other text becomes a single cell, even
+    /// if it contains commas.
+    pub fn paste_text(&mut self, at: (usize, usize), text: &str) -> Result<(), Error> {
+        let text = text.trim_end_matches(['\r', '\n']);
+        if text.contains('\t') {
+            let tsv = csv::CsvFile::parse_with_seperator(text, '\t')?;
+            self.paste_range(at, tsv.rows());
+        } else if text.contains('\n') {
+            let csv: csv::CsvFile = text.parse()?;
+            self.paste_range(at, csv.rows());
+        } else {
+            let content = CellContent::parse(text, at, (self.width, self.height));
+            self.update_cell_at(at, content);
+        }
+        Ok(())
+    }
+
+    /// Parses the given rows into the cells starting at `at`. The
+    /// spreadsheet is resized, if the pasted cells do not fit.
+    pub fn paste_range<'a>(
+        &mut self,
+        at: (usize, usize),
+        rows: impl IntoIterator<Item = &'a [String]>,
+    ) {
+        for (y, row) in rows.into_iter().enumerate() {
+            let y = at.1 + y;
+            for (x, text) in row.iter().enumerate() {
+                let x = at.0 + x;
+                if x >= self.width || y >= self.height {
+                    self.resize(self.width.max(x + 1), self.height.max(y + 1));
+                }
+                let content = CellContent::parse(text, (x, y), (self.width, self.height));
+                self.update_cell_at((x, y), content);
+            }
+        }
+    }
+
+    pub fn update_cell_at(&mut self, cell_position: (usize, usize), cell_content: CellContent) {
+        if self.readonly {
+            return;
+        }
+        self.dirty = true;
+        let index = self.index(cell_position);
+        self.cells[index].content = cell_content;
+    }
+
+    /// Sets the cell at `position` to `value` without parsing it. The
+    /// spreadsheet is resized, if the position is outside of it. Read-only
+    /// spreadsheets return [`Error::ReadOnly`].
+    pub fn set_value(
+        &mut self,
+        position: (usize, usize),
+        value: impl Into<CellContent>,
+    ) -> Result<(), Error> {
+        if self.readonly {
+            return Err(Error::ReadOnly);
+        }
+        let (x, y) = position;
+        if x >= self.width || y >= self.height {
+            self.resize(self.width.max(x + 1), self.height.max(y + 1));
+        }
+        self.update_cell_at(position, value.into());
+        Ok(())
+    }
+
+    /// Sets the cells of row `y` starting in the first column to `values`.
+    /// Cells right of the values are left unchanged. Read-only spreadsheets
+    /// return [`Error::ReadOnly`].
+    pub fn set_row(&mut self, y: usize, values: &[CellContent]) -> Result<(), Error> {
+        for (x, value) in values.iter().enumerate() {
+            self.set_value((x, y), value.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the numeric value of the cell at `position` or `None`, if it
+    /// does not contain a number or is outside of the spreadsheet.
+    pub fn get_number(&self, position: (usize, usize)) -> Option<f64> {
+        if position.0 >= self.width || position.1 >= self.height {
+            return None;
+        }
+        self.cell_at(position).content.numeric_value()
+    }
+
+    /// Replaces every occurrence of `pattern` in the cells with
+    /// `replacement` and returns the number of replaced occurrences. Only
+    /// text, the shown text of hyperlinks and formulas are changed. Formulas
+    /// are changed as text (including the leading `=`) and parsed again.
+    pub fn replace_all(&mut self, pattern: &str, replacement: &str) -> usize {
+        self.replace_in_cells(&Pattern::Text(pattern.into()), replacement)
+    }
+
+    /// Replaces every occurrence of `pattern` in the next cell containing it.
+    /// Like [`Spreadsheet::find_with`] the search starts after the current
+    /// cell and the position of the changed cell is returned. The cursor is
+    /// not moved, so the caller can move its view along with it.
+    pub fn replace_next(&mut self, pattern: &str, replacement: &str) -> Option<(usize, usize)> {
+        self.replace_in_next_cell(&Pattern::Text(pattern.into()), replacement)
+    }
+
+    /// Like [`Spreadsheet::replace_next`], but with a regex.
+    pub fn replace_next_regex(
+        &mut self,
+        regex: &str,
+        replacement: &str,
+    ) -> Result<Option<(usize, usize)>, Error> {
+        let pattern = Pattern::regex(regex, false)?;
+        Ok(self.replace_in_next_cell(&pattern, replacement))
+    }
+
+    /// Like [`Spreadsheet::replace_all`], but with a regex. The replacement
+    /// can refer to groups like `$1`.
+    pub fn replace_all_regex(&mut self, regex: &str, replacement: &str) -> Result<usize, Error> {
+        let pattern = Pattern::regex(regex, false)?;
+        Ok(self.replace_in_cells(&pattern, replacement))
+    }
+
+    /// Counts the occurrences of `pattern`, which
+    /// [`Spreadsheet::replace_all`] would replace.
+    pub fn count_occurrences(&self, pattern: &str) -> usize {
+        self.count_in_cells(&Pattern::Text(pattern.into()))
+    }
+
+    /// Like [`Spreadsheet::count_occurrences`], but with a regex.
+    pub fn count_occurrences_regex(&self, regex: &str) -> Result<usize, Error> {
+        Ok(self.count_in_cells(&Pattern::regex(regex, false)?))
+    }
+
+    fn count_in_cells(&self, pattern: &Pattern) -> usize {
+        self.cells
+            .iter()
+            .filter_map(|cell| cell.content.replaceable_text())
+            .map(|text| pattern.count(&text))
+            .sum()
+    }
+
+    /// Replaces `pattern` in every cell and returns the number of replaced
+    /// occurrences.
+    fn replace_in_cells(&mut self, pattern: &Pattern, replacement: &str) -> usize {
+        let size = (self.width, self.height);
+        let mut result = 0;
+        for cell in &mut self.cells {
+            let Some((content, count)) =
+                cell.content
+                    .replaced(pattern, replacement, cell.position(), size)
+            else {
+                continue;
+            };
+            cell.content = content;
+            result += count;
+        }
+        if result > 0 {
+            self.dirty = true;
+        }
+        result
+    }
+
+    /// Replaces `pattern` in the first cell containing it after the current
+    /// one and returns its position.
+    fn replace_in_next_cell(
+        &mut self,
+        pattern: &Pattern,
+        replacement: &str,
+    ) -> Option<(usize, usize)> {
+        let size = (self.width, self.height);
+        let index = self.index(self.current_cell());
+        let (position, content) = self.cells_from(index + 1).find_map(|cell| {
+            cell.content
+                .replaced(pattern, replacement, cell.position(), size)
+                .map(|(content, _)| (cell.position(), content))
+        })?;
+        self.cell_at_mut(position).content = content;
+        self.dirty = true;
+        Some(position)
+    }
+
+    /// Replaces every cell in `column` with the result of `f`, which is
+    /// called with the old content and the row of the cell.
+    pub fn apply_to_column<F>(&mut self, column: usize, f: F)
+    where
+        F: Fn(&CellContent, usize) -> CellContent,
+    {
+        for row in 0..self.height {
+            let content = f(&self.cell_at((column, row)).content, row);
+            self.update_cell_at((column, row), content);
+        }
+    }
+
+    /// Replaces every non empty cell in `column` (except for fixed rows) with
+    /// the result of the python `expression`, where `x` is the old value of
+    /// the cell. Cells, for which the expression fails, are not changed and
+    /// the first error is returned.
+    pub fn apply_expression_to_column(
+        &mut self,
+        column: usize,
+        expression: &str,
+    ) -> Result<(), Error> {
+        let expression = Expression::compile(expression)?;
+        let fixed_rows = self.fixed_rows;
+        let error = std::cell::RefCell::new(None);
+        self.apply_to_column(column, |content, row| {
+            if row < fixed_rows || content.is_empty() {
+                return content.clone();
+            }
+            match expression.evaluate(content) {
+                Ok(it) => it,
+                Err(err) => {
+                    error.borrow_mut().get_or_insert(err);
+                    content.clone()
+                }
+            }
+        });
+        match error.into_inner() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the python `script` with the spreadsheet bound to `sheet`. Cells
+    /// are read with `sheet["A0"]` or `sheet.get(column, row)` and written
+    /// with `sheet["A0"] = value` or `sheet.set(column, row, value)`.
+    /// Iterating over `sheet` yields its rows. Written strings are parsed
+    /// like typed text, so they can be formulas, and the spreadsheet grows to
+    /// fit the written cells. Returns what the script printed.
+    pub fn run_script(&mut self, script: &str) -> Result<String, Error> {
+        let result = run_script(self, script)?;
+        if self.readonly {
+            return Ok(result.output);
+        }
+        let (columns, rows) = result.changes.iter().fold(
+            (self.width, self.height),
+            |(columns, rows), &((x, y), _)| (columns.max(x + 1), rows.max(y + 1)),
+        );
+        if (columns, rows) != (self.width, self.height) {
+            self.resize(columns, rows);
+        }
+        for (position, value) in result.changes {
+            let content = match value {
+                Value::String(it) => CellContent::parse(&it, position, (columns, rows)),
+                Value::Number(it) => CellContent::Number(it),
+                Value::FloatNumber(it) => CellContent::FloatNumber(it, 0),
+                Value::Empty | Value::Error | Value::Cycle => CellContent::Empty,
+            };
+            self.update_cell_at(position, content);
+        }
+        self.evaluate();
+        Ok(result.output)
+    }
+
+    /// Returns the cells of the row `row` or `None` if it is out of bounds.
+    pub fn row_at(&self, row: usize) -> Option<&[Cell]> {
+        if row >= self.height {
+            None
+        } else {
+            Some(&self.cells[row * self.width..(row + 1) * self.width])
+        }
+    }
+
+    pub fn as_rows(&self) -> SpreadsheetRowIter<'_> {
+        SpreadsheetRowIter {
+            spreadsheet: self,
+            index: 0,
+            back_index: self.height,
+        }
+    }
+
+    pub fn sort_column(&mut self, column: usize) {
+        self.sort_by(&[SortKey::ascending(column)]);
+    }
+
+    /// Sorts the rows after the fixed rows by the columns of the keys. Later
+    /// keys only decide between rows, which are equal for all keys before.
+    /// The sort is stable, so rows equal for all keys keep their order.
+    pub fn sort_by(&mut self, keys: &[SortKey]) {
+        let fixed_rows = self.fixed_rows.min(self.height);
+        let mut rows: Vec<_> = (fixed_rows..self.height)
+            .filter_map(|row| {
+                Some((
+                    self.row_at(row)?,
+                    (row, self.is_row_hidden(row), self.row_height(row)),
+                ))
+            })
+            .collect();
+        let keys: Vec<_> = keys
+            .iter()
+            .filter(|k| k.column < self.width)
+            .map(|key| (key, self.column_type(key.column)))
+            .collect();
+        rows.sort_by(|(a, _), (b, _)| {
+            keys.iter()
+                .map(|(key, column_type)| {
+                    key.compare(&a[key.column].content, &b[key.column].content, *column_type)
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        // Hidden rows stay hidden after they moved, rows keep their height
+        // and merged cells move with their rows.
+        let hidden_rows = (0..fixed_rows)
+            .map(|row| self.is_row_hidden(row))
+            .chain(rows.iter().map(|(_, (_, hidden, _))| *hidden))
+            .collect();
+        let row_heights: Vec<_> = (0..fixed_rows)
+            .map(|row| self.row_height(row))
+            .chain(rows.iter().map(|(_, (_, _, height))| *height))
+            .collect();
+        let mut new_rows: Vec<_> = (0..self.height).collect();
+        for (index, (_, (row, _, _))) in rows.iter().enumerate() {
+            new_rows[*row] = fixed_rows + index;
+        }
+        let rows = rows.into_iter().map(|(row, _)| row);
+        self.cells = (0..fixed_rows)
+            .filter_map(|row| self.row_at(row))
+            .chain(rows)
+            .flatten()
+            .cloned()
+            .collect();
+        self.hidden_rows = hidden_rows;
+        self.row_heights = row_heights;
+        self.trim_row_heights();
+        self.remap_merged_cells(|c| c, |r| new_rows[r]);
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            cell.position = CellPosition::from_index(index, self.width);
+        }
+    }
+
+    pub fn fit_column_width(&mut self, column: usize) {
+        let width = self
+            .as_rows()
+            .map(|r| r[column].display_content().as_ref().width())
+            .fold(0, |a, w| a.max(w));
+        self.set_column_width(column, width + 1);
+    }
+
+    pub fn fix_rows(&mut self, fixed_rows: usize) {
+        self.fixed_rows = fixed_rows;
+    }
+
+    pub fn fix_columns(&mut self, fixed_columns: usize) {
+        self.fixed_columns = fixed_columns;
+    }
+
+    pub fn fixed_rows(&self) -> usize {
+        self.fixed_rows
+    }
+
+    pub fn fixed_columns(&self) -> usize {
+        self.fixed_columns
+    }
+
+    pub fn add_column_format_rule(
+        &mut self,
+        column: usize,
+        threshold: f64,
+        above_color: (u8, u8, u8),
+        below_color: (u8, u8, u8),
+    ) {
+        self.add_format_rule(FormatRule {
+            column,
+            threshold,
+            above_color: Some(above_color),
+            below_color: Some(below_color),
+        });
+    }
+
+    pub fn add_format_rule(&mut self, rule: FormatRule) {
+        self.format_rules.push(rule);
+    }
+
+    pub fn format_rules(&self) -> &[FormatRule] {
+        &self.format_rules
+    }
+
+    /// Merges the cells from `from` to `to`, so they are shown as one cell.
+    /// The contents of all cells but the top left one are cleared. Merged
+    /// cells overlapping the new ones are unmerged first.
+    pub fn merge_cells(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let merged = MergedCells {
+            from: (from.0.min(to.0), from.1.min(to.1)),
+            to: (
+                from.0.max(to.0).min(self.width - 1),
+                from.1.max(to.1).min(self.height - 1),
+            ),
+        };
+        if merged.from == merged.to {
+            return;
+        }
+        for row in merged.from.1..=merged.to.1 {
+            for column in merged.from.0..=merged.to.0 {
+                if (column, row) != merged.from {
+                    let index = self.index((column, row));
+                    self.cells[index].content = CellContent::Empty;
+                }
+            }
+        }
+        self.merged_cells.retain(|it| !it.overlaps(&merged));
+        self.merged_cells.push(merged);
+    }
+
+    /// Unmerges the merged cells containing `position`. Returns `false` if
+    /// the cell is not merged.
+    pub fn unmerge_cells(&mut self, position: (usize, usize)) -> bool {
+        let count = self.merged_cells.len();
+        self.merged_cells.retain(|it| !it.contains(position));
+        count != self.merged_cells.len()
+    }
+
+    pub fn merged_cells_at(&self, position: (usize, usize)) -> Option<MergedCells> {
+        self.merged_cells
+            .iter()
+            .find(|it| it.contains(position))
+            .copied()
+    }
+
+    pub fn merged_cells(&self) -> &[MergedCells] {
+        &self.merged_cells
+    }
+
+    /// Returns the background color of `row` according to the format rules.
+    /// If multiple rules apply, the one added last wins.
+    pub fn row_background(&self, row: usize) -> Option<(u8, u8, u8)> {
+        self.format_rules.iter().rev().find_map(|rule| {
+            if rule.column >= self.width || row >= self.height {
+                return None;
+            }
+            let value = self.cell_at((rule.column, row)).content.numeric_value()?;
+            rule.color_for(value)
+        })
+    }
+
+    /// Disables editing of cells. This is not saved with the spreadsheet.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    pub fn is_row_hidden(&self, row: usize) -> bool {
+        self.hidden_rows.get(row).copied().unwrap_or_default()
+    }
+
+    /// Returns `true` if a filter hides at least one row.
+    pub fn is_filtered(&self) -> bool {
+        self.hidden_rows.contains(&true)
+    }
+
+    /// Makes all rows hidden by a filter visible again.
+    pub fn clear_filter(&mut self) {
+        self.hidden_rows.fill(false);
+    }
+
+    /// Hides every row after the fixed rows, which does not match `filter`.
+    /// Rows hidden by earlier filters stay hidden, so filters can be
+    /// combined. If no row matches, nothing is hidden. Returns the number of
+    /// matching rows.
+    pub fn apply_filter(&mut self, filter: &Filter) -> usize {
+        if filter.column >= self.width {
+            return 0;
+        }
+        let fixed_rows = self.fixed_rows.min(self.height);
+        let hidden: Vec<_> = (0..self.height)
+            .map(|row| {
+                self.is_row_hidden(row)
+                    || (row >= fixed_rows && !filter.matches(self.cell_at((filter.column, row))))
+            })
+            .collect();
+        let matches = hidden[fixed_rows..].iter().filter(|h| !**h).count();
+        if matches == 0 {
+            return 0;
+        }
+        self.hidden_rows = hidden;
+        // The cursor moves to the next visible row.
+        let row = self.display_row(self.current_cell.1);
+        self.current_cell.1 = self.row_at_display(row.min(self.visible_row_count() - 1));
+        matches
+    }
+
+    /// The rows, which are not hidden by a filter, in order.
+    pub fn visible_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.height).filter(|&row| !self.is_row_hidden(row))
+    }
+
+    pub fn visible_row_count(&self) -> usize {
+        self.height
+            - self
+                .hidden_rows
+                .iter()
+                .take(self.height)
+                .filter(|h| **h)
+                .count()
+    }
+
+    /// Returns the number of visible rows before `row`, which is where `row`
+    /// is shown if rows are hidden by a filter.
+    pub fn display_row(&self, row: usize) -> usize {
+        row - self.hidden_rows.iter().take(row).filter(|h| **h).count()
+    }
+
+    /// The inverse of [`Spreadsheet::display_row`]. Rows past the last
+    /// visible row are counted as if they were visible.
+    pub fn row_at_display(&self, display_row: usize) -> usize {
+        let visible_rows = self.visible_row_count();
+        if display_row >= visible_rows {
+            self.height + display_row - visible_rows
+        } else {
+            self.visible_rows().nth(display_row).unwrap()
+        }
+    }
+
+    /// Remembers the current state, so that the following changes can be
+    /// undone with [`Spreadsheet::undo`].
+    ///
+    /// Since a checkpoint is taken before every change, this also marks the
+    /// spreadsheet as dirty.
+    pub fn checkpoint(&mut self) {
+        if self.readonly {
+            return;
+        }
+        self.dirty = true;
+        if self.undo_stack.len() == MAX_UNDO_STEPS {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(self.snapshot());
+        self.redo_stack.clear();
+    }
+
+    /// Restores the state of the last checkpoint. Returns `false` if there
+    /// is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Restores the state before the last [`Spreadsheet::undo`]. Returns
+    /// `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.clone(),
+            column_widths: self.column_widths.clone(),
+            column_headers: self.column_headers.clone(),
+            fixed_rows: self.fixed_rows,
+            fixed_columns: self.fixed_columns,
+            format_rules: self.format_rules.clone(),
+            hidden_rows: self.hidden_rows.clone(),
+            row_heights: self.row_heights.clone(),
+            merged_cells: self.merged_cells.clone(),
+            column_types: self.column_types.clone(),
+            csv_separator: self.csv_separator,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.width = snapshot.width;
+        self.height = snapshot.height;
+        self.cells = snapshot.cells;
+        self.column_widths = snapshot.column_widths;
+        self.column_headers = snapshot.column_headers;
+        self.fixed_rows = snapshot.fixed_rows;
+        self.fixed_columns = snapshot.fixed_columns;
+        self.format_rules = snapshot.format_rules;
+        self.hidden_rows = snapshot.hidden_rows;
+        self.row_heights = snapshot.row_heights;
+        self.merged_cells = snapshot.merged_cells;
+        self.column_types = snapshot.column_types;
+        self.csv_separator = snapshot.csv_separator;
+        self.current_cell = CellPosition(
+            self.current_cell.0.min(self.width - 1),
+            self.current_cell.1.min(self.height - 1),
+        );
+    }
+}
+
+/// Renders the spreadsheet as a plain ASCII table, like the terminal shows
+/// it.
+impl fmt::Display for Spreadsheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut separator = String::from("-----+");
+        for width in &self.column_widths {
+            separator.push_str(&"-".repeat(width + 2));
+            separator.push('+');
+        }
+        write!(f, "     |")?;
+        for column in 0..self.width {
+            let header = self.column_header(column);
+            write!(f, " {} |", pad(&header, self.column_widths[column], false))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{separator}")?;
+        for (row, cells) in self.as_rows().enumerate() {
+            write!(f, "{row:5}|")?;
+            for cell in cells {
+                let content = cell.display_content();
+                let width = self.column_widths[cell.column()];
+                write!(f, " {} |", pad(&content, width, cell.is_right_aligned()))?;
+            }
+            writeln!(f)?;
+            writeln!(f, "{separator}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Truncates or pads `text` to exactly `width` columns.
+fn pad(text: &str, width: usize, right_aligned: bool) -> String {
+    let mut result = String::with_capacity(width);
+    let mut result_width = 0;
+    for ch in text.chars() {
+        let ch_width = unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
+        if result_width + ch_width > width {
+            break;
+        }
+        result.push(ch);
+        result_width += ch_width;
+    }
+    let padding = " ".repeat(width - result_width);
+    if right_aligned {
+        result.insert_str(0, &padding);
+    } else {
+        result.push_str(&padding);
+    }
+    result
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Default for Spreadsheet {
+    fn default() -> Self {
+        Self::new(5, 5)
+    }
+}
+
+impl<'a> IntoIterator for &'a Spreadsheet {
+    type Item = &'a Cell;
+
+    type IntoIter = std::slice::Iter<'a, Cell>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.iter()
+    }
+}
+
+pub struct SpreadsheetRowIter<'a> {
+    spreadsheet: &'a Spreadsheet,
+    index: usize,
+    /// The row after the last row, which has not been returned by
+    /// [`DoubleEndedIterator::next_back`] yet.
+    back_index: usize,
+}
+
+impl<'a> Iterator for SpreadsheetRowIter<'a> {
+    type Item = &'a [Cell];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.back_index {
+            return None;
+        }
+        let row = self.spreadsheet.row_at(self.index)?;
+        self.index += 1;
+        Some(row)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.index = self.index.saturating_add(n).min(self.back_index);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back_index.saturating_sub(self.index);
+        (len, Some(len))
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+}
+
+impl<'a> DoubleEndedIterator for SpreadsheetRowIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.index >= self.back_index {
+            return None;
+        }
+        self.back_index -= 1;
+        self.spreadsheet.row_at(self.back_index)
+    }
+}
+
+impl<'a> ExactSizeIterator for SpreadsheetRowIter<'a> {}
+
+/// Returns the new index of `index` after the entry at `from` has been moved
+/// to `to`.
+fn moved_index(index: usize, from: usize, to: usize) -> usize {
+    if index == from {
+        to
+    } else if from < to && index > from && index <= to {
+        index - 1
+    } else if to < from && index >= to && index < from {
+        index + 1
+    } else {
+        index
+    }
+}
+
+/// Maps every index of `range` with `map` and returns the range of the new
+/// indices, if they are still next to each other.
+fn remapped_range(
+    range: RangeInclusive<usize>,
+    map: impl Fn(usize) -> usize,
+) -> Option<RangeInclusive<usize>> {
+    let len = range.end() - range.start();
+    let start = range.clone().map(&map).min()?;
+    let end = range.map(map).max()?;
+    (end - start == len).then_some(start..=end)
+}
+
+pub fn to_column_name(mut index: usize) -> String {
+    let mut result = String::new();
+    let letters = [
+        'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R',
+        'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+    ];
+    while index >= 26 {
+        result.insert(0, letters[index % 26]);
+        index /= 26;
+    }
+    result.insert(0, letters[index]);
+    result
+}
+
+pub fn column_name_to_index(column: &str) -> Result<usize, &str> {
+    let mut result = 0;
+    if column.is_empty() {
+        return Err(column);
+    }
+    for ch in column.chars() {
+        if ch.is_ascii_alphabetic() {
+            let ch = ch.to_ascii_uppercase();
+            let digit = (ch as u8) - b'A';
+            result = result * 26 + digit as usize;
+        } else {
+            return Err(column);
+        }
+    }
+    Ok(result)
+}
+
+pub fn cell_name_to_position(cell: &str) -> Result<(usize, usize), &str> {
+    let mut x = 0;
+    let mut y = 0;
+    let mut is_at_column = true;
+    let mut has_column = false;
+    for (i, ch) in cell.char_indices() {
+        if is_at_column && ch.is_ascii_alphabetic() && ch.is_ascii_uppercase() {
+            has_column = true;
+            let ch = ch.to_ascii_uppercase();
+            let digit = (ch as u8) - b'A';
+            x = x * 26 + digit as usize;
+        } else if is_at_column {
+            if !has_column {
+                return Err(cell);
+            }
+            is_at_column = false;
+            y = cell[i..].parse().map_err(|_| &cell[i..])?;
+            break;
+        } else {
+            return Err(&cell[i..]);
+        }
+    }
+    if cell.is_empty() || is_at_column {
+        Err(cell)
+    } else {
+        Ok((x, y))
+    }
+}
+
+pub fn cell_position_to_name((x, y): (usize, usize)) -> String {
+    CellPosition(x, y).name()
+}
+
+/// Splits a raw formula (without the leading `=`) into references,
+/// operators and everything else, for a spreadsheet of the given size.
+pub fn tokenize_formula(raw: &str, size: (usize, usize)) -> Vec<(FormulaTokenKind, &str)> {
+    cells::cell_content::Formula::tokenize(raw, size)
+}
+
+/// Returns the references of a raw formula (without the leading `=`), like
+/// [`Formula::references`] does for a parsed formula. This works on
+/// incomplete formulas as well, so references can be shown while typing.
+pub fn formula_references(raw: &str, size: (usize, usize)) -> Vec<FormulaReference> {
+    Formula::find_references(raw, size)
+}
+
+/// Translates a raw formula (without the leading `=`) into python code and
+/// returns it with the number of references found. Only exported for the
+/// fuzzing targets.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub fn parse_formula(raw: &str, size: (usize, usize)) -> (String, usize) {
+    let (parsed, references) = cells::cell_content::Formula::parse_raw(raw, size);
+    (parsed, references.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a path in the temporary directory, which includes the id of
+    /// this process and the name of the test, so tests running at the same
+    /// time never share a file.
+    fn temp_path(test: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tabelle-{}-{test}", std::process::id()))
+    }
+
+    #[test]
+    pub fn dataframe_json() {
+        let mut spreadsheet = Spreadsheet::load_csv("name,amount\napple,3\npear,1.5").unwrap();
+        assert_eq!(
+            spreadsheet.serialize_as_dataframe_json(),
+            r#"[{"col_0":"name","col_1":"amount"},{"col_0":"apple","col_1":3},{"col_0":"pear","col_1":1.5}]"#
+        );
+        spreadsheet.fix_rows(1);
+        assert_eq!(
+            spreadsheet.serialize_as_dataframe_json(),
+            r#"[{"name":"apple","amount":3},{"name":"pear","amount":1.5}]"#
+        );
+
+        let spreadsheet = Spreadsheet::load_csv("name,name,name_2,\na,b,c,d").unwrap();
+        assert_eq!(
+            spreadsheet.serialize_as_json(),
+            r#"[{"name":"a","name_2":"b","name_2_2":"c","col_3":"d"}]"#
+        );
+    }
+
+    #[test]
+    pub fn insert_rows_and_columns_update_references() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n=A0+B0,=sum(A)").unwrap();
+        spreadsheet.insert_rows_before(1, 2);
+        assert_eq!(spreadsheet.rows(), 4);
+        assert_eq!(
+            spreadsheet.cell_at((0, 3)).serialize_display_content(),
+            "=A0+B0"
+        );
+        spreadsheet.insert_rows_before(0, 1);
+        assert_eq!(
+            spreadsheet.cell_at((0, 4)).serialize_display_content(),
+            "=A1+B1"
+        );
+        spreadsheet.insert_columns_before(1, 1);
+        assert_eq!(spreadsheet.columns(), 3);
+        assert_eq!(
+            spreadsheet.cell_at((0, 4)).serialize_display_content(),
+            "=A1+C1"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((2, 4)).serialize_display_content(),
+            "=sum(A)"
+        );
+    }
+
+    #[test]
+    pub fn references_are_rewritten_where_they_are() {
+        let mut spreadsheet =
+            Spreadsheet::load_csv("=sum(a),=math.e * 2,=A0+1,,\n,,,,\n,,,,\n,,,,\n,,,,").unwrap();
+        spreadsheet.insert_rows_before(0, 1);
+        let formulas = |spreadsheet: &Spreadsheet| {
+            (0..3)
+                .map(|x| {
+                    spreadsheet
+                        .cell_at((x, 1))
+                        .serialize_display_content()
+                        .into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(formulas(&spreadsheet), ["=sum(a)", "=math.e * 2", "=A1+1"]);
+        spreadsheet.insert_columns_before(0, 1);
+        spreadsheet.move_row(1, 0);
+        spreadsheet.duplicate_column(1);
+        assert_eq!(
+            spreadsheet.cell_at((1, 0)).serialize_display_content(),
+            "=sum(B)"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((3, 0)).serialize_display_content(),
+            "=math.e * 2"
+        );
+        spreadsheet.fill_range((2, 0), (2, 1));
+        assert_eq!(
+            spreadsheet.cell_at((2, 1)).serialize_display_content(),
+            "=sum(B)"
+        );
+    }
+
+    #[test]
+    pub fn cell_range_as_csv_escapes_cells() {
+        let mut spreadsheet = Spreadsheet::new(3, 3);
+        spreadsheet.update_cell_at((1, 1), CellContent::Text("a, \"b\"".into()));
+        spreadsheet.update_cell_at((2, 1), CellContent::Number(3));
+        assert_eq!(
+            spreadsheet.cell_range_as_csv((1, 0), (2, 1)),
+            ",\n\"a, \"\"b\"\"\",3"
+        );
+        assert_eq!(
+            spreadsheet.cell_range_as_tsv((1, 0), (2, 1)),
+            "\t\n\"a, \"\"b\"\"\"\t3"
+        );
+    }
+
+    #[test]
+    pub fn save_range_as_csv_uses_the_separator() {
+        let mut spreadsheet = Spreadsheet::load_csv("1;2;3\n4;5,5;6\n7;8;9").unwrap();
+        spreadsheet.update_cell_at((0, 0), CellContent::Text("x".into()));
+        let path = temp_path("save_range_as_csv_uses_the_separator.csv");
+        spreadsheet
+            .save_range_as_csv(&path, (2, 1), (1, 0), spreadsheet.csv_separator())
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "2;3\n5,5;6");
+        assert!(spreadsheet.is_dirty());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn clipboard_round_trip() {
+        let spreadsheet =
+            Spreadsheet::load_csv("\"Hello, world\",\"a\tb\"\n\"x\ny\",\"say \"\"hi\"\"\"")
+                .unwrap();
+        for (from, to) in [((0, 0), (0, 0)), ((0, 0), (1, 1)), ((1, 0), (1, 1))] {
+            let text = spreadsheet.cell_range_as_tsv(from, to);
+            let mut pasted = Spreadsheet::new(1, 1);
+            pasted.paste_text((0, 0), &text).unwrap();
+            assert_eq!(
+                pasted.cell_range_as_tsv((0, 0), (to.0 - from.0, to.1 - from.1)),
+                text
+            );
+        }
+        let mut pasted = Spreadsheet::new(2, 2);
+        pasted.paste_text((1, 1), "Hello, world\n").unwrap();
+        assert_eq!(pasted.cell_at((1, 1)).display_content(), "Hello, world");
+        let mut pasted = Spreadsheet::new(1, 1);
+        pasted.paste_text((0, 0), "a;1\nb;2\n").unwrap();
+        assert_eq!(pasted.cell_range_as_csv((0, 0), (1, 1)), "a,1\nb,2");
+    }
+
+    #[test]
+    pub fn input_negative_numbers() {
+        let mut spreadsheet = Spreadsheet::new(2, 2);
+        "-12".chars().for_each(|ch| spreadsheet.input_char(ch));
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).content,
+            CellContent::Number(-12)
+        );
+        spreadsheet.set_cursor((1, 0));
+        "-0.5".chars().for_each(|ch| spreadsheet.input_char(ch));
+        assert_eq!(
+            spreadsheet.cell_at((1, 0)).content,
+            CellContent::FloatNumber(-0.5, 2)
+        );
+    }
+
+    #[test]
+    pub fn move_column_and_row_update_references() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2,3\n=A0+C0,=sum(B),").unwrap();
+        spreadsheet.move_column(0, 2);
+        assert_eq!(
+            spreadsheet.cell_at((2, 1)).serialize_display_content(),
+            "=C0+B0"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((0, 1)).serialize_display_content(),
+            "=sum(A)"
+        );
+        spreadsheet.move_row(0, 1);
+        assert_eq!(
+            spreadsheet.cell_at((2, 0)).serialize_display_content(),
+            "=C1+B1"
+        );
+        assert_eq!(spreadsheet.cell_at((2, 1)).serialize_display_content(), "1");
+    }
+
+    #[test]
+    pub fn duplicate_row_and_column_keep_references() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,=A0*2\n3,=A1+B0").unwrap();
+        spreadsheet.duplicate_row(0);
+        assert_eq!(spreadsheet.rows(), 3);
+        assert_eq!(spreadsheet.cell_at((0, 1)).serialize_display_content(), "1");
+        assert_eq!(
+            spreadsheet.cell_at((1, 1)).serialize_display_content(),
+            "=A0*2"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((1, 2)).serialize_display_content(),
+            "=A2+B0"
+        );
+        spreadsheet.duplicate_column(0);
+        assert_eq!(spreadsheet.columns(), 3);
+        assert_eq!(spreadsheet.cell_at((1, 2)).serialize_display_content(), "3");
+        assert_eq!(
+            spreadsheet.cell_at((2, 1)).serialize_display_content(),
+            "=A0*2"
+        );
+        assert_eq!(spreadsheet.cell_at((2, 1)).position(), (2, 1));
+    }
+
+    #[test]
+    pub fn merge_from_appends_rows() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n3,4").unwrap();
+        let other = Spreadsheet::load_csv("5,6,7\n=A0+C0,,").unwrap();
+        spreadsheet.merge_from(&other);
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (3, 4));
+        assert!(spreadsheet.cell_at((2, 1)).is_empty());
+        assert_eq!(spreadsheet.cell_at((2, 2)).serialize_display_content(), "7");
+        assert_eq!(spreadsheet.cell_at((0, 3)).position(), (0, 3));
+        assert_eq!(
+            spreadsheet.cell_at((0, 3)).serialize_display_content(),
+            "=A2+C2"
+        );
+    }
+
+    #[test]
+    pub fn get_formula_returns_raw_formula() {
+        let spreadsheet = Spreadsheet::load_csv("1,=A0 * 2").unwrap();
+        assert_eq!(spreadsheet.get_formula((0, 0)), None);
+        assert_eq!(spreadsheet.get_formula((1, 0)), Some("A0 * 2"));
+    }
+
+    #[test]
+    pub fn formula_dependencies_expand_ranges() {
+        let spreadsheet =
+            Spreadsheet::load_csv("1,2,=A0 + B1\n3,4,=sum(A0:1)\n5,6,=sum(B)\n7,8,=sum(1:2)")
+                .unwrap();
+        assert_eq!(spreadsheet.formula_dependencies((0, 0)), []);
+        assert_eq!(spreadsheet.formula_dependencies((2, 0)), [(0, 0), (1, 1)]);
+        assert_eq!(spreadsheet.formula_dependencies((2, 1)), [(0, 0), (0, 1)]);
+        assert_eq!(
+            spreadsheet.formula_dependencies((2, 2)),
+            [(1, 0), (1, 1), (1, 2), (1, 3)]
+        );
+        assert_eq!(
+            spreadsheet.formula_dependencies((2, 3)),
+            [(0, 1), (1, 1), (2, 1), (0, 2), (1, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    pub fn format_rules_color_rows() {
+        let mut spreadsheet = Spreadsheet::load_csv("a,1\nb,5\nc,3\nd,").unwrap();
+        spreadsheet.add_column_format_rule(1, 3.0, (0, 255, 0), (255, 0, 0));
+        assert_eq!(spreadsheet.row_background(0), Some((255, 0, 0)));
+        assert_eq!(spreadsheet.row_background(1), Some((0, 255, 0)));
+        assert_eq!(spreadsheet.row_background(2), None);
+        assert_eq!(spreadsheet.row_background(3), None);
+    }
+
+    #[test]
+    pub fn copy_column_format_copies_width_header_type_and_units() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n3,4").unwrap();
+        spreadsheet.set_column_width(0, 20);
+        spreadsheet.set_column_header(0, "Price".into());
+        spreadsheet.set_column_type(0, ColumnType::Integer);
+        spreadsheet.cell_at_mut((0, 1)).set_unit(UnitKind::Dollar);
+        spreadsheet.copy_column_format(0, 1);
+        assert_eq!(spreadsheet.column_width(1), 20);
+        assert_eq!(spreadsheet.column_header(1), "Price");
+        assert_eq!(spreadsheet.column_type(1), ColumnType::Integer);
+        assert_eq!(spreadsheet.cell_at((1, 0)).unit(), UnitKind::None);
+        assert_eq!(spreadsheet.cell_at((1, 1)).unit(), UnitKind::Dollar);
+    }
+
+    #[test]
+    pub fn units_format_numbers() {
+        let mut spreadsheet = Spreadsheet::load_csv("150,0.25,45000,0.75").unwrap();
+        spreadsheet.cell_at_mut((0, 0)).set_unit(UnitKind::Euro);
+        spreadsheet.cell_at_mut((1, 0)).set_unit(UnitKind::Percent);
+        spreadsheet.cell_at_mut((2, 0)).set_unit(UnitKind::Date);
+        spreadsheet.cell_at_mut((3, 0)).set_unit(UnitKind::Time);
+        assert_eq!(spreadsheet.cell_at((0, 0)).display_content(), "1.50 €");
+        assert_eq!(spreadsheet.cell_at((1, 0)).display_content(), "25%");
+        assert_eq!(spreadsheet.cell_at((2, 0)).display_content(), "2023-03-15");
+        assert_eq!(spreadsheet.cell_at((3, 0)).display_content(), "18:00:00");
+    }
+
+    #[test]
+    pub fn number_formats_change_only_the_display() {
+        let mut spreadsheet = Spreadsheet::load_csv("1234567.891,0.1234,-1234,5").unwrap();
+        let formats = ["#,##0.00", "0.0%", "#,##0", "0.000"];
+        for (column, code) in formats.into_iter().enumerate() {
+            spreadsheet
+                .cell_at_mut((column, 0))
+                .set_unit(UnitKind::Custom(code.into()));
+        }
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).display_content(),
+            "1,234,567.89"
+        );
+        assert_eq!(spreadsheet.cell_at((1, 0)).display_content(), "12.3%");
+        assert_eq!(spreadsheet.cell_at((2, 0)).display_content(), "-1,234");
+        assert_eq!(spreadsheet.cell_at((3, 0)).display_content(), "5.000");
+        assert_eq!(spreadsheet.get_number((1, 0)), Some(0.1234));
+        assert_eq!(units::NumberFormat::parse("yyyy-mm-dd"), None);
+    }
+
+    #[test]
+    pub fn large_and_small_numbers_use_scientific_notation() {
+        let mut spreadsheet =
+            Spreadsheet::load_csv("1e9,99999999999999999999,0.0000001,inf,NaN,=2**70").unwrap();
+        spreadsheet.evaluate();
+        let display: Vec<_> = (0..6)
+            .map(|column| {
+                spreadsheet
+                    .cell_at((column, 0))
+                    .display_content()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(
+            display,
+            [
+                "1000000000",
+                "1e20",
+                "1e-7",
+                "inf",
+                "NaN",
+                "1.1805916207174113e21"
+            ]
+        );
+        assert_eq!(spreadsheet.get_number((1, 0)), Some(1e20));
+        assert_eq!(spreadsheet.get_number((3, 0)), None);
+
+        spreadsheet
+            .cell_at_mut((0, 0))
+            .set_unit(UnitKind::Custom("0.00E+00".into()));
+        assert_eq!(spreadsheet.cell_at((0, 0)).display_content(), "1.00E+09");
+        spreadsheet
+            .cell_at_mut((2, 0))
+            .set_unit(UnitKind::Custom("0.0E+00".into()));
+        assert_eq!(spreadsheet.cell_at((2, 0)).display_content(), "1.0E-07");
+
+        // Typing more digits than fit into an i64 does not overflow.
+        spreadsheet.set_cursor((0, 0));
+        spreadsheet.clear_current_cell();
+        "99999999999999999999"
+            .chars()
+            .for_each(|ch| spreadsheet.input_char(ch));
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).content,
+            CellContent::Text("99999999999999999999".into())
+        );
+    }
+
+    #[test]
+    pub fn units_survive_xlsx_round_trip() {
+        let path = temp_path("units_survive_xlsx_round_trip.xlsx");
+        let mut spreadsheet = Spreadsheet::load_csv("1,2,3").unwrap();
+        spreadsheet.cell_at_mut((0, 0)).set_unit(UnitKind::Percent);
+        spreadsheet.cell_at_mut((1, 0)).set_unit(UnitKind::Time);
+        spreadsheet
+            .cell_at_mut((2, 0))
+            .set_unit(UnitKind::Custom("0.000".into()));
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let loaded = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.cell_at((0, 0)).unit(), UnitKind::Percent);
+        assert_eq!(loaded.cell_at((1, 0)).unit(), UnitKind::Time);
+        assert_eq!(
+            loaded.cell_at((2, 0)).unit(),
+            UnitKind::Custom("0.000".into())
+        );
+    }
+
+    #[test]
+    pub fn invalid_ranges_are_errors() {
+        let mut spreadsheet =
+            Spreadsheet::load_csv("1,=A0:AA\n2,=sum(A0:)\n3,=sum(A0:ZZZZ9)").unwrap();
+        spreadsheet.evaluate();
+        assert!(spreadsheet.cell_at((1, 0)).is_error());
+        assert!(spreadsheet.cell_at((1, 1)).is_error());
+        assert!(spreadsheet.cell_at((1, 2)).is_error());
+    }
+
+    #[test]
+    pub fn replace_all_replaces_text_and_formulas() {
+        let mut spreadsheet = Spreadsheet::load_csv("foo,food\n12,=A1 + 1").unwrap();
+        assert_eq!(spreadsheet.count_occurrences("foo"), 2);
+        assert_eq!(spreadsheet.replace_all("foo", "bar"), 2);
+        assert_eq!(
+            spreadsheet.cell_at((1, 0)).serialize_display_content(),
+            "bard"
+        );
+        assert_eq!(spreadsheet.replace_all("A1", "A1 * 2"), 1);
+        assert_eq!(spreadsheet.get_formula((1, 1)), Some("A1 * 2 + 1"));
+        assert_eq!(spreadsheet.count_occurrences_regex("(b)a").unwrap(), 2);
+        assert_eq!(spreadsheet.replace_all_regex("(b)a", "${1}o").unwrap(), 2);
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).serialize_display_content(),
+            "bor"
+        );
+        assert!(spreadsheet.replace_all_regex("[", "x").is_err());
+        // Numbers are not changed.
+        assert_eq!(spreadsheet.replace_all("1", "x"), 2);
+        assert_eq!(spreadsheet.cell_at((0, 1)).content, CellContent::Number(12));
+    }
+
+    #[test]
+    pub fn replace_keeps_hyperlinks_and_skips_empty_matches() {
+        let mut spreadsheet = Spreadsheet::load_csv(",\n,x").unwrap();
+        spreadsheet.update_cell_at((0, 0), CellContent::FloatNumber(1.5, 2));
+        spreadsheet.update_cell_at(
+            (0, 1),
+            CellContent::Hyperlink {
+                display: "old site".into(),
+                url: "https://example.org/old".into(),
+            },
+        );
+        assert_eq!(spreadsheet.replace_all("old", "new"), 1);
+        assert_eq!(
+            spreadsheet.cell_at((0, 1)).content,
+            CellContent::Hyperlink {
+                display: "new site".into(),
+                url: "https://example.org/old".into(),
+            }
+        );
+        assert_eq!(spreadsheet.replace_all("5", "6"), 0);
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).content,
+            CellContent::FloatNumber(1.5, 2)
+        );
+        assert_eq!(spreadsheet.count_occurrences_regex("x*").unwrap(), 1);
+        assert_eq!(spreadsheet.replace_all_regex("x*", "y").unwrap(), 1);
+        assert!(spreadsheet.cell_at((1, 0)).is_empty());
+        assert_eq!(spreadsheet.cell_at((1, 1)).display_content(), "y");
+    }
+
+    #[test]
+    pub fn find_numbers_formulas_and_ignore_case() {
+        let mut spreadsheet = Spreadsheet::load_csv("Total,12\n=A1 + 1,total").unwrap();
+        assert_eq!(spreadsheet.find("12"), Some((1, 0)));
+        assert_eq!(spreadsheet.find("A1"), Some((0, 1)));
+        assert_eq!(spreadsheet.find("total"), Some((1, 1)));
+        let options = FindOptions {
+            case_insensitive: true,
+            backwards: true,
+            ..Default::default()
+        };
+        assert_eq!(spreadsheet.find_with("TOTAL", options), Some((1, 1)));
+        spreadsheet.set_cursor((1, 1));
+        assert_eq!(spreadsheet.find_with("TOTAL", options), Some((0, 0)));
+        assert_eq!(spreadsheet.find("Total"), Some((0, 0)));
+
+        spreadsheet.set_cursor((1, 0));
+        assert_eq!(spreadsheet.replace_next("otal", "ext"), Some((1, 1)));
+        assert_eq!(spreadsheet.current_cell(), (1, 0));
+        assert_eq!(spreadsheet.cell_at((1, 1)).display_content(), "text");
+        assert_eq!(spreadsheet.cell_at((0, 0)).display_content(), "Total");
+        assert_eq!(spreadsheet.replace_next("missing", "x"), None);
+
+        spreadsheet.set_cursor((1, 1));
+        assert_eq!(spreadsheet.replace_next("t", "tt"), Some((0, 0)));
+        spreadsheet.set_cursor((0, 0));
+        assert_eq!(spreadsheet.replace_next("t", "tt"), Some((1, 1)));
+        assert_eq!(spreadsheet.cell_at((0, 0)).display_content(), "Tottal");
+        assert_eq!(spreadsheet.cell_at((1, 1)).display_content(), "ttextt");
+    }
+
+    #[test]
+    pub fn find_regex_returns_all_matches() {
+        let mut spreadsheet = Spreadsheet::load_csv("Total,12\nSubtotal,Total 2").unwrap();
+        let matches: Vec<_> = spreadsheet.find_regex("^Total.*", false).unwrap().collect();
+        assert_eq!(matches, [(0, 0), (1, 1)]);
+        let options = FindOptions {
+            regex: true,
+            ..Default::default()
+        };
+        spreadsheet.set_cursor((0, 0));
+        assert_eq!(spreadsheet.find_with("^Total.*", options), Some((1, 1)));
+        assert_eq!(spreadsheet.find_with("[", options), None);
+
+        let spreadsheet = Spreadsheet::load_csv(",\n,x").unwrap();
+        let matches: Vec<_> = spreadsheet.find_regex("x*", false).unwrap().collect();
+        assert_eq!(matches, [(1, 1)]);
+        assert_eq!(spreadsheet.find_with("x*", options), Some((1, 1)));
+    }
+
+    #[test]
+    pub fn tokenize_formula_finds_references() {
+        use FormulaTokenKind::*;
+        assert_eq!(
+            tokenize_formula("sum(A0:3)*b", (2, 4)),
+            [
+                (Other, "sum"),
+                (Operator, "("),
+                (Reference, "A0"),
+                (Operator, ":"),
+                (Reference, "3"),
+                (Operator, ")"),
+                (Operator, "*"),
+                (Reference, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn formula_references_cover_ranges() {
+        let references = formula_references("sum(A0:B1)+c*A2", (3, 3));
+        let areas: Vec<_> = references
+            .iter()
+            .map(|r| (r.text_range.clone(), r.columns.clone(), r.rows.clone()))
+            .collect();
+        assert_eq!(
+            areas,
+            [
+                (4..9, 0..=1, 0..=1),
+                (11..12, 2..=2, 0..=2),
+                (13..15, 0..=0, 2..=2)
+            ]
+        );
+        assert!(references[0].contains((1, 1)));
+        assert!(!references[0].contains((2, 1)));
+
+        let spreadsheet = Spreadsheet::load_csv("1,2\n=A0+B0:B1,").unwrap();
+        let CellContent::Formula(formula) = &spreadsheet.cell_at((0, 1)).content else {
+            panic!("expected a formula");
+        };
+        let references = formula.references((2, 2));
+        assert_eq!(references.len(), 2);
+        assert_eq!(references[1].rows, 0..=1);
+    }
+
+    #[test]
+    pub fn fixed_rows_survive_xlsx_round_trip() {
+        let path = temp_path("fixed_rows_survive_xlsx_round_trip.xlsx");
+        let mut spreadsheet = Spreadsheet::load_csv("a,b\n1,2\n3,4").unwrap();
+        spreadsheet.fix_rows(2);
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let mut spreadsheet = Spreadsheet::load_xlsx(&path).unwrap();
+        assert_eq!(spreadsheet.fixed_rows, 2);
+        assert_eq!(spreadsheet.fixed_columns, 0);
+        spreadsheet.fix_rows(1);
+        spreadsheet.fix_columns(1);
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let spreadsheet = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(spreadsheet.fixed_rows, 1);
+        assert_eq!(spreadsheet.fixed_columns, 1);
+    }
+
+    #[test]
+    pub fn fixed_columns_survive_xlsx_round_trip() {
+        let path = temp_path("fixed_columns_survive_xlsx_round_trip.xlsx");
+        let mut spreadsheet = Spreadsheet::load_csv("a,b,c\n1,2,3").unwrap();
+        spreadsheet.fix_columns(2);
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let spreadsheet = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(spreadsheet.fixed_rows(), 0);
+        assert_eq!(spreadsheet.fixed_columns(), 2);
+    }
+
+    #[test]
+    pub fn hyperlinks_survive_xlsx_round_trip() {
+        let path = temp_path("hyperlinks_survive_xlsx_round_trip.xlsx");
+        let mut spreadsheet = Spreadsheet::load_csv("a,b").unwrap();
+        spreadsheet.update_cell_at(
+            (1, 0),
+            CellContent::Hyperlink {
+                display: "tabelle".into(),
+                url: "https://github.com/wert007/tabelle?tab=readme&plain=1".into(),
+            },
+        );
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let spreadsheet = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let content = &spreadsheet.cell_at((1, 0)).content;
+        assert_eq!(content.as_str(), Some("tabelle"));
+        assert_eq!(
+            content.url(),
+            Some("https://github.com/wert007/tabelle?tab=readme&plain=1")
+        );
+        assert_eq!(spreadsheet.cell_at((0, 0)).content.url(), None);
+    }
+
+    #[test]
+    pub fn undo_and_redo_changes() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2").unwrap();
+        assert!(!spreadsheet.undo());
+        spreadsheet.checkpoint();
+        spreadsheet.update_cell_at((0, 0), CellContent::Number(5));
+        spreadsheet.checkpoint();
+        spreadsheet.resize(3, 2);
+        assert!(spreadsheet.undo());
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 1));
+        assert!(spreadsheet.undo());
+        assert_eq!(spreadsheet.cell_at((0, 0)).serialize_display_content(), "1");
+        assert!(spreadsheet.redo());
+        assert_eq!(spreadsheet.cell_at((0, 0)).serialize_display_content(), "5");
+        spreadsheet.checkpoint();
+        assert!(!spreadsheet.redo());
+    }
+
+    #[test]
+    pub fn display_as_ascii_table() {
+        let mut spreadsheet = Spreadsheet::load_csv("name,amount\napple,3").unwrap();
+        spreadsheet.set_column_width(0, 4);
+        spreadsheet.set_column_width(1, 6);
+        assert_eq!(
+            spreadsheet.to_string(),
+            concat!(
+                "     | A    | B      |\n",
+                "-----+------+--------+\n",
+                "    0| name | amount |\n",
+                "-----+------+--------+\n",
+                "    1| appl |      3 |\n",
+                "-----+------+--------+\n",
+            )
+        );
+    }
+
+    #[test]
+    pub fn csv_keeps_separator() {
+        let mut spreadsheet = Spreadsheet::load_csv("a;b\n1;2").unwrap();
+        assert_eq!(spreadsheet.csv_separator(), ';');
+        assert_eq!(spreadsheet.serialize_as_csv(), "a;b\n1;2");
+        spreadsheet.checkpoint();
+        spreadsheet.set_csv_separator('\t');
+        assert_eq!(spreadsheet.serialize_as_csv(), "a\tb\n1\t2");
+        assert!(spreadsheet.undo());
+        assert_eq!(spreadsheet.csv_separator(), ';');
+    }
+
+    #[test]
+    pub fn csv_write_options() {
+        let mut spreadsheet = Spreadsheet::load_csv("a,b,\n1,2,\n,,").unwrap();
+        spreadsheet.update_cell_at((1, 0), CellContent::Text("b,c".into()));
+        spreadsheet.resize(4, 4);
+        assert_eq!(spreadsheet.serialize_as_csv(), "a,\"b,c\"\n1,2");
+        let options = csv::CsvWriteOptions {
+            separator: ';',
+            quote: csv::QuotePolicy::Always,
+            line_ending: csv::LineEnding::CrLf,
+            trailing_empty_columns: true,
+        };
+        assert_eq!(
+            spreadsheet.serialize_csv_with(&options),
+            "\"a\";\"b,c\";\"\";\"\"\r\n\"1\";\"2\";\"\";\"\""
+        );
+        let options = csv::CsvWriteOptions {
+            separator: ';',
+            ..Default::default()
+        };
+        assert_eq!(spreadsheet.serialize_csv_with(&options), "a;b,c\n1;2");
+    }
+
+    #[test]
+    pub fn row_ranges_in_formulas() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n3,4\n=sum(0:1),").unwrap();
+        spreadsheet.evaluate();
+        assert_eq!(spreadsheet.cell_at((0, 2)).display_content(), "10");
+        spreadsheet.insert_rows_before(1, 1);
+        assert_eq!(
+            spreadsheet.cell_at((0, 3)).serialize_display_content(),
+            "=sum(0:2)"
+        );
+
+        // Numbers before the range are left alone.
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n3,4\n=10+sum(0:1),").unwrap();
+        spreadsheet.insert_rows_before(0, 1);
+        assert_eq!(
+            spreadsheet.cell_at((0, 3)).serialize_display_content(),
+            "=10+sum(1:2)"
+        );
+    }
+
+    #[test]
+    pub fn row_at_returns_rows() {
+        let spreadsheet = Spreadsheet::load_csv("1,2\n3,4").unwrap();
+        let row = spreadsheet.row_at(1).unwrap();
+        assert_eq!(row[0].serialize_display_content(), "3");
+        assert_eq!(row[1].position(), (1, 1));
+        assert!(spreadsheet.row_at(2).is_none());
+        assert_eq!(spreadsheet.as_rows().nth(1), Some(row));
+        assert_eq!(spreadsheet.as_rows().nth(2), None);
+    }
+
+    #[test]
+    pub fn rows_can_be_iterated_from_the_back() {
+        let spreadsheet = Spreadsheet::load_csv("1\n2\n3").unwrap();
+        let rows: Vec<_> = spreadsheet
+            .as_rows()
+            .rev()
+            .map(|row| row[0].serialize_display_content())
+            .collect();
+        assert_eq!(rows, ["3", "2", "1"]);
+        assert_eq!(spreadsheet.as_rows().last(), spreadsheet.row_at(2));
+        let mut rows = spreadsheet.as_rows();
+        assert_eq!(rows.next(), spreadsheet.row_at(0));
+        assert_eq!(rows.next_back(), spreadsheet.row_at(2));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows.next_back(), spreadsheet.row_at(1));
+        assert_eq!(rows.next(), None);
+        assert_eq!(rows.next_back(), None);
+    }
+
+    #[test]
+    pub fn html_has_header_and_escaped_cells() {
+        let mut spreadsheet = Spreadsheet::load_csv("Name,Count\n<b>&'co',3").unwrap();
+        spreadsheet.fix_rows(1);
+        spreadsheet.add_column_format_rule(1, 2.0, (255, 0, 0), (0, 0, 255));
+        assert_eq!(
+            spreadsheet.serialize_as_html(),
+            "<table>\n<thead>\n<tr>\n<th>Name</th>\n<th>Count</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr style=\"background-color:#ff0000\">\n<td>&lt;b&gt;&amp;'co'</td>\n\
+             <td class=\"number\" style=\"text-align:right\">3</td>\n</tr>\n</tbody>\n</table>\n"
+        );
+
+        let mut spreadsheet = Spreadsheet::load_csv("Name").unwrap();
+        spreadsheet.fix_rows(3);
+        assert_eq!(
+            spreadsheet.serialize_as_html(),
+            "<table>\n<thead>\n<tr>\n<th>Name</th>\n</tr>\n</thead>\n</table>\n"
+        );
+    }
+
+    #[test]
+    pub fn formulas_are_evaluated_after_their_dependencies() {
+        let mut spreadsheet = Spreadsheet::load_csv("=B0 + 1,=C0 * 2,3\n=sum(A),=C1,=B1").unwrap();
+        spreadsheet.evaluate();
+        assert_eq!(spreadsheet.cell_at((0, 0)).display_content(), "7");
+        assert_eq!(spreadsheet.cell_at((1, 0)).display_content(), "6");
+        assert_eq!(spreadsheet.cell_at((0, 1)).display_content(), "7");
+        assert_eq!(spreadsheet.cell_at((1, 1)).display_content(), "#cycle");
+        assert_eq!(spreadsheet.cell_at((2, 1)).display_content(), "#cycle");
+        assert!(spreadsheet.cell_at((2, 1)).is_error());
+    }
+
+    #[test]
+    pub fn copy_paste_clear_and_fill_ranges() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,=A0 * 2,\n2,,\n,,").unwrap();
+        let copied = spreadsheet.copy_range((1, 0), (0, 1));
+        assert_eq!(copied, [["1", "=A0 * 2"], ["2", ""]]);
+        spreadsheet.paste_range((1, 1), copied.iter().map(Vec::as_slice));
+        assert_eq!(
+            spreadsheet.copy_range((1, 1), (2, 2)),
+            [["1", "=A0 * 2"], ["2", ""]]
+        );
+        spreadsheet.fill_range((1, 0), (1, 2));
+        assert_eq!(
+            spreadsheet.copy_range((1, 0), (1, 2)),
+            [["=A0 * 2"], ["=A1 * 2"], ["=A2 * 2"]]
+        );
+        spreadsheet.clear_range((0, 0), (1, 1));
+        assert_eq!(spreadsheet.copy_range((0, 0), (1, 1)), [["", ""], ["", ""]]);
+    }
+
+    #[test]
+    pub fn fill_continues_series() {
+        let mut spreadsheet = Spreadsheet::load_csv(
+            "1,5,0.5,Mon,JAN,Item 9,2024-01-01,2024-01-01 10:00,text\n\
+             3,,0.75,Wed,,Item 7,2024-01-08,2024-01-01 10:30,text\n\
+             ,,,,,,,,\n,,,,,,,,",
+        )
+        .unwrap();
+        for column in 0..9 {
+            let from = if column == 1 || column == 4 { 0 } else { 1 };
+            spreadsheet.fill_range((column, from), (column, 3));
+        }
+        assert_eq!(
+            spreadsheet.copy_range((0, 2), (8, 3)),
+            [
+                [
+                    "5",
+                    "7",
+                    "1",
+                    "Fri",
+                    "MAR",
+                    "Item 5",
+                    "2024-01-15",
+                    "2024-01-01 11:00:00",
+                    "text"
+                ],
+                [
+                    "7",
+                    "8",
+                    "1.25",
+                    "Sun",
+                    "APR",
+                    "Item 3",
+                    "2024-01-22",
+                    "2024-01-01 11:30:00",
+                    "text"
+                ],
+            ]
+        );
+        assert_eq!(spreadsheet.cell_at((1, 1)).display_content(), "6");
+        assert_eq!(spreadsheet.cell_at((4, 1)).display_content(), "FEB");
+
+        // Rows are continued from the cell left of the first one.
+        let mut spreadsheet = Spreadsheet::load_csv("sat,sun,,").unwrap();
+        spreadsheet.fill_range((1, 0), (3, 0));
+        assert_eq!(spreadsheet.copy_range((2, 0), (3, 0)), [["mon", "tue"]]);
+
+        // Single numbers count up by one and numbers at the end of text keep
+        // their leading zeros.
+        let mut spreadsheet = Spreadsheet::load_csv("Item 009,,\n,,").unwrap();
+        spreadsheet.update_cell_at((0, 1), CellContent::FloatNumber(1.5, 2));
+        spreadsheet.fill_range((0, 0), (2, 0));
+        spreadsheet.fill_range((0, 1), (2, 1));
+        assert_eq!(
+            spreadsheet.copy_range((1, 0), (2, 1)),
+            [["Item 010", "Item 011"], ["2.5", "3.5"]]
+        );
+        assert_eq!(
+            spreadsheet.cell_at((2, 1)).content,
+            CellContent::FloatNumber(3.5, 2)
+        );
+
+        // Like dates, a single date and time is continued by one day.
+        let mut spreadsheet = Spreadsheet::load_csv("2024-01-31 23:00,,").unwrap();
+        spreadsheet.fill_range((0, 0), (2, 0));
+        assert_eq!(
+            spreadsheet.copy_range((1, 0), (2, 0)),
+            [["2024-02-01 23:00:00", "2024-02-02 23:00:00"]]
+        );
+    }
+
+    #[test]
+    pub fn used_range_follows_all_changes() {
+        let mut spreadsheet = Spreadsheet::new(6, 6);
+        assert_eq!(spreadsheet.used_range(), ((0, 0), (0, 0)));
+        spreadsheet.update_cell_at((1, 1), CellContent::Number(1));
+        spreadsheet.update_cell_at((1, 2), CellContent::Number(2));
+        assert_eq!(spreadsheet.used_range(), ((1, 1), (1, 2)));
+        spreadsheet.fill_range((1, 2), (1, 4));
+        assert_eq!(spreadsheet.used_range(), ((1, 1), (1, 4)));
+        spreadsheet.paste_range((2, 0), [&["a".to_owned(), "b".to_owned()][..]]);
+        assert_eq!(spreadsheet.used_range(), ((1, 0), (3, 4)));
+        assert_eq!(
+            spreadsheet.serialize_as_csv(),
+            ",,a,b\n,1,,\n,2,,\n,3,,\n,4,,"
+        );
+        spreadsheet.clear_range((2, 0), (3, 0));
+        spreadsheet.update_cell_at((1, 4), CellContent::Empty);
+        assert_eq!(spreadsheet.used_range(), ((1, 1), (1, 3)));
+        spreadsheet.insert_rows_before(0, 2);
+        assert_eq!(spreadsheet.used_range(), ((1, 3), (1, 5)));
+    }
+
+    #[test]
+    pub fn shrink_to_used_removes_trailing_empty_cells() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,,,\n,=A0+1,,\n,,,\n,,,").unwrap();
+        spreadsheet.set_cursor((3, 3));
+        spreadsheet.set_column_width(1, 20);
+        assert_eq!(spreadsheet.shrink_to_used(), (2, 2));
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 2));
+        assert_eq!(spreadsheet.current_cell(), (1, 1));
+        assert_eq!(spreadsheet.column_width(1), 20);
+        assert_eq!(spreadsheet.serialize_as_csv(), "1,\n,=A0+1");
+        assert_eq!(spreadsheet.shrink_to_used(), (0, 0));
+
+        // Merged cells and referenced cells are kept.
+        let mut spreadsheet = Spreadsheet::load_csv("=sum(A0:A3),,,\n,,,\n,,,\n,,,").unwrap();
+        spreadsheet.merge_cells((1, 0), (2, 1));
+        assert_eq!(spreadsheet.shrink_to_used(), (1, 0));
+        assert_eq!(spreadsheet.merged_cells_at((2, 1)).unwrap().to, (2, 1));
+    }
+
+    #[test]
+    pub fn resize_can_shrink() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2,3\n4,5,6\n7,8,9").unwrap();
+        spreadsheet.set_cursor((2, 2));
+        spreadsheet.merge_cells((1, 1), (2, 2));
+        spreadsheet.resize(2, 4);
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 4));
+        assert_eq!(spreadsheet.current_cell(), (1, 2));
+        assert_eq!(spreadsheet.serialize_as_csv(), "1,2\n4,5\n7,");
+        assert_eq!(spreadsheet.merged_cells_at((1, 2)).unwrap().to, (1, 2));
+        assert_eq!(spreadsheet.used_range(), ((0, 0), (1, 2)));
+
+        spreadsheet.resize(1, 1);
+        assert!(spreadsheet.merged_cells_at((0, 0)).is_none());
+        assert_eq!(spreadsheet.current_cell(), (0, 0));
+        spreadsheet.resize(0, 0);
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (1, 1));
+        assert_eq!(spreadsheet.serialize_as_csv(), "1");
+    }
+
+    #[test]
+    pub fn shrinking_clamps_a_selection() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2,3,4,5\n6,7,8,9,10\n,,,,\n,,,,").unwrap();
+        let anchor = (3, 3);
+        spreadsheet.set_cursor((4, 3));
+        spreadsheet.resize(2, 2);
+        let anchor = spreadsheet.clamp_position(anchor);
+        assert_eq!(anchor, (1, 1));
+        let aggregates = spreadsheet.aggregate_range(anchor, spreadsheet.current_cell());
+        assert_eq!(aggregates.sum, 7.0);
+    }
+
+    #[test]
+    pub fn trimming_clamps_a_selection() {
+        let mut spreadsheet = Spreadsheet::load_csv(
+            "1,2,,,
+6,7,,,
+,,,,
+,,,,
+,,,,",
+        )
+        .unwrap();
+        spreadsheet.set_cursor((4, 4));
+        assert_eq!(spreadsheet.shrink_to_used(), (3, 3));
+        assert_eq!(spreadsheet.clamp_position((4, 4)), (1, 1));
+        assert_eq!(spreadsheet.current_cell(), (1, 1));
+    }
+
+    #[test]
+    pub fn ods_round_trip() {
+        let path = temp_path("ods_round_trip.ods");
+        let mut spreadsheet =
+            Spreadsheet::load_csv("Name,Price\n<Tom & \"Jerry\">,12\n,=B1 * 2").unwrap();
+        spreadsheet.update_cell_at(
+            (0, 2),
+            CellContent::Hyperlink {
+                display: "tabelle".into(),
+                url: "https://github.com/wert007/tabelle?a=1&b=2".into(),
+            },
+        );
+        spreadsheet.evaluate();
+        spreadsheet.save_as_ods(&path).unwrap();
+        let loaded = Spreadsheet::load_ods(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.columns(), 2);
+        assert_eq!(loaded.rows(), 3);
+        assert_eq!(
+            loaded.copy_range((0, 0), (1, 1)),
+            spreadsheet.copy_range((0, 0), (1, 1))
+        );
+        // Formulas are saved as text, so they are loaded as text.
+        assert_eq!(
+            loaded.cell_at((1, 2)).content,
+            CellContent::Text("=B1 * 2".into())
+        );
+        assert_eq!(
+            loaded.cell_at((0, 2)).content.url(),
+            Some("https://github.com/wert007/tabelle?a=1&b=2")
+        );
+    }
+
+    #[test]
+    pub fn ods_strings_are_text_and_comments_are_skipped() {
+        use std::io::Write;
+        let path = temp_path("ods_strings_are_text_and_comments_are_skipped.ods");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        zip.start_file("content.xml", zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(
+            concat!(
+                "<office:document-content><office:body><office:spreadsheet><table:table>",
+                "<table:table-row>",
+                r#"<table:table-cell office:value-type="string"><text:p>=1 + 2</text:p></table:table-cell>"#,
+                r#"<table:table-cell office:value-type="string">"#,
+                "<office:annotation><text:p>A comment</text:p></office:annotation>",
+                "<text:p>007</text:p></table:table-cell>",
+                r#"<table:table-cell office:value-type="float" office:value="7">"#,
+                "<text:p>7</text:p></table:table-cell>",
+                "</table:table-row>",
+                "</table:table></office:spreadsheet></office:body></office:document-content>"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        zip.finish().unwrap();
+        let loaded = Spreadsheet::load_ods(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            loaded.cell_at((0, 0)).content,
+            CellContent::Text("=1 + 2".into())
+        );
+        assert_eq!(
+            loaded.cell_at((1, 0)).content,
+            CellContent::Text("007".into())
+        );
+        assert_eq!(loaded.cell_at((2, 0)).content, CellContent::Number(7));
+    }
+
+    #[test]
+    pub fn cell_styles_survive_xlsx_round_trip() {
+        let path = temp_path("cell_styles_survive_xlsx_round_trip.xlsx");
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n3,4").unwrap();
+        let style = CellStyle {
+            bold: true,
+            italic: false,
+            foreground: Some((255, 128, 0)),
+            background: Some((0, 0, 64)),
+            alignment: Some(CellAlignment::Center),
+        };
+        spreadsheet.cell_at_mut((1, 0)).set_style(style);
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let loaded = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.cell_at((1, 0)).style(), style);
+        assert_eq!(loaded.cell_at((0, 0)).style(), CellStyle::default());
+    }
+
+    #[test]
+    pub fn dates_are_parsed_sorted_and_saved() {
+        let mut spreadsheet = Spreadsheet::load_csv(
+            "2023-01-05 13:30:00\n2023-01-05\n2022-12-31\n=A0.year + 1\n=A1.month",
+        )
+        .unwrap();
+        spreadsheet.evaluate();
+        assert!(matches!(
+            spreadsheet.cell_at((0, 0)).content,
+            CellContent::DateTime(_)
+        ));
+        assert!(matches!(
+            spreadsheet.cell_at((0, 1)).content,
+            CellContent::Date(_)
+        ));
+        assert!(spreadsheet.cell_at((0, 1)).is_right_aligned());
+        assert_eq!(spreadsheet.cell_at((0, 3)).display_content(), "2024");
+        assert_eq!(spreadsheet.cell_at((0, 4)).display_content(), "1");
+
+        let path = temp_path("dates_are_parsed_sorted_and_saved.xlsx");
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let loaded = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        for row in 0..3 {
+            assert_eq!(
+                loaded.cell_at((0, row)).content,
+                spreadsheet.cell_at((0, row)).content
+            );
+        }
+
+        let mut spreadsheet =
+            Spreadsheet::load_csv("2023-01-05\n2023-01-05 13:30:00\n2022-12-31").unwrap();
+        spreadsheet.sort_column(0);
+        let dates: Vec<_> = (0..3)
+            .map(|row| spreadsheet.cell_at((0, row)).display_content().into_owned())
+            .collect();
+        assert_eq!(dates, ["2022-12-31", "2023-01-05", "2023-01-05 13:30:00"]);
+
+        // Numbers are sorted between dates by the serial numbers of them.
+        let mut spreadsheet =
+            Spreadsheet::load_csv("2023-01-05\n50000\n2022-12-31\n40000").unwrap();
+        spreadsheet.sort_column(0);
+        assert_eq!(
+            spreadsheet.copy_range((0, 0), (0, 3)),
+            [["40000"], ["2022-12-31"], ["2023-01-05"], ["50000"]]
+        );
+    }
+
+    #[test]
+    pub fn merged_cells_move_with_their_rows_and_columns() {
+        let mut spreadsheet = Spreadsheet::load_csv("3,a,x\n1,b,y\n2,c,z").unwrap();
+        spreadsheet.merge_cells((1, 0), (2, 0));
+        spreadsheet.sort_by(&[SortKey::ascending(0)]);
+        assert_eq!(
+            spreadsheet.merged_cells(),
+            [MergedCells {
+                from: (1, 2),
+                to: (2, 2)
+            }]
+        );
+        spreadsheet.move_row(2, 0);
+        assert_eq!(
+            spreadsheet.merged_cells(),
+            [MergedCells {
+                from: (1, 0),
+                to: (2, 0)
+            }]
+        );
+        spreadsheet.move_column(0, 2);
+        assert_eq!(
+            spreadsheet.merged_cells(),
+            [MergedCells {
+                from: (0, 0),
+                to: (1, 0)
+            }]
+        );
+
+        // Merged cells are unmerged, once their rows are not next to each
+        // other anymore.
+        spreadsheet.merge_cells((2, 1), (2, 2));
+        spreadsheet.move_row(1, 0);
+        assert_eq!(
+            spreadsheet.merged_cells(),
+            [MergedCells {
+                from: (0, 1),
+                to: (1, 1)
+            }]
+        );
+        spreadsheet.move_column(1, 0);
+        assert_eq!(
+            spreadsheet.merged_cells(),
+            [MergedCells {
+                from: (0, 1),
+                to: (1, 1)
+            }]
+        );
+        spreadsheet.move_column(2, 1);
+        assert!(spreadsheet.merged_cells().is_empty());
+    }
+
+    #[test]
+    pub fn sort_by_multiple_keys() {
+        let mut spreadsheet =
+            Spreadsheet::load_csv("b,2\nitem 10,1\na,10\nitem 9,1\n5,0\n,7\nb,1").unwrap();
+        let column = |spreadsheet: &Spreadsheet, column| -> Vec<_> {
+            (0..spreadsheet.height)
+                .map(|row| {
+                    spreadsheet
+                        .cell_at((column, row))
+                        .display_content()
+                        .into_owned()
+                })
+                .collect()
+        };
+
+        spreadsheet.sort_by(&[SortKey::ascending(0), SortKey::descending(1)]);
+        assert_eq!(
+            column(&spreadsheet, 0),
+            ["5", "a", "b", "b", "item 9", "item 10", ""]
+        );
+        assert_eq!(
+            column(&spreadsheet, 1),
+            ["0", "10", "2", "1", "1", "1", "7"]
+        );
+
+        spreadsheet.sort_by(&[SortKey::descending(0)]);
+        assert_eq!(
+            column(&spreadsheet, 0),
+            ["item 10", "item 9", "b", "b", "a", "5", ""]
+        );
+        assert_eq!(
+            column(&spreadsheet, 1),
+            ["1", "1", "2", "1", "10", "0", "7"]
+        );
+    }
+
+    #[test]
+    pub fn filters_hide_rows() {
+        let mut spreadsheet =
+            Spreadsheet::load_csv("name,amount\nb,150\na,50\nc,300\nd,text").unwrap();
+        spreadsheet.fix_rows(1);
+        let matches = spreadsheet.apply_filter(&Filter {
+            column: 1,
+            operator: FilterOperator::Greater,
+            value: "100".into(),
+        });
+        assert_eq!(matches, 2);
+        assert!(spreadsheet.is_filtered());
+        assert_eq!(spreadsheet.visible_rows().collect::<Vec<_>>(), [0, 1, 3]);
+        assert_eq!(spreadsheet.serialize_as_csv(), "name,amount\nb,150\nc,300");
+
+        spreadsheet.set_cursor((0, 1));
+        assert!(spreadsheet.move_cursor(0, 1));
+        assert_eq!(spreadsheet.current_cell(), (0, 3));
+        assert!(!spreadsheet.move_cursor(0, 1));
+        assert_eq!(spreadsheet.current_cell(), (0, 3));
+
+        spreadsheet.sort_column(0);
+        assert_eq!(spreadsheet.serialize_as_csv(), "name,amount\nb,150\nc,300");
+        let matches = spreadsheet.apply_filter(&Filter {
+            column: 0,
+            operator: FilterOperator::Contains,
+            value: "C".into(),
+        });
+        assert_eq!(matches, 1);
+        assert_eq!(spreadsheet.serialize_as_csv(), "name,amount\nc,300");
+
+        spreadsheet.clear_filter();
+        assert!(!spreadsheet.is_filtered());
+        assert_eq!(spreadsheet.visible_row_count(), 5);
+    }
+
+    #[test]
+    pub fn evaluate_dirty_only_updates_dependents() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,=A0*2\n2,=B0+A1\n5,=A2").unwrap();
+        spreadsheet.evaluate();
+        let size = (spreadsheet.columns(), spreadsheet.rows());
+        spreadsheet.update_cell_at((0, 0), CellContent::parse("10", (0, 0), size));
+        // B2 does not depend on A0, so it keeps its old value.
+        spreadsheet.update_cell_at((0, 2), CellContent::parse("7", (0, 2), size));
+        spreadsheet.evaluate_dirty((0, 0));
+        let values: Vec<_> = (0..3)
+            .map(|row| spreadsheet.cell_at((1, row)).display_content().into_owned())
+            .collect();
+        assert_eq!(values, ["20", "22", "5"]);
+
+        spreadsheet.update_cell_at((1, 0), CellContent::parse("=B1", (1, 0), size));
+        spreadsheet.evaluate_dirty((1, 0));
+        assert_eq!(spreadsheet.cell_at((1, 0)).display_content(), "#cycle");
+        assert_eq!(spreadsheet.cell_at((1, 1)).display_content(), "#cycle");
+
+        // A changed formula is part of the dependencies of the next edit.
+        spreadsheet.update_cell_at((0, 2), CellContent::parse("=A0+1", (0, 2), size));
+        spreadsheet.evaluate_dirty((0, 2));
+        spreadsheet.update_cell_at((0, 0), CellContent::parse("3", (0, 0), size));
+        spreadsheet.evaluate_dirty((0, 0));
+        assert_eq!(spreadsheet.cell_at((0, 2)).display_content(), "4");
+    }
+
+    #[test]
+    pub fn absolute_references_and_range_functions() {
+        let mut spreadsheet = Spreadsheet::load_csv(
+            "1,2,=sum(A0:A1)\n3,x,=avg(A0:B1)\n=$A$0+B0,=A$1*2,=count(A0:B1)\n,,",
+        )
+        .unwrap();
+        spreadsheet.evaluate();
+        let values: Vec<_> = [(2, 0), (2, 1), (2, 2), (0, 2), (1, 2)]
+            .into_iter()
+            .map(|position| spreadsheet.cell_at(position).display_content().into_owned())
+            .collect();
+        assert_eq!(values, ["4", "2", "3", "3", "6"]);
+
+        spreadsheet.fill_range((0, 2), (0, 3));
+        spreadsheet.fill_range((1, 2), (1, 3));
+        assert_eq!(
+            spreadsheet.cell_at((0, 3)).serialize_display_content(),
+            "=$A$0+B1"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((1, 3)).serialize_display_content(),
+            "=A$1*2"
+        );
+
+        // Inserting rows still moves absolute references.
+        spreadsheet.insert_rows_before(0, 1);
+        assert_eq!(
+            spreadsheet.cell_at((0, 4)).serialize_display_content(),
+            "=$A$1+B2"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((1, 4)).serialize_display_content(),
+            "=A$2*2"
+        );
+    }
+
+    #[test]
+    pub fn diff_finds_added_removed_and_changed_cells() {
+        let mut old = Spreadsheet::load_csv("a,b\n1,2\n3,=A1+B1\n").unwrap();
+        let mut new = Spreadsheet::load_csv("a,c\n1,\n3,=A1*B1\n5,6\n").unwrap();
+        old.evaluate();
+        new.evaluate();
+        let changed = |old: &str, new: &str| CellDiffKind::Changed {
+            old: old.into(),
+            new: new.into(),
+        };
+        let diff: Vec<_> = old
+            .diff(&new)
+            .into_iter()
+            .map(|d| (d.position, d.kind))
+            .collect();
+        assert_eq!(
+            diff,
+            [
+                ((1, 0), changed("b", "c")),
+                ((1, 1), CellDiffKind::Removed("2".into())),
+                ((1, 2), changed("=A1+B1", "=A1*B1")),
+                ((0, 3), CellDiffKind::Added("5".into())),
+                ((1, 3), CellDiffKind::Added("6".into())),
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    pub fn svg_shows_styles_and_merged_cells() {
+        let mut spreadsheet = Spreadsheet::load_csv("a,1\n<b>,2\nc,3").unwrap();
+        spreadsheet.cell_at_mut((0, 0)).set_style(CellStyle {
+            bold: true,
+            background: Some((255, 0, 0)),
+            ..Default::default()
+        });
+        spreadsheet.merge_cells((0, 1), (1, 1));
+        let svg = spreadsheet.serialize_range_as_svg((0, 0), (1, 1));
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("fill=\"#ff0000\""));
+        assert!(svg.contains("font-weight=\"bold\" xml:space=\"preserve\">a</text>"));
+        assert!(svg.contains("text-anchor=\"end\" xml:space=\"preserve\">1</text>"));
+        assert!(svg.contains(">&lt;b&gt;</text>"));
+        // The merged cell hides the 2 and the last row is not part of the
+        // range.
+        assert!(!svg.contains(">2</text>"));
+        assert!(!svg.contains(">c</text>"));
+
+        // Columns without space for text show nothing.
+        spreadsheet.set_column_width(1, 0);
+        let svg = spreadsheet.serialize_range_as_svg((0, 0), (1, 0));
+        assert!(!svg.contains(">1</text>"));
+    }
+
+    #[test]
+    pub fn scripts_read_and_write_cells() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\n3,4").unwrap();
+        let output = spreadsheet
+            .run_script(
+                "for row in sheet:\n    print(sum(row))\n\
+                 sheet['A1'] = sheet['B0'] * 10\n\
+                 sheet.set(2, 2, '=A1+1')\n\
+                 sheet['B1'] = None",
+            )
+            .unwrap();
+        assert_eq!(output, "3\n7\n");
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (3, 3));
+        assert_eq!(spreadsheet.cell_at((0, 1)).display_content(), "20");
+        assert_eq!(spreadsheet.cell_at((2, 2)).display_content(), "21");
+        assert!(spreadsheet.cell_at((1, 1)).is_empty());
+        assert!(matches!(
+            spreadsheet.run_script("sheet['A0'] = [1]"),
+            Err(Error::Script(_))
+        ));
+        assert!(spreadsheet.run_script("1 +").is_err());
+    }
+
+    #[test]
+    pub fn typed_accessors_build_spreadsheets() {
+        let mut spreadsheet = Spreadsheet::from_rows([
+            vec![CellContent::from("name"), CellContent::from("price")],
+            vec![CellContent::from("=1+2"), CellContent::from(2.5)],
+            vec![CellContent::from(3_i64)],
+        ]);
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 3));
+        assert!(!spreadsheet.is_dirty());
+        assert_eq!(
+            spreadsheet.cell_at((0, 1)).content,
+            CellContent::Text("=1+2".into())
+        );
+        assert_eq!(spreadsheet.get_number((1, 1)), Some(2.5));
+        assert_eq!(spreadsheet.get_number((0, 2)), Some(3.0));
+        assert_eq!(spreadsheet.get_number((1, 2)), None);
+        assert_eq!(spreadsheet.get_number((5, 5)), None);
+
+        spreadsheet.set_value((3, 4), 7_i64).unwrap();
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (4, 5));
+        assert_eq!(spreadsheet.used_range().1, (3, 4));
+        assert_eq!(spreadsheet.get_number((3, 4)), Some(7.0));
+        spreadsheet
+            .set_row(0, &[CellContent::Number(1), CellContent::Empty])
+            .unwrap();
+        assert_eq!(spreadsheet.get_number((0, 0)), Some(1.0));
+        assert_eq!(spreadsheet.cell_at((1, 0)).content, CellContent::Empty);
+        assert!(spreadsheet.is_dirty());
+
+        spreadsheet.set_readonly(true);
+        assert!(matches!(
+            spreadsheet.set_value((0, 0), 2_i64),
+            Err(Error::ReadOnly)
+        ));
+        assert_eq!(spreadsheet.get_number((0, 0)), Some(1.0));
+    }
+
+    #[test]
+    pub fn builder_validates_rows() {
+        let spreadsheet = SpreadsheetBuilder::new()
+            .headers(["item", "price"])
+            .column_types([ColumnType::Text, ColumnType::Number])
+            .push_row([CellContent::from("apple"), CellContent::from(0.5)])
+            .push_row([CellContent::from("melon"), CellContent::Empty])
+            .build()
+            .unwrap();
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 3));
+        assert_eq!(spreadsheet.fixed_rows(), 1);
+        assert_eq!(spreadsheet.cell_at((1, 0)).display_content(), "price");
+        assert_eq!(spreadsheet.get_number((1, 1)), Some(0.5));
+        assert_eq!(spreadsheet.column_width(0), 5);
+
+        let builder = SpreadsheetBuilder::new()
+            .headers(["item", "price"])
+            .push_row(["apple"]);
+        assert!(matches!(builder.build(), Err(Error::Builder(_))));
+
+        let builder = SpreadsheetBuilder::new()
+            .column_types([ColumnType::Number])
+            .default_width(12)
+            .push_row(["apple"]);
+        assert!(matches!(builder.build(), Err(Error::Builder(_))));
+        let spreadsheet = SpreadsheetBuilder::new()
+            .default_width(12)
+            .rows([[1_i64, 2], [3, 4]])
+            .build()
+            .unwrap();
+        assert_eq!(spreadsheet.fixed_rows(), 0);
+        assert_eq!(spreadsheet.rows(), 2);
+        assert_eq!(spreadsheet.column_width(1), 12);
+    }
+
+    #[test]
+    pub fn column_types_are_inferred() {
+        let mut spreadsheet = Spreadsheet::load_csv(
+            "id,price,day,code\n1,2.5,2024-01-02,b10\n2,3,2024-01-03,9\nthree,n/a,2024-01-04,a",
+        )
+        .unwrap();
+        spreadsheet.fix_rows(1);
+        spreadsheet.infer_column_types();
+        assert_eq!(spreadsheet.column_type(0), ColumnType::Integer);
+        assert_eq!(spreadsheet.column_type(1), ColumnType::Number);
+        assert_eq!(spreadsheet.column_type(2), ColumnType::Date);
+        assert_eq!(spreadsheet.column_type(3), ColumnType::Text);
+        assert!(!spreadsheet.is_type_mismatch((0, 0)));
+        assert!(!spreadsheet.is_type_mismatch((0, 1)));
+        assert!(spreadsheet.is_type_mismatch((0, 3)));
+        assert!(spreadsheet.is_type_mismatch((1, 3)));
+        assert_eq!(spreadsheet.cell_alignment((1, 3)), CellAlignment::Right);
+        assert_eq!(spreadsheet.cell_alignment((3, 2)), CellAlignment::Left);
+
+        // Text columns compare numbers as text.
+        spreadsheet.sort_by(&[SortKey::ascending(3)]);
+        let codes: Vec<_> = (1..4)
+            .map(|row| spreadsheet.cell_at((3, row)).display_content().into_owned())
+            .collect();
+        assert_eq!(codes, ["9", "a", "b10"]);
+
+        spreadsheet.set_column_type(0, ColumnType::Text);
+        assert!(!spreadsheet.is_type_mismatch((0, 2)));
+        assert!(spreadsheet.is_type_mismatch((0, 3)));
+        spreadsheet.clear_column_types();
+        assert_eq!(spreadsheet.column_type(2), ColumnType::Any);
+        assert_eq!(spreadsheet.cell_alignment((3, 1)), CellAlignment::Right);
+    }
+
+    #[test]
+    pub fn markdown_round_trip() {
+        let markdown = "Some text\n\n| name | amount |\n|:-----|-------:|\n| a \\| b | 1 |\n| c<br>d |\n\nMore text\n";
+        let spreadsheet = Spreadsheet::load_markdown(markdown).unwrap();
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 3));
+        assert_eq!(spreadsheet.fixed_rows(), 1);
+        assert_eq!(spreadsheet.cell_at((0, 1)).display_content(), "a | b");
+        assert_eq!(spreadsheet.cell_at((0, 2)).display_content(), "c\nd");
+        assert_eq!(
+            spreadsheet.cell_at((1, 2)).style().alignment,
+            Some(CellAlignment::Right)
+        );
+        assert_eq!(
+            spreadsheet.serialize_as_markdown(),
+            "| name   | amount |\n\
+             | :----- | -----: |\n\
+             | a \\| b | 1      |\n\
+             | c<br>d |        |\n"
+        );
+        let spreadsheet = Spreadsheet::load_csv("1,x\n2,y").unwrap();
+        assert_eq!(
+            spreadsheet.serialize_as_markdown(),
+            "| A   | B   |\n| --: | --- |\n| 1   | x   |\n| 2   | y   |\n"
+        );
+        assert!(Spreadsheet::load_markdown("| a |\n| b |").is_err());
+    }
+
+    #[test]
+    pub fn json_round_trip() {
+        let json = r#"[{"name":"a","amount":1.5},{"amount":2,"tags":["x"],"note":"=1+1"}]"#;
+        let spreadsheet = Spreadsheet::load_json(json).unwrap();
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (4, 3));
+        assert_eq!(
+            spreadsheet.cell_range_as_csv((0, 0), (3, 2)),
+            "name,amount,tags,note\na,1.5,,\n,2,\"[\"\"x\"\"]\",=1+1"
+        );
+        assert_eq!(
+            spreadsheet.serialize_as_json(),
+            r#"[{"name":"a","amount":1.5,"tags":null,"note":null},{"name":null,"amount":2,"tags":"[\"x\"]","note":"=1+1"}]"#
+        );
+        assert!(Spreadsheet::load_json(r#"{"a":1}"#).is_err());
+        let spreadsheet = Spreadsheet::load_json(r#"[{"zip":"01234","count":7}]"#).unwrap();
+        assert_eq!(
+            spreadsheet.cell_at((0, 1)).content,
+            CellContent::Text("01234".into())
+        );
+        assert_eq!(spreadsheet.cell_at((1, 1)).content, CellContent::Number(7));
+    }
+
+    #[test]
+    #[cfg(feature = "parquet")]
+    pub fn load_parquet_row_ranges() {
+        use parquet::{
+            data_type::{ByteArrayType, DoubleType, Int64Type},
+            file::{properties::WriterProperties, writer::SerializedFileWriter},
+            schema::parser::parse_message_type,
+        };
+        let schema = parse_message_type(
+            "message schema { REQUIRED INT64 id; REQUIRED DOUBLE value; REQUIRED BINARY name (UTF8); }",
+        )
+        .unwrap();
+        let path = temp_path("load_parquet_row_ranges.parquet");
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = SerializedFileWriter::new(
+            file,
+            std::sync::Arc::new(schema),
+            std::sync::Arc::new(WriterProperties::builder().build()),
+        )
+        .unwrap();
+        // Two row groups with the ids 0 to 2 and 3 to 5.
+        for group in 0..2 {
+            let ids: Vec<i64> = (group * 3..group * 3 + 3).collect();
+            let mut row_group = writer.next_row_group().unwrap();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&ids, None, None)
+                .unwrap();
+            column.close().unwrap();
+            let values: Vec<f64> = ids.iter().map(|&id| id as f64 / 2.0).collect();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column
+                .typed::<DoubleType>()
+                .write_batch(&values, None, None)
+                .unwrap();
+            column.close().unwrap();
+            let names: Vec<_> = ids
+                .iter()
+                .map(|id| format!("row {id}").as_str().into())
+                .collect();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&names, None, None)
+                .unwrap();
+            column.close().unwrap();
+            row_group.close().unwrap();
+        }
+        writer.close().unwrap();
+
+        let spreadsheet = Spreadsheet::load_parquet(&path).unwrap();
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (3, 7));
+        assert_eq!(spreadsheet.fixed_rows(), 1);
+        assert_eq!(
+            spreadsheet.cell_range_as_csv((0, 0), (2, 1)),
+            "id,value,name\n0,0,row 0"
+        );
+
+        let file = ParquetFile::open(&path).unwrap();
+        let spreadsheet = file.load(2..4).unwrap();
+        assert_eq!(
+            spreadsheet.cell_range_as_csv((0, 1), (2, 2)),
+            "2,1,row 2\n3,1.5,row 3"
+        );
+        let mut spreadsheet_from_start = file.load(0..2).unwrap();
+        assert_eq!(file.load_more(&mut spreadsheet_from_start, 3).unwrap(), 3);
+        assert_eq!(file.load_more(&mut spreadsheet_from_start, 3).unwrap(), 1);
+        assert_eq!(file.load_more(&mut spreadsheet_from_start, 3).unwrap(), 0);
+        assert_eq!(
+            spreadsheet_from_start.cell_at((0, 6)).display_content(),
+            "5"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn csv_stream_loads_rows_in_chunks() {
+        let path = temp_path("csv_stream_loads_rows_in_chunks.csv");
+        std::fs::write(&path, "a;b\n1;\"x\ny\"\n\n2;3;4\n5;6\n").unwrap();
+        let mut stream = CsvStream::open(&path).unwrap();
+        let mut spreadsheet = stream.load_first(2).unwrap();
+        assert_eq!((spreadsheet.columns(), spreadsheet.rows()), (2, 2));
+        assert_eq!(spreadsheet.csv_separator(), ';');
+        assert_eq!(stream.load_more(&mut spreadsheet, 2).unwrap(), 2);
+        assert_eq!(stream.load_more(&mut spreadsheet, 2).unwrap(), 0);
+        assert_eq!(
+            spreadsheet.serialize_as_csv(),
+            "a;b;\n1;\"x\ny\";\n2;3;4\n5;6;"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn csv_stream_keeps_a_window_of_pages() {
+        let path = temp_path("csv_stream_keeps_a_window_of_pages.csv");
+        let csv = (0..25)
+            .map(|row| format!("{row},\"{row}\n\"\n"))
+            .collect::<String>();
+        std::fs::write(&path, csv).unwrap();
+        let mut stream = CsvStream::open(&path).unwrap().with_pages(5, 2);
+        let mut spreadsheet = stream.load_first(5).unwrap();
+        spreadsheet.set_cursor((0, 4));
+        assert_eq!(stream.load_more(&mut spreadsheet, 12).unwrap(), 12);
+        assert_eq!((spreadsheet.first_row(), spreadsheet.rows()), (10, 7));
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).serialize_display_content(),
+            "10"
+        );
+        assert_eq!(spreadsheet.current_cell(), (0, 0));
+        assert_eq!(stream.load_more(&mut spreadsheet, 10).unwrap(), 8);
+        assert_eq!((spreadsheet.first_row(), spreadsheet.rows()), (15, 10));
+        assert_eq!(stream.load_more(&mut spreadsheet, 10).unwrap(), 0);
+
+        spreadsheet.set_cursor((0, 2));
+        assert_eq!(stream.load_previous(&mut spreadsheet, 3).unwrap(), 5);
+        assert_eq!((spreadsheet.first_row(), spreadsheet.rows()), (10, 10));
+        assert_eq!(spreadsheet.current_cell(), (0, 7));
+        assert_eq!(
+            spreadsheet.cell_at((0, 0)).serialize_display_content(),
+            "10"
+        );
+        assert_eq!(
+            spreadsheet.cell_at((1, 9)).serialize_display_content(),
+            "19\n"
+        );
+        assert_eq!(stream.load_previous(&mut spreadsheet, 20).unwrap(), 10);
+        assert_eq!((spreadsheet.first_row(), spreadsheet.rows()), (0, 10));
+        assert_eq!(spreadsheet.cell_at((0, 9)).serialize_display_content(), "9");
+        assert_eq!(stream.load_previous(&mut spreadsheet, 5).unwrap(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn column_stats_skip_fixed_rows() {
+        let mut spreadsheet =
+            Spreadsheet::load_csv("amount,name\n4,a\n2,b\n,a\n=A1*3,\nnone,c").unwrap();
+        spreadsheet.evaluate();
+        spreadsheet.fix_rows(1);
+        assert_eq!(
+            spreadsheet.column_stats(0),
+            ColumnStats {
+                count: 4,
+                sum: 18.0,
+                mean: Some(6.0),
+                min: Some(2.0),
+                max: Some(12.0),
+                distinct: 4,
+            }
+        );
+        let stats = spreadsheet.column_stats(1);
+        assert_eq!((stats.count, stats.distinct, stats.mean), (4, 3, None));
+    }
+
+    #[test]
+    pub fn aggregate_range_skips_text_and_hidden_rows() {
+        let mut spreadsheet = Spreadsheet::load_csv("1,2\nx,4\n5,\n7,8").unwrap();
+        let aggregates = spreadsheet.aggregate_range((1, 2), (0, 0));
+        assert_eq!(
+            aggregates,
+            Aggregates {
+                sum: 12.0,
+                average: Some(3.0),
+                count: 5,
+                numeric_count: 4,
+            }
+        );
+        spreadsheet.apply_filter(&Filter {
+            column: 0,
+            operator: FilterOperator::NotEqual,
+            value: "1".into(),
+        });
+        assert_eq!(spreadsheet.aggregate_range((0, 0), (1, 3)).sum, 24.0);
+        assert_eq!(spreadsheet.aggregate_range((0, 1), (0, 1)).average, None);
+    }
+
+    #[test]
+    pub fn merged_cells_round_trip_through_xlsx() {
+        let mut spreadsheet = Spreadsheet::load_csv("a,b,c\nd,e,f\ng,h,i").unwrap();
+        spreadsheet.merge_cells((2, 1), (0, 0));
+        let merged = MergedCells {
+            from: (0, 0),
+            to: (2, 1),
+        };
+        assert_eq!(spreadsheet.merged_cells(), [merged]);
+        assert_eq!(spreadsheet.cell_at((0, 0)).display_content(), "a");
+        assert!(spreadsheet.cell_at((1, 1)).is_empty());
+        assert_eq!(spreadsheet.cell_at((1, 2)).display_content(), "h");
+        assert_eq!(spreadsheet.merged_cells_at((1, 2)), None);
+
+        let path = temp_path("merged_cells_round_trip_through_xlsx.xlsx");
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let mut loaded = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.merged_cells(), [merged]);
+
+        loaded.insert_rows_before(1, 2);
+        loaded.insert_columns_before(0, 1);
+        assert_eq!(loaded.merged_cells_at((3, 3)).unwrap().to, (3, 3));
+        assert!(loaded.unmerge_cells((1, 0)));
+        assert!(!loaded.unmerge_cells((1, 0)));
+    }
+
+    #[test]
+    pub fn xlsx_merges_outside_of_the_cells_are_dropped() {
+        let mut book = umya_spreadsheet::new_file();
+        let worksheet = book.get_sheet_mut(&0).unwrap();
+        worksheet.get_cell_mut("A1").set_value("a");
+        worksheet.get_cell_mut("B2").set_value("b");
+        worksheet.add_merge_cells("A1:A2");
+        worksheet.add_merge_cells("B2:C3");
+        worksheet.add_merge_cells("D1:E2");
+        let path = temp_path("xlsx_merges_outside_of_the_cells_are_dropped.xlsx");
+        umya_spreadsheet::writer::xlsx::write(&book, &path).unwrap();
+        let spreadsheet = Spreadsheet::load_xlsx(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            spreadsheet.merged_cells(),
+            [MergedCells {
+                from: (0, 0),
+                to: (0, 1)
+            }]
+        );
+    }
+
+    #[test]
+    pub fn row_heights_move_with_their_rows() {
+        let mut spreadsheet = Spreadsheet::load_csv("3\n1\n2").unwrap();
+        spreadsheet.set_row_height(0, 3);
+        spreadsheet.set_row_height(2, 0);
+        assert_eq!(spreadsheet.row_height(2), 1);
+        assert_eq!(spreadsheet.rows_with_custom_height(), 1);
+
+        spreadsheet.sort_column(0);
+        assert_eq!(spreadsheet.row_height(2), 3);
+        assert_eq!(spreadsheet.row_height(0), 1);
+
+        spreadsheet.checkpoint();
+        spreadsheet.insert_rows_before(1, 2);
+        assert_eq!(spreadsheet.row_height(4), 3);
+        spreadsheet.move_row(4, 0);
+        assert_eq!(spreadsheet.row_height(0), 3);
+        assert_eq!(spreadsheet.rows_with_custom_height(), 1);
+
+        spreadsheet.undo();
+        assert_eq!(spreadsheet.row_height(2), 3);
+    }
+
+    #[test]
+    pub fn load_xlsx_reports_progress() {
+        let spreadsheet = Spreadsheet::load_csv("a\nb\nc\nd").unwrap();
+        let path = temp_path("load_xlsx_reports_progress.xlsx");
+        spreadsheet.save_as_xlsx(&path).unwrap();
+        let mut reports = Vec::new();
+        Spreadsheet::load_xlsx_with_progress(&path, |progress| reports.push(progress)).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let percents: Vec<_> = reports.iter().map(Progress::percent).collect();
+        assert_eq!(percents, [None, Some(25), Some(50), Some(75), Some(100)]);
+    }
+
+    #[test]
+    pub fn load_xlsx_returns_errors() {
+        let path = temp_path("load_xlsx_returns_errors_missing.xlsx");
+        assert!(Spreadsheet::load_xlsx(&path).is_err());
+        let path = temp_path("load_xlsx_returns_errors.xlsx");
+        std::fs::write(&path, "1,2,3").unwrap();
+        assert!(Spreadsheet::load_xlsx(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    pub fn evaluate_async_matches_evaluate() {
+        let csv = "1,=A0*2,=C1\n2,=B0+A1,=C0\n5,=A2*A1,=B0+B1";
+        let mut expected = Spreadsheet::load_csv(csv).unwrap();
+        expected.evaluate();
+        let mut spreadsheet = Spreadsheet::load_csv(csv).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(spreadsheet.evaluate_async());
+        let values = |spreadsheet: &Spreadsheet| {
+            spreadsheet
+                .cells
+                .iter()
+                .map(|cell| cell.display_content().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(&spreadsheet), values(&expected));
+        assert_eq!(spreadsheet.cell_at((2, 1)).display_content(), "#cycle");
+    }
 }
@@ -0,0 +1,131 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::{CellAlignment, Error};
+
+/// A GitHub flavored markdown table. The header is the first row.
+pub(crate) struct MarkdownTable {
+    pub(crate) rows: Vec<Vec<String>>,
+    pub(crate) alignments: Vec<Option<CellAlignment>>,
+}
+
+/// Reads the first table of `text`. Text before the table is skipped and the
+/// table ends at the first line, which is not a row. Rows are padded or cut
+/// to the number of columns of the header.
+pub(crate) fn parse(text: &str) -> Result<MarkdownTable, Error> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| !line.starts_with('|'));
+    let header = lines
+        .next()
+        .ok_or_else(|| Error::Markdown("no table found".into()))?;
+    let header = split_row(header);
+    let alignments = lines
+        .next()
+        .map(split_row)
+        .filter(|delimiter| delimiter.len() == header.len())
+        .and_then(|delimiter| delimiter.iter().map(|d| parse_alignment(d)).collect())
+        .ok_or_else(|| Error::Markdown("expected a delimiter row like |---|---|".into()))?;
+    let mut rows = vec![header];
+    for line in lines.take_while(|line| line.starts_with('|')) {
+        let mut row = split_row(line);
+        row.resize(rows[0].len(), String::new());
+        rows.push(row);
+    }
+    Ok(MarkdownTable { rows, alignments })
+}
+
+/// Splits a row at the pipes, which are not escaped. Line breaks are written
+/// as `<br>`.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = if line.ends_with('|') && !line.ends_with("\\|") {
+        &line[..line.len() - 1]
+    } else {
+        line
+    };
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(ch),
+        }
+    }
+    cells
+        .into_iter()
+        .map(|cell| cell.trim().replace("<br>", "\n"))
+        .collect()
+}
+
+/// Parses a cell of the delimiter row like `:--` or `---:`.
+fn parse_alignment(delimiter: &str) -> Option<Option<CellAlignment>> {
+    let dashes = delimiter.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || dashes.chars().any(|ch| ch != '-') {
+        return None;
+    }
+    Some(
+        match (delimiter.starts_with(':'), delimiter.ends_with(':')) {
+            (true, true) => Some(CellAlignment::Center),
+            (true, false) => Some(CellAlignment::Left),
+            (false, true) => Some(CellAlignment::Right),
+            (false, false) => None,
+        },
+    )
+}
+
+fn escape(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Writes the table with padded columns, so it is readable as text as well.
+pub(crate) fn write(table: &MarkdownTable) -> String {
+    let rows: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|cell| escape(cell)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..table.alignments.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].width())
+                .max()
+                .unwrap_or_default()
+                // The delimiter needs at least three characters.
+                .max(3)
+        })
+        .collect();
+    let mut result = String::new();
+    for (index, row) in rows.iter().enumerate() {
+        write_row(&mut result, row.iter().map(String::as_str), &widths);
+        if index == 0 {
+            let delimiters = table.alignments.iter().zip(&widths).map(|(a, &w)| match a {
+                Some(CellAlignment::Left) => format!(":{}", "-".repeat(w - 1)),
+                Some(CellAlignment::Center) => format!(":{}:", "-".repeat(w - 2)),
+                Some(CellAlignment::Right) => format!("{}:", "-".repeat(w - 1)),
+                None => "-".repeat(w),
+            });
+            write_row(
+                &mut result,
+                delimiters.collect::<Vec<_>>().iter().map(String::as_str),
+                &widths,
+            );
+        }
+    }
+    result
+}
+
+fn write_row<'a>(result: &mut String, cells: impl Iterator<Item = &'a str>, widths: &[usize]) {
+    result.push('|');
+    for (cell, &width) in cells.zip(widths) {
+        result.push(' ');
+        result.push_str(cell);
+        result.extend(std::iter::repeat_n(' ', width - cell.width()));
+        result.push_str(" |");
+    }
+    result.push('\n');
+}
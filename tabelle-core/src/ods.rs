@@ -0,0 +1,291 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{Cell, CellContent, Error};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+/// A cell with content read from an ods file.
+pub(crate) struct OdsCell {
+    pub(crate) position: (usize, usize),
+    pub(crate) text: String,
+    pub(crate) url: Option<String>,
+    /// Whether the cell has no value besides its text, so it is loaded as
+    /// text even if it looks like a number or formula.
+    pub(crate) is_text: bool,
+}
+
+/// Reads the non empty cells of the first table of an ods file. Formulas of
+/// other applications can not be evaluated by tabelle, so their last
+/// calculated value is used. Comments of cells are skipped.
+pub(crate) fn read(path: &Path) -> Result<Vec<OdsCell>, Error> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(to_error)?;
+    let mut content = String::new();
+    archive
+        .by_name("content.xml")
+        .map_err(to_error)?
+        .read_to_string(&mut content)?;
+
+    let mut reader = Reader::from_str(&content);
+    let mut result = Vec::new();
+    let (mut column, mut row) = (0, 0);
+    // Rows with content can be repeated as well, so the cells of the current
+    // row are collected first.
+    let mut row_cells: Vec<OdsCell> = Vec::new();
+    let mut repeated_rows = 1;
+    let mut cell: Option<(OdsCell, Option<String>, usize)> = None;
+    let mut paragraphs = 0;
+    loop {
+        match reader.read_event().map_err(to_error)? {
+            Event::Start(element) => match element.name().as_ref() {
+                b"table:table-row" => {
+                    column = 0;
+                    repeated_rows = repeated(&element, "table:number-rows-repeated")?;
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    let value = ["office:value", "office:date-value", "office:boolean-value"]
+                        .into_iter()
+                        .find_map(|name| attribute(&element, name).transpose())
+                        .transpose()?;
+                    let ods_cell = OdsCell {
+                        position: (column, row),
+                        text: String::new(),
+                        url: None,
+                        is_text: value.is_none(),
+                    };
+                    let repetitions = repeated(&element, "table:number-columns-repeated")?;
+                    cell = Some((ods_cell, value, repetitions));
+                    paragraphs = 0;
+                }
+                b"text:p" => {
+                    if let Some((cell, _, _)) = &mut cell {
+                        if paragraphs > 0 {
+                            cell.text.push('\n');
+                        }
+                        paragraphs += 1;
+                    }
+                }
+                b"text:a" => {
+                    if let Some((cell, _, _)) = &mut cell {
+                        cell.url = attribute(&element, "xlink:href")?;
+                    }
+                }
+                b"office:annotation" => {
+                    reader.read_to_end(element.name()).map_err(to_error)?;
+                }
+                _ => {}
+            },
+            Event::Empty(element) => match element.name().as_ref() {
+                b"table:table-row" => {
+                    row += repeated(&element, "table:number-rows-repeated")?;
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    column += repeated(&element, "table:number-columns-repeated")?;
+                }
+                b"text:s" => {
+                    if let Some((cell, _, _)) = &mut cell {
+                        let spaces = repeated(&element, "text:c")?;
+                        cell.text.extend(std::iter::repeat_n(' ', spaces));
+                    }
+                }
+                b"text:tab" => {
+                    if let Some((cell, _, _)) = &mut cell {
+                        cell.text.push('\t');
+                    }
+                }
+                b"text:line-break" => {
+                    if let Some((cell, _, _)) = &mut cell {
+                        cell.text.push('\n');
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let Some((cell, _, _)) = &mut cell {
+                    cell.text.push_str(&text.unescape().map_err(to_error)?);
+                }
+            }
+            Event::End(element) => match element.name().as_ref() {
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    let Some((mut ods_cell, value, repetitions)) = cell.take() else {
+                        continue;
+                    };
+                    if let (Some(value), None) = (value, &ods_cell.url) {
+                        ods_cell.text = value;
+                    }
+                    if !ods_cell.text.is_empty() {
+                        for offset in 0..repetitions {
+                            row_cells.push(OdsCell {
+                                position: (column + offset, row),
+                                text: ods_cell.text.clone(),
+                                url: ods_cell.url.clone(),
+                                is_text: ods_cell.is_text,
+                            });
+                        }
+                    }
+                    column += repetitions;
+                }
+                b"table:table-row" => {
+                    // Empty rows are repeated until the end of the sheet, so
+                    // only rows with content are copied.
+                    if !row_cells.is_empty() {
+                        for offset in 1..repeated_rows {
+                            result.extend(row_cells.iter().map(|cell| OdsCell {
+                                position: (cell.position.0, row + offset),
+                                text: cell.text.clone(),
+                                url: cell.url.clone(),
+                                is_text: cell.is_text,
+                            }));
+                        }
+                    }
+                    result.append(&mut row_cells);
+                    row += repeated_rows;
+                }
+                // Only the first table is read.
+                b"table:table" => break,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+/// Writes the rows as the only table of an ods file. Formulas are written as
+/// text including their leading `=`, since other applications can not
+/// evaluate python.
+pub(crate) fn write<'a>(
+    path: &Path,
+    rows: impl Iterator<Item = &'a [Cell]>,
+    columns: usize,
+) -> Result<(), Error> {
+    let mut content = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        r#"<office:document-content"#,
+        r#" xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0""#,
+        r#" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0""#,
+        r#" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0""#,
+        r#" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.2">"#,
+        r#"<office:body><office:spreadsheet><table:table table:name="Sheet1">"#,
+    ));
+    write!(
+        content,
+        r#"<table:table-column table:number-columns-repeated="{columns}"/>"#
+    )
+    .unwrap();
+    for row in rows {
+        content.push_str("<table:table-row>");
+        for cell in row {
+            let text = match &cell.content {
+                CellContent::Empty => {
+                    content.push_str("<table:table-cell/>");
+                    continue;
+                }
+                CellContent::Number(_) | CellContent::FloatNumber(..) => {
+                    let value = cell.serialize_display_content();
+                    write!(
+                        content,
+                        r#"<table:table-cell office:value-type="float" office:value="{value}">"#
+                    )
+                    .unwrap();
+                    value
+                }
+                CellContent::Date(_) | CellContent::DateTime(_) => {
+                    let value = cell.serialize_display_content();
+                    // ods expects a `T` between the date and the time.
+                    write!(
+                        content,
+                        r#"<table:table-cell office:value-type="date" office:date-value="{}">"#,
+                        value.replace(' ', "T")
+                    )
+                    .unwrap();
+                    value
+                }
+                CellContent::Text(_) | CellContent::Formula(_) | CellContent::Hyperlink { .. } => {
+                    content.push_str(r#"<table:table-cell office:value-type="string">"#);
+                    cell.serialize_display_content()
+                }
+            };
+            for line in text.lines() {
+                match cell.content.url() {
+                    Some(url) => write!(
+                        content,
+                        r#"<text:p><text:a xlink:href="{}">{}</text:a></text:p>"#,
+                        escape(url),
+                        escape(line)
+                    ),
+                    None => write!(content, "<text:p>{}</text:p>", escape(line)),
+                }
+                .unwrap();
+            }
+            content.push_str("</table:table-cell>");
+        }
+        content.push_str("</table:table-row>");
+    }
+    content.push_str("</table:table></office:spreadsheet></office:body></office:document-content>");
+
+    let manifest = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<manifest:manifest"#,
+            r#" xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0""#,
+            r#" manifest:version="1.2">"#,
+            r#"<manifest:file-entry manifest:full-path="/" manifest:version="1.2""#,
+            r#" manifest:media-type="{}"/>"#,
+            r#"<manifest:file-entry manifest:full-path="content.xml""#,
+            r#" manifest:media-type="text/xml"/>"#,
+            r#"</manifest:manifest>"#
+        ),
+        MIMETYPE
+    );
+
+    let mut zip = ZipWriter::new(File::create(path)?);
+    // The mimetype has to be the first file and must not be compressed.
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )
+    .map_err(to_error)?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+    zip.start_file("META-INF/manifest.xml", FileOptions::default())
+        .map_err(to_error)?;
+    zip.write_all(manifest.as_bytes())?;
+    zip.start_file("content.xml", FileOptions::default())
+        .map_err(to_error)?;
+    zip.write_all(content.as_bytes())?;
+    zip.finish().map_err(to_error)?;
+    Ok(())
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, Error> {
+    match element.try_get_attribute(name).map_err(to_error)? {
+        Some(attribute) => Ok(Some(
+            attribute.unescape_value().map_err(to_error)?.into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Returns how often an element is repeated, which is 1 if the attribute
+/// `name` is missing.
+fn repeated(element: &BytesStart, name: &str) -> Result<usize, Error> {
+    Ok(attribute(element, name)?
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(1))
+}
+
+fn to_error(err: impl std::fmt::Display) -> Error {
+    Error::Ods(err.to_string())
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDate};
+use parquet::{
+    file::reader::{FileReader, SerializedFileReader},
+    record::Field,
+};
+
+use crate::{CellContent, Error, LazyRows, Spreadsheet};
+
+/// A parquet file, whose rows are only read when they are needed, so large
+/// files can be shown without loading them completely.
+pub struct ParquetFile {
+    reader: SerializedFileReader<File>,
+    path: PathBuf,
+}
+
+impl ParquetFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let reader = SerializedFileReader::new(File::open(path)?).map_err(to_error)?;
+        Ok(Self {
+            reader,
+            path: path.into(),
+        })
+    }
+
+    /// The number of rows in the file without the header.
+    pub fn rows(&self) -> usize {
+        self.reader.metadata().file_metadata().num_rows() as usize
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.reader
+            .metadata()
+            .file_metadata()
+            .schema()
+            .get_fields()
+            .iter()
+            .map(|field| field.name().to_owned())
+            .collect()
+    }
+
+    /// Loads the rows in `rows` below a fixed header row with the column
+    /// names. Only the row groups containing these rows are read.
+    pub fn load(&self, rows: Range<usize>) -> Result<Spreadsheet, Error> {
+        let names = self.column_names();
+        let mut result = Spreadsheet::new(names.len().max(1), 1);
+        for (cell, name) in result.cells.iter_mut().zip(names) {
+            cell.content = CellContent::Text(name);
+        }
+        result.fixed_rows = 1;
+        result.path = Some(self.path.clone());
+        self.append_rows(&mut result, rows)?;
+        Ok(result)
+    }
+
+    /// Appends up to `count` rows to `spreadsheet`, which was loaded with
+    /// [`ParquetFile::load`] starting at the first row. Returns the number of
+    /// appended rows, which is 0 once the whole file is loaded.
+    pub fn load_more(&self, spreadsheet: &mut Spreadsheet, count: usize) -> Result<usize, Error> {
+        let loaded = spreadsheet.rows() - 1;
+        let end = (loaded + count).min(self.rows());
+        self.append_rows(spreadsheet, loaded..end)?;
+        Ok(end.saturating_sub(loaded))
+    }
+
+    fn append_rows(&self, spreadsheet: &mut Spreadsheet, rows: Range<usize>) -> Result<(), Error> {
+        let rows = rows.start..rows.end.min(self.rows());
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let offset = spreadsheet.rows();
+        spreadsheet.resize(spreadsheet.columns(), offset + rows.len());
+        let mut y = offset;
+        let mut group_start = 0;
+        for index in 0..self.reader.num_row_groups() {
+            let group_rows = self.reader.metadata().row_group(index).num_rows() as usize;
+            let group = group_start..group_start + group_rows;
+            group_start += group_rows;
+            if group.end <= rows.start {
+                continue;
+            }
+            if group.start >= rows.end {
+                break;
+            }
+            let skip = rows.start.saturating_sub(group.start);
+            let take = rows.end.min(group.end) - group.start.max(rows.start);
+            let group = self.reader.get_row_group(index).map_err(to_error)?;
+            for row in group
+                .get_row_iter(None)
+                .map_err(to_error)?
+                .skip(skip)
+                .take(take)
+            {
+                let row = row.map_err(to_error)?;
+                for (x, (_, field)) in row.get_column_iter().enumerate() {
+                    let content = to_cell_content(field);
+                    spreadsheet.cell_at_mut((x, y)).content = content;
+                }
+                y += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LazyRows for ParquetFile {
+    fn load_first(&mut self, count: usize) -> Result<Spreadsheet, Error> {
+        self.load(0..count)
+    }
+
+    fn load_more(&mut self, spreadsheet: &mut Spreadsheet, count: usize) -> Result<usize, Error> {
+        ParquetFile::load_more(self, spreadsheet, count)
+    }
+}
+
+/// Integers become numbers, floats become float numbers and dates and
+/// timestamps become dates. Everything else is shown as text.
+fn to_cell_content(field: &Field) -> CellContent {
+    let float = |value: f64| CellContent::FloatNumber(value, 0);
+    match field {
+        Field::Null => CellContent::Empty,
+        &Field::Byte(it) => CellContent::Number(it.into()),
+        &Field::Short(it) => CellContent::Number(it.into()),
+        &Field::Int(it) => CellContent::Number(it.into()),
+        &Field::Long(it) => CellContent::Number(it),
+        &Field::UByte(it) => CellContent::Number(it.into()),
+        &Field::UShort(it) => CellContent::Number(it.into()),
+        &Field::UInt(it) => CellContent::Number(it.into()),
+        &Field::ULong(it) => i64::try_from(it)
+            .map(CellContent::Number)
+            .unwrap_or_else(|_| float(it as f64)),
+        Field::Float16(it) => float(it.to_f64()),
+        &Field::Float(it) => float(it.into()),
+        &Field::Double(it) => float(it),
+        Field::Str(it) => CellContent::Text(it.clone()),
+        &Field::Date(days) => NaiveDate::from_ymd_opt(1970, 1, 1)
+            .and_then(|epoch| epoch.checked_add_signed(chrono::Duration::days(days.into())))
+            .map_or_else(|| CellContent::Text(field.to_string()), CellContent::Date),
+        &Field::TimestampMillis(millis) => DateTime::from_timestamp_millis(millis).map_or_else(
+            || CellContent::Text(field.to_string()),
+            |it| CellContent::DateTime(it.naive_utc()),
+        ),
+        &Field::TimestampMicros(micros) => DateTime::from_timestamp_micros(micros).map_or_else(
+            || CellContent::Text(field.to_string()),
+            |it| CellContent::DateTime(it.naive_utc()),
+        ),
+        _ => CellContent::Text(field.to_string()),
+    }
+}
+
+fn to_error(err: impl std::fmt::Display) -> Error {
+    Error::Parquet(err.to_string())
+}
@@ -0,0 +1,66 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::Error;
+
+/// The needle of find and replace. Empty matches are ignored, so a regex like
+/// `x*` does not match every cell.
+pub(crate) enum Pattern {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Compiles `pattern` as a regex, which ignores the case of letters, if
+    /// `case_insensitive` is set.
+    pub(crate) fn regex(pattern: &str, case_insensitive: bool) -> Result<Self, Error> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map(Self::Regex)
+            .map_err(|err| Error::Regex(err.to_string()))
+    }
+
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(needle) => !needle.is_empty() && text.contains(needle.as_str()),
+            Pattern::Regex(regex) => regex.find_iter(text).any(|m| !m.is_empty()),
+        }
+    }
+
+    pub(crate) fn count(&self, text: &str) -> usize {
+        match self {
+            Pattern::Text(needle) if needle.is_empty() => 0,
+            Pattern::Text(needle) => text.matches(needle.as_str()).count(),
+            Pattern::Regex(regex) => regex.find_iter(text).filter(|m| !m.is_empty()).count(),
+        }
+    }
+
+    /// Replaces every match in `text` and returns the new text and the number
+    /// of replaced matches or `None`, if nothing matches. The replacement of a
+    /// regex can refer to its groups like `$1`.
+    pub(crate) fn replace(&self, text: &str, replacement: &str) -> Option<(String, usize)> {
+        match self {
+            Pattern::Text(needle) => {
+                let count = self.count(text);
+                (count > 0).then(|| (text.replace(needle.as_str(), replacement), count))
+            }
+            Pattern::Regex(regex) => {
+                let mut result = String::with_capacity(text.len());
+                let mut last = 0;
+                let mut count = 0;
+                for captures in regex.captures_iter(text) {
+                    let m = captures.get(0).expect("group 0 is always set");
+                    if m.is_empty() {
+                        continue;
+                    }
+                    result.push_str(&text[last..m.start()]);
+                    captures.expand(replacement, &mut result);
+                    last = m.end();
+                    count += 1;
+                }
+                result.push_str(&text[last..]);
+                (count > 0).then_some((result, count))
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::CellContent;
+
+/// Lists of names, which are continued in their order and start again
+/// after their last name.
+const NAME_LISTS: [&[&str]; 4] = [
+    &["mon", "tue", "wed", "thu", "fri", "sat", "sun"],
+    &[
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ],
+    &[
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ],
+    &[
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ],
+];
+
+/// Returns the value `distance` steps after `last` in the series ending with
+/// `previous` and `last`. The step is the difference between them, if they
+/// have the same kind of value, otherwise numbers, dates, names and text
+/// ending with a number count up by one. Returns `None` for everything
+/// else.
+pub(crate) fn continue_series(
+    previous: Option<&CellContent>,
+    last: &CellContent,
+    distance: i64,
+) -> Option<CellContent> {
+    match (previous, last) {
+        (Some(&CellContent::Number(previous)), &CellContent::Number(last)) => {
+            let step = last.checked_sub(previous)?;
+            Some(CellContent::Number(step_by(last, step, distance)?))
+        }
+        (
+            Some(previous @ (CellContent::Number(_) | CellContent::FloatNumber(..))),
+            CellContent::Number(_) | CellContent::FloatNumber(..),
+        ) => {
+            let (previous, last) = (previous.numeric_value()?, last.numeric_value()?);
+            let value = last + (last - previous) * distance as f64;
+            Some(CellContent::FloatNumber(value, 0))
+        }
+        (_, &CellContent::Number(last)) => Some(CellContent::Number(step_by(last, 1, distance)?)),
+        (_, &CellContent::FloatNumber(last, decimals)) => {
+            Some(CellContent::FloatNumber(last + distance as f64, decimals))
+        }
+        (previous, &CellContent::Date(last)) => {
+            let step = match previous {
+                Some(&CellContent::Date(previous)) => (last - previous).num_days(),
+                _ => 1,
+            };
+            Some(CellContent::Date(add_days(last, step * distance)?))
+        }
+        (previous, &CellContent::DateTime(last)) => {
+            let step = match previous {
+                Some(&CellContent::DateTime(previous)) => last - previous,
+                _ => Duration::days(1),
+            };
+            Some(CellContent::DateTime(add_steps(last, step, distance)?))
+        }
+        (previous, CellContent::Text(last)) => {
+            continue_text(previous.and_then(CellContent::as_str), last, distance)
+                .map(CellContent::Text)
+        }
+        _ => None,
+    }
+}
+
+/// Returns `last + step * count` or `None`, if it does not fit into an
+/// i64.
+fn step_by(last: i64, step: i64, count: i64) -> Option<i64> {
+    last.checked_add(step.checked_mul(count)?)
+}
+
+fn add_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    date.checked_add_signed(Duration::try_days(days)?)
+}
+
+fn add_steps(date_time: NaiveDateTime, step: Duration, count: i64) -> Option<NaiveDateTime> {
+    let count = i32::try_from(count).ok()?;
+    date_time.checked_add_signed(step.checked_mul(count)?)
+}
+
+/// Continues names like `Mon` or `January` and text ending with a number
+/// like `Item 9`.
+fn continue_text(previous: Option<&str>, last: &str, distance: i64) -> Option<String> {
+    if let Some((list, index)) = find_name(last) {
+        let step = match previous.and_then(find_name) {
+            Some((previous_list, previous_index)) if previous_list == list => {
+                index as i64 - previous_index as i64
+            }
+            _ => 1,
+        };
+        let len = list.len() as i64;
+        let index = (index as i64 + step * distance).rem_euclid(len) as usize;
+        return Some(with_case_of(list[index], last));
+    }
+    let (prefix, number, width) = split_trailing_number(last)?;
+    let step = match previous.and_then(split_trailing_number) {
+        Some((previous_prefix, previous_number, _)) if previous_prefix == prefix => {
+            number.checked_sub(previous_number)?
+        }
+        _ => 1,
+    };
+    Some(format!(
+        "{prefix}{:0width$}",
+        step_by(number, step, distance)?
+    ))
+}
+
+/// Returns the list containing `name` (ignoring the case) and the index of
+/// the name in it.
+fn find_name(name: &str) -> Option<(&'static [&'static str], usize)> {
+    let name = name.to_lowercase();
+    NAME_LISTS.into_iter().find_map(|list| {
+        list.iter()
+            .position(|it| *it == name)
+            .map(|index| (list, index))
+    })
+}
+
+/// Writes `name` in upper case, if `example` is, capitalized, if `example`
+/// starts with an upper case letter, and in lower case otherwise.
+fn with_case_of(name: &str, example: &str) -> String {
+    if example.chars().all(char::is_uppercase) {
+        name.to_uppercase()
+    } else if example.starts_with(char::is_uppercase) {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Splits text like `Item 009` into `Item `, 9 and the number of digits,
+/// which is 0 for numbers without leading zeros.
+fn split_trailing_number(text: &str) -> Option<(&str, i64, usize)> {
+    let start = text.trim_end_matches(|ch: char| ch.is_ascii_digit()).len();
+    if start == 0 || start == text.len() {
+        return None;
+    }
+    let digits = &text[start..];
+    let width = if digits.starts_with('0') {
+        digits.len()
+    } else {
+        0
+    };
+    Some((&text[..start], digits.parse().ok()?, width))
+}
@@ -0,0 +1,125 @@
+use std::{borrow::Cow, cmp::Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CellContent, ColumnType};
+
+/// A column to sort by and the direction, see [`crate::Spreadsheet::sort_by`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub column: usize,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn ascending(column: usize) -> Self {
+        Self {
+            column,
+            descending: false,
+        }
+    }
+
+    pub fn descending(column: usize) -> Self {
+        Self {
+            column,
+            descending: true,
+        }
+    }
+
+    /// Numbers and dates come before text, which comes before errors. Empty
+    /// cells are always last, no matter the direction. In text columns all
+    /// values are compared as text.
+    pub(crate) fn compare(
+        &self,
+        a: &CellContent,
+        b: &CellContent,
+        column_type: ColumnType,
+    ) -> Ordering {
+        let (a, b) = (SortValue::of(a, column_type), SortValue::of(b, column_type));
+        let ordering = match (&a, &b) {
+            (SortValue::Empty, SortValue::Empty) => return Ordering::Equal,
+            (SortValue::Empty, _) => return Ordering::Greater,
+            (_, SortValue::Empty) => return Ordering::Less,
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Text(a), SortValue::Text(b)) => natural_cmp(a, b),
+            _ => a.rank().cmp(&b.rank()),
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+enum SortValue<'a> {
+    Number(f64),
+    Text(Cow<'a, str>),
+    Error,
+    Empty,
+}
+
+impl<'a> SortValue<'a> {
+    fn of(content: &'a CellContent, column_type: ColumnType) -> Self {
+        if column_type == ColumnType::Text && !content.is_empty() && !content.is_error() {
+            SortValue::Text(content.display())
+        } else if let Some(value) = content.numeric_value().or_else(|| content.date_serial()) {
+            SortValue::Number(value)
+        } else if let Some(text) = content.text_value() {
+            SortValue::Text(text.into())
+        } else if content.is_error() {
+            SortValue::Error
+        } else {
+            SortValue::Empty
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortValue::Number(_) => 0,
+            SortValue::Text(_) => 1,
+            SortValue::Error => 2,
+            SortValue::Empty => 3,
+        }
+    }
+}
+
+/// Compares text ignoring the case and with numbers inside the text compared
+/// by their value, so `item 9` comes before `item 10`.
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chunks, mut b_chunks) = (chunks(a), chunks(b));
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            // Text only differing in case is still sorted the same way every
+            // time.
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b))
+                if a.starts_with(|c: char| c.is_ascii_digit())
+                    && b.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            (Some(a), Some(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Splits the text into runs of digits and runs of other characters.
+fn chunks(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let is_digit = rest.chars().next()?.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (chunk, remaining) = rest.split_at(end);
+        rest = remaining;
+        Some(chunk)
+    })
+}
@@ -0,0 +1,162 @@
+use std::{
+    fs::File,
+    io::{BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    csv::{CsvParseError, CsvRowReader},
+    Error, Spreadsheet,
+};
+
+/// A file, whose rows are loaded in chunks while scrolling through it, so
+/// opening it does not have to wait for the whole file.
+pub trait LazyRows {
+    /// Loads the first `count` rows.
+    fn load_first(&mut self, count: usize) -> Result<Spreadsheet, Error>;
+
+    /// Appends up to `count` rows to `spreadsheet`, which was loaded with
+    /// [`LazyRows::load_first`]. Returns the number of appended rows, which
+    /// is 0 once the whole file is loaded.
+    fn load_more(&mut self, spreadsheet: &mut Spreadsheet, count: usize) -> Result<usize, Error>;
+
+    /// Inserts up to `count` rows before the first row of `spreadsheet`,
+    /// which were dropped to save memory. Returns the number of inserted
+    /// rows, which is 0 if the first row of the file is loaded. Files, which
+    /// keep all loaded rows, have nothing to load here.
+    fn load_previous(
+        &mut self,
+        _spreadsheet: &mut Spreadsheet,
+        _count: usize,
+    ) -> Result<usize, Error> {
+        Ok(0)
+    }
+}
+
+/// The number of rows, which are loaded and dropped together by a
+/// [`CsvStream`].
+const PAGE_ROWS: usize = 1000;
+
+/// The number of pages a [`CsvStream`] keeps in memory.
+const MAX_PAGES: usize = 100;
+
+/// A csv file, which is parsed row by row instead of reading it completely
+/// first. Only a window of [`MAX_PAGES`] pages stays loaded, the pages
+/// before it are read again when scrolling back up.
+pub struct CsvStream {
+    rows: CsvRowReader<BufReader<File>>,
+    path: PathBuf,
+    /// The number of rows read by `rows`.
+    read: usize,
+    /// The byte offset of the first row of every page read so far.
+    pages: Vec<u64>,
+    page_rows: usize,
+    max_pages: usize,
+}
+
+impl CsvStream {
+    /// Opens the file and detects its seperator from the first lines.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut reader = BufReader::new(File::open(path)?);
+        let seperator = CsvRowReader::detect_seperator(&mut reader)?;
+        reader.rewind()?;
+        Ok(Self {
+            rows: CsvRowReader::new(reader, seperator),
+            path: path.into(),
+            read: 0,
+            pages: Vec::new(),
+            page_rows: PAGE_ROWS,
+            max_pages: MAX_PAGES,
+        })
+    }
+
+    /// Keeps at most `max_pages` pages of `page_rows` rows loaded instead of
+    /// [`MAX_PAGES`] pages of [`PAGE_ROWS`] rows.
+    pub fn with_pages(mut self, page_rows: usize, max_pages: usize) -> Self {
+        self.page_rows = page_rows.max(1);
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    fn max_rows(&self) -> usize {
+        self.page_rows * self.max_pages
+    }
+
+    /// Reads up to `count` rows from the row `start` on. Rows, which were
+    /// read before, are read again from the start of their page.
+    fn read_rows(&mut self, start: usize, count: usize) -> Result<Vec<Vec<String>>, Error> {
+        let mut rows = Vec::new();
+        if start < self.read {
+            let page = start / self.page_rows;
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(self.pages[page]))?;
+            rows = CsvRowReader::new(BufReader::new(file), self.rows.seperator())
+                .skip(start - page * self.page_rows)
+                .take(count.min(self.read - start))
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+        while rows.len() < count {
+            if self.pages.len() * self.page_rows == self.read {
+                self.pages.push(self.rows.position());
+            }
+            let Some(row) = self.rows.next() else {
+                break;
+            };
+            rows.push(row?);
+            self.read += 1;
+        }
+        Ok(rows)
+    }
+}
+
+impl LazyRows for CsvStream {
+    fn load_first(&mut self, count: usize) -> Result<Spreadsheet, Error> {
+        let mut result = Spreadsheet::new(1, 0);
+        if self.load_more(&mut result, count)? == 0 {
+            return Err(CsvParseError::NoCellsFound(0, 0).into());
+        }
+        result.csv_separator = self.rows.seperator();
+        result.path = Some(self.path.clone());
+        Ok(result)
+    }
+
+    /// Loads the rows page by page and drops the first pages, once more than
+    /// the maximum number of pages is loaded.
+    fn load_more(&mut self, spreadsheet: &mut Spreadsheet, count: usize) -> Result<usize, Error> {
+        let mut loaded = 0;
+        while loaded < count {
+            let start = spreadsheet.first_row() + spreadsheet.rows();
+            let rows = self.read_rows(start, (count - loaded).min(self.page_rows))?;
+            if rows.is_empty() {
+                break;
+            }
+            loaded += rows.len();
+            spreadsheet.append_csv_rows(rows);
+            let excess = spreadsheet.rows().saturating_sub(self.max_rows());
+            spreadsheet.drop_first_rows(excess.div_ceil(self.page_rows) * self.page_rows);
+        }
+        Ok(loaded)
+    }
+
+    /// Loads whole pages, so the first row stays at the start of a page, and
+    /// drops the last rows, once more than the maximum number of pages is
+    /// loaded.
+    fn load_previous(
+        &mut self,
+        spreadsheet: &mut Spreadsheet,
+        count: usize,
+    ) -> Result<usize, Error> {
+        let mut loaded = 0;
+        while loaded < count && spreadsheet.first_row() > 0 {
+            let first_row = spreadsheet.first_row();
+            let start = first_row.saturating_sub(self.page_rows);
+            let rows = self.read_rows(start, first_row - start)?;
+            loaded += rows.len();
+            spreadsheet.prepend_csv_rows(rows);
+            let excess = spreadsheet.rows().saturating_sub(self.max_rows());
+            spreadsheet.truncate(spreadsheet.columns(), spreadsheet.rows() - excess);
+        }
+        Ok(loaded)
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt::Write;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{escape_html, CellAlignment, Spreadsheet};
+
+/// The width of a character of the monospace font in pixels.
+const CHAR_WIDTH: usize = 8;
+/// The height of a line of text in pixels.
+const LINE_HEIGHT: usize = 20;
+/// The width of the column with the row numbers in characters.
+const ROW_NUMBER_WIDTH: usize = 5;
+
+const BORDER_COLOR: &str = "#a0a0a0";
+const HEADER_COLOR: &str = "#e8e8e8";
+
+/// Draws the cells between `from` and `to` like the terminal does, with
+/// the column names above and the row numbers left of them. Rows hidden by
+/// a filter are left out and merged cells are drawn as one cell.
+pub(crate) fn write(spreadsheet: &Spreadsheet, from: (usize, usize), to: (usize, usize)) -> String {
+    let columns: Vec<usize> = (from.0..=to.0.min(spreadsheet.columns() - 1)).collect();
+    let rows: Vec<usize> = spreadsheet
+        .visible_rows()
+        .skip_while(|&row| row < from.1)
+        .take_while(|&row| row <= to.1)
+        .collect();
+    // The left edges of the columns and the top edges of the rows including
+    // the right and bottom edge of the table.
+    let mut xs = vec![0, (ROW_NUMBER_WIDTH + 2) * CHAR_WIDTH];
+    for &column in &columns {
+        xs.push(xs.last().unwrap() + (spreadsheet.column_width(column) + 2) * CHAR_WIDTH);
+    }
+    let mut ys = vec![0, LINE_HEIGHT];
+    for &row in &rows {
+        ys.push(ys.last().unwrap() + spreadsheet.row_height(row) * LINE_HEIGHT);
+    }
+    let (width, height) = (*xs.last().unwrap(), *ys.last().unwrap());
+    let mut result = String::new();
+    writeln!(
+        result,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"monospace\" font-size=\"14\">"
+    )
+    .unwrap();
+    writeln!(
+        result,
+        "<rect width=\"{width}\" height=\"{height}\" fill=\"white\"/>"
+    )
+    .unwrap();
+    let header = |result: &mut String, (x, y, w): (usize, usize, usize), text: &str, anchor| {
+        rect(result, (x, y, w, LINE_HEIGHT), HEADER_COLOR);
+        let chars = w / CHAR_WIDTH - 2;
+        text_line(result, (x, y, w), &truncate(text, chars), anchor, "");
+    };
+    header(&mut result, (xs[0], ys[0], xs[1]), "", "start");
+    for (index, &column) in columns.iter().enumerate() {
+        let (x, w) = (xs[index + 1], xs[index + 2] - xs[index + 1]);
+        header(
+            &mut result,
+            (x, 0, w),
+            &spreadsheet.column_header(column),
+            "start",
+        );
+    }
+    for (row_index, &row) in rows.iter().enumerate() {
+        let (y, h) = (ys[row_index + 1], ys[row_index + 2] - ys[row_index + 1]);
+        rect(&mut result, (0, y, xs[1], h), HEADER_COLOR);
+        text_line(&mut result, (0, y, xs[1]), &row.to_string(), "end", "");
+        for (column_index, &column) in columns.iter().enumerate() {
+            let merged = spreadsheet.merged_cells_at((column, row));
+            let shown = match merged {
+                // Only the first visible cell of a merged area is drawn.
+                Some(merged) if (column, row) != visible_start(merged.from, from, &rows) => {
+                    continue
+                }
+                Some(merged) => merged.from,
+                None => (column, row),
+            };
+            let to = merged.map_or((column, row), |merged| merged.to);
+            let last_column = columns.iter().rposition(|&c| c <= to.0).unwrap();
+            let last_row = rows.iter().rposition(|&r| r <= to.1).unwrap();
+            let (x, w) = (
+                xs[column_index + 1],
+                xs[last_column + 2] - xs[column_index + 1],
+            );
+            let h = ys[last_row + 2] - y;
+            let cell = spreadsheet.cell_at(shown);
+            let style = cell.style();
+            let background = style
+                .background
+                .or_else(|| spreadsheet.row_background(row))
+                .map_or_else(|| "white".to_owned(), color);
+            rect(&mut result, (x, y, w, h), &background);
+            let anchor = match spreadsheet.cell_alignment(shown) {
+                CellAlignment::Left => "start",
+                CellAlignment::Center => "middle",
+                CellAlignment::Right => "end",
+            };
+            let mut attributes = String::new();
+            if style.bold {
+                attributes.push_str(" font-weight=\"bold\"");
+            }
+            if style.italic {
+                attributes.push_str(" font-style=\"italic\"");
+            }
+            if let Some(foreground) = style.foreground {
+                write!(attributes, " fill=\"{}\"", color(foreground)).unwrap();
+            }
+            let content = cell.display_content();
+            let chars = w / CHAR_WIDTH - 2;
+            for (index, line) in content.splitn(h / LINE_HEIGHT, '\n').enumerate() {
+                let y = y + index * LINE_HEIGHT;
+                text_line(
+                    &mut result,
+                    (x, y, w),
+                    &truncate(line, chars),
+                    anchor,
+                    &attributes,
+                );
+            }
+        }
+    }
+    result.push_str("</svg>\n");
+    result
+}
+
+/// Returns the top left cell of a merged area starting at `merged_from`,
+/// which is drawn, when the area starts above or left of `from` or in a
+/// hidden row.
+fn visible_start(
+    merged_from: (usize, usize),
+    from: (usize, usize),
+    rows: &[usize],
+) -> (usize, usize) {
+    let row = rows
+        .iter()
+        .copied()
+        .find(|&row| row >= merged_from.1)
+        .unwrap_or(merged_from.1);
+    (merged_from.0.max(from.0), row)
+}
+
+fn rect(result: &mut String, (x, y, w, h): (usize, usize, usize, usize), fill: &str) {
+    writeln!(
+        result,
+        "<rect x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\" fill=\"{fill}\" \
+         stroke=\"{BORDER_COLOR}\"/>"
+    )
+    .unwrap();
+}
+
+/// Writes a line of text into the cell starting at `x` with the width `w`,
+/// whose line starts at `y`.
+fn text_line(
+    result: &mut String,
+    (x, y, w): (usize, usize, usize),
+    text: &str,
+    anchor: &str,
+    attributes: &str,
+) {
+    if text.is_empty() {
+        return;
+    }
+    let x = match anchor {
+        "middle" => x + w / 2,
+        "end" => x + w - CHAR_WIDTH,
+        _ => x + CHAR_WIDTH,
+    };
+    // The baseline of the text.
+    let y = y + LINE_HEIGHT * 3 / 4;
+    writeln!(
+        result,
+        "<text x=\"{x}\" y=\"{y}\" text-anchor=\"{anchor}\"{attributes} xml:space=\"preserve\">{}</text>",
+        escape_html(text)
+    )
+    .unwrap();
+}
+
+/// Cuts `text` to `width` characters and marks it with an ellipsis, like
+/// cells in the terminal.
+fn truncate(text: &str, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+    if text.width() <= width {
+        return text.to_owned();
+    }
+    let mut result = String::new();
+    let mut result_width = 0;
+    for ch in text.chars() {
+        let ch_width = ch.width().unwrap_or_default();
+        if result_width + ch_width > width - 1 {
+            break;
+        }
+        result.push(ch);
+        result_width += ch_width;
+    }
+    result.push('…');
+    result
+}
+
+fn color((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -1,12 +1,27 @@
 use std::fmt::Display;
 
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+use crate::CellContent;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnitKind {
     #[default]
     None,
     Dollar,
+    Euro,
+    /// Displays the value as a percentage, so `0.5` becomes `50%`.
+    Percent,
+    /// Displays the value as a date, where the value is the number of days
+    /// since 1899-12-30, like in xlsx files.
+    Date,
+    /// Displays the fractional part of the value as the time of the day.
+    Time,
+    /// A xlsx format code. Codes like `#,##0.00` or `0.0%` are used for
+    /// displaying numbers, see [`NumberFormat`]. Other codes are only kept,
+    /// so they are saved again.
+    Custom(String),
 }
 
 impl Display for UnitKind {
@@ -17,6 +32,11 @@ impl Display for UnitKind {
             match self {
                 UnitKind::None => "",
                 UnitKind::Dollar => "$",
+                UnitKind::Euro => "€",
+                UnitKind::Percent => "%",
+                UnitKind::Date => "date",
+                UnitKind::Time => "time",
+                UnitKind::Custom(code) => code,
             }
         )
     }
@@ -27,44 +47,223 @@ impl UnitKind {
         match content {
             crate::CellContent::Empty => "".into(),
             crate::CellContent::Text(it) => it.into(),
-            &crate::CellContent::Number(it) => match self {
-                UnitKind::None => it.to_string(),
-                UnitKind::Dollar => format!("$ {:.2}", it as f64 * 0.01),
-            }
-            .into(),
-            crate::CellContent::FloatNumber(it, _) => it.to_string().into(),
+            crate::CellContent::Hyperlink { display, .. } => display.into(),
+            crate::CellContent::Date(_) | crate::CellContent::DateTime(_) => content.display(),
+            &crate::CellContent::Number(it) => self.display_number(it).into(),
+            &crate::CellContent::FloatNumber(it, _) => self.display_float_number(it).into(),
             crate::CellContent::Formula(it) => match it.value() {
                 crate::cells::cell_content::Value::String(it) => it.into(),
-                &crate::cells::cell_content::Value::Number(it) => match self {
-                    UnitKind::None => it.to_string(),
-                    UnitKind::Dollar => format!("$ {:.2}", it as f64 * 0.01),
+                &crate::cells::cell_content::Value::Number(it) => self.display_number(it).into(),
+                &crate::cells::cell_content::Value::FloatNumber(it) => {
+                    self.display_float_number(it).into()
                 }
-                .into(),
-                crate::cells::cell_content::Value::FloatNumber(it) => it.to_string().into(),
                 crate::cells::cell_content::Value::Empty => "".into(),
                 crate::cells::cell_content::Value::Error => "#error".into(),
+                crate::cells::cell_content::Value::Cycle => "#cycle".into(),
+            },
+        }
+    }
+
+    fn display_number(&self, it: i64) -> String {
+        match self {
+            UnitKind::None => it.to_string(),
+            UnitKind::Custom(code) => match NumberFormat::parse(code) {
+                Some(format) => format.display(it as f64),
+                None => it.to_string(),
+            },
+            UnitKind::Dollar => format!("$ {:.2}", it as f64 * 0.01),
+            UnitKind::Euro => format!("{:.2} €", it as f64 * 0.01),
+            UnitKind::Percent | UnitKind::Date | UnitKind::Time => {
+                self.display_float_number(it as f64)
+            }
+        }
+    }
+
+    fn display_float_number(&self, it: f64) -> String {
+        match self {
+            UnitKind::None | UnitKind::Dollar | UnitKind::Euro => display_float(it),
+            UnitKind::Custom(code) => match NumberFormat::parse(code) {
+                Some(format) => format.display(it),
+                None => it.to_string(),
+            },
+            // Rounding hides errors like 0.07 * 100 = 7.000000000000001.
+            UnitKind::Percent => format!("{}%", (it * 100.0 * 1e9).round() / 1e9),
+            UnitKind::Date => match date_time_from_serial(it) {
+                Some(date_time) => date_time.format("%Y-%m-%d").to_string(),
+                None => it.to_string(),
+            },
+            UnitKind::Time => match date_time_from_serial(it) {
+                Some(date_time) => date_time.format("%H:%M:%S").to_string(),
+                None => it.to_string(),
             },
         }
     }
+
+    /// Turns a number of a xlsx cell into a date, if this unit is a date
+    /// format.
+    pub(crate) fn content_from_serial(&self, serial: f64) -> Option<CellContent> {
+        let date_time = date_time_from_serial(serial)?;
+        match self {
+            UnitKind::Date => Some(CellContent::Date(date_time.date())),
+            UnitKind::Custom(code) => {
+                // Quoted text and sections like [Red] are no placeholders.
+                let mut placeholders = String::with_capacity(code.len());
+                let (mut quoted, mut bracketed) = (false, false);
+                for ch in code.chars() {
+                    match ch {
+                        '"' => quoted = !quoted,
+                        '[' if !quoted => bracketed = true,
+                        ']' if !quoted => bracketed = false,
+                        ch if !quoted && !bracketed => placeholders.push(ch.to_ascii_lowercase()),
+                        _ => {}
+                    }
+                }
+                if !placeholders.contains(['y', 'd']) {
+                    None
+                } else if placeholders.contains(['h', 's']) {
+                    Some(CellContent::DateTime(date_time))
+                } else {
+                    Some(CellContent::Date(date_time.date()))
+                }
+            }
+            _ => None,
+        }
+    }
 }
 
-impl<'a> TryFrom<&'a umya_spreadsheet::NumberingFormat> for UnitKind {
-    type Error = &'a umya_spreadsheet::NumberingFormat;
+/// Numbers at least this large are displayed in scientific notation.
+const SCIENTIFIC_ABOVE: f64 = 1e15;
+/// Numbers smaller than this (but not 0) are displayed in scientific
+/// notation.
+const SCIENTIFIC_BELOW: f64 = 1e-5;
+
+/// Displays a floating point number with as many digits as needed to read
+/// it back exactly. Very large and very small numbers are displayed in
+/// scientific notation like `1.5e20`, instead of with lots of zeros.
+pub(crate) fn display_float(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude >= SCIENTIFIC_ABOVE || (magnitude < SCIENTIFIC_BELOW && magnitude != 0.0) {
+        format!("{value:e}")
+    } else {
+        value.to_string()
+    }
+}
+
+/// The simple number formats of xlsx files, like `0.00`, `#,##0`, `0.0%` or
+/// `0.00E+00`. They change only how a number is displayed, not its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub decimal_places: usize,
+    /// Separates groups of three digits with commas, like `1,234,567`.
+    pub thousands_separator: bool,
+    /// Multiplies the value by 100 and appends `%`.
+    pub percent: bool,
+    /// Displays the value in scientific notation like `1.23E+09`.
+    pub scientific: bool,
+}
+
+impl NumberFormat {
+    /// Parses a format code made of an integer part of `0`, `#` and `,`, an
+    /// optional `.` followed by the decimal places as `0` or `#` and an
+    /// optional `%` or `E+00`. Returns `None` for every other code.
+    pub fn parse(code: &str) -> Option<Self> {
+        let (code, scientific) = match code.split_once(['E', 'e']) {
+            Some((code, "+00" | "+0" | "-00" | "-0")) => (code, true),
+            Some(_) => return None,
+            None => (code, false),
+        };
+        let (code, percent) = match code.strip_suffix('%') {
+            Some(code) if !scientific => (code, true),
+            Some(_) => return None,
+            None => (code, false),
+        };
+        let (integer, decimals) = code.split_once('.').unwrap_or((code, ""));
+        if !integer.contains('0')
+            || !integer.chars().all(|ch| matches!(ch, '0' | '#' | ','))
+            || !decimals.chars().all(|ch| matches!(ch, '0' | '#'))
+        {
+            return None;
+        }
+        Some(Self {
+            decimal_places: decimals.len(),
+            thousands_separator: integer.contains(',') && !scientific,
+            percent,
+            scientific,
+        })
+    }
+
+    pub fn display(&self, value: f64) -> String {
+        if self.scientific {
+            // Rust writes `1.23e9`, xlsx files show `1.23E+09`.
+            let formatted = format!("{value:.*e}", self.decimal_places);
+            let (mantissa, exponent) = formatted.split_once('e').unwrap();
+            let exponent: i32 = exponent.parse().unwrap();
+            let sign = if exponent < 0 { '-' } else { '+' };
+            return format!("{mantissa}E{sign}{:02}", exponent.abs());
+        }
+        let value = if self.percent { value * 100.0 } else { value };
+        let mut result = format!("{value:.*}", self.decimal_places);
+        if self.thousands_separator {
+            let digits_start = usize::from(result.starts_with('-'));
+            let digits_end = result.find('.').unwrap_or(result.len());
+            let mut index = digits_end;
+            while index > digits_start + 3 {
+                index -= 3;
+                result.insert(index, ',');
+            }
+        }
+        if self.percent {
+            result.push('%');
+        }
+        result
+    }
+}
+
+fn xlsx_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1899, 12, 30)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap()
+}
+
+/// Converts the number of days since 1899-12-30, which is how xlsx files
+/// store dates, into a date and time.
+pub(crate) fn date_time_from_serial(serial: f64) -> Option<NaiveDateTime> {
+    let milliseconds = (serial * 86_400_000.0).round();
+    if !milliseconds.is_finite() || milliseconds.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    xlsx_epoch().checked_add_signed(chrono::Duration::try_milliseconds(milliseconds as i64)?)
+}
+
+/// The inverse of [`date_time_from_serial`].
+pub(crate) fn serial_from_date_time(date_time: NaiveDateTime) -> f64 {
+    (date_time - xlsx_epoch()).num_milliseconds() as f64 / 86_400_000.0
+}
 
-    fn try_from(value: &'a umya_spreadsheet::NumberingFormat) -> Result<Self, Self::Error> {
+impl From<&umya_spreadsheet::NumberingFormat> for UnitKind {
+    fn from(value: &umya_spreadsheet::NumberingFormat) -> Self {
         match value.get_format_code() {
-            umya_spreadsheet::NumberingFormat::FORMAT_CURRENCY_USD => Ok(Self::Dollar),
-            umya_spreadsheet::NumberingFormat::FORMAT_GENERAL => Ok(Self::None),
-            _ => Err(value),
+            umya_spreadsheet::NumberingFormat::FORMAT_GENERAL => Self::None,
+            umya_spreadsheet::NumberingFormat::FORMAT_CURRENCY_USD => Self::Dollar,
+            umya_spreadsheet::NumberingFormat::FORMAT_CURRENCY_EUR_SIMPLE => Self::Euro,
+            umya_spreadsheet::NumberingFormat::FORMAT_PERCENTAGE => Self::Percent,
+            umya_spreadsheet::NumberingFormat::FORMAT_DATE_YYYYMMDD => Self::Date,
+            umya_spreadsheet::NumberingFormat::FORMAT_DATE_TIME4 => Self::Time,
+            code => Self::Custom(code.into()),
         }
     }
 }
 
 impl From<UnitKind> for umya_spreadsheet::NumberingFormat {
     fn from(value: UnitKind) -> Self {
-        let format = match value {
+        let format = match &value {
             UnitKind::None => umya_spreadsheet::NumberingFormat::FORMAT_GENERAL,
             UnitKind::Dollar => umya_spreadsheet::NumberingFormat::FORMAT_CURRENCY_USD,
+            UnitKind::Euro => umya_spreadsheet::NumberingFormat::FORMAT_CURRENCY_EUR_SIMPLE,
+            UnitKind::Percent => umya_spreadsheet::NumberingFormat::FORMAT_PERCENTAGE,
+            UnitKind::Date => umya_spreadsheet::NumberingFormat::FORMAT_DATE_YYYYMMDD,
+            UnitKind::Time => umya_spreadsheet::NumberingFormat::FORMAT_DATE_TIME4,
+            UnitKind::Custom(code) => code,
         };
         let mut nf = umya_spreadsheet::NumberingFormat::default();
         nf.set_format_code(format);
@@ -1,11 +1,22 @@
+use serde::{Deserialize, Serialize};
 use std::{fmt::Display, io::stdout, path::PathBuf};
 
 use crossterm::{
     terminal::{Clear, ClearType},
     ExecutableCommand,
 };
-use strum::{Display, EnumVariantNames};
-use tabelle_core::units::UnitKind;
+use strum::{Display, EnumVariantNames, IntoEnumIterator};
+use tabelle_core::{
+    units::{NumberFormat, UnitKind},
+    CellAlignment, ColumnType, Filter, FilterOperator, FindOptions, FormatRule, SortKey,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    dialog::{Dialog, DialogPurpose},
+    watch::FileWatch,
+    DisplayStyle, ScrollPage, Split, SplitDirection,
+};
 
 #[derive(strum::EnumIter, Display, PartialEq)]
 #[strum(serialize_all = "kebab-case")]
@@ -16,52 +27,288 @@ pub enum CommandKind {
     Set,
     Save,
     Find,
+    FindNext,
+    FindPrev,
     Sort,
     Fit,
     Fix,
     Resize,
+    Trim,
     Clear,
+    ClearFormat,
     Fill,
     Goto,
+    Stats,
+    StatsPanel,
+    Watch,
+    Types,
+    Split,
+    Snapshot,
+    Script,
+    InsertRows,
+    #[strum(to_string = "insert-cols")]
+    InsertColumns,
+    #[strum(to_string = "copy-system")]
+    CopyToSystem,
+    #[strum(to_string = "paste-system")]
+    PasteFromSystem,
+    #[strum(to_string = "move-col")]
+    MoveColumn,
+    MoveRow,
+    #[strum(to_string = "apply-col")]
+    ApplyColumn,
+    DuplicateRow,
+    #[strum(to_string = "duplicate-col")]
+    DuplicateColumn,
+    Filter,
+    ClearFilter,
+    Merge,
+    Unmerge,
+    FreezePane,
+    #[strum(to_string = "show-deps")]
+    ShowDependencies,
+    #[strum(to_string = "cond-format")]
+    ConditionalFormat,
+    CopyFormat,
+    Record,
+    Run,
+    Replace,
+    ReplaceCount,
+    Copy,
+    Cut,
+    Paste,
 }
 
 impl CommandKind {
     pub fn description(&self) -> &'static str {
         match self {
             CommandKind::None => "",
-            CommandKind::Help => "Displays this help with an overview over all commands and a general tutorial for this application.",
+            CommandKind::Help => "Displays this help with an overview over all commands and a general tutorial for this application. If a command is given, only its description and examples are shown.",
             CommandKind::New => "Creates a new spreadsheet. Make sure to save before.",
             CommandKind::Set => "Change the current cell. Takes two arguments, the first is the property, which will be changed (see the example for all possible values) and the second is the value for that key.",
-            CommandKind::Save => "Saves the current spreadsheet to a path.",
-            CommandKind::Find => "Finds a string in all the cells. Starts looking at the current cell, so you can checkout all results by repeating the command.",
-            CommandKind::Sort => "Takes a column (case insensitive) as an argument. This sorts the spreadsheet by this column. The ordering is `Text > Numbers > Empty`, where text is sorted alphabetically and numbers by their value. Formulas are ordered by their last evaluated value (which is the one displayed).",
+            CommandKind::Save => "Saves the current spreadsheet to a path. Paths ending in .xlsx, .ods, .md or .json are saved as xlsx, ods, a markdown table or an array of json objects keyed by the first row, everything else as csv using the separator of `set separator` or the one given with --sep, which is only used for this file. If cells are selected, only they are saved to csv files.",
+            CommandKind::Find => "Finds a string in all the cells, including numbers and the text of formulas. A string between slashes is a regex and all matching cells are highlighted. With /i the case of letters is ignored. Starts looking at the current cell, so you can checkout all results by repeating the command or with find-next.",
+            CommandKind::FindNext => "Moves to the next cell matching the last find. Can also be accessed by pressing F3.",
+            CommandKind::FindPrev => "Moves to the previous cell matching the last find. Can also be accessed by pressing Shift+F3.",
+            CommandKind::Sort => "Takes comma separated columns (case insensitive), each optionally followed by `asc` or `desc`. This sorts the spreadsheet by the first column and rows with equal values by the next one. The ordering is `Numbers < Text < Errors`, where numbers and dates are sorted by their value and text alphabetically, comparing numbers inside the text by their value. Empty cells always come last. Formulas are ordered by their last evaluated value (which is the one displayed).",
             CommandKind::Fit => "Sets the width of the given column automatically, so that its content fits inside.",
-            CommandKind::Fix => "This pins the given number of rows to the top. They will not be sorted.",
-            CommandKind::Resize => "Takes the new number of columns and rows as arguments. The have to be >= then the old size, otherwise bugs might be triggered.",
-            CommandKind::Clear => "Clears the cells between the current cell and the supplied cell of any content.",
-            CommandKind::Fill => "Auto fills from the current cell to the given cell.",
-            CommandKind::Goto => "Go to a given cell. Can also be accessed by pressing Ctrl+G.",
+            CommandKind::Fix => "This pins the given number of rows to the top or columns to the left. They stay visible while scrolling and fixed rows will not be sorted.",
+            CommandKind::Resize => "Takes the new number of columns and rows as arguments. Shrinking removes the cells outside of the new size. Without arguments a dialog asks for them. Can also be accessed by pressing Ctrl+R.",
+            CommandKind::Trim => "Removes the empty rows and columns after the last used cell, so they are not saved. Cells used by formulas or merged cells are kept.",
+            CommandKind::Clear => "Clears the cells between the current cell and the supplied cell of any content. Without a cell the selected cells are cleared. Cells are selected by holding Shift while moving the cursor.",
+            CommandKind::ClearFormat => "Resets the formatting (like the unit or the style) of the current cell, but keeps its value.",
+            CommandKind::Fill => "Auto fills from the current cell to the given cell. Without a cell the selection is filled with its top left cell. Numbers and dates continue the series of the cell above (or left of) the first cell and the first cell, like 1, 3, 5, and count up by one otherwise. Day and month names like Mon or January and text ending with a number like Item 1 are continued the same way.",
+            CommandKind::Goto => "Go to a given cell. If only a column or a row is given, the cursor stays in the current row or column. goto end goes to the last used cell, like Ctrl+End. Columns can be lowercase, like goto a5. Can also be accessed by pressing Ctrl+G.",
+            CommandKind::Stats => "Shows an overview over the contents of the spreadsheet. With a column it shows the count, sum, mean, minimum, maximum and number of distinct values of the column, skipping fixed rows.",
+            CommandKind::StatsPanel => "Shows or hides a panel at the right, which shows the statistics of the current column like `stats A`.",
+            CommandKind::Split => "Splits the view into two panes above each other (h) or side by side (v), which show different parts of the spreadsheet. F6 or a click moves the focus to the other pane. split off shows a single pane again.",
+            CommandKind::Snapshot => "Saves the visible cells as a svg image with their borders and styles. With a range like A0:C10 the cells in it are saved instead.",
+            CommandKind::Script => "Runs a python script, which can read and write the cells of the spreadsheet through `sheet`. Cells are accessed like sheet[\"A0\"] or sheet.get(column, row) and changed like sheet[\"A0\"] = 5 or sheet.set(column, row, 5). Iterating over sheet yields its rows. Text starting with = becomes a formula. What the script prints is shown afterwards.",
+            CommandKind::Watch => "Reloads the opened file, whenever another program changes it, or stops doing so. The cursor stays in its cell. Files with unsaved changes are not reloaded. Can also be turned on by starting tabelle with --watch.",
+            CommandKind::Types => "Gives every column the type (text, integer, number, date or datetime) most of its values below the fixed rows have, or removes the types again. Values not fitting the type of their column are shown in red, the values of typed columns are aligned alike and text columns sort numbers as text. Change the type of the current column with `set type`.",
+            CommandKind::InsertRows => "Inserts the given number of empty rows before the given row.",
+            CommandKind::InsertColumns => "Inserts the given number of empty columns before the given column.",
+            CommandKind::CopyToSystem => "Copies the current cell or the selected cells separated by tabs to the system clipboard. Can also be accessed by pressing Ctrl+C.",
+            CommandKind::PasteFromSystem => "Pastes the text of the system clipboard into the current cell. If it contains tabs, it is parsed as cells separated by tabs, which are quoted like csv. Other text with multiple lines is parsed as csv with a detected separator like , or ;. The cells are pasted starting at the current cell. The spreadsheet grows, if the cells do not fit. Can also be accessed by pressing Ctrl+V or with its old name paste-clipboard.",
+            CommandKind::MoveColumn => "Moves the first column to the position of the second column. Formulas keep referencing the same cells.",
+            CommandKind::MoveRow => "Moves the first row to the position of the second row. Formulas keep referencing the same cells.",
+            CommandKind::ApplyColumn => "Replaces every cell of the given column with the result of the python expression. The old value of the cell can be accessed as `x`. Fixed rows are not changed.",
+            CommandKind::DuplicateRow => "Inserts a copy of the current row below it. Formulas in the copy reference the same cells as the original.",
+            CommandKind::DuplicateColumn => "Inserts a copy of the current column right of it. Formulas in the copy reference the same cells as the original.",
+            CommandKind::FreezePane => "Pins the given number of rows to the top and columns to the left, like the freeze panes of Excel. They are saved as frozen panes in xlsx files.",
+            CommandKind::Replace => "Replaces the first text with the second text in the next cell containing it after the current cell. With all every cell is changed. With /r the first text is a regex and the second one can refer to its groups like $1.",
+            CommandKind::ReplaceCount => "Counts how often the text (or with /r the regex) appears in the cells without replacing anything.",
+            CommandKind::Record => "Starts recording the entered commands. Executing record again stops the recording and asks for the name of the macro.",
+            CommandKind::Run => "Executes the commands of the macro with the given name.",
+            CommandKind::CopyFormat => "Copies the width, the header, the type and the units of the first column to the second column.",
+            CommandKind::ConditionalFormat => "Colors the background of every row, whose value in the given column is above (>) or below (<) the threshold.",
+            CommandKind::ShowDependencies => "Highlights all cells the formula in the current cell depends on. Press Esc to remove the highlights again.",
+            CommandKind::Merge => "Appends the rows of the given file below the last row. With a range like B2:D2 the cells in it are merged into one cell instead, which keeps the content of the top left cell. Without arguments the selected cells are merged. Merged cells are saved in xlsx files.",
+            CommandKind::Unmerge => "Splits the merged cells containing the current cell into single cells again.",
+            CommandKind::Filter => "Hides every row, whose value in the given column does not match. The operators are =, !=, <, <=, >, >= and contains. Numbers and dates are compared by their value, text ignores the case. Hidden rows are not deleted, but left out when saving as csv. Filters can be combined, fixed rows are never hidden.",
+            CommandKind::ClearFilter => "Shows all rows hidden by a filter again. While a filter is active, the status bar shows [filtered].",
+            CommandKind::Copy => "Copies the selected cells. Cells are selected by holding Shift while moving the cursor.",
+            CommandKind::Cut => "Copies the selected cells and clears them afterwards.",
+            CommandKind::Paste => "Pastes the copied cells starting at the current cell. The spreadsheet grows, if they do not fit.",
         }
     }
 
     pub fn example_values(&self) -> Vec<Command> {
         match self {
             CommandKind::None => vec![Command::None],
-            CommandKind::Help => vec![Command::Help],
+            CommandKind::Help => vec![Command::Help(None), Command::Help(Some("goto".into()))],
             CommandKind::New => vec![Command::New],
             CommandKind::Set => vec![
                 Command::Set(SetCommand::ColumnWidth(10)),
+                Command::Set(SetCommand::RowHeight(3)),
                 Command::Set(SetCommand::Unit(UnitKind::Dollar)),
+                Command::Set(SetCommand::Unit(UnitKind::Percent)),
+                Command::Set(SetCommand::Unit(UnitKind::Date)),
+                Command::Set(SetCommand::Format("#,##0.00".into())),
+                Command::Set(SetCommand::Format("0.0%".into())),
+                Command::Set(SetCommand::Format("0.00E+00".into())),
+                Command::Set(SetCommand::Header(0, "Name".into())),
+                Command::Set(SetCommand::ColumnType(ColumnType::Number)),
+                Command::Set(SetCommand::Separator(';')),
+                Command::Set(SetCommand::Separator('\t')),
+                Command::Set(SetCommand::Style(DisplayStyle::Compact)),
+                Command::Set(SetCommand::CellStyle(StyleChange::Bold)),
+                Command::Set(SetCommand::CellStyle(StyleChange::Align(
+                    CellAlignment::Center,
+                ))),
+                Command::Set(SetCommand::CellStyle(StyleChange::Background((
+                    255, 255, 0,
+                )))),
+                Command::Set(SetCommand::CellStyle(StyleChange::Plain)),
+            ],
+            CommandKind::Save => vec![
+                Command::Save("table.xlsx".into(), None),
+                Command::Save("table.csv".into(), Some(';')),
+                Command::Save("table.md".into(), None),
+                Command::Save("data.json".into(), None),
+            ],
+            CommandKind::Find => vec![
+                Command::Find {
+                    needle: "total".into(),
+                    case_insensitive: false,
+                    regex: false,
+                },
+                Command::Find {
+                    needle: "total".into(),
+                    case_insensitive: true,
+                    regex: false,
+                },
+                Command::Find {
+                    needle: "^Total.*".into(),
+                    case_insensitive: false,
+                    regex: true,
+                },
+            ],
+            CommandKind::FindNext => vec![Command::FindNext],
+            CommandKind::FindPrev => vec![Command::FindPrev],
+            CommandKind::Sort => vec![
+                Command::Sort(vec![SortKey::ascending(0)]),
+                Command::Sort(vec![SortKey::descending(0), SortKey::ascending(1)]),
             ],
-            CommandKind::Save => vec![Command::Save("table.xlsx".into())],
-            CommandKind::Find => vec![Command::Find("total".into())],
-            CommandKind::Sort => vec![Command::Sort(0)],
             CommandKind::Fit => vec![Command::Fit(0)],
-            CommandKind::Fix => vec![Command::Fix(1), Command::Fix(5)],
-            CommandKind::Resize => vec![Command::Resize(5, 5)],
-            CommandKind::Clear => vec![Command::Clear((3, 2))],
-            CommandKind::Fill => vec![Command::Fill((5, 5))],
-            CommandKind::Goto => vec![Command::Goto((0, 550))],
+            CommandKind::Fix => vec![Command::Fix(1), Command::Fix(5), Command::FixColumns(2)],
+            CommandKind::Resize => vec![Command::Resize(None), Command::Resize(Some((5, 5)))],
+            CommandKind::Trim => vec![Command::Trim],
+            CommandKind::Clear => vec![Command::Clear(None), Command::Clear(Some((3, 2)))],
+            CommandKind::ClearFormat => vec![Command::ClearFormat],
+            CommandKind::Fill => vec![Command::Fill(None), Command::Fill(Some((5, 5)))],
+            CommandKind::Goto => vec![
+                Command::Goto(Some(0), Some(550)),
+                Command::Goto(Some(2), None),
+                Command::Goto(None, Some(12)),
+                Command::GotoEnd,
+            ],
+            CommandKind::Stats => vec![Command::Stats, Command::ColumnStats(0)],
+            CommandKind::StatsPanel => vec![Command::StatsPanel],
+            CommandKind::Watch => vec![Command::Watch(true), Command::Watch(false)],
+            CommandKind::Types => vec![Command::Types(true), Command::Types(false)],
+            CommandKind::Snapshot => vec![
+                Command::Snapshot(PathBuf::from("table.svg"), None),
+                Command::Snapshot(PathBuf::from("table.svg"), Some(((0, 0), (2, 10)))),
+            ],
+            CommandKind::Script => vec![Command::Script(PathBuf::from("transform.py"))],
+            CommandKind::Split => vec![
+                Command::Split(Some(SplitDirection::Horizontal)),
+                Command::Split(Some(SplitDirection::Vertical)),
+                Command::Split(None),
+            ],
+            CommandKind::InsertRows => vec![Command::InsertRows {
+                before: 3,
+                count: 2,
+            }],
+            CommandKind::InsertColumns => vec![Command::InsertColumns {
+                before: 1,
+                count: 1,
+            }],
+            CommandKind::CopyToSystem => vec![Command::CopyToSystem],
+            CommandKind::PasteFromSystem => vec![Command::PasteFromSystem],
+            CommandKind::MoveColumn => vec![Command::MoveColumn(0, 2)],
+            CommandKind::MoveRow => vec![Command::MoveRow(5, 1)],
+            CommandKind::ApplyColumn => vec![
+                Command::ApplyColumn(0, "x.upper()".into()),
+                Command::ApplyColumn(2, "x * 2".into()),
+            ],
+            CommandKind::DuplicateRow => vec![Command::DuplicateRow],
+            CommandKind::DuplicateColumn => vec![Command::DuplicateColumn],
+            CommandKind::Filter => vec![
+                Command::Filter(Filter {
+                    column: 0,
+                    operator: FilterOperator::Greater,
+                    value: "100".into(),
+                }),
+                Command::Filter(Filter {
+                    column: 1,
+                    operator: FilterOperator::Contains,
+                    value: "open".into(),
+                }),
+            ],
+            CommandKind::ClearFilter => vec![Command::ClearFilter],
+            CommandKind::Merge => vec![
+                Command::Merge("other.csv".into()),
+                Command::MergeCells(None),
+                Command::MergeCells(Some(((1, 2), (3, 2)))),
+            ],
+            CommandKind::Unmerge => vec![Command::Unmerge],
+            CommandKind::ShowDependencies => vec![Command::ShowDependencies],
+            CommandKind::CopyFormat => vec![Command::CopyFormat(0, 3)],
+            CommandKind::Record => vec![Command::Record],
+            CommandKind::Replace => vec![
+                Command::Replace {
+                    pattern: "2022".into(),
+                    replacement: "2023".into(),
+                    regex: false,
+                    all: false,
+                },
+                Command::Replace {
+                    pattern: "2022".into(),
+                    replacement: "2023".into(),
+                    regex: false,
+                    all: true,
+                },
+                Command::Replace {
+                    pattern: "(\\d+)€".into(),
+                    replacement: "$1".into(),
+                    regex: true,
+                    all: true,
+                },
+            ],
+            CommandKind::ReplaceCount => vec![
+                Command::ReplaceCount {
+                    pattern: "TODO".into(),
+                    regex: false,
+                },
+                Command::ReplaceCount {
+                    pattern: "^Total".into(),
+                    regex: true,
+                },
+            ],
+            CommandKind::Run => vec![Command::Run("format-prices".into())],
+            CommandKind::Copy => vec![Command::Copy],
+            CommandKind::Cut => vec![Command::Cut],
+            CommandKind::Paste => vec![Command::Paste],
+            CommandKind::ConditionalFormat => vec![
+                Command::ConditionalFormat {
+                    column: 1,
+                    above: true,
+                    threshold: 100.0,
+                    color: (0, 128, 0),
+                },
+                Command::ConditionalFormat {
+                    column: 1,
+                    above: false,
+                    threshold: 0.0,
+                    color: (128, 0, 0),
+                },
+            ],
+            CommandKind::FreezePane => vec![
+                Command::FreezePane { rows: 1, cols: 0 },
+                Command::FreezePane { rows: 2, cols: 1 },
+            ],
         }
     }
 }
@@ -70,55 +317,255 @@ impl From<Command> for CommandKind {
     fn from(value: Command) -> Self {
         match value {
             Command::None => Self::None,
-            Command::Help => Self::Help,
+            Command::Help(_) => Self::Help,
             Command::New => Self::New,
             Command::Set(_) => Self::Set,
-            Command::Save(_) => Self::Save,
-            Command::Find(_) => Self::Find,
+            Command::Save(..) => Self::Save,
+            Command::Find { .. } => Self::Find,
+            Command::FindNext => Self::FindNext,
+            Command::FindPrev => Self::FindPrev,
             Command::Sort(_) => Self::Sort,
             Command::Fit(_) => Self::Fit,
-            Command::Fix(_) => Self::Fix,
-            Command::Resize(_, _) => Self::Resize,
+            Command::Fix(_) | Command::FixColumns(_) => Self::Fix,
+            Command::Resize(_) => Self::Resize,
+            Command::Trim => Self::Trim,
             Command::Clear(_) => Self::Clear,
+            Command::ClearFormat => Self::ClearFormat,
             Command::Fill(_) => Self::Fill,
-            Command::Goto(_) => Self::Goto,
+            Command::Goto(_, _) | Command::GotoEnd => Self::Goto,
+            Command::Stats | Command::ColumnStats(_) => Self::Stats,
+            Command::StatsPanel => Self::StatsPanel,
+            Command::Watch(_) => Self::Watch,
+            Command::Types(_) => Self::Types,
+            Command::Split(_) => Self::Split,
+            Command::Snapshot(..) => Self::Snapshot,
+            Command::Script(_) => Self::Script,
+            Command::InsertRows { .. } => Self::InsertRows,
+            Command::InsertColumns { .. } => Self::InsertColumns,
+            Command::CopyToSystem => Self::CopyToSystem,
+            Command::PasteFromSystem => Self::PasteFromSystem,
+            Command::MoveColumn(_, _) => Self::MoveColumn,
+            Command::MoveRow(_, _) => Self::MoveRow,
+            Command::ApplyColumn(_, _) => Self::ApplyColumn,
+            Command::DuplicateRow => Self::DuplicateRow,
+            Command::DuplicateColumn => Self::DuplicateColumn,
+            Command::Filter(_) => Self::Filter,
+            Command::ClearFilter => Self::ClearFilter,
+            Command::Merge(_) | Command::MergeCells(_) => Self::Merge,
+            Command::Unmerge => Self::Unmerge,
+            Command::FreezePane { .. } => Self::FreezePane,
+            Command::ShowDependencies => Self::ShowDependencies,
+            Command::ConditionalFormat { .. } => Self::ConditionalFormat,
+            Command::CopyFormat(_, _) => Self::CopyFormat,
+            Command::Record => Self::Record,
+            Command::Run(_) => Self::Run,
+            Command::Replace { .. } => Self::Replace,
+            Command::ReplaceCount { .. } => Self::ReplaceCount,
+            Command::Copy => Self::Copy,
+            Command::Cut => Self::Cut,
+            Command::Paste => Self::Paste,
         }
     }
 }
 
-#[derive(Debug, EnumVariantNames, Display, strum::EnumDiscriminants, PartialEq)]
+#[derive(
+    Debug,
+    Clone,
+    EnumVariantNames,
+    Display,
+    strum::EnumDiscriminants,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
 #[strum(serialize_all = "kebab-case")]
 pub enum Command {
     None,
-    Help,
+    Help(Option<String>),
     New,
     Set(SetCommand),
-    Save(PathBuf),
-    Find(String),
-    Sort(usize),
+    /// Saves to the path. csv files use the separator, if one is given.
+    Save(PathBuf, Option<char>),
+    Find {
+        needle: String,
+        case_insensitive: bool,
+        /// The needle was written between slashes like `/^Total/`.
+        regex: bool,
+    },
+    FindNext,
+    FindPrev,
+    Sort(Vec<SortKey>),
     Fit(usize),
     Fix(usize),
-    Resize(usize, usize),
-    Clear((usize, usize)),
-    Fill((usize, usize)),
-    Goto((usize, usize)),
+    #[strum(to_string = "fix")]
+    FixColumns(usize),
+    Resize(Option<(usize, usize)>),
+    Trim,
+    Clear(Option<(usize, usize)>),
+    ClearFormat,
+    Fill(Option<(usize, usize)>),
+    Goto(Option<usize>, Option<usize>),
+    /// Goes to the last used cell.
+    #[strum(to_string = "goto")]
+    GotoEnd,
+    Stats,
+    #[strum(to_string = "stats")]
+    ColumnStats(usize),
+    StatsPanel,
+    Watch(bool),
+    Types(bool),
+    Split(Option<SplitDirection>),
+    Snapshot(PathBuf, Option<((usize, usize), (usize, usize))>),
+    Script(PathBuf),
+    InsertRows {
+        before: usize,
+        count: usize,
+    },
+    #[strum(to_string = "insert-cols")]
+    InsertColumns {
+        before: usize,
+        count: usize,
+    },
+    #[strum(to_string = "copy-system")]
+    CopyToSystem,
+    #[strum(to_string = "paste-system")]
+    PasteFromSystem,
+    #[strum(to_string = "move-col")]
+    MoveColumn(usize, usize),
+    MoveRow(usize, usize),
+    #[strum(to_string = "apply-col")]
+    ApplyColumn(usize, String),
+    DuplicateRow,
+    #[strum(to_string = "duplicate-col")]
+    DuplicateColumn,
+    Filter(Filter),
+    ClearFilter,
+    Merge(PathBuf),
+    /// Merges the cells of the range or of the selection.
+    #[strum(to_string = "merge")]
+    MergeCells(Option<((usize, usize), (usize, usize))>),
+    Unmerge,
+    FreezePane {
+        rows: usize,
+        cols: usize,
+    },
+    #[strum(to_string = "show-deps")]
+    ShowDependencies,
+    #[strum(to_string = "cond-format")]
+    ConditionalFormat {
+        column: usize,
+        above: bool,
+        threshold: f64,
+        color: (u8, u8, u8),
+    },
+    CopyFormat(usize, usize),
+    Record,
+    Run(String),
+    Replace {
+        pattern: String,
+        replacement: String,
+        regex: bool,
+        /// Replaces the pattern in every cell instead of the next one.
+        all: bool,
+    },
+    ReplaceCount {
+        pattern: String,
+        regex: bool,
+    },
+    Copy,
+    Cut,
+    Paste,
 }
 
 impl Command {
+    /// Creates a [`Command::Find`], which searches for a regex, if `needle`
+    /// is written between slashes.
+    fn find(needle: &str, case_insensitive: bool) -> Self {
+        let regex = needle
+            .strip_prefix('/')
+            .and_then(|needle| needle.strip_suffix('/'));
+        Self::Find {
+            needle: regex.unwrap_or(needle).to_string(),
+            case_insensitive,
+            regex: regex.is_some(),
+        }
+    }
+
     pub fn parse(text: &str) -> Result<Self, &str> {
         match text {
             "" => Ok(Self::None),
-            "help" => Ok(Self::Help),
+            "help" => Ok(Self::Help(None)),
             "new" => Ok(Self::New),
+            "clear-format" => Ok(Self::ClearFormat),
+            "trim" => Ok(Self::Trim),
+            "find-next" => Ok(Self::FindNext),
+            "find-prev" => Ok(Self::FindPrev),
+            "stats" => Ok(Self::Stats),
+            "stats-panel" => Ok(Self::StatsPanel),
+            "copy-system" => Ok(Self::CopyToSystem),
+            // paste-clipboard is the name from before copy-system existed.
+            "paste-system" | "paste-clipboard" => Ok(Self::PasteFromSystem),
+            "duplicate-row" => Ok(Self::DuplicateRow),
+            "duplicate-col" => Ok(Self::DuplicateColumn),
+            "clear-filter" => Ok(Self::ClearFilter),
+            "merge" => Ok(Self::MergeCells(None)),
+            "unmerge" => Ok(Self::Unmerge),
+            "show-deps" => Ok(Self::ShowDependencies),
+            "record" => Ok(Self::Record),
+            "clear" => Ok(Self::Clear(None)),
+            "fill" => Ok(Self::Fill(None)),
+            "copy" => Ok(Self::Copy),
+            "cut" => Ok(Self::Cut),
+            "paste" => Ok(Self::Paste),
             err => {
                 let parts: Vec<&str> = text.split(' ').collect();
                 match &parts[..] {
+                    ["set", "header", column, name @ ..] if !name.is_empty() => {
+                        Ok(Self::Set(SetCommand::Header(
+                            tabelle_core::column_name_to_index(&column.to_ascii_uppercase())
+                                .map_err(|_| *column)?,
+                            name.join(" "),
+                        )))
+                    }
                     ["set", key, value] => parse_set_command(key, value),
-                    ["save", path] => {
-                        Ok(Self::Save(std::path::PathBuf::from(path.to_owned()).into()))
+                    ["save", path] => Ok(Self::Save(PathBuf::from(path.to_owned()), None)),
+                    ["save", path, separator] => {
+                        let separator = separator
+                            .strip_prefix("--sep=")
+                            .ok_or("save expected --sep=<separator>")?;
+                        Ok(Self::Save(
+                            PathBuf::from(path.to_owned()),
+                            Some(parse_separator(separator)?),
+                        ))
+                    }
+                    ["freeze-pane", rows, cols] => Ok(Self::FreezePane {
+                        rows: rows.parse().map_err(|_| *rows)?,
+                        cols: cols.parse().map_err(|_| *cols)?,
+                    }),
+                    ["help", command] => Ok(Self::Help(Some(command.to_string()))),
+                    ["watch", "on"] => Ok(Self::Watch(true)),
+                    ["watch", "off"] => Ok(Self::Watch(false)),
+                    ["types", "on"] => Ok(Self::Types(true)),
+                    ["types", "off"] => Ok(Self::Types(false)),
+                    ["split", "h"] => Ok(Self::Split(Some(SplitDirection::Horizontal))),
+                    ["split", "v"] => Ok(Self::Split(Some(SplitDirection::Vertical))),
+                    ["split", "off"] => Ok(Self::Split(None)),
+                    ["script", "run", path] => Ok(Self::Script(PathBuf::from(path.to_owned()))),
+                    ["snapshot", path] => Ok(Self::Snapshot(PathBuf::from(path.to_owned()), None)),
+                    ["snapshot", path, range] => Ok(Self::Snapshot(
+                        PathBuf::from(path.to_owned()),
+                        Some(parse_range(range).ok_or(*range)?),
+                    )),
+                    ["merge", range] if parse_range(range).is_some() => {
+                        Ok(Self::MergeCells(parse_range(range)))
+                    }
+                    ["merge", path] => Ok(Self::Merge(PathBuf::from(path.to_owned()))),
+                    ["find", "/i", needle] => Ok(Self::find(needle, true)),
+                    ["find", needle] => Ok(Self::find(needle, false)),
+                    ["sort", keys @ ..] if !keys.is_empty() => {
+                        Ok(Self::Sort(parse_sort_keys(keys)?))
                     }
-                    ["find", needle] => Ok(Self::Find(needle.to_string().into())),
-                    ["sort", column] => Ok(Self::Sort(
+                    ["stats", column] => Ok(Self::ColumnStats(
                         tabelle_core::column_name_to_index(&column.to_ascii_uppercase())
                             .map_err(|_| *column)?,
                     )),
@@ -128,13 +575,114 @@ impl Command {
                     )),
                     ["fix", row, "rows"] => Ok(Self::Fix(row.parse().map_err(|_| *row)?)),
                     ["fix", "1", "row"] => Ok(Self::Fix(1)),
-                    ["resize", width, height] => Ok(Self::Resize(
+                    ["fix", columns, "cols" | "columns"] => {
+                        Ok(Self::FixColumns(columns.parse().map_err(|_| *columns)?))
+                    }
+                    ["fix", "1", "col" | "column"] => Ok(Self::FixColumns(1)),
+                    ["resize"] => Ok(Self::Resize(None)),
+                    ["resize", width, height] => Ok(Self::Resize(Some((
                         width.parse().map_err(|_| *width)?,
                         height.parse().map_err(|_| *height)?,
+                    )))),
+                    ["clear", cell] => Ok(Self::Clear(Some(tabelle_core::cell_name_to_position(
+                        cell,
+                    )?))),
+                    ["fill", cell] => {
+                        Ok(Self::Fill(Some(tabelle_core::cell_name_to_position(cell)?)))
+                    }
+                    ["insert-rows", before, count] => Ok(Self::InsertRows {
+                        before: before.parse().map_err(|_| *before)?,
+                        count: count.parse().map_err(|_| *count)?,
+                    }),
+                    ["insert-cols", before, count] => Ok(Self::InsertColumns {
+                        before: tabelle_core::column_name_to_index(&before.to_ascii_uppercase())
+                            .map_err(|_| *before)?,
+                        count: count.parse().map_err(|_| *count)?,
+                    }),
+                    ["move-col", from, to] => Ok(Self::MoveColumn(
+                        tabelle_core::column_name_to_index(&from.to_ascii_uppercase())
+                            .map_err(|_| *from)?,
+                        tabelle_core::column_name_to_index(&to.to_ascii_uppercase())
+                            .map_err(|_| *to)?,
                     )),
-                    ["clear", cell] => Ok(Self::Clear(tabelle_core::cell_name_to_position(cell)?)),
-                    ["fill", cell] => Ok(Self::Fill(tabelle_core::cell_name_to_position(cell)?)),
-                    ["goto", cell] => Ok(Self::Goto(tabelle_core::cell_name_to_position(cell)?)),
+                    ["apply-col", column, expression @ ..] if !expression.is_empty() => {
+                        Ok(Self::ApplyColumn(
+                            tabelle_core::column_name_to_index(&column.to_ascii_uppercase())
+                                .map_err(|_| *column)?,
+                            expression.join(" "),
+                        ))
+                    }
+                    ["filter", column, operator, value @ ..] if !value.is_empty() => {
+                        Ok(Self::Filter(Filter {
+                            column: tabelle_core::column_name_to_index(
+                                &column.to_ascii_uppercase(),
+                            )
+                            .map_err(|_| *column)?,
+                            operator: FilterOperator::parse(operator).ok_or(*operator)?,
+                            value: value.join(" "),
+                        }))
+                    }
+                    ["cond-format", column, operator @ (">" | "<"), threshold, color] => {
+                        Ok(Self::ConditionalFormat {
+                            column: tabelle_core::column_name_to_index(
+                                &column.to_ascii_uppercase(),
+                            )
+                            .map_err(|_| *column)?,
+                            above: *operator == ">",
+                            threshold: threshold.parse().map_err(|_| *threshold)?,
+                            color: parse_hex_color(color).ok_or(*color)?,
+                        })
+                    }
+                    ["replace", "/r", pattern, replacement, rest @ ..]
+                        if matches!(rest, [] | ["all"]) =>
+                    {
+                        Ok(Self::Replace {
+                            pattern: pattern.to_string(),
+                            replacement: replacement.to_string(),
+                            regex: true,
+                            all: !rest.is_empty(),
+                        })
+                    }
+                    ["replace", pattern, replacement, rest @ ..]
+                        if matches!(rest, [] | ["all"]) =>
+                    {
+                        Ok(Self::Replace {
+                            pattern: pattern.to_string(),
+                            replacement: replacement.to_string(),
+                            regex: false,
+                            all: !rest.is_empty(),
+                        })
+                    }
+                    ["replace-count", "/r", pattern] => Ok(Self::ReplaceCount {
+                        pattern: pattern.to_string(),
+                        regex: true,
+                    }),
+                    ["replace-count", pattern] => Ok(Self::ReplaceCount {
+                        pattern: pattern.to_string(),
+                        regex: false,
+                    }),
+                    ["run", name] => Ok(Self::Run(name.to_string())),
+                    ["copy-format", from, to] => Ok(Self::CopyFormat(
+                        tabelle_core::column_name_to_index(&from.to_ascii_uppercase())
+                            .map_err(|_| *from)?,
+                        tabelle_core::column_name_to_index(&to.to_ascii_uppercase())
+                            .map_err(|_| *to)?,
+                    )),
+                    ["move-row", from, to] => Ok(Self::MoveRow(
+                        from.parse().map_err(|_| *from)?,
+                        to.parse().map_err(|_| *to)?,
+                    )),
+                    ["goto", "end"] => Ok(Self::GotoEnd),
+                    ["goto", cell] => {
+                        let name = cell.to_ascii_uppercase();
+                        if let Ok((column, row)) = tabelle_core::cell_name_to_position(&name) {
+                            Ok(Self::Goto(Some(column), Some(row)))
+                        } else if let Ok(column) = tabelle_core::column_name_to_index(&name) {
+                            Ok(Self::Goto(Some(column), None))
+                        } else {
+                            Ok(Self::Goto(None, Some(cell.parse().map_err(|_| *cell)?)))
+                        }
+                    }
                     _ => Err(err),
                 }
             }
@@ -144,28 +692,228 @@ impl Command {
     pub fn full_display(&self) -> String {
         match self {
             Command::Set(kind) => format!("{self} {kind}"),
-            Command::Save(path) => format!("{self} {}", path.display()),
-            Command::Find(text) => format!("{self} {text}"),
-            Command::Sort(column) => format!("{self} {}", tabelle_core::to_column_name(*column)),
-            Command::Fit(column) => format!("{self} {}", tabelle_core::to_column_name(*column)),
+            Command::Help(Some(command)) => format!("{self} {command}"),
+            Command::Save(path, Some('\t')) => format!("{self} {} --sep=tab", path.display()),
+            Command::Save(path, Some(separator)) => {
+                format!("{self} {} --sep={separator}", path.display())
+            }
+            Command::Filter(filter) => format!(
+                "{self} {} {} {}",
+                tabelle_core::to_column_name(filter.column),
+                filter.operator.as_str(),
+                filter.value
+            ),
+            Command::Save(path, None) | Command::Merge(path) => {
+                format!("{self} {}", path.display())
+            }
+            Command::Find {
+                needle,
+                case_insensitive,
+                regex,
+            } => format!(
+                "{self}{} {}",
+                if *case_insensitive { " /i" } else { "" },
+                if *regex {
+                    format!("/{needle}/")
+                } else {
+                    needle.clone()
+                }
+            ),
+            Command::Run(text) => format!("{self} {text}"),
+            Command::Watch(on) | Command::Types(on) => {
+                format!("{self} {}", if *on { "on" } else { "off" })
+            }
+            Command::Split(direction) => format!(
+                "{self} {}",
+                match direction {
+                    Some(SplitDirection::Horizontal) => "h",
+                    Some(SplitDirection::Vertical) => "v",
+                    None => "off",
+                }
+            ),
+            Command::Sort(keys) => format!(
+                "{self} {}",
+                keys.iter()
+                    .map(|key| format!(
+                        "{}{}",
+                        tabelle_core::to_column_name(key.column),
+                        if key.descending { " desc" } else { "" }
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Command::Fit(column) | Command::ColumnStats(column) => {
+                format!("{self} {}", tabelle_core::to_column_name(*column))
+            }
             Command::Fix(rows) => {
                 format!("{self} {rows} {}", if *rows == 1 { "row" } else { "rows" })
             }
-            Command::Resize(columns, rows) => format!("{self} {columns} {rows}"),
-            Command::Goto(cell) | Command::Clear(cell) | Command::Fill(cell) => {
+            Command::FixColumns(columns) => {
+                format!(
+                    "{self} {columns} {}",
+                    if *columns == 1 { "col" } else { "cols" }
+                )
+            }
+            Command::Resize(Some((columns, rows))) => format!("{self} {columns} {rows}"),
+            Command::InsertRows { before, count } => format!("{self} {before} {count}"),
+            Command::FreezePane { rows, cols } => format!("{self} {rows} {cols}"),
+            Command::MoveColumn(from, to) | Command::CopyFormat(from, to) => format!(
+                "{self} {} {}",
+                tabelle_core::to_column_name(*from),
+                tabelle_core::to_column_name(*to)
+            ),
+            Command::MoveRow(from, to) => format!("{self} {from} {to}"),
+            Command::Replace {
+                pattern,
+                replacement,
+                regex,
+                all,
+            } => format!(
+                "{self}{} {pattern} {replacement}{}",
+                if *regex { " /r" } else { "" },
+                if *all { " all" } else { "" }
+            ),
+            Command::ReplaceCount { pattern, regex } => {
+                format!("{self}{} {pattern}", if *regex { " /r" } else { "" })
+            }
+            Command::ConditionalFormat {
+                column,
+                above,
+                threshold,
+                color: (r, g, b),
+            } => format!(
+                "{self} {} {} {threshold} #{r:02x}{g:02x}{b:02x}",
+                tabelle_core::to_column_name(*column),
+                if *above { '>' } else { '<' },
+            ),
+            Command::ApplyColumn(column, expression) => format!(
+                "{self} {} {expression}",
+                tabelle_core::to_column_name(*column)
+            ),
+            Command::InsertColumns { before, count } => {
+                format!("{self} {} {count}", tabelle_core::to_column_name(*before))
+            }
+            Command::Goto(column, row) => match (column, row) {
+                (Some(column), Some(row)) => format!(
+                    "{self} {}",
+                    tabelle_core::cell_position_to_name((*column, *row))
+                ),
+                (Some(column), None) => format!("{self} {}", tabelle_core::to_column_name(*column)),
+                (None, Some(row)) => format!("{self} {row}"),
+                (None, None) => self.to_string(),
+            },
+            Command::GotoEnd => format!("{self} end"),
+            Command::MergeCells(Some((from, to))) => format!(
+                "{self} {}:{}",
+                tabelle_core::cell_position_to_name(*from),
+                tabelle_core::cell_position_to_name(*to)
+            ),
+            Command::Snapshot(path, None) => format!("{self} {}", path.display()),
+            Command::Script(path) => format!("{self} run {}", path.display()),
+            Command::Snapshot(path, Some((from, to))) => format!(
+                "{self} {} {}:{}",
+                path.display(),
+                tabelle_core::cell_position_to_name(*from),
+                tabelle_core::cell_position_to_name(*to)
+            ),
+            Command::Clear(Some(cell)) | Command::Fill(Some(cell)) => {
                 format!("{self} {}", tabelle_core::cell_position_to_name(*cell))
             }
             default => default.to_string(),
         }
     }
 
+    /// Returns `false` for commands, which do not change the spreadsheet and
+    /// therefore can not be undone.
+    fn changes_spreadsheet(&self) -> bool {
+        !matches!(
+            self,
+            Command::None
+                | Command::Help(_)
+                | Command::Save(..)
+                | Command::Find { .. }
+                | Command::FindNext
+                | Command::FindPrev
+                | Command::Goto(..)
+                | Command::GotoEnd
+                | Command::Stats
+                | Command::ColumnStats(_)
+                | Command::StatsPanel
+                | Command::Watch(_)
+                | Command::Split(_)
+                | Command::Snapshot(..)
+                | Command::CopyToSystem
+                | Command::ShowDependencies
+                | Command::Record
+                | Command::Run(_)
+                | Command::ReplaceCount { .. }
+                | Command::Copy
+                | Command::Set(SetCommand::Style(_))
+        )
+    }
+
+    /// Returns `true` for commands, which are disabled in read-only mode.
+    /// Filters only change what is shown.
+    fn edits_spreadsheet(&self) -> bool {
+        self.changes_spreadsheet() && !matches!(self, Command::Filter(_) | Command::ClearFilter)
+    }
+
+    /// Returns `true` for commands, which throw away the content of cells.
+    fn needs_confirmation(&self) -> bool {
+        matches!(self, Command::New | Command::Clear(Some(_)))
+    }
+
     pub(crate) fn execute(&self, terminal: &mut crate::Terminal) -> crossterm::Result<bool> {
+        if self.edits_spreadsheet() && terminal.spreadsheet.is_readonly() {
+            terminal.show_readonly_message()?;
+            return Ok(true);
+        }
+        if self.needs_confirmation() && terminal.spreadsheet.is_dirty() {
+            terminal.dialog = Some(Dialog::confirmation(
+                format!(
+                    "There are unsaved changes. Do you really want to execute `{}`?",
+                    self.full_display()
+                ),
+                self.clone(),
+            ));
+            return Ok(true);
+        }
+        self.execute_confirmed(terminal)
+    }
+
+    /// Executes the command without asking for confirmation.
+    pub(crate) fn execute_confirmed(
+        &self,
+        terminal: &mut crate::Terminal,
+    ) -> crossterm::Result<bool> {
+        if self.changes_spreadsheet() {
+            terminal.spreadsheet.checkpoint();
+        }
         let exits_command_mode = match self {
             Command::None => true,
-            Command::Help => {
+            Command::Help(None) => {
                 terminal.render_help()?;
                 false
             }
+            Command::Help(Some(name)) => {
+                terminal.dialog = Some(
+                    match CommandKind::iter().find(|kind| &kind.to_string() == name) {
+                        Some(kind) => {
+                            let mut message = wrap_text(
+                                kind.description(),
+                                (terminal.width as usize).saturating_sub(4),
+                            );
+                            for example in kind.example_values() {
+                                message.push_str("\nExample: ");
+                                message.push_str(&example.full_display());
+                            }
+                            Dialog::display_message(message)
+                        }
+                        None => Dialog::display_error(format!("Unknown command {name}")),
+                    },
+                );
+                true
+            }
             Command::New => {
                 terminal.set_cursor(0, 0)?;
                 terminal.spreadsheet = tabelle_core::Spreadsheet::new(5, 5);
@@ -178,31 +926,153 @@ impl Command {
                     terminal.spreadsheet.set_column_width(column, *width);
                     true
                 }
+                SetCommand::RowHeight(height) if *height >= terminal.height as usize => {
+                    terminal.set_status_message(format!(
+                        "row-height can be at most {}",
+                        terminal.height.saturating_sub(1)
+                    ))?;
+                    true
+                }
+                SetCommand::RowHeight(height) => {
+                    let row = terminal.spreadsheet.current_cell().1;
+                    terminal.spreadsheet.set_row_height(row, *height);
+                    terminal.reset_scroll_page()?;
+                    true
+                }
                 SetCommand::Unit(unit) => {
                     terminal
                         .spreadsheet
                         .cell_at_mut(terminal.spreadsheet.current_cell())
-                        .set_unit(*unit);
+                        .set_unit(unit.clone());
+                    true
+                }
+                SetCommand::Format(code) => {
+                    let (from, to) = terminal.selection();
+                    for row in from.1..=to.1 {
+                        for column in from.0..=to.0 {
+                            terminal
+                                .spreadsheet
+                                .cell_at_mut((column, row))
+                                .set_unit(UnitKind::Custom(code.clone()));
+                        }
+                    }
+                    true
+                }
+                SetCommand::Separator(separator) => {
+                    terminal.spreadsheet.set_csv_separator(*separator);
+                    true
+                }
+                SetCommand::Style(style) => {
+                    terminal.display_style = *style;
+                    terminal.reset_scroll_page()?;
+                    true
+                }
+                SetCommand::CellStyle(change) => {
+                    let (from, to) = terminal.selection();
+                    for row in from.1..=to.1 {
+                        for column in from.0..=to.0 {
+                            let cell = terminal.spreadsheet.cell_at_mut((column, row));
+                            let mut style = cell.style();
+                            change.apply(&mut style);
+                            cell.set_style(style);
+                        }
+                    }
+                    true
+                }
+                SetCommand::ColumnType(column_type) => {
+                    let column = terminal.spreadsheet.current_cell().0;
+                    terminal.spreadsheet.set_column_type(column, *column_type);
+                    true
+                }
+                SetCommand::Header(column, name) => {
+                    if *column < terminal.spreadsheet.columns() {
+                        terminal
+                            .spreadsheet
+                            .set_column_header(*column, name.clone());
+                    }
                     true
                 }
             },
-            Command::Save(path) => {
-                terminal.spreadsheet.save_as_xlsx(path);
+            Command::Save(..) if terminal.spreadsheet.is_readonly() => {
+                terminal.show_readonly_message()?;
                 true
             }
-            Command::Find(needle) => {
-                if let Some(cell_position) = terminal.spreadsheet.find(needle) {
-                    let old_cursor = terminal.scroll_page.cursor;
-                    terminal.spreadsheet.set_cursor(cell_position);
+            Command::Save(path, separator) => {
+                // --sep only changes the separator of this file.
+                let separator = separator.unwrap_or(terminal.spreadsheet.csv_separator());
+                let is_csv = !matches!(
+                    path.extension().and_then(|e| e.to_str()),
+                    Some("xlsx" | "ods" | "md" | "json")
+                );
+                let saves_selection = terminal.selection_anchor.is_some() && is_csv;
+                let result = if saves_selection {
+                    let (from, to) = terminal.selection();
                     terminal
-                        .scroll_page
-                        .set_cursor(cell_position, terminal.cell_size());
-                    terminal.update_cursor(old_cursor)?;
+                        .spreadsheet
+                        .save_range_as_csv(path, from, to, separator)
+                } else {
+                    crate::save_spreadsheet(&terminal.spreadsheet, path, separator)
+                };
+                match result {
+                    // Only the selected cells are saved, so the spreadsheet
+                    // stays dirty.
+                    Ok(()) if saves_selection => terminal
+                        .set_status_message(format!("Saved the selection to {}", path.display()))?,
+                    Ok(()) => {
+                        terminal.spreadsheet.mark_saved();
+                        if let Some(watch) = &mut terminal.watch {
+                            watch.mark_saved();
+                        }
+                    }
+                    Err(err) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Error while saving {}: {err}",
+                            path.display()
+                        )))
+                    }
+                }
+                true
+            }
+            Command::Find {
+                needle,
+                case_insensitive,
+                regex,
+            } => {
+                let options = FindOptions {
+                    case_insensitive: *case_insensitive,
+                    regex: *regex,
+                    ..Default::default()
+                };
+                if *regex {
+                    let positions = terminal
+                        .spreadsheet
+                        .find_regex(needle, *case_insensitive)
+                        .map(Iterator::collect);
+                    match positions {
+                        Ok(positions) => {
+                            terminal.highlighted_positions = positions;
+                            terminal.render()?;
+                        }
+                        Err(err) => {
+                            terminal.dialog = Some(Dialog::display_error(err.to_string()));
+                            return Ok(true);
+                        }
+                    }
                 }
+                terminal.last_search = Some((needle.clone(), options));
+                terminal.find_next(false)?;
                 true
             }
-            &Command::Sort(column) => {
-                terminal.spreadsheet.sort_column(column);
+            Command::FindNext => {
+                terminal.find_next(false)?;
+                true
+            }
+            Command::FindPrev => {
+                terminal.find_next(true)?;
+                true
+            }
+            Command::Sort(keys) => {
+                terminal.spreadsheet.sort_by(keys);
                 // terminal.render()?;
                 true
             }
@@ -211,69 +1081,506 @@ impl Command {
                 // terminal.render()?;
                 true
             }
+            &Command::FreezePane { rows, cols } => {
+                terminal.spreadsheet.fix_rows(rows);
+                terminal.spreadsheet.fix_columns(cols);
+                terminal.reset_scroll_page()?;
+                true
+            }
             &Command::Fix(rows) => {
                 terminal.spreadsheet.fix_rows(rows);
+                terminal.reset_scroll_page()?;
                 true
             }
-            &Command::Resize(width, height) => {
+            &Command::FixColumns(columns) => {
+                terminal.spreadsheet.fix_columns(columns);
+                terminal.reset_scroll_page()?;
+                true
+            }
+            &Command::Resize(Some((width, height))) => {
                 terminal.spreadsheet.resize(width, height);
+                terminal.reset_scroll_page()?;
                 true
             }
-            &Command::Clear((to_x, to_y)) => {
-                let (from_x, from_y) = terminal.spreadsheet.current_cell();
-                for x in from_x..=to_x {
-                    for y in from_y..=to_y {
-                        terminal
-                            .spreadsheet
-                            .update_cell_at((x, y), tabelle_core::CellContent::Empty);
-                        if !terminal.move_cursor(0, 1)? {
-                            break;
+            Command::Resize(None) => {
+                let mut dialog = Dialog::input_multi(
+                    "Resize spreadsheet",
+                    vec![
+                        ("Width:".into(), terminal.spreadsheet.columns().to_string()),
+                        ("Height:".into(), terminal.spreadsheet.rows().to_string()),
+                    ],
+                );
+                dialog.purpose = DialogPurpose::Resize;
+                terminal.dialog = Some(dialog);
+                true
+            }
+            Command::Trim => {
+                let (columns, rows) = terminal.spreadsheet.shrink_to_used();
+                terminal.reset_scroll_page()?;
+                terminal
+                    .set_status_message(format!("Removed {columns} columns and {rows} rows"))?;
+                true
+            }
+            &Command::Clear(to) => {
+                let (from, to) = match to {
+                    Some(to) => (terminal.spreadsheet.current_cell(), to),
+                    None => terminal.selection(),
+                };
+                let last = (
+                    terminal.spreadsheet.columns() - 1,
+                    terminal.spreadsheet.rows() - 1,
+                );
+                terminal
+                    .spreadsheet
+                    .clear_range(from, (to.0.min(last.0), to.1.min(last.1)));
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::ClearFormat => {
+                terminal
+                    .spreadsheet
+                    .cell_at_mut(terminal.spreadsheet.current_cell())
+                    .set_unit(UnitKind::None);
+                terminal
+                    .spreadsheet
+                    .cell_at_mut(terminal.spreadsheet.current_cell())
+                    .set_style(Default::default());
+                true
+            }
+            &Command::Fill(to) => {
+                let (from, to) = match to {
+                    Some(to) => (terminal.spreadsheet.current_cell(), to),
+                    None => terminal.selection(),
+                };
+                let last = (
+                    terminal.spreadsheet.columns() - 1,
+                    terminal.spreadsheet.rows() - 1,
+                );
+                terminal
+                    .spreadsheet
+                    .fill_range(from, (to.0.min(last.0), to.1.min(last.1)));
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::Copy => {
+                let (from, to) = terminal.selection();
+                terminal.clipboard = terminal.spreadsheet.copy_range(from, to);
+                true
+            }
+            Command::Cut => {
+                let (from, to) = terminal.selection();
+                terminal.clipboard = terminal.spreadsheet.copy_range(from, to);
+                terminal.spreadsheet.clear_range(from, to);
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::Paste => {
+                let position = terminal.spreadsheet.current_cell();
+                terminal
+                    .spreadsheet
+                    .paste_range(position, terminal.clipboard.iter().map(Vec::as_slice));
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            &Command::Goto(column, row) => {
+                let (current_column, current_row) = terminal.spreadsheet.current_cell();
+                let column = column
+                    .unwrap_or(current_column)
+                    .min(terminal.spreadsheet.columns() - 1);
+                let row = row.unwrap_or(terminal.spreadsheet.first_row() + current_row);
+                terminal.goto_cell(column, row)?;
+                true
+            }
+            Command::GotoEnd => {
+                let (_, last) = terminal.spreadsheet.used_range();
+                terminal.set_cursor(last.0, last.1)?;
+                true
+            }
+            &Command::InsertRows { before, count } => {
+                let before = before.min(terminal.spreadsheet.rows());
+                terminal.spreadsheet.insert_rows_before(before, count);
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            &Command::InsertColumns { before, count } => {
+                let before = before.min(terminal.spreadsheet.columns());
+                terminal.spreadsheet.insert_columns_before(before, count);
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            &Command::MoveColumn(from, to) => {
+                let last = terminal.spreadsheet.columns() - 1;
+                terminal
+                    .spreadsheet
+                    .move_column(from.min(last), to.min(last));
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            &Command::MergeCells(range) => {
+                let (from, to) = range.unwrap_or_else(|| terminal.selection());
+                terminal.spreadsheet.merge_cells(from, to);
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::Unmerge => {
+                let position = terminal.spreadsheet.current_cell();
+                if !terminal.spreadsheet.unmerge_cells(position) {
+                    terminal.dialog =
+                        Some(Dialog::display_error("The current cell is not merged."));
+                }
+                true
+            }
+            Command::Merge(path) => {
+                match crate::load_spreadsheet(path) {
+                    Ok(other) => {
+                        terminal.spreadsheet.merge_from(&other);
+                        terminal.spreadsheet.evaluate();
+                    }
+                    Err(err) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Error while opening {}: {err}",
+                            path.display()
+                        )))
+                    }
+                }
+                true
+            }
+            &Command::ConditionalFormat {
+                column,
+                above,
+                threshold,
+                color,
+            } => {
+                terminal.spreadsheet.add_format_rule(FormatRule {
+                    column,
+                    threshold,
+                    above_color: above.then_some(color),
+                    below_color: (!above).then_some(color),
+                });
+                true
+            }
+            &Command::CopyFormat(from, to) => {
+                if from >= terminal.spreadsheet.columns() || to >= terminal.spreadsheet.columns() {
+                    terminal.dialog = Some(Dialog::display_error(format!(
+                        "The spreadsheet has only {} columns.",
+                        terminal.spreadsheet.columns()
+                    )));
+                    return Ok(true);
+                }
+                terminal.spreadsheet.copy_column_format(from, to);
+                stdout().execute(Clear(ClearType::All))?;
+                true
+            }
+            Command::Script(path) => {
+                let result = std::fs::read_to_string(path)
+                    .map_err(tabelle_core::Error::from)
+                    .and_then(|script| terminal.spreadsheet.run_script(&script));
+                match result {
+                    Ok(output) if output.trim().is_empty() => {}
+                    Ok(output) => {
+                        terminal.dialog = Some(Dialog::display_message(output.trim_end()))
+                    }
+                    Err(err) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Error while running {}: {err}",
+                            path.display()
+                        )))
+                    }
+                }
+                terminal.reset_scroll_page()?;
+                true
+            }
+            Command::Replace {
+                pattern,
+                replacement,
+                regex,
+                all: false,
+            } => {
+                let position = if *regex {
+                    match terminal
+                        .spreadsheet
+                        .replace_next_regex(pattern, replacement)
+                    {
+                        Ok(position) => position,
+                        Err(err) => {
+                            terminal.dialog = Some(Dialog::display_error(err.to_string()));
+                            return Ok(true);
                         }
                     }
-                    // TODO: Fix handling, if the break before was triggered,
-                    // since then we did not move to_y - from_y cells.
-                    if !terminal.move_cursor(1, -((to_y - from_y) as isize))? {
-                        break;
+                } else {
+                    terminal.spreadsheet.replace_next(pattern, replacement)
+                };
+                match position {
+                    Some((x, y)) => {
+                        terminal.spreadsheet.evaluate();
+                        terminal.set_cursor(x, y)?;
                     }
+                    None => terminal.set_status_message(format!("{pattern} not found"))?,
                 }
+                true
+            }
+            Command::Replace {
+                pattern,
+                replacement,
+                regex,
+                all: true,
+            } => {
+                let count = if *regex {
+                    match terminal.spreadsheet.replace_all_regex(pattern, replacement) {
+                        Ok(count) => count,
+                        Err(err) => {
+                            terminal.dialog = Some(Dialog::display_error(err.to_string()));
+                            return Ok(true);
+                        }
+                    }
+                } else {
+                    terminal.spreadsheet.replace_all(pattern, replacement)
+                };
                 terminal.spreadsheet.evaluate();
-                terminal.update_cursor((from_x, from_y))?;
+                terminal.render()?;
+                terminal.set_status_message(format!("Replaced {count} occurrences"))?;
                 true
             }
-            &Command::Fill((to_x, to_y)) => {
-                let (from_x, from_y) = terminal.spreadsheet.current_cell();
-                for x in from_x..=to_x {
-                    for y in from_y..=to_y {
-                        terminal.spreadsheet.update_cell_at(
-                            (x, y),
-                            terminal
-                                .spreadsheet
-                                .recommended_cell_content((from_x, from_y)),
-                        );
-                        terminal.spreadsheet.evaluate();
-                        if !terminal.move_cursor(0, 1)? {
-                            break;
+            Command::ReplaceCount { pattern, regex } => {
+                let count = if *regex {
+                    match terminal.spreadsheet.count_occurrences_regex(pattern) {
+                        Ok(count) => count,
+                        Err(err) => {
+                            terminal.dialog = Some(Dialog::display_error(err.to_string()));
+                            return Ok(true);
                         }
                     }
-                    assert!(
-                        to_y >= from_y && to_x >= from_x,
-                        "to_y = {to_y}; from_y = {from_y}; to_x = {to_x}; from_x = {from_x}; x = {x}"
+                } else {
+                    terminal.spreadsheet.count_occurrences(pattern)
+                };
+                terminal.dialog = Some(Dialog::display_message(format!(
+                    "Found {count} occurrences of {pattern}"
+                )));
+                true
+            }
+            Command::Record => match terminal.recording.take() {
+                Some(commands) => {
+                    let mut dialog = Dialog::input_multi(
+                        &format!("Save {} recorded commands as", commands.len()),
+                        vec![("Name:".into(), String::new())],
                     );
-                    // TODO: Fix handling, if the break before was triggered,
-                    // since then we did not move to_y - from_y cells.
-                    if !terminal.move_cursor(1, -((to_y - from_y) as isize))? {
-                        break;
+                    dialog.purpose = DialogPurpose::MacroName(commands);
+                    terminal.dialog = Some(dialog);
+                    true
+                }
+                None => {
+                    terminal.recording = Some(Vec::new());
+                    true
+                }
+            },
+            Command::Run(name) => {
+                let Some(commands) = terminal.macros.get(name).cloned() else {
+                    terminal.dialog = Some(Dialog::display_error(format!(
+                        "There is no macro named {name}."
+                    )));
+                    return Ok(true);
+                };
+                for text in commands {
+                    match Command::parse(&text) {
+                        // Macros could run themselves otherwise.
+                        Ok(Command::Run(_)) => {
+                            terminal.dialog =
+                                Some(Dialog::display_error("Macros can not run other macros."));
+                            break;
+                        }
+                        Ok(command) => {
+                            command.execute(terminal)?;
+                        }
+                        Err(err) => {
+                            terminal.dialog = Some(Dialog::display_error(format!(
+                                "Invalid command in macro {name}: {err}"
+                            )));
+                            break;
+                        }
                     }
                 }
-                terminal.update_cursor((from_x, from_y))?;
                 true
             }
-            &Command::Goto(cell) => {
-                let cell = (
-                    cell.0.min(terminal.spreadsheet.columns() - 1),
-                    cell.1.min(terminal.spreadsheet.rows() - 1),
-                );
-                terminal.set_cursor(cell.0, cell.1)?;
+            Command::ShowDependencies => {
+                let position = terminal.spreadsheet.current_cell();
+                terminal.highlighted_positions =
+                    terminal.spreadsheet.formula_dependencies(position);
+                if terminal.highlighted_positions.is_empty() {
+                    terminal.set_status_message("The current cell depends on no other cells")?;
+                }
+                true
+            }
+            Command::Filter(filter) => {
+                if terminal.spreadsheet.apply_filter(filter) == 0 {
+                    terminal.set_status_message("No row matches the filter")?;
+                } else {
+                    terminal.reset_scroll_page()?;
+                }
+                true
+            }
+            Command::ClearFilter => {
+                terminal.spreadsheet.clear_filter();
+                stdout().execute(Clear(ClearType::All))?;
+                terminal.reset_scroll_page()?;
+                true
+            }
+            Command::DuplicateRow => {
+                let (_, row) = terminal.spreadsheet.current_cell();
+                terminal.spreadsheet.duplicate_row(row);
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::DuplicateColumn => {
+                let (column, _) = terminal.spreadsheet.current_cell();
+                terminal.spreadsheet.duplicate_column(column);
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            &Command::MoveRow(from, to) => {
+                let last = terminal.spreadsheet.rows() - 1;
+                terminal.spreadsheet.move_row(from.min(last), to.min(last));
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::ApplyColumn(column, expression) => {
+                if *column < terminal.spreadsheet.columns() {
+                    if let Err(err) = terminal
+                        .spreadsheet
+                        .apply_expression_to_column(*column, expression)
+                    {
+                        terminal.dialog = Some(Dialog::display_error(err));
+                    }
+                    terminal.spreadsheet.evaluate();
+                }
+                true
+            }
+            Command::CopyToSystem => {
+                let (from, to) = terminal.selection();
+                let text = terminal.spreadsheet.cell_range_as_tsv(from, to);
+                match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+                    Ok(()) => terminal.set_status_message("Copied to clipboard")?,
+                    Err(err) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Could not write to the clipboard: {err}"
+                        )))
+                    }
+                }
+                true
+            }
+            Command::PasteFromSystem => {
+                let text = match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+                    Ok(it) => it,
+                    Err(err) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Could not read the clipboard: {err}"
+                        )));
+                        return Ok(true);
+                    }
+                };
+                let position = terminal.spreadsheet.current_cell();
+                if let Err(err) = terminal.spreadsheet.paste_text(position, &text) {
+                    terminal.dialog = Some(Dialog::display_error(format!(
+                        "Could not paste the clipboard: {err}"
+                    )));
+                    return Ok(true);
+                }
+                terminal.spreadsheet.evaluate();
+                true
+            }
+            Command::Stats => {
+                let stats = terminal.spreadsheet.sheet_statistics();
+                terminal.dialog = Some(Dialog::display_message(format!(
+                    "Cells: {}\nFilled: {}\nFormulas: {}\nErrors: {}\nSum: {}\nMax: {}\nMin: {}",
+                    stats.total_cells,
+                    stats.filled_cells,
+                    stats.formula_cells,
+                    stats.error_cells,
+                    stats.numeric_sum,
+                    stats.numeric_max,
+                    stats.numeric_min,
+                )));
+                true
+            }
+            &Command::ColumnStats(column) => {
+                if column >= terminal.spreadsheet.columns() {
+                    terminal.dialog = Some(Dialog::display_error(format!(
+                        "The spreadsheet has only {} columns.",
+                        terminal.spreadsheet.columns()
+                    )));
+                    return Ok(true);
+                }
+                terminal.dialog = Some(Dialog::display_message(
+                    crate::column_stats_lines(&terminal.spreadsheet, column).join("\n"),
+                ));
+                true
+            }
+            Command::StatsPanel => {
+                terminal.show_stats_panel = !terminal.show_stats_panel;
+                terminal.reset_scroll_page()?;
+                true
+            }
+            Command::Snapshot(path, _) if path.extension().is_none_or(|e| e != "svg") => {
+                terminal.dialog = Some(Dialog::display_error(
+                    "Snapshots can only be saved as svg images.",
+                ));
+                true
+            }
+            Command::Snapshot(path, range) => {
+                let (from, to) = range.unwrap_or_else(|| terminal.visible_range());
+                match terminal.spreadsheet.save_range_as_svg(path, from, to) {
+                    Ok(()) => terminal
+                        .set_status_message(format!("Saved snapshot to {}", path.display()))?,
+                    Err(err) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Error while saving {}: {err}",
+                            path.display()
+                        )))
+                    }
+                }
+                true
+            }
+            &Command::Split(direction) => {
+                terminal.split = direction.map(|direction| Split {
+                    direction,
+                    scroll_page: ScrollPage::default(),
+                    current_cell: terminal.spreadsheet.current_cell(),
+                    second_has_focus: false,
+                });
+                let (area, other) = terminal.pane_areas();
+                if !area.fits() || other.is_some_and(|other| !other.fits()) {
+                    terminal.split = None;
+                    terminal.dialog = Some(Dialog::display_error(
+                        "The terminal is too small to split the view.",
+                    ));
+                }
+                terminal.reset_scroll_page()?;
+                true
+            }
+            Command::Watch(false) => {
+                terminal.watch = None;
+                true
+            }
+            Command::Types(true) => {
+                terminal.spreadsheet.infer_column_types();
+                true
+            }
+            Command::Types(false) => {
+                terminal.spreadsheet.clear_column_types();
+                true
+            }
+            Command::Watch(true) => {
+                match terminal.spreadsheet.path().map(FileWatch::new) {
+                    Some(Ok(watch)) => terminal.watch = Some(watch),
+                    Some(Err(err)) => {
+                        terminal.dialog = Some(Dialog::display_error(format!(
+                            "Could not watch the file: {err}"
+                        )))
+                    }
+                    None => {
+                        terminal.dialog =
+                            Some(Dialog::display_error("Only saved files can be watched."))
+                    }
+                }
                 true
             }
         };
@@ -281,34 +1588,241 @@ impl Command {
     }
 }
 
+/// Parses ranges like `B2:D2`.
+fn parse_range(text: &str) -> Option<((usize, usize), (usize, usize))> {
+    let (from, to) = text.split_once(':')?;
+    let from = tabelle_core::cell_name_to_position(&from.to_ascii_uppercase()).ok()?;
+    let to = tabelle_core::cell_name_to_position(&to.to_ascii_uppercase()).ok()?;
+    Some((from, to))
+}
+
+/// Parses colors like `#ff8000`.
+fn parse_hex_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Breaks `text` into lines of at most `width` characters at whitespace.
+fn wrap_text(text: &str, width: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut line_width = 0;
+    for word in text.split_whitespace() {
+        if line_width > 0 && line_width + 1 + word.width() > width {
+            result.push('\n');
+            line_width = 0;
+        } else if line_width > 0 {
+            result.push(' ');
+            line_width += 1;
+        }
+        result.push_str(word);
+        line_width += word.width();
+    }
+    result
+}
+
+/// Parses `A asc, B desc, C`, where the direction defaults to ascending.
+fn parse_sort_keys<'a>(words: &[&'a str]) -> Result<Vec<SortKey>, &'a str> {
+    let mut keys: Vec<SortKey> = Vec::new();
+    let mut expects_column = true;
+    for word in words {
+        let (word, ends_key) = match word.strip_suffix(',') {
+            Some(word) => (word, true),
+            None => (*word, false),
+        };
+        match word.to_ascii_lowercase().as_str() {
+            "" => {}
+            "asc" | "desc" if !expects_column => {
+                keys.last_mut().unwrap().descending = word.eq_ignore_ascii_case("desc");
+            }
+            _ if expects_column => {
+                let column = tabelle_core::column_name_to_index(&word.to_ascii_uppercase())
+                    .map_err(|_| word)?;
+                keys.push(SortKey::ascending(column));
+                expects_column = false;
+            }
+            _ => return Err(word),
+        }
+        if ends_key {
+            if expects_column {
+                return Err(word);
+            }
+            expects_column = true;
+        }
+    }
+    if keys.is_empty() {
+        return Err("sort expected a column");
+    }
+    Ok(keys)
+}
+
+/// Parses a single character or `tab`.
+fn parse_separator(value: &str) -> Result<char, &'static str> {
+    match value {
+        "tab" => Ok('\t'),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(separator), None) => Ok(separator),
+                _ => Err("separator expected a single character or tab"),
+            }
+        }
+    }
+}
+
 fn parse_set_command<'a>(key: &'a str, value: &'a str) -> Result<Command, &'a str> {
     Ok(match key {
         "column-width" => {
             let value: usize = value.parse().map_err(|_| "column-width expected integer")?;
             Command::Set(SetCommand::ColumnWidth(value))
         }
+        "row-height" => {
+            // Rows are drawn with a u16 number of lines.
+            let value: u16 = value
+                .parse()
+                .map_err(|_| "row-height expected an integer up to 65535")?;
+            Command::Set(SetCommand::RowHeight(value.into()))
+        }
+        "separator" => Command::Set(SetCommand::Separator(parse_separator(value)?)),
+        "style" => {
+            let value = match value {
+                "borders" => DisplayStyle::Borders,
+                "compact" => DisplayStyle::Compact,
+                "minimal" => DisplayStyle::Minimal,
+                _ => return Err("style expected borders, compact or minimal"),
+            };
+            Command::Set(SetCommand::Style(value))
+        }
+        "cell-style" => {
+            let value = StyleChange::parse(value).ok_or(
+                "cell-style expected bold, italic, plain, left, center, right, fg=#rrggbb or bg=#rrggbb",
+            )?;
+            Command::Set(SetCommand::CellStyle(value))
+        }
         "unit" => {
             let value = match value {
+                "none" => UnitKind::None,
                 "$" => UnitKind::Dollar,
-                _ => return Err("Invalid unit kind found"),
+                "€" => UnitKind::Euro,
+                "%" => UnitKind::Percent,
+                "date" => UnitKind::Date,
+                "time" => UnitKind::Time,
+                _ => return Err("unit expected none, $, €, %, date or time"),
             };
             Command::Set(SetCommand::Unit(value))
         }
+        "format" => {
+            NumberFormat::parse(value)
+                .ok_or("format expected a code like 0.00, #,##0, 0.0% or 0.00E+00")?;
+            Command::Set(SetCommand::Format(value.into()))
+        }
+        "type" => {
+            let value = match value {
+                "any" => ColumnType::Any,
+                "text" => ColumnType::Text,
+                "integer" => ColumnType::Integer,
+                "number" => ColumnType::Number,
+                "date" => ColumnType::Date,
+                "datetime" => ColumnType::DateTime,
+                _ => return Err("type expected any, text, integer, number, date or datetime"),
+            };
+            Command::Set(SetCommand::ColumnType(value))
+        }
         _ => return Err(key),
     })
 }
 
-#[derive(Debug, EnumVariantNames, PartialEq)]
+#[derive(Debug, Clone, EnumVariantNames, PartialEq, Serialize, Deserialize)]
 pub enum SetCommand {
     ColumnWidth(usize),
+    RowHeight(usize),
     Unit(UnitKind),
+    /// A xlsx number format code for the selected cells.
+    Format(String),
+    Header(usize, String),
+    ColumnType(ColumnType),
+    Separator(char),
+    Style(DisplayStyle),
+    CellStyle(StyleChange),
+}
+
+/// A change to the [`tabelle_core::CellStyle`] of the selected cells.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StyleChange {
+    Bold,
+    Italic,
+    /// Removes all styling.
+    Plain,
+    Align(CellAlignment),
+    Foreground((u8, u8, u8)),
+    Background((u8, u8, u8)),
+}
+
+impl StyleChange {
+    fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "bold" => Self::Bold,
+            "italic" => Self::Italic,
+            "plain" => Self::Plain,
+            "left" => Self::Align(CellAlignment::Left),
+            "center" => Self::Align(CellAlignment::Center),
+            "right" => Self::Align(CellAlignment::Right),
+            _ => {
+                if let Some(color) = value.strip_prefix("fg=") {
+                    Self::Foreground(parse_hex_color(color)?)
+                } else {
+                    Self::Background(parse_hex_color(value.strip_prefix("bg=")?)?)
+                }
+            }
+        })
+    }
+
+    /// Bold and italic are toggled, everything else is overwritten.
+    fn apply(self, style: &mut tabelle_core::CellStyle) {
+        match self {
+            StyleChange::Bold => style.bold = !style.bold,
+            StyleChange::Italic => style.italic = !style.italic,
+            StyleChange::Plain => *style = Default::default(),
+            StyleChange::Align(alignment) => style.alignment = Some(alignment),
+            StyleChange::Foreground(color) => style.foreground = Some(color),
+            StyleChange::Background(color) => style.background = Some(color),
+        }
+    }
+}
+
+impl Display for StyleChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StyleChange::Bold => write!(f, "bold"),
+            StyleChange::Italic => write!(f, "italic"),
+            StyleChange::Plain => write!(f, "plain"),
+            StyleChange::Align(CellAlignment::Left) => write!(f, "left"),
+            StyleChange::Align(CellAlignment::Center) => write!(f, "center"),
+            StyleChange::Align(CellAlignment::Right) => write!(f, "right"),
+            StyleChange::Foreground((r, g, b)) => write!(f, "fg=#{r:02x}{g:02x}{b:02x}"),
+            StyleChange::Background((r, g, b)) => write!(f, "bg=#{r:02x}{g:02x}{b:02x}"),
+        }
+    }
 }
 
 impl Display for SetCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SetCommand::ColumnWidth(width) => write!(f, "column-width {width}"),
+            SetCommand::RowHeight(height) => write!(f, "row-height {height}"),
             SetCommand::Unit(unit) => write!(f, "unit {unit}"),
+            SetCommand::Format(code) => write!(f, "format {code}"),
+            SetCommand::Separator('\t') => write!(f, "separator tab"),
+            SetCommand::Separator(separator) => write!(f, "separator {separator}"),
+            SetCommand::Style(style) => write!(f, "style {style}"),
+            SetCommand::CellStyle(change) => write!(f, "cell-style {change}"),
+            SetCommand::ColumnType(column_type) => write!(f, "type {column_type}"),
+            SetCommand::Header(column, name) => {
+                write!(f, "header {} {name}", tabelle_core::to_column_name(*column))
+            }
         }
     }
 }
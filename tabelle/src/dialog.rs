@@ -13,11 +13,16 @@ use crossterm::{
 use serde::{Deserialize, Serialize};
 use unicode_truncate::UnicodeTruncateStr;
 
-use crate::print_blank_line;
+use crate::{commands::Command, print_blank_line};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DialogPurpose {
     CommandOutput,
+    Resize,
+    /// Asks before executing a command, which would lose unsaved changes.
+    Confirmation(Box<Command>),
+    /// Asks for the name of the recorded macro.
+    MacroName(Vec<String>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +34,10 @@ pub struct Dialog {
     pub answers: DialogAnswers,
     pub selected_answer: usize,
     pub height: usize,
+    #[serde(default)]
+    pub fields: Vec<(String, String)>,
+    #[serde(default)]
+    pub focused_field: usize,
 }
 
 impl Dialog {
@@ -41,23 +50,77 @@ impl Dialog {
             answers: DialogAnswers::Ok,
             selected_answer: 0,
             height: 5,
+            fields: Vec::new(),
+            focused_field: 0,
+        }
+    }
+
+    pub(crate) fn display_message(message: impl Display) -> Dialog {
+        let message = message.to_string();
+        let height = message.lines().count().max(1) + 4;
+        Self {
+            purpose: DialogPurpose::CommandOutput,
+            message,
+            buffer: None,
+            background_color: Color::DarkBlue,
+            answers: DialogAnswers::Ok,
+            selected_answer: 0,
+            height,
+            fields: Vec::new(),
+            focused_field: 0,
+        }
+    }
+
+    /// Asks with Yes and No whether `command` should really be executed.
+    pub(crate) fn confirmation(message: impl Display, command: Command) -> Dialog {
+        Self {
+            purpose: DialogPurpose::Confirmation(Box::new(command)),
+            message: message.to_string(),
+            buffer: None,
+            background_color: Color::DarkYellow,
+            answers: DialogAnswers::YesNo,
+            selected_answer: 1,
+            height: 5,
+            fields: Vec::new(),
+            focused_field: 0,
+        }
+    }
+
+    /// Creates a dialog with a labeled text input for every field, which
+    /// are `(label, default_value)` pairs. Tab cycles through the fields and
+    /// Enter on the last field returns [`DialogResult::MultipleValues`].
+    pub(crate) fn input_multi(title: &str, fields: Vec<(String, String)>) -> Dialog {
+        let height = title.lines().count().max(1) + fields.len() + 4;
+        Self {
+            purpose: DialogPurpose::CommandOutput,
+            message: title.to_owned(),
+            buffer: None,
+            background_color: Color::DarkBlue,
+            answers: DialogAnswers::Ok,
+            selected_answer: 0,
+            height,
+            fields,
+            focused_field: 0,
         }
     }
 
     pub fn render(&self) -> crossterm::Result<()> {
-        let box_height = 5;
+        let box_height = self.height as u16;
         let size = terminal::size()?;
         let width = size.0 as usize;
         execute!(
             stdout(),
-            MoveTo(0, (size.1 - box_height) / 2),
+            MoveTo(0, size.1.saturating_sub(box_height) / 2),
             SetBackgroundColor(self.background_color)
         )?;
         for _ in 0..box_height {
             print_blank_line(width);
         }
         let offset = if self.buffer.is_some() { 1 } else { 0 };
-        execute!(stdout(), MoveTo(0, (size.1 - box_height) / 2 + 1),)?;
+        execute!(
+            stdout(),
+            MoveTo(0, size.1.saturating_sub(box_height) / 2 + 1),
+        )?;
         for line in self.message.lines() {
             execute!(
                 stdout(),
@@ -67,6 +130,20 @@ impl Dialog {
         }
         execute!(stdout(), MoveDown(1 - offset))?;
 
+        for (index, (label, value)) in self.fields.iter().enumerate() {
+            let field = if index == self.focused_field {
+                format!("> {label} {value}_")
+            } else {
+                format!("  {label} {value} ")
+            };
+            execute!(
+                stdout(),
+                MoveToColumn(0),
+                Print(field.unicode_pad(width, unicode_truncate::Alignment::Center, true)),
+                MoveDown(1),
+            )?;
+        }
+
         if let Some(buffer) = &self.buffer {
             execute!(
                 stdout(),
@@ -114,15 +191,26 @@ impl Dialog {
         let mut result = DialogResult::None;
         match key.code {
             KeyCode::Backspace => {
-                if let Some(buffer) = &mut self.buffer {
+                if let Some((_, value)) = self.fields.get_mut(self.focused_field) {
+                    value.pop();
+                } else if let Some(buffer) = &mut self.buffer {
                     buffer.pop();
                 }
             }
+            KeyCode::Enter if !self.fields.is_empty() => {
+                if self.focused_field + 1 < self.fields.len() {
+                    self.focused_field += 1;
+                } else {
+                    result = DialogResult::MultipleValues(
+                        self.fields.iter().map(|(_, value)| value.clone()).collect(),
+                    );
+                }
+            }
             KeyCode::Enter => {
                 result = match self.answers {
-                    DialogAnswers::Ok => DialogResult::Yes(self.buffer.take()),
+                    DialogAnswers::Ok => DialogResult::Yes,
                     DialogAnswers::YesNo => match self.selected_answer {
-                        0 => DialogResult::Yes(self.buffer.take()),
+                        0 => DialogResult::Yes,
                         1 => DialogResult::Close,
                         _ => unreachable!(),
                     },
@@ -138,35 +226,55 @@ impl Dialog {
                     self.selected_answer += 1;
                 }
             }
-            KeyCode::Up => todo!(),
-            KeyCode::Down => todo!(),
-            KeyCode::Home => todo!(),
-            KeyCode::End => todo!(),
-            KeyCode::PageUp => todo!(),
-            KeyCode::PageDown => todo!(),
-            KeyCode::Tab => todo!(),
-            KeyCode::BackTab => todo!(),
-            KeyCode::Delete => todo!(),
-            KeyCode::Insert => todo!(),
-            KeyCode::F(_) => todo!(),
+            KeyCode::Up => {
+                self.focused_field = self.focused_field.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                if self.focused_field + 1 < self.fields.len() {
+                    self.focused_field += 1;
+                }
+            }
+            KeyCode::Home => self.selected_answer = 0,
+            KeyCode::End => self.selected_answer = self.answers.len() - 1,
+            KeyCode::PageUp => {}
+            KeyCode::PageDown => {}
+            KeyCode::Tab if !self.fields.is_empty() => {
+                self.focused_field = (self.focused_field + 1) % self.fields.len();
+            }
+            KeyCode::Tab => {
+                self.selected_answer = (self.selected_answer + 1) % self.answers.len();
+            }
+            KeyCode::BackTab if !self.fields.is_empty() => {
+                self.focused_field =
+                    (self.focused_field + self.fields.len() - 1) % self.fields.len();
+            }
+            KeyCode::BackTab => {
+                self.selected_answer =
+                    (self.selected_answer + self.answers.len() - 1) % self.answers.len();
+            }
+            KeyCode::Delete => {}
+            KeyCode::Insert => {}
+            KeyCode::F(_) => {}
             KeyCode::Char(ch) => {
-                if let Some(buffer) = &mut self.buffer {
+                if let Some((_, value)) = self.fields.get_mut(self.focused_field) {
+                    value.push(ch);
+                } else if let Some(buffer) = &mut self.buffer {
                     buffer.push(ch);
                 }
             }
-            KeyCode::Null => todo!(),
+            KeyCode::Null => {}
             KeyCode::Esc => {
                 result = DialogResult::Close;
             }
-            KeyCode::CapsLock => todo!(),
-            KeyCode::ScrollLock => todo!(),
-            KeyCode::NumLock => todo!(),
-            KeyCode::PrintScreen => todo!(),
-            KeyCode::Pause => todo!(),
-            KeyCode::Menu => todo!(),
-            KeyCode::KeypadBegin => todo!(),
-            KeyCode::Media(_) => todo!(),
-            KeyCode::Modifier(_) => todo!(),
+            KeyCode::CapsLock => {}
+            KeyCode::ScrollLock => {}
+            KeyCode::NumLock => {}
+            KeyCode::PrintScreen => {}
+            KeyCode::Pause => {}
+            KeyCode::Menu => {}
+            KeyCode::KeypadBegin => {}
+            KeyCode::Media(_) => {}
+            KeyCode::Modifier(_) => {}
         }
         Ok(result)
     }
@@ -175,7 +283,7 @@ impl Dialog {
         let size = terminal::size()?;
         execute!(
             stdout(),
-            MoveTo(0, (size.1 - box_height as u16) / 2),
+            MoveTo(0, size.1.saturating_sub(box_height as u16) / 2),
             ResetColor,
         )?;
         for _ in 0..box_height {
@@ -184,15 +292,16 @@ impl Dialog {
         Ok(())
     }
 
-    pub(crate) fn purpose(&self) -> DialogPurpose {
-        self.purpose
+    pub(crate) fn purpose(&self) -> &DialogPurpose {
+        &self.purpose
     }
 }
 
 pub enum DialogResult {
     None,
     Close,
-    Yes(Option<String>),
+    Yes,
+    MultipleValues(Vec<String>),
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -0,0 +1,195 @@
+use std::{
+    io::{stdout, Write},
+    path::Path,
+};
+
+use tabelle_core::{Cell, CellAlignment, Spreadsheet};
+use tabled::{
+    builder::Builder,
+    settings::{Alignment as TableAlignment, Style},
+    Table,
+};
+use unicode_truncate::{Alignment, UnicodeTruncateStr};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{convert, load_spreadsheet};
+
+const USAGE: &str = "Usage:
+    tabelle convert <input> <output>
+    tabelle eval <file> --cell <cell>
+    tabelle print [--style <style>] <file>";
+
+/// The styles of `tabelle print --style`.
+const STYLES: [&str; 12] = [
+    "ascii",
+    "ascii-rounded",
+    "blank",
+    "dots",
+    "extended",
+    "markdown",
+    "modern",
+    "modern-rounded",
+    "psql",
+    "rounded",
+    "rst",
+    "sharp",
+];
+
+/// Runs the subcommands, which do not start the terminal ui. Returns the
+/// exit code, if `args` (without the name of the executable) are one of
+/// them.
+pub(crate) fn run(args: &[String]) -> Option<i32> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["convert", input, output] => convert(Path::new(input), Path::new(output)),
+        ["eval", file, "--cell", cell] | ["eval", "--cell", cell, file] => {
+            match tabelle_core::cell_name_to_position(&cell.to_ascii_uppercase()) {
+                Ok(position) => eval(file, position),
+                Err(_) => {
+                    eprintln!("{cell} is not a cell like B7");
+                    return Some(2);
+                }
+            }
+        }
+        ["print", file] => print(file),
+        ["print", "--style", style, file] | ["print", file, "--style", style] => {
+            if !STYLES.contains(style) {
+                eprintln!(
+                    "Unknown style {style}, expected one of {}",
+                    STYLES.join(", ")
+                );
+                return Some(2);
+            }
+            print_styled(file, style)
+        }
+        ["convert" | "eval" | "print", ..] => {
+            eprintln!("{USAGE}");
+            return Some(2);
+        }
+        _ => return None,
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(err) => {
+            eprintln!("Error: {err}");
+            Some(1)
+        }
+    }
+}
+
+fn load_evaluated(file: &str) -> Result<Spreadsheet, tabelle_core::Error> {
+    let mut spreadsheet = load_spreadsheet(Path::new(file))?;
+    spreadsheet.evaluate();
+    Ok(spreadsheet)
+}
+
+/// Prints the evaluated value of a single cell.
+fn eval(file: &str, position: (usize, usize)) -> Result<(), tabelle_core::Error> {
+    let spreadsheet = load_evaluated(file)?;
+    if position.0 >= spreadsheet.columns() || position.1 >= spreadsheet.rows() {
+        return Err(tabelle_core::Error::OutOfBounds {
+            col: position.0,
+            row: position.1,
+            width: spreadsheet.columns(),
+            height: spreadsheet.rows(),
+        });
+    }
+    let mut stdout = stdout().lock();
+    writeln!(
+        stdout,
+        "{}",
+        spreadsheet.cell_at(position).display_content()
+    )?;
+    Ok(())
+}
+
+/// The rows of the used range of the spreadsheet.
+fn used_rows(spreadsheet: &Spreadsheet) -> Vec<&[Cell]> {
+    let (_, last) = spreadsheet.used_range();
+    spreadsheet
+        .as_rows()
+        .take(last.1 + 1)
+        .map(|row| &row[..=last.0])
+        .collect()
+}
+
+/// Prints the used range of the spreadsheet as a table with aligned
+/// columns.
+fn print(file: &str) -> Result<(), tabelle_core::Error> {
+    let spreadsheet = load_evaluated(file)?;
+    let rows = used_rows(&spreadsheet);
+    let widths: Vec<_> = (0..rows.first().map_or(0, |row| row.len()))
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].display_content().width())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let mut stdout = stdout().lock();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| {
+                let alignment = match spreadsheet.cell_alignment(cell.position()) {
+                    CellAlignment::Left => Alignment::Left,
+                    CellAlignment::Center => Alignment::Center,
+                    CellAlignment::Right => Alignment::Right,
+                };
+                cell.display_content()
+                    .unicode_pad(width, alignment, false)
+                    .into_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(stdout, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Prints the used range of the spreadsheet drawn in one of the [`STYLES`].
+/// The first row is drawn as the header of the table.
+fn print_styled(file: &str, style: &str) -> Result<(), tabelle_core::Error> {
+    let spreadsheet = load_evaluated(file)?;
+    let mut table = to_table(&spreadsheet);
+    match style {
+        "ascii" => table.with(Style::ascii()),
+        "ascii-rounded" => table.with(Style::ascii_rounded()),
+        "blank" => table.with(Style::blank()),
+        "dots" => table.with(Style::dots()),
+        "extended" => table.with(Style::extended()),
+        "markdown" => table.with(Style::markdown()),
+        "modern" => table.with(Style::modern()),
+        "modern-rounded" => table.with(Style::modern_rounded()),
+        "psql" => table.with(Style::psql()),
+        "rounded" => table.with(Style::rounded()),
+        "rst" => table.with(Style::re_structured_text()),
+        "sharp" => table.with(Style::sharp()),
+        _ => unreachable!("unknown styles are rejected before loading the file"),
+    };
+    writeln!(stdout().lock(), "{table}")?;
+    Ok(())
+}
+
+/// Converts the used range of the spreadsheet to a table, which keeps the
+/// alignment and the units of the cells.
+fn to_table(spreadsheet: &Spreadsheet) -> Table {
+    let rows = used_rows(spreadsheet);
+    let mut builder = Builder::default();
+    for row in &rows {
+        builder.push_record(row.iter().map(|cell| cell.display_content().into_owned()));
+    }
+    let mut table = builder.build();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let alignment = match spreadsheet.cell_alignment(cell.position()) {
+                CellAlignment::Left => TableAlignment::left(),
+                CellAlignment::Center => TableAlignment::center(),
+                CellAlignment::Right => TableAlignment::right(),
+            };
+            table.modify((y, x), alignment);
+        }
+    }
+    table
+}
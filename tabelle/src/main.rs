@@ -11,20 +11,31 @@
 //!
 //! [![][license]][repository]
 //!
-//! A simple `.csv` and `.xlsx` viewer for your terminal.
+//! A simple `.csv`, `.xlsx` and `.ods` viewer for your terminal.
 //!
 //! ## Running & Commandline Args
 //!
 //! You can open a file by typing `tabelle file.csv` or just start a new one by
-//! running `tabelle`.
+//! running `tabelle`. Several files can be opened at once like `tabelle a.csv
+//! b.xlsx`, Ctrl+PageUp and Ctrl+PageDown switch between them. Pass
+//! `--readonly` or `-r` to open a file without being able to edit or save it.
+//! Files named like a flag can be opened after `--`, like `tabelle -- -r`.
+//! `tabelle diff old.csv new.csv` shows the cells,
+//! which were added (green), removed (red) or changed (yellow) in the newer
+//! file, without being able to edit them. Csv files larger than 64 MiB are
+//! loaded while scrolling through them and are read-only. Only about 100000
+//! rows of them stay in memory. With `--watch` the file is reloaded, whenever
+//! another program changes it, which can also be turned on with `watch on`.
 //!
 //! ## Features
 //!
 //! It supports formulas, just like any other spreadsheet program. They start
 //! with an `=` and then contain python code. You can refer to columns and cells
-//! by their names, both in UPPERCASE and lowercase (not mixed though!). If you
-//! save as csv it will just save the value of the formula. To keep the formula
-//! use the `.xlsx` format.
+//! by their names, both in UPPERCASE and lowercase (not mixed though!). Ranges
+//! like `A0:B3` can be passed to `sum`, `min`, `max`, `avg` and `count`. A `$`
+//! in front of a column or row, like `$A$0`, keeps it from changing, when the
+//! formula is copied to other cells. If you save as csv it will just save the
+//! value of the formula. To keep the formula use the `.xlsx` or `.ods` format.
 //!
 //! ## Installation
 //!
@@ -34,6 +45,9 @@
 //! cargo install --git https://github.com/wert007/tabelle
 //! ```
 //!
+//! Add `--features parquet` to open `.parquet` files. Like large csv files
+//! they are loaded while scrolling through them.
+//!
 //! ## Contributions
 //!
 //! This is just a small personal project for me, at the same time I feel like
@@ -43,27 +57,87 @@
 //! fmt` and `cargo clippy` before opening your pull request.
 
 use commands::{Command, CommandKind};
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent};
 use crossterm::{cursor::*, event::KeyModifiers, style::*, terminal::*, *};
 use dialog::{Dialog, DialogPurpose};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::io::{stdout, Write};
-use std::path::PathBuf;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
-use tabelle_core::{to_column_name, CellContent, Spreadsheet};
+use tabelle_core::{
+    csv::CsvWriteOptions, to_column_name, CellAlignment, CellContent, CellDiff, CellDiffKind,
+    CellStyle, CsvStream, FindOptions, FormulaReference, FormulaTokenKind, LazyRows, Progress,
+    Spreadsheet,
+};
 use text_input::TextInput;
 use unicode_truncate::UnicodeTruncateStr;
 use unicode_width::UnicodeWidthStr;
+use watch::FileWatch;
 
 mod commands;
 mod dialog;
+mod headless;
 mod text_input;
+mod watch;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Config {
+    #[serde(default)]
     spreadsheet: Spreadsheet,
+    /// The other open files, `spreadsheet` comes before the entry
+    /// `current_sheet` of them.
+    #[serde(default)]
+    sheets: Vec<Spreadsheet>,
+    #[serde(default)]
+    current_sheet: usize,
     cursor: (u16, u16),
     dialog: Option<Dialog>,
+    /// Named macros, see `record` and `run`.
+    #[serde(default)]
+    macros: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    display_style: DisplayStyle,
+}
+
+/// How cells are drawn, changed by `set style`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum DisplayStyle {
+    /// Every cell is surrounded by a box.
+    #[default]
+    Borders,
+    /// Cells take a single line and columns are separated by a line.
+    Compact,
+    /// Like [`DisplayStyle::Compact`], but without any lines.
+    Minimal,
+}
+
+impl DisplayStyle {
+    /// The number of lines used by a row of cells, whose content has
+    /// `row_height` lines.
+    fn row_lines(self, row_height: usize) -> u16 {
+        match self {
+            DisplayStyle::Borders => (row_height as u16).saturating_add(1),
+            DisplayStyle::Compact | DisplayStyle::Minimal => row_height as u16,
+        }
+    }
+
+    /// The line of the content of the first row of cells.
+    fn first_row_line(self) -> u16 {
+        match self {
+            DisplayStyle::Borders => 3,
+            DisplayStyle::Compact | DisplayStyle::Minimal => 2,
+        }
+    }
+
+    fn column_separator(self) -> char {
+        match self {
+            DisplayStyle::Borders | DisplayStyle::Compact => '│',
+            DisplayStyle::Minimal => ' ',
+        }
+    }
 }
 
 struct Terminal {
@@ -76,13 +150,257 @@ struct Terminal {
     command_line_has_focus: bool,
     command_line: TextInput,
     cell_editor: Option<TextInput>,
+    status_message: Option<String>,
+    status_message_expiry: Instant,
+    /// Cells drawn with a yellow border, e.g. the dependencies of a formula.
+    highlighted_positions: Vec<(usize, usize)>,
+    /// The commands entered since `record` was started.
+    recording: Option<Vec<String>>,
+    macros: HashMap<String, Vec<String>>,
+    display_style: DisplayStyle,
+    /// The cell where the selection started. The selection is the rectangle
+    /// between this cell and the current cell.
+    selection_anchor: Option<(usize, usize)>,
+    /// The cells copied with `copy` or `cut`.
+    clipboard: Vec<Vec<String>>,
+    /// The needle of the last `find`, which is used by `find-next`.
+    last_search: Option<(String, FindOptions)>,
+    /// What is dragged while the left mouse button is pressed.
+    mouse_drag: Option<MouseDrag>,
+    /// The references of the formula in the cell editor, which are shown in
+    /// the grid.
+    shown_references: Vec<FormulaReference>,
+    /// The differences shown by `tabelle diff`. The last opened spreadsheet
+    /// is not replaced by a diff.
+    diff: Option<HashMap<(usize, usize), CellDiffKind>>,
+    /// The file, whose remaining rows are loaded once the cursor gets close
+    /// to the last loaded row.
+    lazy_rows: Option<Box<dyn LazyRows>>,
+    /// Shows the statistics of the current column at the right, toggled by
+    /// `stats-panel`.
+    show_stats_panel: bool,
+    /// The opened file, which is reloaded once it changes on disk, see
+    /// `watch`.
+    watch: Option<FileWatch>,
+    /// The second pane, if the view is split with `split`.
+    split: Option<Split>,
+    /// All opened files. The entry of the shown file is empty, because its
+    /// state is kept in the fields above, see [`Terminal::switch_sheet`].
+    sheets: Vec<SheetView>,
+    current_sheet: usize,
+    /// The events read while loading the files, which are handled before
+    /// reading new ones.
+    pending_events: VecDeque<event::Event>,
+}
+
+/// The smallest area of a pane, which still fits a cell.
+const MIN_PANE_SIZE: (u16, u16) = (24, 8);
+
+/// How the view is split by `split`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitDirection {
+    /// One pane above the other.
+    Horizontal,
+    /// Two panes side by side.
+    Vertical,
+}
+
+/// The pane without focus of a split view. Both panes show the same
+/// spreadsheet, but each has its own current cell and scroll position. F6
+/// moves the focus to the other pane.
+struct Split {
+    direction: SplitDirection,
+    scroll_page: ScrollPage,
+    current_cell: (usize, usize),
+    /// `true` if the bottom or right pane has focus.
+    second_has_focus: bool,
+}
+
+/// The part of the terminal used by a pane of cells. Like the whole
+/// terminal, its first line is left for the status bar and its last two
+/// lines for the horizontal scrollbar and the command line.
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+impl Area {
+    /// Splits the area into two panes. The lines of the second pane, which
+    /// are not used for cells, overlap the first pane.
+    fn split(self, direction: SplitDirection) -> (Area, Area) {
+        match direction {
+            SplitDirection::Horizontal => {
+                let height = self.height / 2 + 1;
+                let first = Area { height, ..self };
+                let second = Area {
+                    y: self.y + height - 2,
+                    height: self.height - height + 2,
+                    ..self
+                };
+                (first, second)
+            }
+            SplitDirection::Vertical => {
+                let width = self.width / 2;
+                let first = Area { width, ..self };
+                let second = Area {
+                    x: self.x + width,
+                    width: self.width - width,
+                    ..self
+                };
+                (first, second)
+            }
+        }
+    }
+
+    /// Returns `true` if `cursor` is on the cells, the column headers or
+    /// the vertical scrollbar of this area.
+    fn contains(self, cursor: (u16, u16)) -> bool {
+        (self.x..self.x + self.width).contains(&cursor.0)
+            && (self.y + 1..self.y + self.height - 2).contains(&cursor.1)
+    }
+
+    fn fits(self) -> bool {
+        self.width >= MIN_PANE_SIZE.0 && self.height >= MIN_PANE_SIZE.1
+    }
+}
+
+/// A file opened by passing several files to tabelle, which can be switched
+/// to with Ctrl+PageUp and Ctrl+PageDown.
+#[derive(Default)]
+struct SheetView {
+    spreadsheet: Spreadsheet,
+    cursor: (u16, u16),
+    scroll_page: ScrollPage,
+    selection_anchor: Option<(usize, usize)>,
+    lazy_rows: Option<Box<dyn LazyRows>>,
+    watch: Option<FileWatch>,
+}
+
+impl SheetView {
+    /// Loads `file` or starts a new spreadsheet, if it does not exist yet.
+    /// Errors are shown in `dialog` and the events read while loading are
+    /// added to `events`.
+    fn open(
+        file: &Path,
+        watch: bool,
+        dialog: &mut Option<Dialog>,
+        events: &mut VecDeque<event::Event>,
+    ) -> Self {
+        if !file.exists() {
+            return Spreadsheet::new(5, 5).into();
+        }
+        let (spreadsheet, lazy_rows) = match load_lazily(file, Some(events)) {
+            Ok(it) => it,
+            Err(err) => {
+                *dialog = Some(Dialog::display_error(format!(
+                    "Error while opening {}: {err}",
+                    file.display(),
+                )));
+                return Spreadsheet::new(5, 5).into();
+            }
+        };
+        let watch = watch
+            .then(|| FileWatch::new(file))
+            .transpose()
+            .unwrap_or_else(|err| {
+                *dialog = Some(Dialog::display_error(format!(
+                    "Could not watch {}: {err}",
+                    file.display()
+                )));
+                None
+            });
+        Self {
+            spreadsheet,
+            lazy_rows,
+            watch,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Spreadsheet> for SheetView {
+    fn from(spreadsheet: Spreadsheet) -> Self {
+        Self {
+            spreadsheet,
+            ..Default::default()
+        }
+    }
+}
+
+/// How often the watched file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The width of the statistics panel including its border.
+const STATS_PANEL_WIDTH: u16 = 24;
+
+/// How many rows of a lazily loaded file are loaded at once.
+const LAZY_CHUNK_ROWS: usize = 1000;
+
+/// Csv files larger than this are loaded lazily.
+const LAZY_CSV_BYTES: u64 = 64 * 1024 * 1024;
+
+/// The colors of the references of a formula while editing it. The first
+/// reference gets the first color and so on.
+const REFERENCE_COLORS: [Color; 6] = [
+    Color::Green,
+    Color::Magenta,
+    Color::Red,
+    Color::DarkYellow,
+    Color::DarkCyan,
+    Color::DarkGreen,
+];
+
+fn reference_color(index: usize) -> Color {
+    REFERENCE_COLORS[index % REFERENCE_COLORS.len()]
+}
+
+/// What is dragged with the left mouse button.
+#[derive(Debug, Clone, Copy)]
+enum MouseDrag {
+    /// Selects the cells between this cell, where the drag started, and the
+    /// cell under the mouse.
+    Selection((usize, usize)),
+    /// Changes the width of `column` by dragging its right border in the
+    /// column headers. `resized` is set with the first move, which creates
+    /// the undo checkpoint, so a plain click does not create one.
+    ColumnBorder { column: usize, resized: bool },
 }
 
 impl Terminal {
     pub fn new() -> Self {
+        Self::open(None)
+    }
+
+    /// Shows `new` in read-only mode with the differences to `old` marked.
+    /// Removed cells are shown with their old content.
+    pub fn new_diff(old: Spreadsheet, mut new: Spreadsheet) -> Self {
+        let diff = old.diff(&new);
+        new.resize(new.columns().max(old.columns()), new.rows().max(old.rows()));
+        let size = (new.columns(), new.rows());
+        for cell in &diff {
+            if let CellDiffKind::Removed(content) = &cell.kind {
+                new.update_cell_at(
+                    cell.position,
+                    CellContent::parse(content, cell.position, size),
+                );
+            }
+        }
+        new.evaluate();
+        Self::open(Some((new, diff)))
+    }
+
+    fn open(diff: Option<(Spreadsheet, Vec<CellDiff>)>) -> Self {
         crossterm::terminal::enable_raw_mode().expect("Failed to enable raw mode!");
-        execute!(stdout(), EnterAlternateScreen, MoveTo(0, 0))
-            .expect("Failed to enter alternate screen.");
+        execute!(
+            stdout(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            MoveTo(0, 0)
+        )
+        .expect("Failed to enter alternate screen.");
         let (width, height) =
             crossterm::terminal::size().expect("Failed to receive terminal size.");
         let config = std::env::current_exe()
@@ -92,56 +410,115 @@ impl Terminal {
             .join("config.json");
         let mut cursor = (7, 3);
         let mut dialog = None;
-        let args: Vec<String> = std::env::args().collect();
-        let spreadsheet = if args.len() > 1 {
-            let file: PathBuf = args[1].as_str().into();
-            if file.exists() {
-                if file.extension().and_then(|e| e.to_str()) == Some("xlsx") {
-                    Spreadsheet::load_xlsx(file)
-                } else {
-                    let content = std::fs::read_to_string(&file).unwrap();
-                    match Spreadsheet::load_csv(&content) {
-                        Ok(it) => it,
-                        Err(err) => {
-                            dialog = Some(Dialog::display_error(format!(
-                                "Error while opening {}: {err:?}",
-                                file.display(),
-                            )));
-                            Spreadsheet::new(5, 5)
-                        }
-                    }
+        let mut pending_events = VecDeque::new();
+        let (flags, args) = split_flags(std::env::args());
+        let readonly = diff.is_some() || flags.iter().any(|f| f == "--readonly" || f == "-r");
+        // A config, which cannot be read (e.g. from an older version), is
+        // ignored and overwritten on exit.
+        let config: Option<Config> = if config.exists() {
+            let loaded = std::fs::read_to_string(&config)
+                .map_err(|err| err.to_string())
+                .and_then(|text| serde_json::from_str(&text).map_err(|err| err.to_string()));
+            match loaded {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    dialog = Some(Dialog::display_error(format!(
+                        "Could not load {}: {err}",
+                        config.display()
+                    )));
+                    None
                 }
-            } else {
-                Spreadsheet::new(5, 5)
             }
-        } else if config.exists() {
-            let config: Config =
-                serde_json::from_str(&std::fs::read_to_string(config).unwrap()).unwrap();
+        } else {
+            None
+        };
+        let macros = config
+            .as_ref()
+            .map(|config| config.macros.clone())
+            .unwrap_or_default();
+        let display_style = config
+            .as_ref()
+            .map(|config| config.display_style)
+            .unwrap_or_default();
+        let (diff_spreadsheet, diff) = diff.unzip();
+        let mut current_sheet = 0;
+        let mut sheets: Vec<SheetView> = if let Some(spreadsheet) = diff_spreadsheet {
+            vec![spreadsheet.into()]
+        } else if args.len() > 1 {
+            let watch = flags.iter().any(|f| f == "--watch");
+            args[1..]
+                .iter()
+                .map(|file| {
+                    SheetView::open(Path::new(file), watch, &mut dialog, &mut pending_events)
+                })
+                .collect()
+        } else if let Some(config) = config {
             cursor = config.cursor;
             dialog = config.dialog;
-            config.spreadsheet
+            let mut sheets: Vec<SheetView> = config.sheets.into_iter().map(Into::into).collect();
+            current_sheet = config.current_sheet.min(sheets.len());
+            sheets.insert(current_sheet, config.spreadsheet.into());
+            sheets
         } else {
-            Spreadsheet::new(5, 5)
+            vec![Spreadsheet::new(5, 5).into()]
         };
-        let size = cursor_to_cell((width, height));
-        let scroll_page = ScrollPage::new(spreadsheet.current_cell(), size);
+        for sheet in &mut sheets {
+            // Only the loaded rows of lazily loaded files could be saved.
+            let spreadsheet = &mut sheet.spreadsheet;
+            spreadsheet.set_readonly(readonly || sheet.lazy_rows.is_some());
+            let size = page_size((width, height), display_style, spreadsheet);
+            let (column, row) = spreadsheet.current_cell();
+            sheet.scroll_page = ScrollPage::new((column, spreadsheet.display_row(row)), &size);
+            sheet.cursor = cursor;
+        }
+        let first = std::mem::take(&mut sheets[current_sheet]);
         Self {
             width,
             height,
-            spreadsheet,
-            cursor,
+            spreadsheet: first.spreadsheet,
+            cursor: first.cursor,
             dialog,
-            scroll_page,
+            scroll_page: first.scroll_page,
             command_line_has_focus: false,
             command_line: TextInput::default(),
             cell_editor: None,
+            status_message: None,
+            status_message_expiry: Instant::now(),
+            highlighted_positions: Vec::new(),
+            recording: None,
+            macros,
+            display_style,
+            selection_anchor: first.selection_anchor,
+            clipboard: Vec::new(),
+            last_search: None,
+            mouse_drag: None,
+            shown_references: Vec::new(),
+            lazy_rows: first.lazy_rows,
+            show_stats_panel: false,
+            watch: first.watch,
+            split: None,
+            sheets,
+            current_sheet,
+            pending_events,
+            diff: diff.map(|diff| {
+                diff.into_iter()
+                    .map(|cell| (cell.position, cell.kind))
+                    .collect()
+            }),
         }
     }
 
     pub fn start(&mut self) -> crossterm::Result<()> {
         self.render()?;
         loop {
-            let event = crossterm::event::read()?;
+            let event = match self.pending_events.pop_front() {
+                Some(event) => event,
+                None if self.watch.is_some() && !event::poll(WATCH_INTERVAL)? => {
+                    self.reload_if_changed()?;
+                    continue;
+                }
+                None => crossterm::event::read()?,
+            };
             if if self.command_line_has_focus {
                 self.handle_command_line_event(event)?
             } else if let Some(cell_editor) = self.cell_editor.as_mut() {
@@ -154,6 +531,7 @@ impl Terminal {
                     }) => {
                         let cell_editor = self.cell_editor.take().unwrap();
                         let cell_position = self.spreadsheet.current_cell();
+                        self.spreadsheet.checkpoint();
                         self.spreadsheet.update_cell_at(
                             cell_position,
                             CellContent::parse(
@@ -162,7 +540,7 @@ impl Terminal {
                                 (self.spreadsheet.columns(), self.spreadsheet.rows()),
                             ),
                         );
-                        self.spreadsheet.evaluate();
+                        self.spreadsheet.evaluate_dirty(cell_position);
                         if !self.move_cursor(0, 1)? {
                             self.spreadsheet
                                 .resize(self.spreadsheet.columns(), self.spreadsheet.rows() + 1);
@@ -177,6 +555,7 @@ impl Terminal {
                     }) => {
                         let cell_editor = self.cell_editor.take().unwrap();
                         let cell_position = self.spreadsheet.current_cell();
+                        self.spreadsheet.checkpoint();
                         self.spreadsheet.update_cell_at(
                             cell_position,
                             CellContent::parse(
@@ -185,7 +564,7 @@ impl Terminal {
                                 (self.spreadsheet.columns(), self.spreadsheet.rows()),
                             ),
                         );
-                        self.spreadsheet.evaluate();
+                        self.spreadsheet.evaluate_dirty(cell_position);
                         if !self.move_cursor(1, 0)? {
                             self.spreadsheet
                                 .resize(self.spreadsheet.columns() + 1, self.spreadsheet.rows());
@@ -195,7 +574,24 @@ impl Terminal {
                         self.render()?;
                         false
                     }
+                    Some(KeyEvent {
+                        code: KeyCode::Char('c'),
+                        ..
+                    }) => {
+                        let text = cell_editor.selected_text().to_owned();
+                        match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+                            Ok(()) => self.set_status_message("Copied to clipboard")?,
+                            Err(err) => {
+                                self.dialog = Some(Dialog::display_error(format!(
+                                    "Could not write to the clipboard: {err}"
+                                )));
+                                self.render()?;
+                            }
+                        }
+                        false
+                    }
                     _ => {
+                        self.update_shown_references()?;
                         self.render_status_bar()?;
                         result
                     }
@@ -210,41 +606,58 @@ impl Terminal {
     }
 
     fn move_cursor(&mut self, x: isize, y: isize) -> crossterm::Result<bool> {
-        if self.spreadsheet.current_cell() != self.scroll_page.no_scroll_cursor(self.cell_size()) {
+        let row = self.spreadsheet.first_row() + self.spreadsheet.current_cell().1;
+        self.load_rows(row.saturating_add_signed(y))?;
+        if self.display_cursor() != self.scroll_page.no_scroll_cursor(&self.page_size()) {
             execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
             panic!(
                 "scroll_page: {:#?}, cell_size: {:?}",
                 self.scroll_page,
-                self.cell_size()
+                self.page_size()
             );
         }
         let old_cursor = self.scroll_page.cursor;
         let result = self.spreadsheet.move_cursor(x, y);
         if result {
-            if self.scroll_page.move_cursor((x, y), self.cell_size()) {
+            if self.scroll_page.move_cursor((x, y), &self.page_size()) {
                 // self.render()? flushes this queue to the terminal
                 queue!(stdout(), Clear(ClearType::All))?;
                 self.render()?;
             } else {
                 self.render_status_bar()?;
             }
+        } else if self.display_cursor() != self.scroll_page.no_scroll_cursor(&self.page_size()) {
+            // The cursor stopped at the border of the spreadsheet, before it
+            // moved by the whole offset.
+            self.scroll_page
+                .set_cursor(self.display_cursor(), &self.page_size());
+            queue!(stdout(), Clear(ClearType::All))?;
+            self.render()?;
         }
         self.update_cursor(old_cursor)?;
         Ok(result)
     }
 
     fn set_cursor(&mut self, x: usize, y: usize) -> crossterm::Result<()> {
-        if self.spreadsheet.current_cell() != self.scroll_page.no_scroll_cursor(self.cell_size()) {
+        self.goto_cell(x, self.spreadsheet.first_row() + y)
+    }
+
+    /// Like [`Terminal::set_cursor`], but `row` is a row of the file, which
+    /// may not be loaded yet.
+    fn goto_cell(&mut self, x: usize, row: usize) -> crossterm::Result<()> {
+        let y = self.load_rows(row)?.min(self.spreadsheet.rows() - 1);
+        if self.display_cursor() != self.scroll_page.no_scroll_cursor(&self.page_size()) {
             execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
             panic!(
                 "scroll_page: {:#?}, cell_size: {:?}",
                 self.scroll_page,
-                self.cell_size()
+                self.page_size()
             );
         }
         let old_cursor = self.scroll_page.cursor;
         self.spreadsheet.set_cursor((x, y));
-        self.scroll_page.set_cursor((x, y), self.cell_size());
+        self.scroll_page
+            .set_cursor(self.display_cursor(), &self.page_size());
         // self.render()? flushes this queue to the terminal
         queue!(stdout(), Clear(ClearType::All))?;
         self.render()?;
@@ -252,19 +665,73 @@ impl Terminal {
         Ok(())
     }
 
+    /// Moves the cursor to the next (or previous) cell matching the last
+    /// `find`.
+    pub(crate) fn find_next(&mut self, backwards: bool) -> crossterm::Result<()> {
+        let Some((needle, options)) = self.last_search.clone() else {
+            return self.set_status_message("Nothing to find, use find first");
+        };
+        let options = FindOptions {
+            backwards,
+            ..options
+        };
+        match self.spreadsheet.find_with(&needle, options) {
+            Some((x, y)) => self.set_cursor(x, y),
+            None => self.set_status_message(format!("{needle} not found")),
+        }
+    }
+
+    /// Moves the cursor and selects every cell between the cursor and the
+    /// cell where the selection started.
+    fn extend_selection(&mut self, x: isize, y: isize) -> crossterm::Result<()> {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.spreadsheet.current_cell());
+        }
+        self.move_cursor(x, y)?;
+        self.render()?;
+        self.update_cursor(self.scroll_page.cursor)
+    }
+
+    /// Moves the cursor and removes the selection.
+    fn move_cursor_and_deselect(&mut self, x: isize, y: isize) -> crossterm::Result<()> {
+        if self.selection_anchor.take().is_some() {
+            self.render()?;
+        }
+        self.move_cursor(x, y)?;
+        Ok(())
+    }
+
+    /// Returns the top left and the bottom right cell of the selection. If
+    /// nothing is selected, this is the current cell.
+    pub(crate) fn selection(&self) -> ((usize, usize), (usize, usize)) {
+        let current = self.spreadsheet.current_cell();
+        let anchor = self.selection_anchor.unwrap_or(current);
+        (
+            (anchor.0.min(current.0), anchor.1.min(current.1)),
+            (anchor.0.max(current.0), anchor.1.max(current.1)),
+        )
+    }
+
+    fn is_selected(&self, position: (usize, usize)) -> bool {
+        let (from, to) = self.selection();
+        self.selection_anchor.is_some()
+            && (from.0..=to.0).contains(&position.0)
+            && (from.1..=to.1).contains(&position.1)
+    }
+
     fn move_cursor_force_render(&mut self, x: isize, y: isize) -> crossterm::Result<bool> {
-        if self.spreadsheet.current_cell() != self.scroll_page.no_scroll_cursor(self.cell_size()) {
+        if self.display_cursor() != self.scroll_page.no_scroll_cursor(&self.page_size()) {
             execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
             panic!(
                 "scroll_page: {:#?}, cell_size: {:?}",
                 self.scroll_page,
-                self.cell_size()
+                self.page_size()
             );
         }
         let old_cursor = self.scroll_page.cursor;
         let result = self.spreadsheet.move_cursor(x, y);
         if result {
-            self.scroll_page.move_cursor((x, y), self.cell_size());
+            self.scroll_page.move_cursor((x, y), &self.page_size());
             self.render()?;
         }
         self.update_cursor(old_cursor)?;
@@ -279,36 +746,102 @@ impl Terminal {
             Color::DarkGrey
         };
         queue!(stdout(), MoveTo(0, 0), SetBackgroundColor(color))?;
-        let index = format!("{}{}", to_column_name(cell_position.0), cell_position.1);
+        let mut index = format!(
+            "{}{}",
+            to_column_name(cell_position.0),
+            self.spreadsheet.first_row() + cell_position.1
+        );
+        if self.spreadsheet.is_filtered() {
+            index.push_str(" [filtered]");
+        }
+        if self.recording.is_some() {
+            index.push_str(" [recording]");
+        }
+        let changed = self.diff.as_ref().and_then(|diff| diff.get(&cell_position));
+        if let Some(changed) = changed {
+            index.push_str(match changed {
+                CellDiffKind::Added(_) => " [added]",
+                CellDiffKind::Removed(_) => " [removed]",
+                CellDiffKind::Changed { .. } => " [changed]",
+            });
+        }
         // let content = content.unicode_pad(self.width as _,
         // unicode_truncate::Alignment::Left, true);
         let mut recommended = String::new();
         let mut cursor = (0, 1);
-        let content = if let Some(cell_editor) = &self.cell_editor {
+        let status_message = self
+            .status_message
+            .as_ref()
+            .filter(|_| Instant::now() < self.status_message_expiry);
+        let content = if let Some(status_message) = status_message {
+            status_message.as_str().into()
+        } else if let Some(cell_editor) = &self.cell_editor {
             cursor = (index.len() as u16 + 2 + cell_editor.cursor() as u16, 0);
             cell_editor.buffer.as_str().into()
+        } else if let Some(CellDiffKind::Changed { old, new }) = changed {
+            format!("{old} → {new}").into()
         } else {
-            let pos = self.spreadsheet.current_cell();
-            let pos = (pos.0, pos.1.saturating_sub(1));
-            recommended = self
-                .spreadsheet
-                .recommended_cell_content(pos)
-                .serialize_display()
-                .into_owned();
+            if self.selection_anchor.is_some() {
+                // Like in Excel, the selection is summarized instead.
+                let (from, to) = self.selection();
+                let aggregates = self.spreadsheet.aggregate_range(from, to);
+                recommended = format!(
+                    "Sum: {}  Avg: {}  Count: {}",
+                    aggregates.sum,
+                    aggregates
+                        .average
+                        .map_or_else(|| "-".into(), |a| a.to_string()),
+                    aggregates.count
+                );
+            } else {
+                let pos = self.spreadsheet.current_cell();
+                let pos = (pos.0, pos.1.saturating_sub(1));
+                recommended = self
+                    .spreadsheet
+                    .recommended_cell_content(pos)
+                    .serialize_display()
+                    .into_owned();
+            }
             self.spreadsheet
                 .cell_at(cell_position)
                 .long_display_content()
         };
-        let available_width = self.width as usize - index.len() - 2;
-        let content = content.unicode_truncate(available_width / 2 - 1).0;
-        let recommended = recommended.unicode_truncate(available_width / 2 - 1).0;
+        let available_width = (self.width as usize).saturating_sub(index.len() + 2);
+        let half_width = (available_width / 2).saturating_sub(1);
+        let content = content.unicode_truncate(half_width).0;
+        let recommended = recommended.unicode_truncate(half_width).0;
         queue!(
             stdout(),
             Clear(ClearType::UntilNewLine),
             MoveToColumn(0),
             Print(index),
             Print(": "),
-            Print(content),
+        )?;
+        match content.strip_prefix('=') {
+            Some(raw) if self.cell_editor.is_some() => {
+                queue!(stdout(), Print('='))?;
+                let size = (self.spreadsheet.columns(), self.spreadsheet.rows());
+                let references = tabelle_core::formula_references(raw, size);
+                for (kind, token) in tabelle_core::tokenize_formula(raw, size) {
+                    // The tokens are slices of `raw`.
+                    let offset = token.as_ptr() as usize - raw.as_ptr() as usize;
+                    let reference = references
+                        .iter()
+                        .position(|r| r.text_range.contains(&offset));
+                    let color = match (reference, kind) {
+                        (Some(index), _) => reference_color(index),
+                        (None, FormulaTokenKind::Reference) => Color::Green,
+                        (None, FormulaTokenKind::Operator) => Color::Yellow,
+                        (None, FormulaTokenKind::Other) => Color::Reset,
+                    };
+                    queue!(stdout(), SetForegroundColor(color), Print(token))?;
+                }
+                queue!(stdout(), SetForegroundColor(Color::Reset))?;
+            }
+            _ => queue!(stdout(), Print(content))?,
+        }
+        queue!(
+            stdout(),
             MoveToColumn(available_width as u16 / 2),
             Print('|'),
             Print(recommended),
@@ -319,108 +852,227 @@ impl Terminal {
         Ok(())
     }
 
-    fn render(&self) -> crossterm::Result<()> {
+    fn render(&mut self) -> crossterm::Result<()> {
         self.render_status_bar()?;
-        let mut cursor = (0, 1);
+        // The left pane clears the rest of its lines, so it is drawn first.
+        let other_pane_first = self
+            .split
+            .as_ref()
+            .is_some_and(|split| split.second_has_focus);
+        if other_pane_first {
+            self.render_other_pane()?;
+        }
+        self.render_cells()?;
+        self.render_scrollbars()?;
+        if !other_pane_first {
+            self.render_other_pane()?;
+        }
+        self.render_highlighted_positions()?;
+        self.render_stats_panel()?;
+        self.render_command_line()?;
+
+        queue!(
+            stdout(),
+            SetBackgroundColor(Color::Reset),
+            MoveTo(self.cursor.0, self.cursor.1)
+        )?;
+
+        stdout().flush()?;
+        if let Some(dialog) = &self.dialog {
+            dialog.render()?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws the pane without focus of a split view.
+    fn render_other_pane(&mut self) -> crossterm::Result<()> {
+        if self.split.is_none() {
+            return Ok(());
+        }
+        self.swap_panes();
+        let result = self.render_cells().and_then(|()| self.render_scrollbars());
+        self.swap_panes();
+        result
+    }
 
-        let scroll = self.scroll_page.scroll(self.cell_size());
+    /// Draws the column headers and the cells of the pane with focus.
+    fn render_cells(&self) -> crossterm::Result<()> {
+        let area = self.area();
+        let mut cursor = (area.x, area.y + 1);
+        // The last column and the line above the command line are reserved
+        // for the scrollbars.
+        let (width, height) = (area.x + area.width - 1, area.y + area.height - 1);
 
-        queue!(stdout(), ResetColor, Print("    "))?;
-        for column in scroll.0..self.spreadsheet.columns() {
+        queue!(
+            stdout(),
+            MoveTo(cursor.0, cursor.1),
+            ResetColor,
+            Print("    ")
+        )?;
+        for column in self.visible_columns() {
             let column_width = self.spreadsheet.column_width(column);
-            let column = to_column_name(column);
+            let column = self.spreadsheet.column_header(column);
             queue!(
                 stdout(),
                 Print(" │ "),
                 Print(column.unicode_pad(column_width, unicode_truncate::Alignment::Left, true)),
             )?;
             cursor.0 += column_width as u16 + 3;
-            if cursor.0 + column_width as u16 + 3 > self.width {
+            if cursor.0 + column_width as u16 + 3 > width {
                 break;
             }
         }
         queue!(stdout(), MoveRight(1), Print('│'),)?;
-        for cell in &self.spreadsheet {
-            if cell.column() < scroll.0 || cell.row() < scroll.1 {
-                continue;
-            }
+        let cells = self
+            .visible_rows()
+            .enumerate()
+            .flat_map(|(row_index, row)| {
+                self.visible_columns()
+                    .enumerate()
+                    .map(move |(column_index, column)| ((column_index, row_index), (column, row)))
+            });
+        let mut row_lines = 0;
+        for ((column_index, row_index), position) in cells {
+            let cell = self.spreadsheet.cell_at(position);
             let column_width = self.spreadsheet.column_width(cell.column());
-            if cell.column() == scroll.0 {
-                if cell.row() != scroll.1 {
+            if column_index == 0 {
+                // Clear the rest of the lines of the previous row, or of the
+                // column headers before the first row.
+                let lines = if row_index == 0 { 1 } else { row_lines };
+                queue!(stdout(), MoveRight(2))?;
+                for _ in 0..lines {
+                    queue!(stdout(), Clear(ClearType::UntilNewLine), MoveDown(1))?;
+                }
+                queue!(stdout(), MoveToColumn(area.x))?;
+                cursor = (area.x + 5, cursor.1 + lines);
+                row_lines = self.row_lines(cell.row());
+                if cursor.1 + row_lines + 1 > height {
+                    break;
+                }
+                if self.display_style != DisplayStyle::Borders {
                     queue!(
                         stdout(),
-                        MoveRight(2),
-                        Clear(ClearType::UntilNewLine),
-                        MoveDown(1),
-                        Clear(ClearType::UntilNewLine),
-                        MoveDown(1),
-                        MoveToColumn(0)
+                        MoveToColumn(area.x),
+                        Print(format!("{:5}", self.spreadsheet.first_row() + cell.row()))
                     )?;
-                    cursor = (5, cursor.1 + 2);
                 } else {
                     queue!(
                         stdout(),
-                        MoveRight(2),
-                        Clear(ClearType::UntilNewLine),
-                        MoveDown(1),
-                        MoveToColumn(0)
+                        Print("─────"),
+                        MoveDown(row_lines),
+                        MoveToColumn(area.x),
+                        Print("─────"),
+                        MoveToColumn(area.x),
+                        MoveUp(row_lines - 1),
+                        Print(format!("{:5}", self.spreadsheet.first_row() + cell.row())),
+                        MoveUp(1),
                     )?;
-                    cursor = (5, cursor.1 + 1);
-                }
-                if cursor.1 + 3 > self.height {
-                    break;
                 }
-                queue!(
-                    stdout(),
-                    Print("─────"),
-                    MoveDown(2),
-                    MoveToColumn(0),
-                    Print("─────"),
-                    MoveToColumn(0),
-                    MoveUp(1),
-                    Print(format!("{:5}", cell.row())),
-                    MoveUp(1),
-                )?;
             }
-            if cursor.0 + column_width as u16 + 2 > self.width {
+            // Merged cells are drawn as one wide cell in each of their rows,
+            // starting at their first visible column. Only the first row
+            // shows the content of the top left cell.
+            let merged = self.spreadsheet.merged_cells_at(position);
+            let column_width = match merged {
+                Some(merged) => {
+                    let previous = column_index
+                        .checked_sub(1)
+                        .and_then(|index| self.visible_columns().nth(index));
+                    if previous.is_some_and(|column| column >= merged.from.0) {
+                        continue;
+                    }
+                    let merged_width = self
+                        .visible_columns()
+                        .skip(column_index)
+                        .take_while(|&column| column <= merged.to.0)
+                        .map(|column| self.spreadsheet.column_width(column) + 3)
+                        .sum::<usize>()
+                        - 3;
+                    let available = (width as usize).saturating_sub(cursor.0 as usize + 2);
+                    merged_width.min(available).max(column_width)
+                }
+                None => column_width,
+            };
+            if cursor.0 + column_width as u16 + 2 > width {
                 continue;
             }
-            let alignment = if cell.is_right_aligned() {
-                unicode_truncate::Alignment::Right
-            } else {
-                unicode_truncate::Alignment::Left
+            let shown = merged.map_or(cell, |merged| self.spreadsheet.cell_at(merged.from));
+            let shown_position = merged.map_or(position, |merged| merged.from);
+            let alignment = match self.spreadsheet.cell_alignment(shown_position) {
+                CellAlignment::Left => unicode_truncate::Alignment::Left,
+                CellAlignment::Center => unicode_truncate::Alignment::Center,
+                CellAlignment::Right => unicode_truncate::Alignment::Right,
             };
             let neighbors = Neighbors {
                 top: true,
-                right: cell.column() + 1 < self.spreadsheet.columns(),
-                bottom: cell.row() + 1 < self.spreadsheet.rows(),
+                right: merged.map_or(cell.column(), |merged| merged.to.0) + 1
+                    < self.spreadsheet.columns(),
+                bottom: self.spreadsheet.display_row(cell.row()) + 1
+                    < self.spreadsheet.visible_row_count(),
                 left: true,
             };
-            print_cell(
-                cell.display_content()
-                    .unicode_pad(column_width, alignment, true)
-                    .as_ref(),
-                cursor.0,
-                neighbors,
-                cell.position() == self.spreadsheet.current_cell(),
-            )?;
+            if let Some((r, g, b)) = self.spreadsheet.row_background(cell.row()) {
+                queue!(stdout(), SetBackgroundColor(Color::Rgb { r, g, b }))?;
+            }
+            if let Some(changed) = self.diff.as_ref().and_then(|diff| diff.get(&position)) {
+                let color = match changed {
+                    CellDiffKind::Added(_) => Color::DarkGreen,
+                    CellDiffKind::Removed(_) => Color::DarkRed,
+                    CellDiffKind::Changed { .. } => Color::DarkYellow,
+                };
+                queue!(stdout(), SetBackgroundColor(color))?;
+            }
+            let content = match merged {
+                Some(merged) if merged.from.1 != position.1 => "".into(),
+                _ => shown.display_content(),
+            };
+            // Taller rows show the lines of the content, the last line keeps
+            // the rest of it.
+            let row_height = self.spreadsheet.row_height(cell.row());
+            let mut lines: Vec<String> = content
+                .splitn(row_height, '\n')
+                .map(|line| {
+                    // Mark cut off content with an ellipsis.
+                    let line = if line.width() > column_width && column_width > 0 {
+                        format!("{}…", line.unicode_truncate(column_width - 1).0)
+                    } else {
+                        line.to_owned()
+                    };
+                    line.unicode_pad(column_width, alignment, true).into_owned()
+                })
+                .collect();
+            lines.resize(row_height, " ".repeat(column_width));
+            let current_cell = self.spreadsheet.current_cell();
+            let highlight = merged.map_or(cell.position() == current_cell, |merged| {
+                merged.contains(current_cell)
+            });
+            // Values not fitting the type of their column are shown in red.
+            let mismatch = self.spreadsheet.is_type_mismatch(shown_position);
+            let lines: Vec<_> = lines
+                .iter()
+                .map(|line| {
+                    let content = styled_cell_content(line, shown.style(), highlight);
+                    if mismatch {
+                        content.red()
+                    } else {
+                        content
+                    }
+                })
+                .collect();
+            if self.display_style == DisplayStyle::Borders {
+                print_cell(&lines, cursor.0, neighbors)?;
+            } else {
+                print_compact_cell(
+                    &lines,
+                    cursor.0,
+                    self.display_style.column_separator(),
+                    highlight,
+                )?;
+            }
             cursor.0 += column_width as u16 + 2 + 1;
             queue!(stdout(), MoveTo(cursor.0, cursor.1), ResetColor)?;
         }
-
-        self.render_command_line()?;
-
-        queue!(
-            stdout(),
-            SetBackgroundColor(Color::Reset),
-            MoveTo(self.cursor.0, self.cursor.1)
-        )?;
-
-        stdout().flush()?;
-        if let Some(dialog) = &self.dialog {
-            dialog.render()?;
-        }
-
         Ok(())
     }
 
@@ -468,6 +1120,71 @@ impl Terminal {
         Ok(())
     }
 
+    /// Shows [`column_stats_lines`] of the current column between the grid
+    /// and the vertical scrollbar.
+    fn render_stats_panel(&self) -> crossterm::Result<()> {
+        if !self.show_stats_panel {
+            return Ok(());
+        }
+        let x = self.grid_width();
+        let lines = column_stats_lines(&self.spreadsheet, self.spreadsheet.current_cell().0);
+        let text_width = STATS_PANEL_WIDTH as usize - 3;
+        for y in 1..self.height.saturating_sub(2) {
+            let line = lines.get(y as usize - 1).map_or("", String::as_str);
+            let line = line.unicode_truncate(text_width).0;
+            queue!(
+                stdout(),
+                MoveTo(x, y),
+                ResetColor,
+                Print("│ "),
+                Print(line.unicode_pad(text_width, unicode_truncate::Alignment::Left, true)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The width of the terminal used for the cells, which is all of it
+    /// unless the statistics panel is shown.
+    fn grid_width(&self) -> u16 {
+        if self.show_stats_panel {
+            self.width.saturating_sub(STATS_PANEL_WIDTH).max(20)
+        } else {
+            self.width
+        }
+    }
+
+    fn render_scrollbars(&self) -> crossterm::Result<()> {
+        let size = self.page_size();
+        let cursor = self.scroll_page.no_scroll_cursor(&size);
+        let area = self.area();
+        // Without a split the vertical scrollbar stays right of the
+        // statistics panel.
+        let right = match self.split {
+            Some(_) => area.x + area.width,
+            None => self.width,
+        };
+        // Tiny terminals have no room for the scrollbars.
+        let visible_height = (area.height as usize).saturating_sub(3);
+        let thumb = cursor.1 * visible_height / self.spreadsheet.visible_row_count().max(1);
+        for y in 0..visible_height {
+            queue!(
+                stdout(),
+                MoveTo(right.saturating_sub(1), area.y + y as u16 + 1),
+                Print(if y == thumb { '█' } else { '░' }),
+            )?;
+        }
+        let visible_width = (right.saturating_sub(area.x) as usize).saturating_sub(1);
+        let thumb = cursor.0 * visible_width / self.spreadsheet.columns();
+        queue!(
+            stdout(),
+            MoveTo(area.x, (area.y + area.height).saturating_sub(2))
+        )?;
+        for x in 0..visible_width {
+            queue!(stdout(), Print(if x == thumb { '█' } else { '░' }))?;
+        }
+        Ok(())
+    }
+
     fn render_command_line(&self) -> crossterm::Result<()> {
         queue!(
             stdout(),
@@ -494,40 +1211,129 @@ impl Terminal {
     }
 
     fn update_cursor(&mut self, old_cursor: (usize, usize)) -> crossterm::Result<()> {
-        if self.spreadsheet.current_cell() != self.scroll_page.no_scroll_cursor(self.cell_size()) {
+        if self.display_cursor() != self.scroll_page.no_scroll_cursor(&self.page_size()) {
             execute!(stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
             println!(
                 "scroll_page: {:#?}, cell_size: {:?}",
                 self.scroll_page,
-                self.cell_size()
+                self.page_size()
             );
         }
         assert_eq!(
-            self.spreadsheet.current_cell(),
-            self.scroll_page.no_scroll_cursor(self.cell_size()),
+            self.display_cursor(),
+            self.scroll_page.no_scroll_cursor(&self.page_size()),
         );
         self.update_highlighted_cell(old_cursor, self.scroll_page.cursor)?;
+        self.render_stats_panel()?;
+        self.render_scrollbars()?;
         let cursor = self.cell_to_cursor(self.scroll_page.cursor);
         self.cursor = cursor;
         execute!(stdout(), MoveTo(self.cursor.0, self.cursor.1))
     }
 
+    /// The columns in the order they are shown, which are the fixed columns
+    /// followed by the scrolled ones.
+    fn visible_columns(&self) -> impl Iterator<Item = usize> {
+        let size = self.page_size();
+        let columns = self.spreadsheet.columns();
+        (0..size.fixed().0.min(columns)).chain(self.scroll_page.scroll(&size).0..columns)
+    }
+
+    /// The rows in the order they are shown, which are the fixed rows
+    /// followed by the scrolled ones. Rows hidden by a filter are left out.
+    fn visible_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        let size = self.page_size();
+        let (fixed, scroll) = (size.fixed().1, self.scroll_page.scroll(&size).1);
+        self.spreadsheet
+            .visible_rows()
+            .enumerate()
+            .filter(move |&(display_row, _)| display_row < fixed || display_row >= scroll)
+            .map(|(_, row)| row)
+    }
+
+    /// The current cell with the row counted in visible rows, which is how
+    /// the scroll page sees it.
+    fn display_cursor(&self) -> (usize, usize) {
+        let (column, row) = self.spreadsheet.current_cell();
+        (column, self.spreadsheet.display_row(row))
+    }
+
+    /// Returns the terminal position of the cell shown at `cell_position`
+    /// on the screen.
     fn cell_to_cursor(&self, cell_position: (usize, usize)) -> (u16, u16) {
-        let offset = (7, 3);
-        let height_per_cell = 2;
-        let width: usize = (0..cell_position.0)
+        let area = self.area();
+        let offset = (area.x + 7, area.y + self.display_style.first_row_line());
+        let width: usize = self
+            .visible_columns()
+            .take(cell_position.0)
             .map(|c| self.spreadsheet.column_width(c) + 3)
             .sum();
-        // let size = cursor_to_cell((self.width, self.height));
-        // let scroll = self.scroll_page.scroll(size);
+        let height: u16 = self
+            .visible_rows()
+            .take(cell_position.1)
+            .map(|row| self.row_lines(row))
+            .sum();
         let x = offset.0 + width as u16;
-        let y = offset.1 + height_per_cell * cell_position.1 as u16;
+        let y = offset.1 + height;
         (x, y)
     }
 
-    fn cell_size(&self) -> (usize, usize) {
-        let result = cursor_to_cell((self.width - 1, self.height - 1));
-        (result.0 - 1, result.1 - 1)
+    /// The number of lines used by `row` in the current display style.
+    fn row_lines(&self, row: usize) -> u16 {
+        self.display_style
+            .row_lines(self.spreadsheet.row_height(row))
+    }
+
+    fn page_size(&self) -> PageSize {
+        let area = self.area();
+        page_size(
+            (area.width, area.height),
+            self.display_style,
+            &self.spreadsheet,
+        )
+    }
+
+    /// The first and the last cell shown in the pane with focus.
+    pub(crate) fn visible_range(&self) -> ((usize, usize), (usize, usize)) {
+        let first = (
+            self.visible_columns().next().unwrap_or_default(),
+            self.visible_rows().next().unwrap_or_default(),
+        );
+        let last = (
+            self.column_borders()
+                .last()
+                .map_or(first.0, |(column, _)| column),
+            self.visible_rows()
+                .take(self.page_size().size.1)
+                .last()
+                .unwrap_or(first.1),
+        );
+        (first, last)
+    }
+
+    /// The areas of the pane with focus and of the other pane, if the view
+    /// is split.
+    fn pane_areas(&self) -> (Area, Option<Area>) {
+        let whole = Area {
+            x: 0,
+            y: 0,
+            width: self.grid_width(),
+            height: self.height,
+        };
+        let Some(split) = &self.split else {
+            return (whole, None);
+        };
+        let (first, second) = whole.split(split.direction);
+        if split.second_has_focus {
+            (second, Some(first))
+        } else {
+            (first, Some(second))
+        }
+    }
+
+    /// The area of the pane with focus.
+    fn area(&self) -> Area {
+        self.pane_areas().0
     }
 
     fn handle_event(&mut self, event: event::Event) -> crossterm::Result<bool> {
@@ -536,16 +1342,52 @@ impl Terminal {
             crossterm::event::Event::FocusLost => {}
             crossterm::event::Event::Key(key) => {
                 if let Some(dialog) = &mut self.dialog {
+                    let dialog_height = dialog.height;
                     match dialog.update(key)? {
                         dialog::DialogResult::None => {}
                         dialog::DialogResult::Close => self.dialog = None,
-                        dialog::DialogResult::Yes(_) => match dialog.purpose() {
-                            DialogPurpose::CommandOutput => {
+                        dialog::DialogResult::Yes => match dialog.purpose().clone() {
+                            DialogPurpose::CommandOutput
+                            | DialogPurpose::Resize
+                            | DialogPurpose::MacroName(_) => {
+                                self.dialog = None;
+                            }
+                            DialogPurpose::Confirmation(command) => {
                                 self.dialog = None;
+                                command.execute_confirmed(self)?;
+                            }
+                        },
+                        dialog::DialogResult::MultipleValues(values) => match dialog.purpose() {
+                            DialogPurpose::CommandOutput | DialogPurpose::Confirmation(_) => {
+                                self.dialog = None;
+                            }
+                            DialogPurpose::Resize => {
+                                self.dialog = match (values[0].parse(), values[1].parse()) {
+                                    (Ok(width), Ok(height)) => {
+                                        self.spreadsheet.resize(width, height);
+                                        self.reset_scroll_page()?;
+                                        None
+                                    }
+                                    _ => Some(Dialog::display_error(format!(
+                                        "Invalid size {}x{}",
+                                        values[0], values[1]
+                                    ))),
+                                };
+                            }
+                            DialogPurpose::MacroName(commands) => {
+                                let name = values[0].trim();
+                                self.dialog = if name.is_empty() || name.contains(' ') {
+                                    Some(Dialog::display_error(format!(
+                                        "Invalid macro name {name:?}"
+                                    )))
+                                } else {
+                                    self.macros.insert(name.to_owned(), commands.clone());
+                                    None
+                                };
                             }
                         },
                     }
-                    Dialog::clear(8)?;
+                    Dialog::clear(dialog_height)?;
                     if let Some(dialog) = &self.dialog {
                         dialog.render()?;
                     } else {
@@ -553,7 +1395,27 @@ impl Terminal {
                     }
                 } else {
                     match key.code {
+                        crossterm::event::KeyCode::Backspace
+                        | crossterm::event::KeyCode::Delete
+                            if self.spreadsheet.is_readonly() =>
+                        {
+                            self.show_readonly_message()?;
+                        }
+                        crossterm::event::KeyCode::Char(_) | crossterm::event::KeyCode::Enter
+                            if self.spreadsheet.is_readonly()
+                                && !key.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            self.show_readonly_message()?;
+                        }
+                        crossterm::event::KeyCode::Backspace
+                        | crossterm::event::KeyCode::Delete
+                            if self.selection_anchor.is_some() =>
+                        {
+                            Command::Clear(None).execute_confirmed(self)?;
+                            self.render()?;
+                        }
                         crossterm::event::KeyCode::Backspace => {
+                            self.spreadsheet.checkpoint();
                             self.spreadsheet.clear_current_cell();
                             self.render()?;
                         }
@@ -573,39 +1435,79 @@ impl Terminal {
                             };
                             self.init_cell_editor(text)?;
                         }
-                        crossterm::event::KeyCode::Left => {
-                            self.move_cursor(-1, 0)?;
+                        crossterm::event::KeyCode::Left if key.modifiers == KeyModifiers::SHIFT => {
+                            self.extend_selection(-1, 0)?;
                         }
-                        crossterm::event::KeyCode::Right => {
-                            self.move_cursor(1, 0)?;
+                        crossterm::event::KeyCode::Right
+                            if key.modifiers == KeyModifiers::SHIFT =>
+                        {
+                            self.extend_selection(1, 0)?;
+                        }
+                        crossterm::event::KeyCode::Up if key.modifiers == KeyModifiers::SHIFT => {
+                            self.extend_selection(0, -1)?;
+                        }
+                        crossterm::event::KeyCode::Down if key.modifiers == KeyModifiers::SHIFT => {
+                            self.extend_selection(0, 1)?;
+                        }
+                        crossterm::event::KeyCode::Left => {
+                            self.move_cursor_and_deselect(-1, 0)?;
+                        }
+                        crossterm::event::KeyCode::Right => {
+                            self.move_cursor_and_deselect(1, 0)?;
                         }
                         crossterm::event::KeyCode::Up => {
-                            self.move_cursor(0, -1)?;
+                            self.move_cursor_and_deselect(0, -1)?;
                         }
                         crossterm::event::KeyCode::Down => {
-                            self.move_cursor(0, 1)?;
+                            self.move_cursor_and_deselect(0, 1)?;
+                        }
+                        crossterm::event::KeyCode::Home
+                            if key.modifiers == KeyModifiers::CONTROL =>
+                        {
+                            let (first, _) = self.spreadsheet.used_range();
+                            self.set_cursor(first.0, first.1)?
+                        }
+                        crossterm::event::KeyCode::End
+                            if key.modifiers == KeyModifiers::CONTROL =>
+                        {
+                            let (_, last) = self.spreadsheet.used_range();
+                            self.set_cursor(last.0, last.1)?
                         }
                         crossterm::event::KeyCode::Home => self.set_cursor(0, 0)?,
                         crossterm::event::KeyCode::End => self.set_cursor(
                             self.spreadsheet.columns() - 1,
                             self.spreadsheet.rows() - 1,
                         )?,
+                        crossterm::event::KeyCode::PageUp
+                            if key.modifiers == KeyModifiers::CONTROL =>
+                        {
+                            self.switch_sheet(-1)?
+                        }
+                        crossterm::event::KeyCode::PageDown
+                            if key.modifiers == KeyModifiers::CONTROL =>
+                        {
+                            self.switch_sheet(1)?
+                        }
                         crossterm::event::KeyCode::PageUp => {
-                            self.move_cursor(0, -(self.cell_size().1 as isize))?;
+                            self.move_cursor(0, -(self.page_size().step().1 as isize))?;
                         }
                         crossterm::event::KeyCode::PageDown => {
-                            self.move_cursor(0, self.cell_size().1 as isize)?;
+                            self.move_cursor(0, self.page_size().step().1 as isize)?;
                         }
                         crossterm::event::KeyCode::Tab => {
                             let old_cursor = self.scroll_page.cursor;
                             if !self.move_cursor(1, 0)? {
-                                self.spreadsheet.resize(
-                                    self.spreadsheet.columns() + 1,
-                                    self.spreadsheet.rows(),
-                                );
+                                if self.spreadsheet.is_readonly() {
+                                    self.show_readonly_message()?;
+                                } else {
+                                    self.spreadsheet.resize(
+                                        self.spreadsheet.columns() + 1,
+                                        self.spreadsheet.rows(),
+                                    );
 
-                                self.move_cursor_force_render(1, 0)?;
-                                self.render()?;
+                                    self.move_cursor_force_render(1, 0)?;
+                                    self.render()?;
+                                }
                             }
                             self.update_cursor(old_cursor)?;
                         }
@@ -613,6 +1515,7 @@ impl Terminal {
                             self.move_cursor(-1, 0)?;
                         }
                         crossterm::event::KeyCode::Delete => {
+                            self.spreadsheet.checkpoint();
                             self.spreadsheet.clear_current_cell();
                             self.render()?;
                         }
@@ -623,8 +1526,12 @@ impl Terminal {
                             self.render_command_line()?;
                             self.render_help()?;
                         }
+                        crossterm::event::KeyCode::F(6) => self.switch_pane()?,
+                        crossterm::event::KeyCode::F(3) => {
+                            self.find_next(key.modifiers.contains(KeyModifiers::SHIFT))?;
+                        }
                         crossterm::event::KeyCode::F(_) => {}
-                        crossterm::event::KeyCode::Char('d' | 'c')
+                        crossterm::event::KeyCode::Char('d')
                             if key.modifiers == KeyModifiers::CONTROL =>
                         {
                             return Ok(true);
@@ -632,9 +1539,8 @@ impl Terminal {
                         crossterm::event::KeyCode::Char('r')
                             if key.modifiers == KeyModifiers::CONTROL =>
                         {
-                            self.command_line_has_focus = true;
-                            self.command_line.set("resize ");
-                            self.render_command_line()?;
+                            Command::Resize(None).execute(self)?;
+                            self.render()?;
                         }
                         crossterm::event::KeyCode::Char('s')
                             if key.modifiers == KeyModifiers::CONTROL =>
@@ -657,6 +1563,37 @@ impl Terminal {
                             self.command_line.set("goto ");
                             self.render_command_line()?;
                         }
+                        crossterm::event::KeyCode::Char('C' | 'c')
+                            if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            Command::CopyToSystem.execute(self)?;
+                            self.render()?;
+                        }
+                        crossterm::event::KeyCode::Char('V' | 'v')
+                            if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            Command::PasteFromSystem.execute(self)?;
+                            queue!(stdout(), Clear(ClearType::All))?;
+                            self.render()?;
+                        }
+                        crossterm::event::KeyCode::Char('z')
+                            if key.modifiers == KeyModifiers::CONTROL =>
+                        {
+                            if self.spreadsheet.undo() {
+                                self.spreadsheet.evaluate();
+                                self.reset_scroll_page()?;
+                                self.set_status_message("Undo")?;
+                            }
+                        }
+                        crossterm::event::KeyCode::Char('y')
+                            if key.modifiers == KeyModifiers::CONTROL =>
+                        {
+                            if self.spreadsheet.redo() {
+                                self.spreadsheet.evaluate();
+                                self.reset_scroll_page()?;
+                                self.set_status_message("Redo")?;
+                            }
+                        }
                         crossterm::event::KeyCode::Char('x')
                             if key.modifiers == KeyModifiers::CONTROL =>
                         {
@@ -676,7 +1613,14 @@ impl Terminal {
                         }
                         crossterm::event::KeyCode::Null => return Ok(true),
                         crossterm::event::KeyCode::Esc => {
-                            return Ok(true);
+                            if self.highlighted_positions.is_empty()
+                                && self.selection_anchor.is_none()
+                            {
+                                return Ok(true);
+                            }
+                            self.highlighted_positions.clear();
+                            self.selection_anchor = None;
+                            self.render()?;
                         }
                         crossterm::event::KeyCode::CapsLock => {}
                         crossterm::event::KeyCode::ScrollLock => {}
@@ -690,6 +1634,11 @@ impl Terminal {
                     }
                 }
             }
+            crossterm::event::Event::Mouse(mouse)
+                if self.dialog.is_none() && self.cell_editor.is_none() =>
+            {
+                self.handle_mouse_event(mouse)?;
+            }
             crossterm::event::Event::Mouse(_) => {}
             crossterm::event::Event::Paste(_) => {}
             crossterm::event::Event::Resize(width, height) => {
@@ -700,6 +1649,181 @@ impl Terminal {
         Ok(false)
     }
 
+    /// Clicking a cell moves the cursor to it, dragging selects cells and
+    /// the wheel moves the cursor by a few rows (or columns while holding
+    /// Shift). Dragging a border between column headers resizes the column.
+    fn handle_mouse_event(&mut self, mouse: event::MouseEvent) -> crossterm::Result<()> {
+        const SCROLL_STEP: isize = 3;
+        let position = (mouse.column, mouse.row);
+        let horizontal = mouse.modifiers.contains(KeyModifiers::SHIFT);
+        match mouse.kind {
+            event::MouseEventKind::Down(event::MouseButton::Left) => {
+                if self
+                    .pane_areas()
+                    .1
+                    .is_some_and(|other| other.contains(position))
+                {
+                    self.switch_pane()?;
+                }
+                if position.1 == self.area().y + 1 {
+                    if let Some(column) = self.column_border_at(position.0) {
+                        if self.spreadsheet.is_readonly() {
+                            return self.show_readonly_message();
+                        }
+                        self.mouse_drag = Some(MouseDrag::ColumnBorder {
+                            column,
+                            resized: false,
+                        });
+                        return Ok(());
+                    }
+                }
+                let Some((x, y)) = self.cell_at_cursor(position) else {
+                    return Ok(());
+                };
+                self.selection_anchor = None;
+                self.mouse_drag = Some(MouseDrag::Selection((x, y)));
+                self.set_cursor(x, y)?;
+            }
+            event::MouseEventKind::Drag(event::MouseButton::Left) => match self.mouse_drag {
+                Some(MouseDrag::Selection(start)) => {
+                    let Some((x, y)) = self.cell_at_cursor(position) else {
+                        return Ok(());
+                    };
+                    if (x, y) != self.spreadsheet.current_cell() {
+                        self.selection_anchor = Some(start);
+                        self.set_cursor(x, y)?;
+                    }
+                }
+                Some(MouseDrag::ColumnBorder { column, resized }) => {
+                    let Some(border) = self
+                        .column_borders()
+                        .find_map(|(c, border)| (c == column).then_some(border))
+                    else {
+                        return Ok(());
+                    };
+                    if !resized {
+                        self.spreadsheet.checkpoint();
+                        self.mouse_drag = Some(MouseDrag::ColumnBorder {
+                            column,
+                            resized: true,
+                        });
+                    }
+                    let width = self.spreadsheet.column_width(column) as isize
+                        + position.0 as isize
+                        - border as isize;
+                    self.spreadsheet
+                        .set_column_width(column, width.max(1) as usize);
+                    queue!(stdout(), Clear(ClearType::All))?;
+                    self.render()?;
+                    self.update_cursor(self.scroll_page.cursor)?;
+                }
+                None => {}
+            },
+            event::MouseEventKind::Up(event::MouseButton::Left) => self.mouse_drag = None,
+            event::MouseEventKind::ScrollDown if horizontal => {
+                self.move_cursor_and_deselect(SCROLL_STEP, 0)?;
+            }
+            event::MouseEventKind::ScrollUp if horizontal => {
+                self.move_cursor_and_deselect(-SCROLL_STEP, 0)?;
+            }
+            event::MouseEventKind::ScrollDown => self.move_cursor_and_deselect(0, SCROLL_STEP)?,
+            event::MouseEventKind::ScrollUp => self.move_cursor_and_deselect(0, -SCROLL_STEP)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Loads rows of a lazily loaded file, if the row `row` of the file is on
+    /// the first or the last page of the loaded rows. Rows may be dropped on
+    /// the other end to save memory. Returns the index of `row` in the
+    /// loaded rows.
+    fn load_rows(&mut self, row: usize) -> crossterm::Result<usize> {
+        let first_row = self.spreadsheet.first_row();
+        let end = first_row + self.spreadsheet.rows();
+        let page = self.page_size().size.1;
+        let Some(lazy_rows) = &mut self.lazy_rows else {
+            return Ok(row.saturating_sub(first_row));
+        };
+        let loaded = if row + page >= end {
+            let count = LAZY_CHUNK_ROWS.max(row + page - end);
+            lazy_rows.load_more(&mut self.spreadsheet, count)
+        } else if row < first_row + page && first_row > 0 {
+            let count = LAZY_CHUNK_ROWS.max(first_row + page - row);
+            lazy_rows.load_previous(&mut self.spreadsheet, count)
+        } else {
+            return Ok(row - first_row);
+        };
+        match loaded {
+            // Nothing is left to load, once the whole file is loaded.
+            Ok(0) if self.spreadsheet.first_row() == 0 => self.lazy_rows = None,
+            Ok(_) => {}
+            Err(err) => {
+                self.dialog = Some(Dialog::display_error(format!(
+                    "Error while loading more rows: {err}"
+                )));
+                self.lazy_rows = None;
+            }
+        }
+        let shift = self.spreadsheet.first_row() as isize - first_row as isize;
+        if shift != 0 {
+            self.selection_anchor = self
+                .selection_anchor
+                .map(|(column, row)| (column, row.saturating_add_signed(-shift)));
+            self.highlighted_positions.clear();
+            self.reset_scroll_page()?;
+        }
+        Ok(row.saturating_sub(self.spreadsheet.first_row()))
+    }
+
+    /// Returns the cell drawn at the terminal position `cursor`, which is
+    /// the reverse of [`Terminal::cell_to_cursor`].
+    fn cell_at_cursor(&self, cursor: (u16, u16)) -> Option<(usize, usize)> {
+        let area = self.area();
+        if !area.contains(cursor) {
+            return None;
+        }
+        let (x, y) = (cursor.0 as usize, (cursor.1 - area.y) as usize);
+        // The border above a cell belongs to the cell.
+        let border = self.display_style.row_lines(1) as usize - 1;
+        let mut bottom = self.display_style.first_row_line() as usize - border;
+        if y < bottom {
+            return None;
+        }
+        let slot_row = self.visible_rows().position(|row| {
+            bottom += self.row_lines(row) as usize;
+            y < bottom
+        })?;
+        let size = self.page_size();
+        if slot_row >= size.size.1 || x + 1 >= (area.x + area.width) as usize {
+            return None;
+        }
+        let slot_column = self.column_borders().position(|(column, right_border)| {
+            let right_border = right_border as usize;
+            let left_border = right_border - self.spreadsheet.column_width(column) - 3;
+            (left_border..right_border).contains(&x)
+        })?;
+        let (column, row) = self.cell_at_slot((slot_column, slot_row));
+        (row < self.spreadsheet.rows()).then_some((column, row))
+    }
+
+    /// The visible columns with the terminal column of their right border.
+    fn column_borders(&self) -> impl Iterator<Item = (usize, u16)> + '_ {
+        // The content of the first column starts at 7 with its left border
+        // two characters before.
+        let area = self.area();
+        self.visible_columns()
+            .scan(area.x + 5, move |border, column| {
+                *border += self.spreadsheet.column_width(column) as u16 + 3;
+                (*border < area.x + area.width).then_some((column, *border))
+            })
+    }
+
+    /// Returns the column, whose right border is at the terminal column `x`.
+    fn column_border_at(&self, x: u16) -> Option<usize> {
+        self.column_borders()
+            .find_map(|(column, border)| (border == x).then_some(column))
+    }
+
     fn handle_command_line_event(&mut self, event: event::Event) -> crossterm::Result<bool> {
         match event {
             event::Event::FocusGained => {}
@@ -712,6 +1836,11 @@ impl Terminal {
                         Err(_) => return Ok(false),
                     };
                     self.command_line.clear();
+                    if let Some(recording) = &mut self.recording {
+                        if !matches!(command, Command::None | Command::Record | Command::Run(_)) {
+                            recording.push(command.full_display());
+                        }
+                    }
                     if command.execute(self)? {
                         self.command_line_has_focus = false;
                         // self.update_cursor()?;
@@ -751,23 +1880,251 @@ impl Terminal {
         Ok(false)
     }
 
+    pub(crate) fn show_readonly_message(&mut self) -> crossterm::Result<()> {
+        self.set_status_message("Read-only mode: editing disabled")
+    }
+
+    /// Outlines the highlighted cells in yellow and the selected cells in
+    /// blue.
+    fn render_highlighted_positions(&self) -> crossterm::Result<()> {
+        let size = self.page_size();
+        let (from, to) = self.selection();
+        let selected_positions = (from.1..=to.1)
+            .flat_map(|row| (from.0..=to.0).map(move |column| (column, row)))
+            .filter(|_| self.selection_anchor.is_some())
+            .map(|position| (position, Color::Blue));
+        let editor_references = self.editor_references();
+        let referenced_positions =
+            editor_references
+                .iter()
+                .enumerate()
+                .flat_map(|(index, reference)| {
+                    reference.rows.clone().flat_map(move |row| {
+                        reference
+                            .columns
+                            .clone()
+                            .map(move |column| ((column, row), reference_color(index)))
+                    })
+                });
+        for ((column, row), color) in self
+            .highlighted_positions
+            .iter()
+            .map(|&position| (position, Color::Yellow))
+            .chain(selected_positions)
+            .chain(referenced_positions)
+        {
+            if self.spreadsheet.is_row_hidden(row) {
+                continue;
+            }
+            let display_position = (column, self.spreadsheet.display_row(row));
+            let Some(position) = self.scroll_page.slot_of_cell(display_position, &size) else {
+                continue;
+            };
+            let neighbors = Neighbors {
+                top: true,
+                right: column + 1 < self.spreadsheet.columns(),
+                bottom: self.spreadsheet.display_row(row) + 1
+                    < self.spreadsheet.visible_row_count(),
+                left: true,
+            };
+            let width = self.spreadsheet.column_width(column) as u16;
+            queue!(stdout(), SetAttribute(Attribute::Bold))?;
+            self.outline_cell(position, width, neighbors, color)?;
+            queue!(stdout(), SetAttribute(Attribute::Reset))?;
+        }
+        Ok(())
+    }
+
+    /// Shows `message` in the status bar for a second.
+    fn set_status_message(&mut self, message: impl Into<String>) -> crossterm::Result<()> {
+        self.status_message = Some(message.into());
+        self.status_message_expiry = Instant::now() + Duration::from_secs(1);
+        self.render_status_bar()
+    }
+
+    /// Reloads the watched file, if another program changed it. The cursor
+    /// stays in the same cell, as long as the file is still large enough.
+    /// Unsaved changes are not thrown away.
+    fn reload_if_changed(&mut self) -> crossterm::Result<()> {
+        let Some(watch) = &mut self.watch else {
+            return Ok(());
+        };
+        if !watch.changed() {
+            return Ok(());
+        }
+        let path = watch.path().to_owned();
+        if self.spreadsheet.is_dirty() {
+            return self.set_status_message(format!(
+                "{} changed, but has unsaved changes",
+                path.display()
+            ));
+        }
+        let (mut spreadsheet, lazy_rows) = match load_lazily(&path, None) {
+            Ok(it) => it,
+            Err(err) => {
+                self.dialog = Some(Dialog::display_error(format!(
+                    "Error while reloading {}: {err}",
+                    path.display()
+                )));
+                return self.render();
+            }
+        };
+        spreadsheet.set_readonly(self.spreadsheet.is_readonly() || lazy_rows.is_some());
+        let (column, row) = self.spreadsheet.current_cell();
+        let row = self.spreadsheet.first_row() + row;
+        self.spreadsheet = spreadsheet;
+        self.lazy_rows = lazy_rows;
+        self.spreadsheet.set_cursor((0, 0));
+        let row = self.load_rows(row)?;
+        self.spreadsheet.set_cursor((
+            column.min(self.spreadsheet.columns() - 1),
+            row.min(self.spreadsheet.rows().saturating_sub(1)),
+        ));
+        self.reset_scroll_page()?;
+        self.set_status_message(format!("Reloaded {}", path.display()))
+    }
+
+    /// Shows the file `offset` files after the current one, wrapping around
+    /// at the ends.
+    fn switch_sheet(&mut self, offset: isize) -> crossterm::Result<()> {
+        if self.sheets.len() < 2 {
+            return self.set_status_message("Only one file is open");
+        }
+        self.swap_sheet(self.current_sheet);
+        self.current_sheet =
+            (self.current_sheet as isize + offset).rem_euclid(self.sheets.len() as isize) as usize;
+        self.swap_sheet(self.current_sheet);
+        self.highlighted_positions.clear();
+        self.mouse_drag = None;
+        if self.split.is_some() {
+            // The other pane still shows the cells of the previous file,
+            // which may not exist in this one.
+            self.reset_scroll_page()?;
+        } else {
+            queue!(stdout(), Clear(ClearType::All))?;
+            self.render()?;
+        }
+        let name = self
+            .spreadsheet
+            .path()
+            .and_then(Path::file_name)
+            .map_or_else(|| "New file".into(), |name| name.to_string_lossy());
+        self.set_status_message(format!(
+            "{name} ({}/{})",
+            self.current_sheet + 1,
+            self.sheets.len()
+        ))
+    }
+
+    /// Exchanges the state of the shown file with the entry `index`.
+    fn swap_sheet(&mut self, index: usize) {
+        let sheet = &mut self.sheets[index];
+        std::mem::swap(&mut self.spreadsheet, &mut sheet.spreadsheet);
+        std::mem::swap(&mut self.cursor, &mut sheet.cursor);
+        std::mem::swap(&mut self.scroll_page, &mut sheet.scroll_page);
+        std::mem::swap(&mut self.selection_anchor, &mut sheet.selection_anchor);
+        std::mem::swap(&mut self.lazy_rows, &mut sheet.lazy_rows);
+        std::mem::swap(&mut self.watch, &mut sheet.watch);
+    }
+
+    /// Moves the focus to the other pane of a split view, which brings back
+    /// the current cell of that pane.
+    fn switch_pane(&mut self) -> crossterm::Result<()> {
+        let Some(split) = &mut self.split else {
+            return self.set_status_message("The view is not split, see split");
+        };
+        let current_cell =
+            std::mem::replace(&mut split.current_cell, self.spreadsheet.current_cell());
+        self.swap_panes();
+        self.spreadsheet.set_cursor((
+            current_cell.0.min(self.spreadsheet.columns() - 1),
+            current_cell
+                .1
+                .min(self.spreadsheet.rows().saturating_sub(1)),
+        ));
+        self.selection_anchor = None;
+        self.reset_scroll_page()
+    }
+
+    /// Exchanges the scroll pages of the two panes and which one has focus,
+    /// so the other pane can be drawn like the one with focus.
+    fn swap_panes(&mut self) {
+        if let Some(split) = &mut self.split {
+            std::mem::swap(&mut self.scroll_page, &mut split.scroll_page);
+            split.second_has_focus = !split.second_has_focus;
+        }
+    }
+
+    /// Undo, redo, `resize` and `trim` can shrink the spreadsheet and display
+    /// styles change the size of cells, so the scroll page has to be
+    /// recalculated.
+    fn reset_scroll_page(&mut self) -> crossterm::Result<()> {
+        // The selection and the highlighted cells may be outside of a
+        // spreadsheet, which shrank.
+        self.selection_anchor = self
+            .selection_anchor
+            .map(|anchor| self.spreadsheet.clamp_position(anchor));
+        let (columns, rows) = (self.spreadsheet.columns(), self.spreadsheet.rows());
+        self.highlighted_positions
+            .retain(|&(column, row)| column < columns && row < rows);
+        if let Some(split) = &mut self.split {
+            let (column, row) = self.spreadsheet.clamp_position(split.current_cell);
+            split.current_cell = (column, row);
+            let cursor = (column, self.spreadsheet.display_row(row));
+            self.swap_panes();
+            self.scroll_page = ScrollPage::new(cursor, &self.page_size());
+            self.swap_panes();
+        }
+        self.scroll_page = ScrollPage::new(self.display_cursor(), &self.page_size());
+        queue!(stdout(), Clear(ClearType::All))?;
+        self.render()?;
+        let cursor = self.cell_to_cursor(self.scroll_page.cursor);
+        self.cursor = cursor;
+        Ok(())
+    }
+
     fn init_cell_editor(&mut self, text: String) -> crossterm::Result<()> {
         let mut cell_editor = TextInput::default();
         cell_editor.set(&text);
         self.cell_editor = Some(cell_editor);
+        self.shown_references.clear();
+        self.update_shown_references()?;
         self.render_status_bar()?;
         Ok(())
     }
 
+    /// The references of the formula in the cell editor, which are outlined
+    /// in the grid in the same color as in the editor.
+    fn editor_references(&self) -> Vec<FormulaReference> {
+        let size = (self.spreadsheet.columns(), self.spreadsheet.rows());
+        self.cell_editor
+            .as_ref()
+            .and_then(|editor| editor.buffer.strip_prefix('='))
+            .map(|raw| tabelle_core::formula_references(raw, size))
+            .unwrap_or_default()
+    }
+
+    /// Redraws the grid, if the references in the cell editor changed since
+    /// the last call.
+    fn update_shown_references(&mut self) -> crossterm::Result<()> {
+        let references = self.editor_references();
+        if references == self.shown_references {
+            return Ok(());
+        }
+        self.shown_references = references;
+        self.render()?;
+        self.update_highlighted_cell(self.scroll_page.cursor, self.scroll_page.cursor)
+    }
+
     pub(crate) fn update_highlighted_cell(
         &self,
         old_cursor: (usize, usize),
         new_cursor: (usize, usize),
     ) -> crossterm::Result<()> {
-        let size = self.cell_size();
+        let page_size = self.page_size();
         let size = (
-            size.0.min(self.spreadsheet.columns()),
-            size.1.min(self.spreadsheet.rows()),
+            page_size.size.0.min(self.spreadsheet.columns()),
+            page_size.size.1.min(self.spreadsheet.visible_row_count()),
         );
         let neighbors = Neighbors {
             top: true,
@@ -775,22 +2132,71 @@ impl Terminal {
             bottom: old_cursor.1 + 1 < size.1,
             left: true,
         };
-        let width = self.spreadsheet.column_width(old_cursor.0) as u16;
-        let cursor = self.cell_to_cursor(old_cursor);
-        let cursor = (cursor.0 - 2, cursor.1 - 1);
-        print_cell_border(cursor, width, neighbors, false)?;
+        let old_position = self.cell_at_slot(old_cursor);
+        let width = self.spreadsheet.column_width(old_position.0) as u16;
+        let color = if self.is_selected(old_position) {
+            Color::Blue
+        } else if self.highlighted_positions.contains(&old_position) {
+            Color::Yellow
+        } else {
+            Color::Reset
+        };
+        self.outline_cell(old_cursor, width, neighbors, color)?;
         let neighbors = Neighbors {
             top: true,
             right: new_cursor.0 + 1 < size.0,
             bottom: new_cursor.1 + 1 < size.1,
             left: true,
         };
-        let cursor = self.cell_to_cursor(new_cursor);
-        let cursor = (cursor.0 - 2, cursor.1 - 1);
-        let width = self.spreadsheet.column_width(new_cursor.0) as u16;
-        print_cell_border(cursor, width, neighbors, true)?;
+        let new_position = self.cell_at_slot(new_cursor);
+        let width = self.spreadsheet.column_width(new_position.0) as u16;
+        self.outline_cell(new_cursor, width, neighbors, Color::Cyan)?;
         Ok(())
     }
+
+    /// Returns the cell shown at `slot` on the screen.
+    fn cell_at_slot(&self, slot: (usize, usize)) -> (usize, usize) {
+        let (column, display_row) = self.scroll_page.cell_at_slot(slot, &self.page_size());
+        (column, self.spreadsheet.row_at_display(display_row))
+    }
+
+    /// Draws the border of the visible cell at `position` in `color`. Compact
+    /// styles only have borders left and right of a cell.
+    fn outline_cell(
+        &self,
+        position: (usize, usize),
+        width: u16,
+        neighbors: Neighbors,
+        color: Color,
+    ) -> crossterm::Result<()> {
+        let cursor = self.cell_to_cursor(position);
+        let height = self.spreadsheet.row_height(self.cell_at_slot(position).1) as u16;
+        if self.display_style == DisplayStyle::Borders {
+            print_cell_border(
+                (cursor.0 - 2, cursor.1 - 1),
+                (width, height),
+                neighbors,
+                color,
+            )
+        } else {
+            let separator = if color == Color::Reset {
+                self.display_style.column_separator()
+            } else {
+                '│'
+            };
+            queue!(stdout(), SetForegroundColor(color))?;
+            for line in 0..height {
+                queue!(
+                    stdout(),
+                    MoveTo(cursor.0 - 2, cursor.1 + line),
+                    Print(separator),
+                    MoveRight(width + 2),
+                    Print(separator),
+                )?;
+            }
+            queue!(stdout(), SetForegroundColor(Color::Reset))
+        }
+    }
 }
 
 fn handle_text_input_event(
@@ -801,35 +2207,52 @@ fn handle_text_input_event(
     match event {
         event::Event::FocusGained => {}
         event::Event::FocusLost => {}
-        event::Event::Key(event) => match event.code {
-            event::KeyCode::Backspace => input.backspace(),
-            event::KeyCode::Tab | event::KeyCode::Enter => {
-                *unhandled_key_event = Some(event);
+        event::Event::Key(event) if event.modifiers == KeyModifiers::CONTROL => match event.code {
+            event::KeyCode::Char('a') => input.select_all(),
+            event::KeyCode::Char('c') => *unhandled_key_event = Some(event),
+            event::KeyCode::Char('w') => {
+                input.clear_selection();
+                input.kill_ring = Some(input.cut_word_before());
             }
-            event::KeyCode::Left => input.left(),
-            event::KeyCode::Right => input.right(),
-            event::KeyCode::Up => input.up(),
-            event::KeyCode::Down => input.down(),
-            event::KeyCode::Home => input.up(),
-            event::KeyCode::End => input.down(),
-            event::KeyCode::PageUp => {}
-            event::KeyCode::PageDown => {}
-            event::KeyCode::BackTab => {}
-            event::KeyCode::Delete => input.delete(),
-            event::KeyCode::Insert => {}
-            event::KeyCode::F(_) => {}
-            event::KeyCode::Char(ch) => input.insert_char(ch),
-            event::KeyCode::Null | event::KeyCode::Esc => return Ok(true),
-            event::KeyCode::CapsLock => {}
-            event::KeyCode::ScrollLock => {}
-            event::KeyCode::NumLock => {}
-            event::KeyCode::PrintScreen => {}
-            event::KeyCode::Pause => {}
-            event::KeyCode::Menu => {}
-            event::KeyCode::KeypadBegin => {}
-            event::KeyCode::Media(_) => {}
-            event::KeyCode::Modifier(_) => {}
+            event::KeyCode::Char('y') => {
+                input.clear_selection();
+                let text = input.kill_ring.clone();
+                input.yank(text.as_deref().unwrap_or(""));
+            }
+            _ => {}
         },
+        event::Event::Key(event) => {
+            input.clear_selection();
+            match event.code {
+                event::KeyCode::Backspace => input.backspace(),
+                event::KeyCode::Tab | event::KeyCode::Enter => {
+                    *unhandled_key_event = Some(event);
+                }
+                event::KeyCode::Left => input.left(),
+                event::KeyCode::Right => input.right(),
+                event::KeyCode::Up => input.up(),
+                event::KeyCode::Down => input.down(),
+                event::KeyCode::Home => input.up(),
+                event::KeyCode::End => input.down(),
+                event::KeyCode::PageUp => {}
+                event::KeyCode::PageDown => {}
+                event::KeyCode::BackTab => {}
+                event::KeyCode::Delete => input.delete(),
+                event::KeyCode::Insert => {}
+                event::KeyCode::F(_) => {}
+                event::KeyCode::Char(ch) => input.insert_char(ch),
+                event::KeyCode::Null | event::KeyCode::Esc => return Ok(true),
+                event::KeyCode::CapsLock => {}
+                event::KeyCode::ScrollLock => {}
+                event::KeyCode::NumLock => {}
+                event::KeyCode::PrintScreen => {}
+                event::KeyCode::Pause => {}
+                event::KeyCode::Menu => {}
+                event::KeyCode::KeypadBegin => {}
+                event::KeyCode::Media(_) => {}
+                event::KeyCode::Modifier(_) => {}
+            }
+        }
         event::Event::Mouse(_) => {}
         event::Event::Paste(_) => {}
         event::Event::Resize(_, _) => {}
@@ -837,26 +2260,249 @@ fn handle_text_input_event(
     Ok(false)
 }
 
-fn cursor_to_cell(cursor: (u16, u16)) -> (usize, usize) {
-    let offset = (7, 3);
+/// The statistics of `column` as lines of text, which are shown by `stats A`
+/// and the statistics panel.
+fn column_stats_lines(spreadsheet: &Spreadsheet, column: usize) -> Vec<String> {
+    let stats = spreadsheet.column_stats(column);
+    let number = |value: Option<f64>| value.map_or_else(|| "-".into(), |v| v.to_string());
+    vec![
+        format!("Column {}", spreadsheet.column_header(column)),
+        format!("Count: {}", stats.count),
+        format!("Sum: {}", stats.sum),
+        format!("Mean: {}", number(stats.mean)),
+        format!("Min: {}", number(stats.min)),
+        format!("Max: {}", number(stats.max)),
+        format!("Distinct: {}", stats.distinct),
+    ]
+}
+
+/// Loads `.xlsx`, `.ods`, `.md`, `.json` and, with the `parquet` feature,
+/// `.parquet` files in their format and everything else as csv.
+fn load_spreadsheet(file: &Path) -> std::result::Result<Spreadsheet, tabelle_core::Error> {
+    let mut spreadsheet = match file.extension().and_then(|e| e.to_str()) {
+        Some("xlsx") => Spreadsheet::load_xlsx(file),
+        Some("ods") => Spreadsheet::load_ods(file),
+        Some("md") => std::fs::read_to_string(file)
+            .map_err(tabelle_core::Error::from)
+            .and_then(|content| Spreadsheet::load_markdown(&content)),
+        Some("json") => std::fs::read_to_string(file)
+            .map_err(tabelle_core::Error::from)
+            .and_then(|content| Spreadsheet::load_json(&content)),
+        #[cfg(feature = "parquet")]
+        Some("parquet") => Spreadsheet::load_parquet(file),
+        _ => std::fs::read_to_string(file)
+            .map_err(tabelle_core::Error::from)
+            .and_then(|content| Spreadsheet::load_csv(&content)),
+    }?;
+    spreadsheet.set_path(file);
+    Ok(spreadsheet)
+}
+
+/// Loads only the first rows of parquet files and large csv files, the
+/// remaining rows are loaded by [`Terminal::load_rows`]. Other files are
+/// loaded completely, with `events` in the background, see
+/// [`load_in_background`].
+fn load_lazily(
+    file: &Path,
+    events: Option<&mut VecDeque<event::Event>>,
+) -> std::result::Result<(Spreadsheet, Option<Box<dyn LazyRows>>), tabelle_core::Error> {
+    let lazy_rows: Option<Box<dyn LazyRows>> = match file.extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "parquet")]
+        Some("parquet") => Some(Box::new(tabelle_core::ParquetFile::open(file)?)),
+        Some("xlsx" | "ods" | "md" | "json") => None,
+        _ if std::fs::metadata(file)?.len() > LAZY_CSV_BYTES => {
+            Some(Box::new(CsvStream::open(file)?))
+        }
+        _ => None,
+    };
+    match (lazy_rows, events) {
+        (Some(mut lazy_rows), _) => {
+            let spreadsheet = lazy_rows.load_first(LAZY_CHUNK_ROWS)?;
+            Ok((spreadsheet, Some(lazy_rows)))
+        }
+        (None, Some(events)) => Ok((load_in_background(file, events)?, None)),
+        (None, None) => Ok((load_spreadsheet(file)?, None)),
+    }
+}
+
+/// The frames of the spinner shown while loading a file.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Loads the file on another thread, while the first line shows a spinner
+/// and the progress of xlsx files. Pressing Ctrl+C stops waiting for the
+/// file and returns an error, every other event is added to `events`.
+fn load_in_background(
+    file: &Path,
+    events: &mut VecDeque<event::Event>,
+) -> std::result::Result<Spreadsheet, tabelle_core::Error> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let loader = {
+        let file = file.to_owned();
+        std::thread::spawn(move || {
+            let progress = |progress| {
+                let _ = sender.send(progress);
+            };
+            match file.extension().and_then(|e| e.to_str()) {
+                Some("xlsx") => Spreadsheet::load_xlsx_with_progress(&file, progress),
+                _ => load_spreadsheet(&file),
+            }
+        })
+    };
+    let mut progress: Option<Progress> = None;
+    let mut frame = 0;
+    while !loader.is_finished() {
+        progress = receiver.try_iter().last().or(progress);
+        let percent = progress
+            .and_then(|progress| progress.percent())
+            .map_or(String::new(), |percent| format!(" {percent}%"));
+        execute!(
+            stdout(),
+            MoveTo(0, 0),
+            Print(format!(
+                "{} Loading {}{percent}",
+                SPINNER[frame % SPINNER.len()],
+                file.display()
+            )),
+            Clear(ClearType::UntilNewLine),
+        )?;
+        frame += 1;
+        if event::poll(Duration::from_millis(100))? {
+            match event::read()? {
+                event::Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Interrupted,
+                        "Loading was cancelled",
+                    )
+                    .into());
+                }
+                event => events.push_back(event),
+            }
+        }
+    }
+    loader.join().expect("Failed to load the file.")
+}
+
+/// Saves `.xlsx`, `.ods`, `.md` and `.json` files in their format and
+/// everything else as csv with `separator`.
+fn save_spreadsheet(
+    spreadsheet: &Spreadsheet,
+    file: &Path,
+    separator: char,
+) -> std::result::Result<(), tabelle_core::Error> {
+    match file.extension().and_then(|e| e.to_str()) {
+        Some("xlsx") => spreadsheet.save_as_xlsx(file),
+        Some("ods") => spreadsheet.save_as_ods(file),
+        Some("md") => spreadsheet.save_as_markdown(file),
+        Some("json") => spreadsheet.save_as_json(file),
+        _ => spreadsheet.save_csv_with(
+            file,
+            &CsvWriteOptions {
+                separator,
+                ..Default::default()
+            },
+        ),
+    }
+}
+
+/// Loads `input`, evaluates it and saves it to `output` without starting the
+/// terminal ui.
+fn convert(input: &Path, output: &Path) -> std::result::Result<(), tabelle_core::Error> {
+    let mut spreadsheet = load_spreadsheet(input)?;
+    spreadsheet.evaluate();
+    save_spreadsheet(&spreadsheet, output, spreadsheet.csv_separator())
+}
+
+fn page_size(
+    terminal_size: (u16, u16),
+    display_style: DisplayStyle,
+    spreadsheet: &Spreadsheet,
+) -> PageSize {
+    // Leave room for the scrollbars.
+    let size = cursor_to_cell(
+        (
+            terminal_size.0.saturating_sub(2),
+            terminal_size.1.saturating_sub(2),
+        ),
+        display_style,
+    );
+    let mut result = PageSize {
+        size: (size.0.saturating_sub(1), size.1.saturating_sub(1)),
+        fixed: (spreadsheet.fixed_columns(), spreadsheet.fixed_rows()),
+        row_step: 1,
+        row_pages: Vec::new(),
+    };
+    // Rows are measured in lines of the default row height, so with only
+    // default heights this is size.1 - fixed.1 rows per page.
+    let fixed = result.fixed().1;
+    let default_lines = display_style.row_lines(1) as usize;
+    let lines_of = |row| display_style.row_lines(spreadsheet.row_height(row)) as usize;
+    let mut rows = spreadsheet.visible_rows();
+    let fixed_lines: usize = rows.by_ref().take(fixed).map(lines_of).sum();
+    let lines = (result.size.1 * default_lines).saturating_sub(fixed_lines);
+    result.row_step = (lines / default_lines).max(1);
+    // Split the scrolled rows into pages until the last row with a custom
+    // height, after it every page has row_step rows.
+    result.row_pages.push(fixed);
+    let (mut page_lines, mut page_rows) = (0, 0);
+    let custom_rows = rows.take_while(|&row| row < spreadsheet.rows_with_custom_height());
+    for (display_row, row) in (fixed..).zip(custom_rows) {
+        let row_lines = lines_of(row);
+        if page_rows > 0 && page_lines + row_lines > lines {
+            result.row_pages.push(display_row);
+            (page_lines, page_rows) = (0, 0);
+        }
+        page_lines += row_lines;
+        page_rows += 1;
+    }
+    if page_rows > 0 {
+        let rest = lines.saturating_sub(page_lines) / default_lines;
+        let last = *result.row_pages.last().unwrap();
+        result.row_pages.push(last + page_rows + rest);
+    }
+    result
+}
+
+fn cursor_to_cell(cursor: (u16, u16), display_style: DisplayStyle) -> (usize, usize) {
+    let offset = (7, display_style.first_row_line());
     // TODO: Fix for variable cell size.
-    let size_per_cell = (12, 2);
-    let x = (cursor.0 - offset.0) / size_per_cell.0;
-    let y = (cursor.1 - offset.1) / size_per_cell.1;
+    let size_per_cell = (12, display_style.row_lines(1));
+    let x = cursor.0.saturating_sub(offset.0) / size_per_cell.0;
+    let y = cursor.1.saturating_sub(offset.1) / size_per_cell.1;
     (x as usize, y as usize)
 }
 
 impl Drop for Terminal {
     fn drop(&mut self) {
+        if self.diff.is_some() {
+            execute!(stdout(), DisableMouseCapture).expect("Failed to disable mouse capture.");
+            crossterm::terminal::disable_raw_mode().expect("Failed to disable raw mode!");
+            return;
+        }
         let config_path = std::env::current_exe()
             .unwrap()
             .parent()
             .unwrap()
             .join("config.json");
+        // The entry of the current file in `sheets` is only a placeholder.
+        let sheets = self
+            .sheets
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != self.current_sheet)
+            .map(|(_, sheet)| sheet.spreadsheet.clone())
+            .collect();
         let config = Config {
             spreadsheet: self.spreadsheet.clone(),
+            sheets,
+            current_sheet: self.current_sheet,
             cursor: self.cursor,
             dialog: self.dialog.clone(),
+            macros: self.macros.clone(),
+            display_style: self.display_style,
         };
 
         std::fs::write(
@@ -866,95 +2512,176 @@ impl Drop for Terminal {
         .expect("Failed to write config!");
         // execute!(stdout(), ResetColor, LeaveAlternateScreen)
         //     .expect("Failed to leave alternate screen.");
+        execute!(stdout(), DisableMouseCapture).expect("Failed to disable mouse capture.");
         crossterm::terminal::disable_raw_mode().expect("Failed to disable raw mode!");
     }
 }
 
-#[derive(Debug)]
+/// The number of visible columns and rows and how many of them are fixed.
+/// Fixed columns and rows are visible on every scroll page, like the freeze
+/// panes of Excel.
+#[derive(Debug, Clone)]
+struct PageSize {
+    size: (usize, usize),
+    fixed: (usize, usize),
+    /// The number of scrolled rows of a page, if they all have the default
+    /// height.
+    row_step: usize,
+    /// The first row of each page of scrolled rows, until the page after
+    /// which all rows have the default height. This is never empty.
+    row_pages: Vec<usize>,
+}
+
+impl PageSize {
+    /// The fixed columns and rows, which leave room for at least one cell to
+    /// scroll.
+    fn fixed(&self) -> (usize, usize) {
+        (
+            self.fixed.0.min(self.size.0.saturating_sub(1)),
+            self.fixed.1.min(self.size.1.saturating_sub(1)),
+        )
+    }
+
+    /// The number of columns and rows, which are scrolled by a page. Pages
+    /// with taller rows have less rows, see [`PageSize::row_page_start`].
+    fn step(&self) -> (usize, usize) {
+        let fixed = self.fixed();
+        ((self.size.0 - fixed.0).max(1), self.row_step)
+    }
+
+    /// The first row of the page `page` of scrolled rows.
+    fn row_page_start(&self, page: usize) -> usize {
+        let last = self.row_pages.len() - 1;
+        match self.row_pages.get(page) {
+            Some(&start) => start,
+            None => self.row_pages[last] + (page - last) * self.row_step,
+        }
+    }
+
+    /// The page of scrolled rows, which contains the scrolled `row`.
+    fn row_page_of(&self, row: usize) -> usize {
+        let last = self.row_pages.len() - 1;
+        if row >= self.row_pages[last] {
+            last + (row - self.row_pages[last]) / self.row_step
+        } else {
+            self.row_pages.partition_point(|&start| start <= row) - 1
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 struct ScrollPage {
     scroll_page: (usize, usize),
+    /// The position of the current cell on the screen in cells.
     cursor: (usize, usize),
 }
 
 impl ScrollPage {
-    pub fn new(mut cursor: (usize, usize), size: (usize, usize)) -> ScrollPage {
-        let mut scroll_page = (0, 0);
-        while cursor.0 > size.0 {
-            scroll_page.0 += 1;
-            cursor.0 -= size.0;
-        }
-        while cursor.1 > size.1 {
-            scroll_page.1 += 1;
-            cursor.1 -= size.1;
-        }
-        ScrollPage {
-            scroll_page,
-            cursor,
-        }
+    pub fn new(cursor: (usize, usize), size: &PageSize) -> ScrollPage {
+        let mut result = ScrollPage {
+            scroll_page: (0, 0),
+            cursor: (0, 0),
+        };
+        result.set_cursor(cursor, size);
+        result
     }
 
-    pub fn move_cursor(&mut self, offset: (isize, isize), size: (usize, usize)) -> bool {
-        let mut result = false;
-        let mut cursor = (
-            self.cursor.0 as isize + offset.0,
-            self.cursor.1 as isize + offset.1,
+    /// Moves the cursor by `offset` and returns true if the page changed.
+    pub fn move_cursor(&mut self, offset: (isize, isize), size: &PageSize) -> bool {
+        let cursor = self.no_scroll_cursor(size);
+        let cursor = (
+            (cursor.0 as isize + offset.0).max(0) as usize,
+            (cursor.1 as isize + offset.1).max(0) as usize,
         );
+        let old_scroll_page = self.scroll_page;
+        self.set_cursor(cursor, size);
+        self.scroll_page != old_scroll_page
+    }
 
-        if cursor.0 < 0 {
-            if self.scroll_page.0 > 0 {
-                result = true;
-                self.scroll_page.0 -= 1;
-                cursor.0 += size.0 as isize;
-            } else {
-                cursor.0 = 0;
-            }
-        }
-        if cursor.1 < 0 {
-            if self.scroll_page.1 > 0 {
-                result = true;
-                self.scroll_page.1 -= 1;
-                cursor.1 += size.1 as isize;
-            } else {
-                cursor.1 = 0;
-            }
-        }
-        let mut cursor = (cursor.0 as usize, cursor.1 as usize);
-        while cursor.0 >= size.0 {
-            result = true;
-            self.scroll_page.0 += 1;
-            cursor.0 -= size.0;
-        }
-        while cursor.1 >= size.1 {
-            result = true;
-            self.scroll_page.1 += 1;
-            cursor.1 -= size.1;
-        }
-        self.cursor = cursor;
-
-        result
+    /// The first column and row after the fixed ones, which is visible.
+    fn scroll(&self, size: &PageSize) -> (usize, usize) {
+        (
+            size.fixed().0 + self.scroll_page.0 * size.step().0,
+            size.row_page_start(self.scroll_page.1),
+        )
     }
 
-    fn scroll(&self, size: (usize, usize)) -> (usize, usize) {
-        (self.scroll_page.0 * size.0, self.scroll_page.1 * size.1)
+    fn no_scroll_cursor(&self, size: &PageSize) -> (usize, usize) {
+        self.cell_at_slot(self.cursor, size)
     }
 
-    fn no_scroll_cursor(&self, size: (usize, usize)) -> (usize, usize) {
+    /// Returns the cell, which is shown at `slot` on the screen.
+    fn cell_at_slot(&self, slot: (usize, usize), size: &PageSize) -> (usize, usize) {
+        let (fixed, scroll) = (size.fixed(), self.scroll(size));
+        let axis = |slot: usize, fixed: usize, scroll: usize| {
+            if slot < fixed {
+                slot
+            } else {
+                scroll + slot - fixed
+            }
+        };
         (
-            self.scroll_page.0 * size.0 + self.cursor.0,
-            self.scroll_page.1 * size.1 + self.cursor.1,
+            axis(slot.0, fixed.0, scroll.0),
+            axis(slot.1, fixed.1, scroll.1),
         )
     }
 
-    fn set_cursor(&mut self, cursor: (usize, usize), size: (usize, usize)) {
-        self.cursor = cursor;
-        while self.cursor.0 > size.0 {
-            self.scroll_page.0 += 1;
-            self.cursor.0 -= size.0;
-        }
-        while self.cursor.1 > size.1 {
-            self.scroll_page.1 += 1;
-            self.cursor.1 -= size.1;
-        }
+    /// Returns where the cell is shown on the screen, if it is visible.
+    fn slot_of_cell(&self, cell: (usize, usize), size: &PageSize) -> Option<(usize, usize)> {
+        let (fixed, scroll) = (size.fixed(), self.scroll(size));
+        let step = (
+            size.step().0,
+            size.row_page_start(self.scroll_page.1 + 1) - scroll.1,
+        );
+        let axis = |cell: usize, fixed: usize, step: usize, scroll: usize| {
+            if cell < fixed {
+                Some(cell)
+            } else if cell >= scroll && cell - scroll < step {
+                Some(fixed + cell - scroll)
+            } else {
+                None
+            }
+        };
+        Some((
+            axis(cell.0, fixed.0, step.0, scroll.0)?,
+            axis(cell.1, fixed.1, step.1, scroll.1)?,
+        ))
+    }
+
+    /// Moves the cursor to the cell at `cursor`. The page only changes, if
+    /// the cell is not visible on the current page.
+    fn set_cursor(&mut self, cursor: (usize, usize), size: &PageSize) {
+        let (fixed, step) = (size.fixed(), size.step());
+        let axis = |cell: usize,
+                    fixed: usize,
+                    page: usize,
+                    start: &dyn Fn(usize) -> usize,
+                    page_of: &dyn Fn(usize) -> usize| {
+            if cell < fixed {
+                (page, cell)
+            } else if (start(page)..start(page + 1)).contains(&cell) {
+                (page, fixed + cell - start(page))
+            } else {
+                let page = page_of(cell);
+                (page, fixed + cell - start(page))
+            }
+        };
+        let column = axis(
+            cursor.0,
+            fixed.0,
+            self.scroll_page.0,
+            &|page| fixed.0 + page * step.0,
+            &|cell| (cell - fixed.0) / step.0,
+        );
+        let row = axis(
+            cursor.1,
+            fixed.1,
+            self.scroll_page.1,
+            &|page| size.row_page_start(page),
+            &|cell| size.row_page_of(cell),
+        );
+        self.scroll_page = (column.0, row.0);
+        self.cursor = (column.1, row.1);
     }
 }
 
@@ -1005,11 +2732,10 @@ impl Neighbors {
 
 fn print_cell_border(
     cursor: (u16, u16),
-    width: u16,
+    (width, height): (u16, u16),
     neighbors: Neighbors,
-    highlight: bool,
+    color: Color,
 ) -> crossterm::Result<()> {
-    let color = if highlight { Color::Cyan } else { Color::Reset };
     queue!(
         stdout(),
         SetForegroundColor(color),
@@ -1019,14 +2745,19 @@ fn print_cell_border(
     for _ in 0..width + 2 {
         queue!(stdout(), Print('─'))?;
     }
+    queue!(stdout(), Print(neighbors.top_right_char()))?;
+    for _ in 0..height {
+        queue!(
+            stdout(),
+            MoveDown(1),
+            MoveToColumn(cursor.0),
+            Print("│ "),
+            MoveRight(width),
+            Print(" │"),
+        )?;
+    }
     queue!(
         stdout(),
-        Print(neighbors.top_right_char()),
-        MoveDown(1),
-        MoveToColumn(cursor.0),
-        Print("│ "),
-        MoveRight(width),
-        Print(" │"),
         MoveDown(1),
         MoveToColumn(cursor.0),
         Print(neighbors.bottom_left_char())
@@ -1043,29 +2774,50 @@ fn print_cell_border(
     Ok(())
 }
 
+/// Applies the style of a cell to its content. The current cell is always
+/// printed in italic.
+fn styled_cell_content(content: &str, style: CellStyle, highlight: bool) -> StyledContent<&str> {
+    let mut content = content.stylize();
+    if style.bold {
+        content = content.bold();
+    }
+    if style.italic || highlight {
+        content = content.italic();
+    }
+    if let Some((r, g, b)) = style.foreground {
+        content = content.with(Color::Rgb { r, g, b });
+    }
+    if let Some((r, g, b)) = style.background {
+        content = content.on(Color::Rgb { r, g, b });
+    }
+    content
+}
+
+/// Prints a cell with one line for each of `lines`, which all have the same
+/// width.
 fn print_cell(
-    content: &str,
+    lines: &[StyledContent<&str>],
     cursor_column: u16,
     neighbors: Neighbors,
-    highlight: bool,
 ) -> crossterm::Result<()> {
-    let width = content.width();
+    let width = lines[0].content().width();
     queue!(stdout(), Print(neighbors.top_left_char()))?;
     for _ in 0..width + 2 {
         queue!(stdout(), Print('─'))?;
     }
+    queue!(stdout(), Print(neighbors.top_right_char()))?;
+    for line in lines {
+        queue!(
+            stdout(),
+            MoveDown(1),
+            MoveToColumn(cursor_column),
+            Print("│ "),
+            PrintStyledContent(*line),
+            Print(" │"),
+        )?;
+    }
     queue!(
         stdout(),
-        Print(neighbors.top_right_char()),
-        MoveDown(1),
-        MoveToColumn(cursor_column),
-        Print("│ "),
-        if highlight {
-            Print(content.italic())
-        } else {
-            Print(content.stylize())
-        },
-        Print(" │"),
         MoveDown(1),
         MoveToColumn(cursor_column),
         Print(neighbors.bottom_left_char())
@@ -1078,6 +2830,36 @@ fn print_cell(
     Ok(())
 }
 
+/// Prints a cell of the compact display styles, which has no top and bottom
+/// border.
+fn print_compact_cell(
+    lines: &[StyledContent<&str>],
+    cursor_column: u16,
+    separator: char,
+    highlight: bool,
+) -> crossterm::Result<()> {
+    let separator_color = if highlight { Color::Cyan } else { Color::Reset };
+    let separator = if highlight { '│' } else { separator };
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            queue!(stdout(), MoveDown(1), MoveToColumn(cursor_column))?;
+        }
+        queue!(
+            stdout(),
+            SetForegroundColor(separator_color),
+            Print(separator),
+            SetForegroundColor(Color::Reset),
+            Print(' '),
+            PrintStyledContent(*line),
+            Print(' '),
+            SetForegroundColor(separator_color),
+            Print(separator),
+            SetForegroundColor(Color::Reset),
+        )?;
+    }
+    Ok(())
+}
+
 fn print_blank_line(len: usize) {
     for _ in 0..len {
         print!(" ");
@@ -1085,8 +2867,63 @@ fn print_blank_line(len: usize) {
     println!();
 }
 
+/// The flags of the terminal ui, see [`split_flags`].
+const FLAGS: [&str; 3] = ["--readonly", "-r", "--watch"];
+
+/// Splits the command line arguments into the known [`FLAGS`] and the other
+/// arguments. Everything after `--` is no flag, so even files named like a
+/// flag can be opened.
+fn split_flags(args: impl IntoIterator<Item = String>) -> (Vec<String>, Vec<String>) {
+    let mut args = args.into_iter();
+    let (mut flags, mut rest) = (Vec::new(), Vec::new());
+    for arg in args.by_ref() {
+        if arg == "--" {
+            break;
+        } else if FLAGS.contains(&arg.as_str()) {
+            flags.push(arg);
+        } else {
+            rest.push(arg);
+        }
+    }
+    rest.extend(args);
+    (flags, rest)
+}
+
 fn main() {
     // tabelle_core::dump("units-test.xlsx");
-    let mut terminal = Terminal::new();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = headless::run(&args) {
+        std::process::exit(code);
+    }
+    if let Some(index) = args.iter().position(|a| a == "--output" || a == "-o") {
+        let (_, inputs) = split_flags(
+            args[..index]
+                .iter()
+                .chain(args.iter().skip(index + 2))
+                .cloned(),
+        );
+        let input = inputs.first();
+        let (Some(input), Some(output)) = (input, args.get(index + 1)) else {
+            eprintln!("Usage: tabelle <input> --output <output>");
+            std::process::exit(2);
+        };
+        if let Err(err) = convert(Path::new(input), Path::new(output)) {
+            eprintln!("Error while converting {input} to {output}: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut terminal = match args.as_slice() {
+        [diff, old, new] if diff == "diff" => {
+            let load = |file: &String| {
+                load_spreadsheet(Path::new(file)).unwrap_or_else(|err| {
+                    eprintln!("Error while opening {file}: {err}");
+                    std::process::exit(1);
+                })
+            };
+            Terminal::new_diff(load(old), load(new))
+        }
+        _ => Terminal::new(),
+    };
     let _ = terminal.start();
 }
@@ -5,6 +5,11 @@ pub struct TextInput {
     pub buffer: String,
     byte_cursor: usize,
     char_cursor: usize,
+    /// The text last cut by [`TextInput::cut_word_before`].
+    pub kill_ring: Option<String>,
+    /// The byte index where the selection starts. The selection always ends
+    /// at the cursor.
+    selection_start: Option<usize>,
 }
 
 impl TextInput {
@@ -15,18 +20,57 @@ impl TextInput {
     }
 
     pub fn backspace(&mut self) {
-        if self.byte_cursor == 0 {
-            return;
+        if let Some(byte_cursor) = self.previous_char_boundary() {
+            self.byte_cursor = byte_cursor;
+            self.buffer.remove(self.byte_cursor);
+            self.char_cursor -= 1;
+        }
+    }
+
+    /// Removes the word before the cursor (and the whitespace between them)
+    /// and returns it.
+    pub fn cut_word_before(&mut self) -> String {
+        let before = &self.buffer[..self.byte_cursor];
+        let word_end = before.trim_end().len();
+        let start = before[..word_end]
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + before[i..].chars().next().unwrap().len_utf8());
+        let cut: String = self.buffer.drain(start..self.byte_cursor).collect();
+        self.byte_cursor = start;
+        self.char_cursor -= cut.chars().count();
+        cut
+    }
+
+    /// Inserts `text` at the cursor.
+    pub fn yank(&mut self, text: &str) {
+        self.buffer.insert_str(self.byte_cursor, text);
+        self.byte_cursor += text.len();
+        self.char_cursor += text.chars().count();
+    }
+
+    /// Selects the whole buffer and moves the cursor to its end.
+    pub fn select_all(&mut self) {
+        self.down();
+        self.selection_start = Some(0);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection_start = None;
+    }
+
+    /// Returns the selected text or the whole buffer, if nothing is selected.
+    pub fn selected_text(&self) -> &str {
+        match self.selection_start {
+            Some(start) => {
+                let (start, end) = if start <= self.byte_cursor {
+                    (start, self.byte_cursor)
+                } else {
+                    (self.byte_cursor, start)
+                };
+                self.buffer.get(start..end).unwrap_or(&self.buffer)
+            }
+            None => &self.buffer,
         }
-        self.byte_cursor = (self.byte_cursor.saturating_sub(5)..=self.byte_cursor - 1)
-            .filter(|&i| self.buffer.is_char_boundary(i))
-            .rev()
-            .next()
-            .unwrap_or_else(|| {
-                panic!("{self:#?}");
-            });
-        self.buffer.remove(self.byte_cursor);
-        self.char_cursor -= 1;
     }
 
     pub fn delete(&mut self) {
@@ -37,12 +81,17 @@ impl TextInput {
     }
 
     pub fn left(&mut self) {
-        self.byte_cursor = (self.byte_cursor.saturating_sub(5)..=self.byte_cursor - 1)
-            .filter(|&i| self.buffer.is_char_boundary(i))
-            .rev()
-            .next()
-            .unwrap();
-        self.char_cursor = self.char_cursor.saturating_sub(1);
+        if let Some(byte_cursor) = self.previous_char_boundary() {
+            self.byte_cursor = byte_cursor;
+            self.char_cursor -= 1;
+        }
+    }
+
+    fn previous_char_boundary(&self) -> Option<usize> {
+        self.buffer[..self.byte_cursor]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
     }
 
     pub fn right(&mut self) {
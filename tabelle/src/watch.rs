@@ -0,0 +1,70 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::SystemTime,
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches a file, so it can be reloaded, when another program changes it.
+/// The directory of the file is watched, because many programs replace a
+/// file instead of writing to it.
+pub struct FileWatch {
+    path: PathBuf,
+    /// The modification time of the file, when it was last loaded or saved.
+    modified: Option<SystemTime>,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// Stops watching, when it is dropped.
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatch {
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let path = path.canonicalize()?;
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(
+            path.parent().unwrap_or(Path::new(".")),
+            RecursiveMode::NonRecursive,
+        )?;
+        Ok(Self {
+            modified: modified(&path),
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true`, if the file was changed since it was last loaded or
+    /// saved.
+    pub fn changed(&mut self) -> bool {
+        let mut touched = false;
+        for event in self.events.try_iter().flatten() {
+            touched |= !event.kind.is_access() && event.paths.contains(&self.path);
+        }
+        if !touched {
+            return false;
+        }
+        let modified = modified(&self.path);
+        // The file might not be written yet after it was removed.
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+
+    /// Remembers the current state of the file, so saving it from tabelle
+    /// does not reload it.
+    pub fn mark_saved(&mut self) {
+        self.modified = modified(&self.path);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|it| it.modified()).ok()
+}